@@ -60,6 +60,8 @@ struct ContinueBlockStats {
     pub n_reverted: usize,
     /// Rejected are txs that were unsucessful and but that were not revertible.
     pub n_rejected: usize,
+    /// Whether the bouncer capacity was reached.
+    pub block_full: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -72,6 +74,8 @@ pub enum Error {
     ExecutionContext(#[from] mc_exec::Error),
     #[error("Import error: {0:#}")]
     Import(#[from] mc_block_import::BlockImportError),
+    #[error("Mempool error: {0:#}")]
+    Mempool(#[from] mc_mempool::Error),
     #[error("Unexpected error: {0:#}")]
     Unexpected(Cow<'static, str>),
     #[error("Class compilation error when continuing the pending block: {0:#}")]
@@ -206,6 +210,8 @@ impl<Mempool: MempoolProvider> BlockProductionTask<Mempool> {
 
         self.executor.bouncer.bouncer_config.block_max_capacity = bouncer_cap;
         let batch_size = self.backend.chain_config().execution_batch_size;
+        // Transactions which cannot pay the current L1 gas price are left out, see `mempool_underpriced_policy`.
+        let gas_prices = self.l1_data_provider.get_gas_prices();
 
         let mut txs_to_process = VecDeque::with_capacity(batch_size);
         let mut txs_to_process_blockifier = Vec::with_capacity(batch_size);
@@ -219,7 +225,7 @@ impl<Mempool: MempoolProvider> BlockProductionTask<Mempool> {
             let to_take = batch_size.saturating_sub(txs_to_process.len());
             let cur_len = txs_to_process.len();
             if to_take > 0 {
-                self.mempool.take_txs_chunk(/* extend */ &mut txs_to_process, batch_size);
+                self.mempool.take_priced_txs_chunk(/* extend */ &mut txs_to_process, batch_size, &gas_prices)?;
 
                 txs_to_process_blockifier.extend(txs_to_process.iter().skip(cur_len).map(|tx| tx.clone_tx()));
             }
@@ -284,6 +290,7 @@ impl<Mempool: MempoolProvider> BlockProductionTask<Mempool> {
             }
 
             if block_now_full {
+                stats.block_full = true;
                 break;
             }
         }
@@ -384,8 +391,10 @@ impl<Mempool: MempoolProvider> BlockProductionTask<Mempool> {
 
         // Complete the block with full bouncer capacity.
         let start_time = Instant::now();
-        let (mut new_state_diff, visited_segments, _weights, _stats) =
+        let (mut new_state_diff, visited_segments, _weights, stats) =
             self.continue_block(self.backend.chain_config().bouncer_config.block_max_capacity)?;
+        // The mempool transaction limit follows the demand, see `mempool_tx_limit_max`.
+        self.mempool.record_block_fullness(stats.block_full);
 
         // SNOS requirement: For blocks >= 10, the hash of the block 10 blocks prior
         // at address 0x1 with the block number as the key
@@ -460,6 +469,13 @@ impl<Mempool: MempoolProvider> BlockProductionTask<Mempool> {
         Ok(())
     }
 
+    /// Block production is paused while the L1 gas prices are stale, so that transactions are not charged with
+    /// outdated prices.
+    fn gas_prices_stale(&self) -> bool {
+        let max_age = self.backend.chain_config().gas_price_max_age;
+        self.l1_data_provider.is_gas_price_stale(max_age) || self.l1_data_provider.is_data_gas_price_stale(max_age)
+    }
+
     #[tracing::instrument(skip(self, ctx), fields(module = "BlockProductionTask"))]
     pub async fn block_production_task(&mut self, ctx: ServiceContext) -> Result<(), anyhow::Error> {
         let start = tokio::time::Instant::now();
@@ -478,7 +494,12 @@ impl<Mempool: MempoolProvider> BlockProductionTask<Mempool> {
         loop {
             tokio::select! {
                 instant = interval_block_time.tick() => {
-                    if let Err(err) = self.on_block_time().await {
+                    if self.gas_prices_stale() {
+                        tracing::warn!(
+                            "L1 gas prices have not been updated for more than {:?}, pausing block production",
+                            self.backend.chain_config().gas_price_max_age
+                        );
+                    } else if let Err(err) = self.on_block_time().await {
                         tracing::error!("Block production task has errored: {err:#}");
                         // Clear pending block. The reason we do this is because if the error happened because the closed
                         // block is invalid or has not been saved properly, we want to avoid redoing the same error in the next
@@ -501,7 +522,9 @@ impl<Mempool: MempoolProvider> BlockProductionTask<Mempool> {
                         continue
                     }
 
-                    if let Err(err) = self.on_pending_time_tick() {
+                    if self.gas_prices_stale() {
+                        // warned on block time
+                    } else if let Err(err) = self.on_pending_time_tick() {
                         tracing::error!("Pending block update task has errored: {err:#}");
                     }
                     self.current_pending_tick += 1;
@@ -1,13 +1,14 @@
-use rocksdb::WriteOptions;
+use rocksdb::{IteratorMode, WriteOptions};
 use serde::{Deserialize, Serialize};
 use starknet_api::core::Nonce;
 
 use crate::error::DbError;
-use crate::{Column, DatabaseExt, MadaraBackend, MadaraStorageError};
+use crate::{Column, DatabaseExt, MadaraBackend, MadaraStorageError, WriteBatchWithTransaction};
 
 type Result<T, E = MadaraStorageError> = std::result::Result<T, E>;
 
 pub const LAST_SYNCED_L1_EVENT_BLOCK: &[u8] = b"LAST_SYNCED_L1_EVENT_BLOCK";
+pub const LAST_PROCESSED_L1_MESSAGING_BLOCK: &[u8] = b"LAST_PROCESSED_L1_MESSAGING_BLOCK";
 
 /// Struct to store block number and event_index where L1->L2 Message occured
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -23,6 +24,20 @@ impl LastSyncedEventBlock {
     }
 }
 
+/// The L1 block an L1->L2 message was consumed from. It is stored with the message nonce, so that the message can be
+/// rolled back when this block is reorged out of the L1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1MessageOrigin {
+    pub block_number: u64,
+    pub block_hash: [u8; 32],
+}
+
+impl L1MessageOrigin {
+    pub fn new(block_number: u64, block_hash: [u8; 32]) -> Self {
+        L1MessageOrigin { block_number, block_hash }
+    }
+}
+
 /// We add method in MadaraBackend to be able to handle L1->L2 messaging related data
 impl MadaraBackend {
     /// Retrieves the last stored L1 block data that contains a message from the database.
@@ -114,18 +129,117 @@ impl MadaraBackend {
         Ok(())
     }
 
+    /// The last L1 block whose messages have all been processed, `None` before the first one. The L1 messaging sync
+    /// skips the L1 blocks at or below it when it restarts.
+    #[tracing::instrument(skip(self), fields(module = "L1DB"))]
+    pub fn messaging_last_processed_l1_block(&self) -> Result<Option<u64>> {
+        let progress_column = self.db.get_column(Column::L1SyncProgress);
+        let Some(res) = self.db.get_cf(&progress_column, LAST_PROCESSED_L1_MESSAGING_BLOCK)? else { return Ok(None) };
+        Ok(Some(bincode::deserialize(&res)?))
+    }
+
+    /// Records that the messages of every L1 block up to `block_number` have been processed.
+    #[tracing::instrument(skip(self), fields(module = "L1DB"))]
+    pub fn messaging_update_last_processed_l1_block(&self, block_number: u64) -> Result<()> {
+        let progress_column = self.db.get_column(Column::L1SyncProgress);
+        let mut writeopts = WriteOptions::default();
+        writeopts.disable_wal(true);
+        self.db.put_cf_opt(
+            &progress_column,
+            LAST_PROCESSED_L1_MESSAGING_BLOCK,
+            bincode::serialize(&block_number)?,
+            &writeopts,
+        )?;
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self, nonce), fields(module = "L1DB"))]
     pub fn has_l1_messaging_nonce(&self, nonce: Nonce) -> Result<bool> {
         let nonce_column = self.db.get_column(Column::L1MessagingNonce);
         Ok(self.db.get_pinned_cf(&nonce_column, bincode::serialize(&nonce)?)?.is_some())
     }
 
+    /// The L1 block the L1->L2 message with this nonce was consumed from. This is `None` when the message was not
+    /// consumed, or when it was consumed before its origin was tracked.
+    #[tracing::instrument(skip(self, nonce), fields(module = "L1DB"))]
+    pub fn get_l1_messaging_origin(&self, nonce: Nonce) -> Result<Option<L1MessageOrigin>> {
+        let nonce_column = self.db.get_column(Column::L1MessagingNonce);
+        match self.db.get_pinned_cf(&nonce_column, bincode::serialize(&nonce)?)? {
+            Some(v) if !v.is_empty() => Ok(Some(bincode::deserialize(&v)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Marks the L1->L2 message with this nonce as consumed, from the L1 block `origin`.
     #[tracing::instrument(skip(self, nonce), fields(module = "L1DB"))]
-    pub fn set_l1_messaging_nonce(&self, nonce: Nonce) -> Result<(), DbError> {
+    pub fn set_l1_messaging_nonce(&self, nonce: Nonce, origin: L1MessageOrigin) -> Result<()> {
         let nonce_column = self.db.get_column(Column::L1MessagingNonce);
         let mut writeopts = WriteOptions::default();
         writeopts.disable_wal(true);
-        self.db.put_cf_opt(&nonce_column, bincode::serialize(&nonce)?, /* empty value */ [], &writeopts)?;
+        self.db.put_cf_opt(&nonce_column, bincode::serialize(&nonce)?, bincode::serialize(&origin)?, &writeopts)?;
         Ok(())
     }
+
+    /// The distinct L1 blocks the consumed L1->L2 messages come from, ordered by block number.
+    ///
+    /// Nonces stored before their origin was tracked have an empty value: they are skipped, and are never rolled back.
+    #[tracing::instrument(skip(self), fields(module = "L1DB"))]
+    pub fn messaging_l1_message_origins(&self) -> Result<Vec<L1MessageOrigin>> {
+        let nonce_column = self.db.get_column(Column::L1MessagingNonce);
+        let mut origins = vec![];
+        for kv in self.db.iterator_cf(&nonce_column, IteratorMode::Start) {
+            let (_, v) = kv?;
+            if v.is_empty() {
+                continue;
+            }
+            origins.push(bincode::deserialize::<L1MessageOrigin>(&v)?);
+        }
+        origins.sort_unstable_by_key(|origin| (origin.block_number, origin.block_hash));
+        origins.dedup();
+        Ok(origins)
+    }
+
+    /// Rolls back the L1->L2 messages consumed from L1 blocks after `block_number`, after an L1 reorg. Their nonces are
+    /// removed so that they are processed again if they are part of the new canonical chain, and the last synced
+    /// event block is moved back to `block_number`.
+    ///
+    /// Returns the nonces of the rolled back messages.
+    #[tracing::instrument(skip(self), fields(module = "L1DB"))]
+    pub fn messaging_rollback_after_l1_block(&self, block_number: u64) -> Result<Vec<Nonce>> {
+        let nonce_column = self.db.get_column(Column::L1MessagingNonce);
+        let messaging_column = self.db.get_column(Column::L1Messaging);
+        let progress_column = self.db.get_column(Column::L1SyncProgress);
+        let mut batch = WriteBatchWithTransaction::default();
+
+        let mut rolled_back = vec![];
+        for kv in self.db.iterator_cf(&nonce_column, IteratorMode::Start) {
+            let (k, v) = kv?;
+            if v.is_empty() {
+                continue;
+            }
+            let origin: L1MessageOrigin = bincode::deserialize(&v)?;
+            if origin.block_number > block_number {
+                batch.delete_cf(&nonce_column, &k);
+                rolled_back.push(bincode::deserialize(&k)?);
+            }
+        }
+
+        let last_synced_event_block = self.messaging_last_synced_l1_block_with_event()?;
+        if last_synced_event_block.is_some_and(|last| last.block_number > block_number) {
+            batch.put_cf(
+                &messaging_column,
+                LAST_SYNCED_L1_EVENT_BLOCK,
+                bincode::serialize(&LastSyncedEventBlock::new(block_number, 0))?,
+            );
+        }
+        // The blocks after the fork point are processed again.
+        if self.messaging_last_processed_l1_block()?.is_some_and(|last| last > block_number) {
+            batch.put_cf(&progress_column, LAST_PROCESSED_L1_MESSAGING_BLOCK, bincode::serialize(&block_number)?);
+        }
+
+        let mut writeopts = WriteOptions::default();
+        writeopts.disable_wal(true);
+        self.db.write_opt(batch, &writeopts)?;
+        Ok(rolled_back)
+    }
 }
@@ -146,6 +146,8 @@ pub enum Column {
 
     L1Messaging,
     L1MessagingNonce,
+    /// Progress of the L1 sync: the L1 blocks it has fully processed
+    L1SyncProgress,
 
     /// Devnet: stores the private keys for the devnet predeployed contracts
     Devnet,
@@ -193,6 +195,7 @@ impl Column {
             BonsaiClassesLog,
             L1Messaging,
             L1MessagingNonce,
+            L1SyncProgress,
             PendingContractToClassHashes,
             PendingContractToNonces,
             PendingContractStorage,
@@ -229,6 +232,7 @@ impl Column {
             ContractStorage => "contract_storage",
             L1Messaging => "l1_messaging",
             L1MessagingNonce => "l1_messaging_nonce",
+            L1SyncProgress => "l1_sync_progress",
             PendingContractToClassHashes => "pending_contract_to_class_hashes",
             PendingContractToNonces => "pending_contract_to_nonces",
             PendingContractStorage => "pending_contract_storage",
@@ -14,6 +14,9 @@ pub struct SavedTransaction {
     pub contract_address: Option<Felt>,
     pub only_query: bool,
     pub arrived_at: u128,
+    /// Milliseconds since the unix epoch, like `arrived_at`.
+    #[serde(default)]
+    pub deadline: Option<u128>,
 }
 
 #[derive(Serialize)]
@@ -1,4 +1,6 @@
 pub mod common;
 pub mod test_block;
 #[cfg(test)]
+pub mod test_l1_db;
+#[cfg(test)]
 pub mod test_open;
@@ -0,0 +1,50 @@
+use super::common::temp_db::temp_db;
+use crate::l1_db::{L1MessageOrigin, LastSyncedEventBlock};
+use starknet_api::core::Nonce;
+use starknet_types_core::felt::Felt;
+
+#[tokio::test]
+async fn test_messaging_rollback_after_l1_block() {
+    let db = temp_db().await;
+    let backend = db.backend();
+
+    let canonical = L1MessageOrigin::new(10, [1; 32]);
+    let reorged = L1MessageOrigin::new(12, [2; 32]);
+    backend.set_l1_messaging_nonce(Nonce(Felt::ZERO), canonical).unwrap();
+    backend.set_l1_messaging_nonce(Nonce(Felt::ONE), canonical).unwrap();
+    backend.messaging_update_last_synced_l1_block_with_event(LastSyncedEventBlock::new(10, 1)).unwrap();
+
+    // Messages consumed from a block that ends up reorged out.
+    backend.set_l1_messaging_nonce(Nonce(Felt::TWO), reorged).unwrap();
+    backend.messaging_update_last_synced_l1_block_with_event(LastSyncedEventBlock::new(12, 0)).unwrap();
+    backend.messaging_update_last_processed_l1_block(20).unwrap();
+    assert_eq!(backend.messaging_l1_message_origins().unwrap(), [canonical, reorged]);
+
+    let rolled_back = backend.messaging_rollback_after_l1_block(10).unwrap();
+    assert_eq!(rolled_back, [Nonce(Felt::TWO)]);
+
+    // The message set is back to its state before the reorged block.
+    assert!(backend.has_l1_messaging_nonce(Nonce(Felt::ZERO)).unwrap());
+    assert!(backend.has_l1_messaging_nonce(Nonce(Felt::ONE)).unwrap());
+    assert!(!backend.has_l1_messaging_nonce(Nonce(Felt::TWO)).unwrap());
+    assert_eq!(backend.get_l1_messaging_origin(Nonce(Felt::ONE)).unwrap(), Some(canonical));
+    assert_eq!(backend.get_l1_messaging_origin(Nonce(Felt::TWO)).unwrap(), None);
+    assert_eq!(backend.messaging_l1_message_origins().unwrap(), [canonical]);
+    assert_eq!(backend.messaging_last_synced_l1_block_with_event().unwrap().unwrap().block_number, 10);
+    // The blocks after the fork point are processed again.
+    assert_eq!(backend.messaging_last_processed_l1_block().unwrap(), Some(10));
+}
+
+#[tokio::test]
+async fn test_messaging_last_processed_l1_block() {
+    let db = temp_db().await;
+    let backend = db.backend();
+
+    assert_eq!(backend.messaging_last_processed_l1_block().unwrap(), None);
+    backend.messaging_update_last_processed_l1_block(42).unwrap();
+    assert_eq!(backend.messaging_last_processed_l1_block().unwrap(), Some(42));
+
+    // A rollback after the last processed block leaves it as is.
+    backend.messaging_rollback_after_l1_block(50).unwrap();
+    assert_eq!(backend.messaging_last_processed_l1_block().unwrap(), Some(42));
+}
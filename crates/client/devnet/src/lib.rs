@@ -192,9 +192,11 @@ mod tests {
     use mc_db::MadaraBackend;
     use mc_mempool::{transaction_hash, L1DataProvider, Mempool, MockL1DataProvider};
     use mc_mempool::{MempoolLimits, MempoolProvider};
+    use mc_rpc::versions::admin::v0_1_0::{BatchTransactionResult, MadaraMempoolRpcApiV0_1_0Server};
 
     use mp_block::header::L1DataAvailabilityMode;
     use mp_block::{BlockId, BlockTag};
+    use mp_chain_config::MempoolUnderpricedPolicy;
     use mp_class::{ClassInfo, FlattenedSierraClass};
 
     use mp_receipt::{Event, ExecutionResult, FeePayment, InvokeTransactionReceipt, PriceUnit, TransactionReceipt};
@@ -207,6 +209,7 @@ mod tests {
         BroadcastedInvokeTxn, BroadcastedTxn, ClassAndTxnHash, ContractAndTxnHash, DaMode, DeployAccountTxnV3,
         InvokeTxnV3, ResourceBounds, ResourceBoundsMapping,
     };
+    use std::sync::atomic::{AtomicU64, Ordering};
     use std::sync::Arc;
     use std::time::Duration;
 
@@ -220,9 +223,20 @@ mod tests {
     impl DevnetForTesting {
         pub fn sign_and_add_invoke_tx(
             &self,
-            mut tx: BroadcastedInvokeTxn<Felt>,
+            tx: BroadcastedInvokeTxn<Felt>,
             contract: &DevnetPredeployedContract,
         ) -> Result<AddInvokeTransactionResult<Felt>, mc_mempool::Error> {
+            let tx = self.sign_invoke_tx(tx, contract);
+            tracing::debug!("tx: {:?}", tx);
+
+            self.mempool.accept_invoke_tx(tx).map(|accepted| accepted.result)
+        }
+
+        pub fn sign_invoke_tx(
+            &self,
+            mut tx: BroadcastedInvokeTxn<Felt>,
+            contract: &DevnetPredeployedContract,
+        ) -> BroadcastedInvokeTxn<Felt> {
             let (blockifier_tx, _classes) = BroadcastedTxn::Invoke(tx.clone())
                 .into_blockifier(
                     self.backend.chain_config().chain_id.to_felt(),
@@ -238,10 +252,7 @@ mod tests {
                 _ => unreachable!("the invoke tx is not query only"),
             };
             *tx_signature = vec![signature.r, signature.s];
-
-            tracing::debug!("tx: {:?}", tx);
-
-            self.mempool.accept_invoke_tx(tx)
+            tx
         }
 
         pub fn sign_and_add_declare_tx(
@@ -265,7 +276,7 @@ mod tests {
             };
             *tx_signature = vec![signature.r, signature.s];
 
-            self.mempool.accept_declare_tx(tx)
+            self.mempool.accept_declare_tx(tx).map(|accepted| accepted.result)
         }
 
         pub fn sign_and_add_deploy_account_tx(
@@ -288,7 +299,7 @@ mod tests {
             };
             *tx_signature = vec![signature.r, signature.s];
 
-            self.mempool.accept_deploy_account_tx(tx)
+            self.mempool.accept_deploy_account_tx(tx).map(|accepted| accepted.result)
         }
 
         /// (STRK in FRI, ETH in WEI)
@@ -302,13 +313,50 @@ mod tests {
         chain_with_mempool_limits(MempoolLimits::for_testing())
     }
 
+    fn l1_data_provider() -> Arc<dyn L1DataProvider> {
+        let mut l1_data_provider = MockL1DataProvider::new();
+        l1_data_provider.expect_get_da_mode().return_const(L1DataAvailabilityMode::Blob);
+        l1_data_provider.expect_get_gas_prices().return_const(GasPrices {
+            eth_l1_gas_price: 128,
+            strk_l1_gas_price: 128,
+            eth_l1_data_gas_price: 128,
+            strk_l1_data_gas_price: 128,
+        });
+        Arc::new(l1_data_provider)
+    }
+
+    /// Like [`l1_data_provider`], but the STRK L1 gas price can be changed during the test.
+    fn l1_data_provider_with_strk_l1_gas_price(strk_l1_gas_price: Arc<AtomicU64>) -> Arc<dyn L1DataProvider> {
+        let mut l1_data_provider = MockL1DataProvider::new();
+        l1_data_provider.expect_get_da_mode().return_const(L1DataAvailabilityMode::Blob);
+        l1_data_provider.expect_get_gas_prices().returning(move || GasPrices {
+            eth_l1_gas_price: 128,
+            strk_l1_gas_price: strk_l1_gas_price.load(Ordering::SeqCst).into(),
+            eth_l1_data_gas_price: 128,
+            strk_l1_data_gas_price: 128,
+        });
+        Arc::new(l1_data_provider)
+    }
+
     fn chain_with_mempool_limits(mempool_limits: MempoolLimits) -> DevnetForTesting {
+        chain_with_config(ChainConfig::madara_devnet(), mempool_limits)
+    }
+
+    fn chain_with_config(chain_config: ChainConfig, mempool_limits: MempoolLimits) -> DevnetForTesting {
+        chain_with_l1_data_provider(chain_config, mempool_limits, l1_data_provider())
+    }
+
+    fn chain_with_l1_data_provider(
+        chain_config: ChainConfig,
+        mempool_limits: MempoolLimits,
+        l1_data_provider: Arc<dyn L1DataProvider>,
+    ) -> DevnetForTesting {
         let _ = tracing_subscriber::fmt().with_test_writer().try_init();
 
         let mut g = ChainGenesisDescription::base_config().unwrap();
         let contracts = g.add_devnet_contracts(10).unwrap();
 
-        let chain_config = Arc::new(ChainConfig::madara_devnet());
+        let chain_config = Arc::new(chain_config);
         let block = g.build(&chain_config).unwrap();
         let backend = MadaraBackend::open_for_testing(Arc::clone(&chain_config));
         let importer = Arc::new(BlockImporter::new(Arc::clone(&backend), None).unwrap());
@@ -326,15 +374,6 @@ mod tests {
 
         tracing::debug!("block imported {:?}", backend.get_block_info(&BlockId::Tag(BlockTag::Latest)));
 
-        let mut l1_data_provider = MockL1DataProvider::new();
-        l1_data_provider.expect_get_da_mode().return_const(L1DataAvailabilityMode::Blob);
-        l1_data_provider.expect_get_gas_prices().return_const(GasPrices {
-            eth_l1_gas_price: 128,
-            strk_l1_gas_price: 128,
-            eth_l1_data_gas_price: 128,
-            strk_l1_data_gas_price: 128,
-        });
-        let l1_data_provider = Arc::new(l1_data_provider) as Arc<dyn L1DataProvider>;
         let mempool = Arc::new(Mempool::new(Arc::clone(&backend), Arc::clone(&l1_data_provider), mempool_limits));
         let metrics = BlockProductionMetrics::register();
 
@@ -641,9 +680,28 @@ mod tests {
     #[rstest]
     fn test_mempool_tx_limit() {
         let chain = chain_with_mempool_limits(MempoolLimits {
-            max_age: Duration::from_millis(1000000),
+            max_age: Some(Duration::from_millis(1000000)),
+            expired_tx_policy: mp_chain_config::MempoolExpiredTxPolicy::Reject,
             max_declare_transactions: 2,
+            declare_limit_policy: mp_chain_config::MempoolDeclareLimitPolicy::Reject,
             max_transactions: 5,
+            max_transactions_per_sender: usize::MAX,
+            reserved_l1_handler_transactions: 0,
+            reserved_deploy_account_transactions: 0,
+            eviction_enabled: false,
+            replacement_bump_percent: 10,
+            replacement_min_interval: Duration::ZERO,
+            near_capacity_watermark: 0.9,
+            privileged_senders: Default::default(),
+            max_total_bytes: usize::MAX,
+            max_declare_bytecode_size: usize::MAX,
+            max_total_l2_gas: u64::MAX,
+            max_nonce_distance: u64::MAX,
+            min_tip_multiplier: 0.0,
+            allow_zero_fee_transactions: true,
+            refresh_on_resubmit: false,
+            dropped_transactions_capacity: 10_000,
+            auto_tune: None,
         });
         tracing::info!("{}", chain.contracts);
 
@@ -714,11 +772,153 @@ mod tests {
         )
     }
 
+    /// Limits of the batch tests: the mempool fits 5 transactions.
+    fn batch_mempool_limits() -> MempoolLimits {
+        MempoolLimits {
+            max_age: Some(Duration::from_millis(1000000)),
+            expired_tx_policy: mp_chain_config::MempoolExpiredTxPolicy::Reject,
+            max_declare_transactions: 2,
+            declare_limit_policy: mp_chain_config::MempoolDeclareLimitPolicy::Reject,
+            max_transactions: 5,
+            max_transactions_per_sender: usize::MAX,
+            reserved_l1_handler_transactions: 0,
+            reserved_deploy_account_transactions: 0,
+            eviction_enabled: false,
+            replacement_bump_percent: 10,
+            replacement_min_interval: Duration::ZERO,
+            near_capacity_watermark: 0.9,
+            privileged_senders: Default::default(),
+            max_total_bytes: usize::MAX,
+            max_declare_bytecode_size: usize::MAX,
+            max_total_l2_gas: u64::MAX,
+            max_nonce_distance: u64::MAX,
+            min_tip_multiplier: 0.0,
+            allow_zero_fee_transactions: true,
+            refresh_on_resubmit: false,
+            dropped_transactions_capacity: 10_000,
+            auto_tune: None,
+        }
+    }
+
+    /// A batch of 7 transfers from the first devnet account, with consecutive nonces.
+    fn transfer_batch(chain: &DevnetForTesting) -> Vec<BroadcastedTxn<Felt>> {
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        (0..7)
+            .map(|nonce| {
+                let tx = BroadcastedInvokeTxn::V3(InvokeTxnV3 {
+                    sender_address: contract_0.address,
+                    calldata: Multicall::default()
+                        .with(Call {
+                            to: ERC20_STRK_CONTRACT_ADDRESS,
+                            selector: Selector::from("transfer"),
+                            calldata: vec![contract_1.address, 15.into(), Felt::ZERO],
+                        })
+                        .flatten()
+                        .collect(),
+                    signature: vec![], // Signature is filled in by `sign_invoke_tx`.
+                    nonce: nonce.into(),
+                    resource_bounds: ResourceBoundsMapping {
+                        l1_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+                        l2_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+                    },
+                    tip: 0,
+                    paymaster_data: vec![],
+                    account_deployment_data: vec![],
+                    nonce_data_availability_mode: DaMode::L1,
+                    fee_data_availability_mode: DaMode::L1,
+                });
+                BroadcastedTxn::Invoke(chain.sign_invoke_tx(tx, contract_0))
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn test_mempool_tx_batch_limit() {
+        let chain = chain_with_mempool_limits(batch_mempool_limits());
+        let batch = transfer_batch(&chain);
+
+        let results = chain.mempool.accept_tx_batch(batch);
+        assert_eq!(results.len(), 7);
+        for result in &results[..5] {
+            assert_matches!(result, Ok(accepted) if accepted.outcome == mc_mempool::InsertOutcome::Added);
+        }
+        for result in &results[5..] {
+            assert_matches!(
+                result,
+                Err(mc_mempool::Error::InnerMempool(mc_mempool::TxInsersionError::Limit(
+                    mc_mempool::MempoolLimitReached::MaxTransactions { max: 5 }
+                )))
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_add_transaction_batch_limit() {
+        let chain = chain_with_mempool_limits(batch_mempool_limits());
+        let batch = transfer_batch(&chain);
+        let rpc = mc_rpc::Starknet::new(
+            Arc::clone(&chain.backend),
+            Arc::new(mc_rpc::providers::MempoolAddTxProvider::new(Arc::clone(&chain.mempool))),
+            Default::default(),
+            mp_utils::service::ServiceContext::new_for_testing(),
+        )
+        .with_mempool(Arc::clone(&chain.mempool));
+
+        let results = tokio::runtime::Runtime::new().unwrap().block_on(rpc.add_transaction_batch(batch)).unwrap();
+
+        // The mempool fills up in the middle of the batch: the first transactions are added, and each of the following
+        // ones is rejected on its own.
+        assert_eq!(results.len(), 7);
+        for result in &results[..5] {
+            assert_matches!(result, BatchTransactionResult::Accepted { .. });
+        }
+        let err = mc_rpc::StarknetRpcApiError::MempoolLimitReached {
+            limit: mc_mempool::MempoolLimitReached::MaxTransactions { max: 5 },
+        };
+        for result in &results[5..] {
+            assert_eq!(
+                result,
+                &BatchTransactionResult::Rejected { code: (&err).into(), message: err.to_string(), data: err.data() }
+            );
+        }
+        let accepted: Vec<_> =
+            chain.mempool.transactions_snapshot(None, usize::MAX).into_iter().map(|tx| tx.tx_hash).collect();
+        assert_eq!(accepted.len(), 5);
+        for result in &results[..5] {
+            let BatchTransactionResult::Accepted { transaction_hash, .. } = result else { unreachable!() };
+            assert!(accepted.contains(transaction_hash));
+        }
+    }
+
     #[rstest]
     fn test_mempool_age_limit() {
         let max_age = Duration::from_millis(1000);
-        let mut chain =
-            chain_with_mempool_limits(MempoolLimits { max_age, max_declare_transactions: 2, max_transactions: 5 });
+        let mut chain = chain_with_mempool_limits(MempoolLimits {
+            max_age: Some(max_age),
+            expired_tx_policy: mp_chain_config::MempoolExpiredTxPolicy::Reject,
+            max_declare_transactions: 2,
+            declare_limit_policy: mp_chain_config::MempoolDeclareLimitPolicy::Reject,
+            max_transactions: 5,
+            max_transactions_per_sender: usize::MAX,
+            reserved_l1_handler_transactions: 0,
+            reserved_deploy_account_transactions: 0,
+            eviction_enabled: false,
+            replacement_bump_percent: 10,
+            replacement_min_interval: Duration::ZERO,
+            near_capacity_watermark: 0.9,
+            privileged_senders: Default::default(),
+            max_total_bytes: usize::MAX,
+            max_declare_bytecode_size: usize::MAX,
+            max_total_l2_gas: u64::MAX,
+            max_nonce_distance: u64::MAX,
+            min_tip_multiplier: 0.0,
+            allow_zero_fee_transactions: true,
+            refresh_on_resubmit: false,
+            dropped_transactions_capacity: 10_000,
+            auto_tune: None,
+        });
         tracing::info!("{}", chain.contracts);
 
         let contract_0 = &chain.contracts.0[0];
@@ -763,4 +963,198 @@ mod tests {
         assert_eq!(block.inner.receipts, vec![]);
         assert!(chain.mempool.is_empty());
     }
+
+    fn transfer_tx(from: &DevnetPredeployedContract, to: &DevnetPredeployedContract) -> BroadcastedInvokeTxn<Felt> {
+        BroadcastedInvokeTxn::V3(InvokeTxnV3 {
+            sender_address: from.address,
+            calldata: Multicall::default()
+                .with(Call {
+                    to: ERC20_STRK_CONTRACT_ADDRESS,
+                    selector: Selector::from("transfer"),
+                    calldata: vec![to.address, 15.into(), Felt::ZERO],
+                })
+                .flatten()
+                .collect(),
+            signature: vec![], // Signature is filled in by `sign_and_add_invoke_tx`.
+            nonce: 0.into(),
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+                l2_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+            },
+            tip: 0,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DaMode::L1,
+            fee_data_availability_mode: DaMode::L1,
+        })
+    }
+
+    #[rstest]
+    fn test_block_production_defers_underpriced_txs() {
+        let strk_l1_gas_price = Arc::new(AtomicU64::new(128));
+        let mut chain = chain_with_l1_data_provider(
+            ChainConfig::madara_devnet(),
+            MempoolLimits::for_testing(),
+            l1_data_provider_with_strk_l1_gas_price(Arc::clone(&strk_l1_gas_price)),
+        );
+        assert_eq!(chain.backend.chain_config().mempool_underpriced_policy, MempoolUnderpricedPolicy::Requeue);
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        let result = chain.sign_and_add_invoke_tx(transfer_tx(contract_0, contract_1), contract_0).unwrap();
+
+        // The L1 gas price goes above the max price of the transaction (10000) before it is included in a block.
+        strk_l1_gas_price.store(20_000, Ordering::SeqCst);
+        chain.block_production.set_current_pending_tick(1);
+        chain.block_production.on_pending_time_tick().unwrap();
+
+        let block = chain.backend.get_block(&BlockId::Tag(BlockTag::Pending)).unwrap().unwrap();
+        assert_eq!(block.inner.transactions, vec![]);
+        // The transaction is deferred, not dropped.
+        assert!(!chain.mempool.is_empty());
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 1);
+
+        // The L1 gas price goes back down: the transaction makes it into the next pending tick.
+        strk_l1_gas_price.store(128, Ordering::SeqCst);
+        chain.block_production.set_current_pending_tick(2);
+        chain.block_production.on_pending_time_tick().unwrap();
+
+        let block = chain.backend.get_block(&BlockId::Tag(BlockTag::Pending)).unwrap().unwrap();
+        assert_eq!(block.inner.transactions.len(), 1);
+        assert_eq!(block.inner.receipts[0].transaction_hash(), result.transaction_hash);
+        assert!(chain.mempool.is_empty());
+    }
+
+    #[rstest]
+    fn test_mempool_reject_unknown_classes() {
+        let chain_config = ChainConfig { mempool_reject_unknown_classes: true, ..ChainConfig::madara_devnet() };
+        let chain = chain_with_config(chain_config, MempoolLimits::for_testing());
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        // The class of the devnet accounts is declared at genesis.
+        chain.sign_and_add_invoke_tx(transfer_tx(contract_0, contract_1), contract_0).unwrap();
+
+        let unknown_class_hash = Felt::from_hex_unchecked("0xdead");
+        let deploy_account_txn = BroadcastedDeployAccountTxn::V3(DeployAccountTxnV3 {
+            signature: vec![], // The class is checked before the signature.
+            nonce: Felt::ZERO,
+            contract_address_salt: Felt::ZERO,
+            constructor_calldata: vec![Felt::ONE],
+            class_hash: unknown_class_hash,
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+                l2_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+            },
+            tip: 0,
+            paymaster_data: vec![],
+            nonce_data_availability_mode: DaMode::L1,
+            fee_data_availability_mode: DaMode::L1,
+        });
+
+        assert_matches!(
+            chain.mempool.accept_deploy_account_tx(deploy_account_txn),
+            Err(mc_mempool::Error::InnerMempool(mc_mempool::TxInsersionError::UndeclaredClass { class_hash }))
+                if class_hash == unknown_class_hash
+        );
+    }
+
+    /// Simulates a node restart: the db is kept, and a new mempool is loaded from it.
+    fn restart_mempool(chain: &DevnetForTesting, mempool_limits: MempoolLimits) -> Mempool {
+        let mut mempool = Mempool::new(Arc::clone(&chain.backend), l1_data_provider(), mempool_limits);
+        mempool.load_txs_from_db().unwrap();
+        mempool
+    }
+
+    #[rstest]
+    fn test_mempool_persistence(chain: DevnetForTesting) {
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        chain.sign_and_add_invoke_tx(transfer_tx(contract_0, contract_1), contract_0).unwrap();
+        chain.sign_and_add_invoke_tx(transfer_tx(contract_1, contract_0), contract_1).unwrap();
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 2);
+
+        let mempool = restart_mempool(&chain, MempoolLimits::for_testing());
+
+        assert!(!mempool.is_empty());
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 2);
+    }
+
+    #[rstest]
+    fn test_mempool_persistence_drops_expired() {
+        let max_age = Duration::from_millis(1000);
+        let mempool_limits = || MempoolLimits { max_age: Some(max_age), ..MempoolLimits::for_testing() };
+        let chain = chain_with_mempool_limits(mempool_limits());
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        chain.sign_and_add_invoke_tx(transfer_tx(contract_0, contract_1), contract_0).unwrap();
+        std::thread::sleep(max_age); // max age reached
+
+        let mempool = restart_mempool(&chain, mempool_limits());
+
+        assert!(mempool.is_empty());
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 0);
+    }
+
+    #[rstest]
+    fn test_mempool_persistence_keeps_deadline(chain: DevnetForTesting) {
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        let deadline_in = Duration::from_millis(1000);
+        let tx = chain.sign_invoke_tx(transfer_tx(contract_0, contract_1), contract_0);
+        chain.mempool.accept_tx_until(BroadcastedTxn::Invoke(tx), SystemTime::now() + deadline_in).unwrap();
+        std::thread::sleep(deadline_in); // deadline passed
+
+        let mempool = restart_mempool(&chain, MempoolLimits::for_testing());
+
+        assert!(mempool.is_empty());
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 0);
+    }
+
+    #[rstest]
+    #[case::already_known(false, mc_mempool::InsertOutcome::AlreadyKnown)]
+    #[case::refreshed(true, mc_mempool::InsertOutcome::Refreshed)]
+    fn test_mempool_persistence_resubmitted_tx_keeps_deadline(
+        #[case] refresh_on_resubmit: bool,
+        #[case] outcome: mc_mempool::InsertOutcome,
+    ) {
+        let chain = chain_with_mempool_limits(MempoolLimits { refresh_on_resubmit, ..MempoolLimits::for_testing() });
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        let deadline_in = Duration::from_millis(1000);
+        let tx = chain.sign_invoke_tx(transfer_tx(contract_0, contract_1), contract_0);
+        chain.mempool.accept_tx_until(BroadcastedTxn::Invoke(tx.clone()), SystemTime::now() + deadline_in).unwrap();
+        // Submitted again without a deadline, the saved transaction is not overwritten.
+        assert_eq!(chain.mempool.accept_invoke_tx(tx).unwrap().outcome, outcome);
+        std::thread::sleep(deadline_in); // deadline passed
+
+        let mempool = restart_mempool(&chain, MempoolLimits::for_testing());
+
+        assert!(mempool.is_empty());
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 0);
+    }
+
+    #[rstest]
+    fn test_block_production_removes_expired_txs_from_db(mut chain: DevnetForTesting) {
+        let contract_0 = &chain.contracts.0[0];
+        let contract_1 = &chain.contracts.0[1];
+
+        let deadline_in = Duration::from_millis(1000);
+        let tx = chain.sign_invoke_tx(transfer_tx(contract_0, contract_1), contract_0);
+        chain.mempool.accept_tx_until(BroadcastedTxn::Invoke(tx), SystemTime::now() + deadline_in).unwrap();
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 1);
+        std::thread::sleep(deadline_in); // deadline passed
+
+        chain.block_production.set_current_pending_tick(1);
+        chain.block_production.on_pending_time_tick().unwrap();
+
+        let block = chain.backend.get_block(&BlockId::Tag(BlockTag::Pending)).unwrap().unwrap();
+        assert_eq!(block.inner.transactions, vec![]);
+        assert!(chain.mempool.is_empty());
+        assert_eq!(chain.backend.get_mempool_transactions().count(), 0);
+    }
 }
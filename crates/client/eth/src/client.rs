@@ -1,30 +1,59 @@
 use crate::client::StarknetCoreContract::StarknetCoreContractInstance;
+use crate::state_submission::{state_update_submissions, StateUpdateSubmission};
 use crate::utils::u256_to_felt;
+use alloy::eips::{BlockId, BlockNumberOrTag};
 use alloy::sol_types::SolEvent;
 use alloy::{
     primitives::Address,
-    providers::{Provider, ProviderBuilder, ReqwestProvider, RootProvider},
+    providers::{Provider, ProviderBuilder, ReqwestProvider, RootProvider, WsConnect},
+    rpc::client::RpcClient,
     rpc::types::Filter,
     sol,
     transports::http::{Client, Http},
 };
-use mc_analytics::register_gauge_metric_instrument;
+use mc_analytics::{register_counter_metric_instrument, register_gauge_metric_instrument};
+use mp_chain_config::{L1CoreAddress, L1Endpoints};
 use opentelemetry::{global, KeyValue};
-use opentelemetry::{global::Error, metrics::Gauge};
+use opentelemetry::{
+    global::Error,
+    metrics::{Counter, Gauge},
+};
 
 use anyhow::{bail, Context};
 use bitvec::macros::internal::funty::Fundamental;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
 use starknet_types_core::felt::Felt;
+use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 use url::Url;
 
+/// Backoff before retrying all the L1 endpoints again, doubled after every failed round.
+const FAILOVER_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const FAILOVER_MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Number of rounds over all the L1 endpoints before giving up on failover.
+const FAILOVER_MAX_ROUNDS: u32 = 5;
+
 #[derive(Clone, Debug)]
 pub struct L1BlockMetrics {
     // L1 network metrics
     pub l1_block_number: Gauge<u64>,
     // gas price is also define in sync/metrics/block_metrics.rs but this would be the price from l1
     pub l1_gas_price_wei: Gauge<u64>,
+    pub l1_gas_price_raw_wei: Gauge<u64>,
     pub l1_gas_price_strk: Gauge<f64>,
+    /// Fetched gas prices clamped to the configured bounds, with the clamped price as the `price` attribute.
+    pub l1_gas_price_clamped: Counter<u64>,
+    // L1 endpoint failover
+    pub l1_active_endpoint: Gauge<u64>,
+    pub l1_endpoint_failovers: Counter<u64>,
+    // L1 sync progress
+    pub l1_latest_block_seen: Gauge<u64>,
+    pub l1_latest_block_processed: Gauge<u64>,
+    pub l1_sync_lag: Gauge<u64>,
+    pub l1_rpc_errors: Counter<u64>,
 }
 
 impl L1BlockMetrics {
@@ -51,6 +80,13 @@ impl L1BlockMetrics {
             "".to_string(),
         );
 
+        let l1_gas_price_raw_wei = register_gauge_metric_instrument(
+            &eth_meter,
+            "l1_gas_price_raw_wei".to_string(),
+            "Gauge for madara L1 gas price in wei, before smoothing".to_string(),
+            "".to_string(),
+        );
+
         let l1_gas_price_strk = register_gauge_metric_instrument(
             &eth_meter,
             "l1_gas_price_strk".to_string(),
@@ -58,7 +94,76 @@ impl L1BlockMetrics {
             "".to_string(),
         );
 
-        Ok(Self { l1_block_number, l1_gas_price_wei, l1_gas_price_strk })
+        let l1_gas_price_clamped = register_counter_metric_instrument(
+            &eth_meter,
+            "l1_gas_price_clamped".to_string(),
+            "A counter to show fetched L1 gas prices clamped to the configured bounds".to_string(),
+            "".to_string(),
+        );
+
+        let l1_active_endpoint = register_gauge_metric_instrument(
+            &eth_meter,
+            "l1_active_endpoint".to_string(),
+            "Gauge for the index of the L1 endpoint currently in use".to_string(),
+            "".to_string(),
+        );
+
+        let l1_endpoint_failovers = register_counter_metric_instrument(
+            &eth_meter,
+            "l1_endpoint_failovers".to_string(),
+            "A counter to show failovers to another L1 endpoint".to_string(),
+            "".to_string(),
+        );
+
+        let l1_latest_block_seen = register_gauge_metric_instrument(
+            &eth_meter,
+            "l1_latest_block_seen".to_string(),
+            "Gauge for the latest L1 block number seen by the L1 sync".to_string(),
+            "".to_string(),
+        );
+
+        let l1_latest_block_processed = register_gauge_metric_instrument(
+            &eth_meter,
+            "l1_latest_block_processed".to_string(),
+            "Gauge for the latest L1 block number processed by the L1 sync".to_string(),
+            "".to_string(),
+        );
+
+        let l1_sync_lag = register_gauge_metric_instrument(
+            &eth_meter,
+            "l1_sync_lag".to_string(),
+            "Gauge for the number of L1 blocks seen but not processed yet by the L1 sync".to_string(),
+            "".to_string(),
+        );
+
+        let l1_rpc_errors = register_counter_metric_instrument(
+            &eth_meter,
+            "l1_rpc_errors".to_string(),
+            "A counter to show failed L1 RPC requests".to_string(),
+            "".to_string(),
+        );
+
+        Ok(Self {
+            l1_block_number,
+            l1_gas_price_wei,
+            l1_gas_price_raw_wei,
+            l1_gas_price_strk,
+            l1_gas_price_clamped,
+            l1_active_endpoint,
+            l1_endpoint_failovers,
+            l1_latest_block_seen,
+            l1_latest_block_processed,
+            l1_sync_lag,
+            l1_rpc_errors,
+        })
+    }
+
+    /// Records the progress of the L1 sync: the latest L1 block seen, the latest one processed, and the lag between
+    /// them.
+    pub fn record_sync_progress(&self, latest_block_seen: u64, latest_block_processed: u64) {
+        self.l1_latest_block_seen.record(latest_block_seen, &[]);
+        self.l1_latest_block_processed.record(latest_block_processed, &[]);
+        self.l1_sync_lag.record(latest_block_seen.saturating_sub(latest_block_processed), &[]);
     }
 }
 
@@ -71,10 +176,103 @@ sol!(
     "src/abis/starknet_core.json"
 );
 
+sol!(
+    /// L1 oracle contract the L1 gas price is read from, see
+    /// [`L1GasPriceSource::OracleContract`](mc_mempool::L1GasPriceSource::OracleContract).
+    #[sol(rpc)]
+    #[derive(Debug)]
+    interface L1GasPriceOracle {
+        /// The L1 gas price, in wei.
+        function gasPrice() external view returns (uint256);
+    }
+);
+
+/// HTTP headers sent with every request to the L1 endpoints, such as the API key or the bearer token required by
+/// managed RPC providers. The header values are secrets: they are never logged.
+#[derive(Clone, Default)]
+pub struct L1EndpointHeaders(HeaderMap);
+
+impl L1EndpointHeaders {
+    /// `bearer_token` is sent as an `Authorization: Bearer <token>` header, it replaces any `Authorization` header of
+    /// `headers`.
+    pub fn new(
+        headers: impl IntoIterator<Item = (String, String)>,
+        bearer_token: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            let name =
+                HeaderName::try_from(&name).with_context(|| format!("Invalid L1 endpoint header name {name:?}"))?;
+            let value =
+                secret_header_value(value).with_context(|| format!("Invalid value for L1 endpoint header {name}"))?;
+            map.append(name, value);
+        }
+        if let Some(token) = bearer_token {
+            map.insert(AUTHORIZATION, secret_header_value(format!("Bearer {token}")).context("Invalid bearer token")?);
+        }
+        Ok(Self(map))
+    }
+}
+
+fn secret_header_value(value: String) -> anyhow::Result<HeaderValue> {
+    let mut value = HeaderValue::try_from(value)?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+impl fmt::Debug for L1EndpointHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only the names of the headers are shown.
+        f.debug_list().entries(self.0.keys()).finish()
+    }
+}
+
+/// The L1 block the state verification is based on. The later the block, the sooner the L2 state is confirmed, but the
+/// more likely the confirmation is reverted by an L1 reorg.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum L1BlockTag {
+    /// The head of the L1 chain. The state updates are still only used after `l1_confirmations` blocks.
+    #[default]
+    Latest,
+    /// The most recent block which is justified by the beacon chain.
+    Safe,
+    /// The most recent finalized block, which cannot be reorged. It lags about two epochs, ~13 minutes, behind the
+    /// head.
+    Finalized,
+}
+
+impl From<L1BlockTag> for BlockNumberOrTag {
+    fn from(tag: L1BlockTag) -> Self {
+        match tag {
+            L1BlockTag::Latest => BlockNumberOrTag::Latest,
+            L1BlockTag::Safe => BlockNumberOrTag::Safe,
+            L1BlockTag::Finalized => BlockNumberOrTag::Finalized,
+        }
+    }
+}
+
 pub struct EthereumClient {
     pub provider: Arc<ReqwestProvider>,
     pub l1_core_contract: StarknetCoreContractInstance<Http<Client>, RootProvider<Http<Client>>>,
     pub l1_block_metrics: L1BlockMetrics,
+    /// Address `l1_core_contract` should be at, it is reloaded with [`EthereumClient::reload_core_contract`] when this
+    /// changes.
+    pub(crate) core_address: L1CoreAddress,
+    /// All the L1 RPC endpoints, tried in order on failover.
+    pub(crate) endpoints: Arc<[Url]>,
+    /// Endpoints to rotate to, the L1 sync connects to them with [`EthereumClient::connect_to`] when they change.
+    pub(crate) l1_endpoints: L1Endpoints,
+    /// Index of the endpoint `provider` is connected to.
+    pub(crate) active_endpoint: usize,
+    /// Sent to every endpoint.
+    pub(crate) headers: L1EndpointHeaders,
+    /// Timeout of every request to the endpoints, so that a slow endpoint cannot hang the L1 sync. A request which
+    /// times out fails like any other, and counts towards the L1 circuit breaker. `None` disables it.
+    pub(crate) request_timeout: Option<Duration>,
+    /// The L1 block the state verification is based on.
+    pub(crate) block_tag: L1BlockTag,
+    /// Websocket endpoint pushing the new L1 heads. The L1 head is polled when unset.
+    pub(crate) head_subscription: Option<Url>,
 }
 
 impl Clone for EthereumClient {
@@ -83,20 +281,229 @@ impl Clone for EthereumClient {
             provider: Arc::clone(&self.provider),
             l1_core_contract: self.l1_core_contract.clone(),
             l1_block_metrics: self.l1_block_metrics.clone(),
+            core_address: self.core_address.clone(),
+            endpoints: Arc::clone(&self.endpoints),
+            l1_endpoints: self.l1_endpoints.clone(),
+            active_endpoint: self.active_endpoint,
+            headers: self.headers.clone(),
+            request_timeout: self.request_timeout,
+            block_tag: self.block_tag,
+            head_subscription: self.head_subscription.clone(),
         }
     }
 }
 
 impl EthereumClient {
-    /// Create a new EthereumClient instance with the given RPC URL
-    pub async fn new(url: Url, l1_core_address: Address, l1_block_metrics: L1BlockMetrics) -> anyhow::Result<Self> {
-        let provider = ProviderBuilder::new().on_http(url);
-
-        EthereumClient::assert_core_contract_exists(&provider, l1_core_address).await?;
+    /// Create a new EthereumClient instance with the given RPC URLs. The first endpoint that works is used, the other
+    /// ones are kept for failover. The `headers` are sent to all of them, and each request fails after
+    /// `request_timeout`.
+    pub async fn new(
+        urls: Vec<Url>,
+        headers: L1EndpointHeaders,
+        request_timeout: Option<Duration>,
+        l1_core_address: Address,
+        l1_block_metrics: L1BlockMetrics,
+    ) -> anyhow::Result<Self> {
+        if urls.is_empty() {
+            bail!("No L1 endpoint provided");
+        }
+        let endpoints: Arc<[Url]> = urls.into();
+        // Fail fast on startup, misconfigured endpoints should be reported right away.
+        let (active_endpoint, provider) =
+            Self::connect(&endpoints, &headers, request_timeout, 0, l1_core_address, 1).await?;
+        l1_block_metrics.l1_active_endpoint.record(active_endpoint as u64, &[]);
 
         let core_contract = StarknetCoreContract::new(l1_core_address, provider.clone());
 
-        Ok(Self { provider: Arc::new(provider), l1_core_contract: core_contract, l1_block_metrics })
+        Ok(Self {
+            provider: Arc::new(provider),
+            l1_core_contract: core_contract,
+            l1_block_metrics,
+            core_address: L1CoreAddress::new(l1_core_address.0 .0.into()),
+            l1_endpoints: L1Endpoints::new(endpoints.to_vec()),
+            endpoints,
+            active_endpoint,
+            headers,
+            request_timeout,
+            block_tag: L1BlockTag::default(),
+            head_subscription: None,
+        })
+    }
+
+    /// Base the state verification on the L1 block with this tag, the latest one by default.
+    pub fn with_block_tag(mut self, block_tag: L1BlockTag) -> Self {
+        self.block_tag = block_tag;
+        self
+    }
+
+    /// Watch the L1 core contract at the address of this handle, instead of the address the client was created
+    /// with. The handle can be shared to change the address at runtime.
+    pub fn with_core_address(mut self, core_address: L1CoreAddress) -> Self {
+        self.core_address = core_address;
+        self
+    }
+
+    /// Handle on the address of the watched L1 core contract.
+    pub fn core_address(&self) -> L1CoreAddress {
+        self.core_address.clone()
+    }
+
+    /// Handle on the L1 endpoints, to rotate them at runtime.
+    pub fn l1_endpoints(&self) -> L1Endpoints {
+        self.l1_endpoints.clone()
+    }
+
+    /// Subscribe to the new L1 heads on the websocket endpoint `ws_endpoint`, instead of polling the L1 head. The L1
+    /// head is still polled when the subscription cannot be established, such as when the endpoint does not support
+    /// `eth_subscribe`.
+    pub fn with_head_subscription(mut self, ws_endpoint: Url) -> Self {
+        self.head_subscription = Some(ws_endpoint);
+        self
+    }
+
+    /// Subscribes to the new L1 heads on the websocket endpoint `ws_endpoint`, and yields their block numbers.
+    pub async fn subscribe_heads(ws_endpoint: &Url) -> anyhow::Result<BoxStream<'static, u64>> {
+        let provider = ProviderBuilder::new()
+            .on_ws(WsConnect::new(ws_endpoint.as_str()))
+            .await
+            .with_context(|| format!("Connecting to L1 websocket endpoint {}", redact_url(ws_endpoint)))?;
+        let subscription = provider.subscribe_blocks().await.context("Subscribing to the new L1 heads")?;
+        // The subscription ends once the provider is dropped, it is moved into the stream.
+        Ok(subscription
+            .into_stream()
+            .map(move |block| {
+                let _ = &provider;
+                block.header.number
+            })
+            .boxed())
+    }
+
+    /// Whether there is another endpoint to fail over to.
+    pub fn has_failover_endpoint(&self) -> bool {
+        self.endpoints.len() > 1
+    }
+
+    /// Switch to the next working L1 endpoint, after the active one has failed.
+    pub async fn failover(&mut self) -> anyhow::Result<()> {
+        let l1_core_address = Address::from_slice(self.core_address.get().as_bytes());
+        let from = (self.active_endpoint + 1) % self.endpoints.len();
+        let (active_endpoint, provider) = Self::connect(
+            &self.endpoints,
+            &self.headers,
+            self.request_timeout,
+            from,
+            l1_core_address,
+            FAILOVER_MAX_ROUNDS,
+        )
+        .await?;
+        tracing::warn!(
+            "L1 endpoint failover from {} to {}",
+            redact_url(&self.endpoints[self.active_endpoint]),
+            redact_url(&self.endpoints[active_endpoint])
+        );
+
+        self.l1_core_contract = StarknetCoreContract::new(l1_core_address, provider.clone());
+        self.provider = Arc::new(provider);
+        self.active_endpoint = active_endpoint;
+        self.l1_block_metrics.l1_active_endpoint.record(active_endpoint as u64, &[]);
+        self.l1_block_metrics.l1_endpoint_failovers.add(1, &[]);
+        Ok(())
+    }
+
+    /// Reconnect to the L1 after the active endpoint has failed. This fails over to the next working endpoint when
+    /// there are several of them, see [`EthereumClient::failover`], and reconnects to the only endpoint otherwise.
+    pub async fn reconnect(&mut self) -> anyhow::Result<()> {
+        if self.has_failover_endpoint() {
+            return self.failover().await;
+        }
+        let l1_core_address = Address::from_slice(self.core_address.get().as_bytes());
+        // The caller retries with its own backoff.
+        let (_, provider) =
+            Self::connect(&self.endpoints, &self.headers, self.request_timeout, 0, l1_core_address, 1).await?;
+
+        self.l1_core_contract = StarknetCoreContract::new(l1_core_address, provider.clone());
+        self.provider = Arc::new(provider);
+        Ok(())
+    }
+
+    /// A client for the endpoints `urls`, with the settings of this one, which is left untouched. Like
+    /// [`EthereumClient::new`], the first endpoint that works is used and the other ones are kept for failover.
+    pub async fn connect_to(&self, urls: Vec<Url>) -> anyhow::Result<Self> {
+        if urls.is_empty() {
+            bail!("No L1 endpoint provided");
+        }
+        let l1_core_address = Address::from_slice(self.core_address.get().as_bytes());
+        let endpoints: Arc<[Url]> = urls.into();
+        // The current client is kept when the new endpoints do not work, there is no need to wait for them.
+        let (active_endpoint, provider) =
+            Self::connect(&endpoints, &self.headers, self.request_timeout, 0, l1_core_address, 1).await?;
+
+        Ok(Self {
+            l1_core_contract: StarknetCoreContract::new(l1_core_address, provider.clone()),
+            provider: Arc::new(provider),
+            endpoints,
+            active_endpoint,
+            ..self.clone()
+        })
+    }
+
+    /// A client connected to the current endpoints of [`EthereumClient::l1_endpoints`], watching the L1 core contract
+    /// at the current address of [`EthereumClient::core_address`], for when they may have been changed since this one
+    /// was created. This one is returned as is when neither changed.
+    pub async fn refreshed(&self) -> anyhow::Result<Self> {
+        let urls = self.l1_endpoints.get();
+        let l1_core_address = Address::from_slice(self.core_address.get().as_bytes());
+        if *self.endpoints == urls[..] && *self.l1_core_contract.address() == l1_core_address {
+            return Ok(self.clone());
+        }
+        self.connect_to(urls).await
+    }
+
+    /// Watch the L1 core contract at the current address of [`EthereumClient::core_address`], on the active endpoint.
+    /// The event filters created afterwards are for the new address.
+    pub async fn reload_core_contract(&mut self) -> anyhow::Result<()> {
+        let l1_core_address = Address::from_slice(self.core_address.get().as_bytes());
+        Self::assert_core_contract_exists(&self.provider, l1_core_address)
+            .await
+            .with_context(|| format!("Watching the L1 core contract at {l1_core_address}"))?;
+        self.l1_core_contract = StarknetCoreContract::new(l1_core_address, (*self.provider).clone());
+        Ok(())
+    }
+
+    /// Connect to the first working endpoint, starting from the one at index `from`. When none of the endpoints
+    /// work, this is retried with exponential backoff, up to `max_rounds` times.
+    async fn connect(
+        endpoints: &[Url],
+        headers: &L1EndpointHeaders,
+        request_timeout: Option<Duration>,
+        from: usize,
+        l1_core_address: Address,
+        max_rounds: u32,
+    ) -> anyhow::Result<(usize, RootProvider<Http<Client>>)> {
+        let mut http_client = Client::builder().default_headers(headers.0.clone());
+        if let Some(request_timeout) = request_timeout {
+            http_client = http_client.timeout(request_timeout);
+        }
+        let http_client = http_client.build().context("Creating the L1 HTTP client")?;
+        let mut backoff = FAILOVER_INITIAL_BACKOFF;
+        for round in 1..=max_rounds {
+            for index in (0..endpoints.len()).map(|i| (from + i) % endpoints.len()) {
+                let transport = Http::with_client(http_client.clone(), endpoints[index].clone());
+                let is_local = transport.guess_local();
+                let provider = ProviderBuilder::new().on_client(RpcClient::new(transport, is_local));
+                match EthereumClient::assert_core_contract_exists(&provider, l1_core_address).await {
+                    Ok(()) => return Ok((index, provider)),
+                    Err(err) => {
+                        tracing::warn!("L1 endpoint {} is not usable: {err:#}", redact_url(&endpoints[index]))
+                    }
+                }
+            }
+            if round < max_rounds {
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(FAILOVER_MAX_BACKOFF);
+            }
+        }
+        bail!("None of the L1 endpoints are usable after {max_rounds} attempt(s)")
     }
 
     /// Assert that L1 Core contract exists by checking its bytecode.
@@ -111,12 +518,77 @@ impl EthereumClient {
         Ok(())
     }
 
+    /// Retrieves the chain id of the L1 network
+    pub async fn get_l1_chain_id(&self) -> anyhow::Result<u64> {
+        Ok(self.provider.get_chain_id().await?)
+    }
+
     /// Retrieves the latest Ethereum block number
     pub async fn get_latest_block_number(&self) -> anyhow::Result<u64> {
         let block_number = self.provider.get_block_number().await?.as_u64();
         Ok(block_number)
     }
 
+    /// Retrieves the latest finalized Ethereum block number
+    pub async fn get_finalized_block_number(&self) -> anyhow::Result<u64> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Finalized, false)
+            .await?
+            .context("No finalized L1 block")?;
+        Ok(block.header.number)
+    }
+
+    /// Retrieves the number of the L1 block the state verification is based on, see [`L1BlockTag`].
+    pub async fn get_verification_block_number(&self) -> anyhow::Result<u64> {
+        let block = self
+            .provider
+            .get_block_by_number(self.block_tag.into(), false)
+            .await?
+            .with_context(|| format!("No {:?} L1 block", self.block_tag))?;
+        Ok(block.header.number)
+    }
+
+    /// Get the base fee of the L1 block after `block_number`, and the `percentile`th percentile of the priority fees of
+    /// each of the `block_count` L1 blocks up to `block_number`, with `eth_feeHistory`.
+    pub async fn get_fee_history(
+        &self,
+        block_count: u64,
+        block_number: u64,
+        percentile: f64,
+    ) -> anyhow::Result<(u128, Vec<u128>)> {
+        let fee_history =
+            self.provider.get_fee_history(block_count, BlockNumberOrTag::Number(block_number), &[percentile]).await?;
+        let base_fee = *fee_history.base_fee_per_gas.last().context("Fee history without a base fee")?;
+        let priority_fees =
+            fee_history.reward.unwrap_or_default().into_iter().filter_map(|rewards| rewards.first().copied()).collect();
+        Ok((base_fee, priority_fees))
+    }
+
+    /// Get the L1 gas price, in wei, returned by the `gasPrice()` view function of the L1 oracle contract at `oracle`.
+    pub async fn get_oracle_gas_price(&self, oracle: Address) -> anyhow::Result<u128> {
+        let oracle = L1GasPriceOracle::new(oracle, (*self.provider).clone());
+        let gas_price = oracle.gasPrice().call().await?._0;
+        gas_price.try_into().context("Oracle gas price does not fit in a u128")
+    }
+
+    /// Get the hash of the L1 block with this number, `None` if the L1 does not have such a block.
+    pub async fn get_block_hash(&self, block_number: u64) -> anyhow::Result<Option<[u8; 32]>> {
+        let block = self.provider.get_block_by_number(BlockNumberOrTag::Number(block_number), false).await?;
+        Ok(block.map(|block| block.header.hash.0))
+    }
+
+    /// Get the state update submissions of the L1 block with this number, whether they post the state diff as calldata
+    /// or in blobs.
+    pub async fn get_state_update_submissions(&self, block_number: u64) -> anyhow::Result<Vec<StateUpdateSubmission>> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number), true)
+            .await?
+            .with_context(|| format!("L1 block #{block_number} not found"))?;
+        state_update_submissions(&block, *self.l1_core_contract.address())
+    }
+
     /// Get the block number of the last occurrence of a given event.
     pub async fn get_last_event_block_number<T: SolEvent>(&self) -> anyhow::Result<u64> {
         let latest_block: u64 = self.get_latest_block_number().await?;
@@ -139,26 +611,31 @@ impl EthereumClient {
         }
     }
 
-    /// Get the last Starknet block number verified on L1
-    pub async fn get_last_verified_block_number(&self) -> anyhow::Result<u64> {
-        let block_number = self.l1_core_contract.stateBlockNumber().call().await?;
+    /// Get the last Starknet block number verified on L1, as of the L1 block `at`
+    pub async fn get_last_verified_block_number(&self, at: BlockId) -> anyhow::Result<u64> {
+        let block_number = self.l1_core_contract.stateBlockNumber().block(at).call().await?;
         let last_block_number: u64 = (block_number._0).as_u64();
         Ok(last_block_number)
     }
 
-    /// Get the last Starknet state root verified on L1
-    pub async fn get_last_state_root(&self) -> anyhow::Result<Felt> {
-        let state_root = self.l1_core_contract.stateRoot().call().await?;
+    /// Get the last Starknet state root verified on L1, as of the L1 block `at`
+    pub async fn get_last_state_root(&self, at: BlockId) -> anyhow::Result<Felt> {
+        let state_root = self.l1_core_contract.stateRoot().block(at).call().await?;
         u256_to_felt(state_root._0)
     }
 
-    /// Get the last Starknet block hash verified on L1
-    pub async fn get_last_verified_block_hash(&self) -> anyhow::Result<Felt> {
-        let block_hash = self.l1_core_contract.stateBlockHash().call().await?;
+    /// Get the last Starknet block hash verified on L1, as of the L1 block `at`
+    pub async fn get_last_verified_block_hash(&self, at: BlockId) -> anyhow::Result<Felt> {
+        let block_hash = self.l1_core_contract.stateBlockHash().block(at).call().await?;
         u256_to_felt(block_hash._0)
     }
 }
 
+/// Endpoint URLs often contain an API key, only log the host.
+pub(crate) fn redact_url(url: &Url) -> &str {
+    url.host_str().unwrap_or("<unknown host>")
+}
+
 #[cfg(test)]
 pub mod eth_client_getter_test {
     use super::*;
@@ -242,7 +719,19 @@ pub mod eth_client_getter_test {
 
         let l1_block_metrics = L1BlockMetrics::register().unwrap();
 
-        EthereumClient { provider: Arc::new(provider), l1_core_contract: contract.clone(), l1_block_metrics }
+        EthereumClient {
+            provider: Arc::new(provider),
+            l1_core_contract: contract.clone(),
+            l1_block_metrics,
+            core_address: L1CoreAddress::new(address.0 .0.into()),
+            l1_endpoints: L1Endpoints::new(vec![rpc_url.clone()]),
+            endpoints: [rpc_url].into(),
+            active_endpoint: 0,
+            headers: Default::default(),
+            request_timeout: None,
+            block_tag: Default::default(),
+            head_subscription: None,
+        }
     }
 
     #[serial]
@@ -257,10 +746,174 @@ pub mod eth_client_getter_test {
         let core_contract_address = Address::parse_checksummed(INVALID_CORE_CONTRACT_ADDRESS, None).unwrap();
         let l1_block_metrics = L1BlockMetrics::register().unwrap();
 
-        let new_client_result = EthereumClient::new(rpc_url, core_contract_address, l1_block_metrics).await;
+        let new_client_result =
+            EthereumClient::new(vec![rpc_url], Default::default(), None, core_contract_address, l1_block_metrics).await;
         assert!(new_client_result.is_err(), "EthereumClient::new should fail with an invalid core contract address");
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn create_new_client_skips_unusable_endpoint() {
+        let anvil = get_shared_anvil();
+        // Nothing listens on this port.
+        let unusable_rpc_url: Url = "http://127.0.0.1:1".parse().unwrap();
+
+        let core_contract_address = Address::parse_checksummed(CORE_CONTRACT_ADDRESS, None).unwrap();
+        let l1_block_metrics = L1BlockMetrics::register().unwrap();
+
+        let eth_client = EthereumClient::new(
+            vec![unusable_rpc_url, anvil.endpoint_url()],
+            Default::default(),
+            None,
+            core_contract_address,
+            l1_block_metrics,
+        )
+        .await
+        .expect("EthereumClient::new should fall back to the second endpoint");
+        assert_eq!(eth_client.active_endpoint, 1);
+        assert!(eth_client.has_failover_endpoint());
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn reconnect_fails_over_to_the_next_endpoint() {
+        let anvil = get_shared_anvil();
+        let core_contract_address = Address::parse_checksummed(CORE_CONTRACT_ADDRESS, None).unwrap();
+        let l1_block_metrics = L1BlockMetrics::register().unwrap();
+
+        let mut eth_client = EthereumClient::new(
+            vec![anvil.endpoint_url(), anvil.endpoint_url()],
+            Default::default(),
+            None,
+            core_contract_address,
+            l1_block_metrics,
+        )
+        .await
+        .unwrap();
+        assert_eq!(eth_client.active_endpoint, 0);
+
+        eth_client.reconnect().await.expect("Reconnecting should fail over to the second endpoint");
+        assert_eq!(eth_client.active_endpoint, 1);
+        eth_client.get_latest_block_number().await.expect("The client should work on the second endpoint");
+    }
+
+    #[tokio::test]
+    async fn create_new_client_sends_endpoint_headers() {
+        let mock_server = httpmock::MockServer::start();
+        let mock = mock_server.mock(|when, then| {
+            when.method("POST")
+                .path("/")
+                .header("x-api-key", "secret-key")
+                .header("authorization", "Bearer secret-token")
+                .body_contains("eth_getCode");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x6080"}));
+        });
+
+        let api_key = ("x-api-key".to_string(), "secret-key".to_string());
+        let headers = L1EndpointHeaders::new([api_key], Some("secret-token")).unwrap();
+        let core_contract_address = Address::parse_checksummed(CORE_CONTRACT_ADDRESS, None).unwrap();
+        let l1_block_metrics = L1BlockMetrics::register().unwrap();
+
+        let rpc_url: Url = mock_server.url("/").parse().unwrap();
+        EthereumClient::new(vec![rpc_url], headers, None, core_contract_address, l1_block_metrics)
+            .await
+            .expect("The endpoint should accept the request with the headers");
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn request_past_timeout_fails_promptly() {
+        let mock_server = httpmock::MockServer::start();
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_getCode");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x6080"}));
+        });
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_blockNumber");
+            then.status(200)
+                .delay(Duration::from_secs(10))
+                .json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":1,"result":"0x1"}));
+        });
+
+        let core_contract_address = Address::parse_checksummed(CORE_CONTRACT_ADDRESS, None).unwrap();
+        let l1_block_metrics = L1BlockMetrics::register().unwrap();
+        let rpc_url: Url = mock_server.url("/").parse().unwrap();
+        let request_timeout = Some(Duration::from_millis(200));
+        let eth_client = EthereumClient::new(
+            vec![rpc_url],
+            Default::default(),
+            request_timeout,
+            core_contract_address,
+            l1_block_metrics,
+        )
+        .await
+        .expect("The endpoint answers the core contract check in time");
+
+        let start = std::time::Instant::now();
+        assert!(eth_client.get_latest_block_number().await.is_err(), "The request should time out");
+        assert!(start.elapsed() < Duration::from_secs(5), "The request took {:?} to fail", start.elapsed());
+    }
+
+    /// A block as returned by `eth_getBlockByNumber`.
+    pub fn block_json(number: u64) -> serde_json::Value {
+        let zero_hash = format!("0x{}", "0".repeat(64));
+        serde_json::json!({
+            "hash": format!("0x{}", "1".repeat(64)),
+            "parentHash": zero_hash,
+            "sha3Uncles": zero_hash,
+            "miner": format!("0x{}", "0".repeat(40)),
+            "stateRoot": zero_hash,
+            "transactionsRoot": zero_hash,
+            "receiptsRoot": zero_hash,
+            "logsBloom": format!("0x{}", "0".repeat(512)),
+            "difficulty": "0x0",
+            "number": format!("{number:#x}"),
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x66a0f6a3",
+            "extraData": "0x",
+            "mixHash": zero_hash,
+            "nonce": "0x0000000000000000",
+            "baseFeePerGas": "0x1",
+            "uncles": [],
+            "transactions": [],
+        })
+    }
+
+    #[rstest::rstest]
+    #[case::latest(L1BlockTag::Latest, "latest")]
+    #[case::safe(L1BlockTag::Safe, "safe")]
+    #[case::finalized(L1BlockTag::Finalized, "finalized")]
+    #[tokio::test]
+    async fn verification_block_number_requests_block_tag(#[case] block_tag: L1BlockTag, #[case] expected: &str) {
+        let mock_server = httpmock::MockServer::start();
+        let response = serde_json::json!({"jsonrpc":"2.0","id":0,"result":block_json(L1_BLOCK_NUMBER)});
+        let mock = mock_server.mock(|when, then| {
+            when.method("POST")
+                .path("/")
+                .body_contains("eth_getBlockByNumber")
+                .body_contains(format!("[\"{expected}\",false]"));
+            then.status(200).json_body_obj(&response);
+        });
+
+        let eth_client = create_ethereum_client(Some(&mock_server.url("/"))).with_block_tag(block_tag);
+        let block_number =
+            eth_client.get_verification_block_number().await.expect("The block with the tag should be requested");
+        assert_eq!(block_number, L1_BLOCK_NUMBER);
+        mock.assert();
+    }
+
+    #[test]
+    fn endpoint_headers_are_masked() {
+        let api_key = ("x-api-key".to_string(), "secret-key".to_string());
+        let headers = L1EndpointHeaders::new([api_key], Some("secret-token")).unwrap();
+        let debug = format!("{headers:?}");
+        assert!(debug.contains("x-api-key") && debug.contains("authorization"));
+        assert!(!debug.contains("secret"));
+
+        assert!(L1EndpointHeaders::new([("invalid name".to_string(), "value".to_string())], None).is_err());
+    }
+
     #[serial]
     #[tokio::test]
     async fn get_latest_block_number_works() {
@@ -288,8 +941,10 @@ pub mod eth_client_getter_test {
     async fn get_last_verified_block_hash_works() {
         let anvil = get_shared_anvil();
         let eth_client = create_ethereum_client(Some(anvil.endpoint().as_str()));
-        let block_hash =
-            eth_client.get_last_verified_block_hash().await.expect("issue while getting the last verified block hash");
+        let block_hash = eth_client
+            .get_last_verified_block_hash(BlockId::latest())
+            .await
+            .expect("issue while getting the last verified block hash");
         let expected = u256_to_felt(U256::from_str_radix(L2_BLOCK_HASH, 10).unwrap()).unwrap();
         assert_eq!(block_hash, expected, "latest block hash not matching");
     }
@@ -299,7 +954,8 @@ pub mod eth_client_getter_test {
     async fn get_last_state_root_works() {
         let anvil = get_shared_anvil();
         let eth_client = create_ethereum_client(Some(anvil.endpoint().as_str()));
-        let state_root = eth_client.get_last_state_root().await.expect("issue while getting the state root");
+        let state_root =
+            eth_client.get_last_state_root(BlockId::latest()).await.expect("issue while getting the state root");
         let expected = u256_to_felt(U256::from_str_radix(L2_STATE_ROOT, 10).unwrap()).unwrap();
         assert_eq!(state_root, expected, "latest block state root not matching");
     }
@@ -309,7 +965,7 @@ pub mod eth_client_getter_test {
     async fn get_last_verified_block_number_works() {
         let anvil = get_shared_anvil();
         let eth_client = create_ethereum_client(Some(anvil.endpoint().as_str()));
-        let block_number = eth_client.get_last_verified_block_number().await.expect("issue");
+        let block_number = eth_client.get_last_verified_block_number(BlockId::latest()).await.expect("issue");
         assert_eq!(block_number, L2_BLOCK_NUMBER, "verified block number not matching");
     }
 }
@@ -1,10 +1,15 @@
 use crate::client::EthereumClient;
+use crate::sync::{iteration_span, record_block_range};
 use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::Address;
 use alloy::providers::Provider;
 use anyhow::Context;
 use bigdecimal::BigDecimal;
-use mc_mempool::{GasPriceProvider, L1DataProvider};
+use mc_mempool::{GasPriceProvider, L1DataProvider, L1GasPriceSource};
+use opentelemetry::KeyValue;
+use rand::Rng;
 use std::time::{Duration, UNIX_EPOCH};
+use tracing::Instrument;
 
 use mp_utils::{service::ServiceContext, wait_or_graceful_shutdown};
 use std::time::SystemTime;
@@ -14,92 +19,202 @@ pub async fn gas_price_worker_once(
     l1_gas_provider: GasPriceProvider,
     gas_price_poll_ms: Duration,
 ) -> anyhow::Result<()> {
-    match update_gas_price(eth_client, l1_gas_provider.clone()).await {
-        Ok(_) => tracing::trace!("Updated gas prices"),
-        Err(e) => tracing::error!("Failed to update gas prices: {:?}", e),
+    // The L1 gas and L1 data gas prices come from different sources: one failing to update does not prevent the other
+    // from being updated.
+    let eth_strk_price = fetch_eth_strk_price(&l1_gas_provider).await;
+    match update_l1_gas_price(eth_client, &l1_gas_provider, &eth_strk_price).await {
+        Ok(_) => tracing::trace!("Updated L1 gas prices"),
+        Err(e) => tracing::error!("Failed to update L1 gas prices: {:?}", e),
+    }
+    match update_l1_data_gas_price(eth_client, &l1_gas_provider, &eth_strk_price).await {
+        Ok(_) => tracing::trace!("Updated L1 data gas prices"),
+        Err(e) => tracing::error!("Failed to update L1 data gas prices: {:?}", e),
+    }
+    // Update block number separately to avoid holding the lock for too long
+    if let Err(e) = update_l1_block_metrics(eth_client, l1_gas_provider.clone()).await {
+        tracing::error!("Failed to update L1 block metrics: {:?}", e);
     }
 
-    let last_update_timestamp = l1_gas_provider.get_gas_prices_last_update();
-    let duration_since_last_update = SystemTime::now().duration_since(last_update_timestamp)?;
-    let last_update_timestemp =
-        last_update_timestamp.duration_since(UNIX_EPOCH).expect("SystemTime before UNIX EPOCH!").as_micros();
-    if duration_since_last_update > 10 * gas_price_poll_ms {
-        anyhow::bail!(
-            "Gas prices have not been updated for {} ms. Last update was at {}",
-            duration_since_last_update.as_micros(),
-            last_update_timestemp
-        );
+    let max_age = 10 * gas_price_poll_ms;
+    if l1_gas_provider.is_gas_price_stale(max_age) {
+        return Err(stale_gas_prices_error("Gas prices", l1_gas_provider.get_gas_prices_last_update()));
+    }
+    if l1_gas_provider.is_data_gas_price_stale(max_age) {
+        return Err(stale_gas_prices_error("Data gas prices", l1_gas_provider.get_data_gas_prices_last_update()));
     }
 
     Ok(())
 }
+
+fn stale_gas_prices_error(name: &str, last_update: SystemTime) -> anyhow::Error {
+    let duration_since_last_update = SystemTime::now().duration_since(last_update).unwrap_or_default();
+    let last_update_timestamp =
+        last_update.duration_since(UNIX_EPOCH).expect("SystemTime before UNIX EPOCH!").as_micros();
+    anyhow::anyhow!(
+        "{name} have not been updated for {} ms. Last update was at {}",
+        duration_since_last_update.as_micros(),
+        last_update_timestamp
+    )
+}
+
+/// Polls the L1 gas prices until `ctx` is cancelled. The poll interval is read from the provider before every poll, a
+/// new interval takes effect once the current wait is over. Every wait is randomly jittered by the
+/// [`GasPriceProvider::poll_jitter`] fraction of the interval. The last gas price update is reported in the status of
+/// the service.
 pub async fn gas_price_worker(
     eth_client: &EthereumClient,
     l1_gas_provider: GasPriceProvider,
-    gas_price_poll_ms: Duration,
     ctx: ServiceContext,
 ) -> anyhow::Result<()> {
     l1_gas_provider.update_last_update_timestamp();
-    let mut interval = tokio::time::interval(gas_price_poll_ms);
-    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-    while wait_or_graceful_shutdown(interval.tick(), &ctx).await.is_some() {
-        gas_price_worker_once(eth_client, l1_gas_provider.clone(), gas_price_poll_ms).await?;
+    loop {
+        let poll_interval = l1_gas_provider.poll_interval();
+        gas_price_worker_once(eth_client, l1_gas_provider.clone(), poll_interval)
+            .instrument(iteration_span("gas_price"))
+            .await?;
+        ctx.report_status(|status| status.last_update = Some(l1_gas_provider.get_gas_prices_last_update()));
+        let wait = jittered_interval(poll_interval, l1_gas_provider.poll_jitter(), &mut rand::thread_rng());
+        if wait_or_graceful_shutdown(tokio::time::sleep(wait), &ctx).await.is_none() {
+            break;
+        }
     }
     Ok(())
 }
 
-async fn update_gas_price(eth_client: &EthereumClient, l1_gas_provider: GasPriceProvider) -> anyhow::Result<()> {
-    let block_number = eth_client.get_latest_block_number().await?;
-    let fee_history = eth_client.provider.get_fee_history(300, BlockNumberOrTag::Number(block_number), &[]).await?;
-
-    // The RPC responds with 301 elements for some reason. It's also just safer to manually
-    // take the last 300. We choose 300 to get average gas caprice for last one hour (300 * 12 sec block
-    // time).
-    let (_, blob_fee_history_one_hour) =
-        fee_history.base_fee_per_blob_gas.split_at(fee_history.base_fee_per_blob_gas.len().max(300) - 300);
-
-    let avg_blob_base_fee = if !blob_fee_history_one_hour.is_empty() {
-        blob_fee_history_one_hour.iter().sum::<u128>() / blob_fee_history_one_hour.len() as u128
-    } else {
-        0 // in case blob_fee_history_one_hour has 0 length
-    };
+/// Randomly shortens or lengthens `interval` by up to `jitter` times the interval.
+fn jittered_interval(interval: Duration, jitter: f64, rng: &mut impl Rng) -> Duration {
+    if jitter == 0.0 {
+        return interval;
+    }
+    interval.mul_f64(1.0 + rng.gen_range(-jitter..=jitter))
+}
 
-    let eth_gas_price = fee_history.base_fee_per_gas.last().context("Getting eth gas price")?;
+/// Number of L1 blocks whose priority fees the L1 gas price is computed from, when a fee history percentile is set.
+const FEE_HISTORY_BLOCK_COUNT: u64 = 20;
 
-    l1_gas_provider.update_eth_l1_gas_price(*eth_gas_price);
-    l1_gas_provider.update_eth_l1_data_gas_price(avg_blob_base_fee);
+type EthStrkPrice = anyhow::Result<Option<(u128, u32)>>;
 
-    // fetch eth/strk price and update
-    if let Some(oracle_provider) = &l1_gas_provider.oracle_provider {
-        let (eth_strk_price, decimals) =
-            oracle_provider.fetch_eth_strk_price().await.context("failed to retrieve ETH/STRK price")?;
-        let strk_gas_price = (BigDecimal::new((*eth_gas_price).into(), decimals.into())
-            / BigDecimal::new(eth_strk_price.into(), decimals.into()))
-        .as_bigint_and_exponent();
-        let strk_data_gas_price = (BigDecimal::new(avg_blob_base_fee.into(), decimals.into())
-            / BigDecimal::new(eth_strk_price.into(), decimals.into()))
-        .as_bigint_and_exponent();
+/// Fetches the ETH/STRK price the STRK gas prices are derived from, if an oracle is configured.
+async fn fetch_eth_strk_price(l1_gas_provider: &GasPriceProvider) -> EthStrkPrice {
+    match &l1_gas_provider.oracle_provider {
+        Some(oracle_provider) => {
+            Ok(Some(oracle_provider.fetch_eth_strk_price().await.context("failed to retrieve ETH/STRK price")?))
+        }
+        None => Ok(None),
+    }
+}
 
-        l1_gas_provider.update_strk_l1_gas_price(
-            strk_gas_price.0.to_str_radix(10).parse::<u128>().context("failed to update strk l1 gas price")?,
-        );
-        l1_gas_provider.update_strk_l1_data_gas_price(
-            strk_data_gas_price
-                .0
-                .to_str_radix(10)
-                .parse::<u128>()
-                .context("failed to update strk l1 data gas price")?,
-        );
+/// Converts a price in wei to fri, if an ETH/STRK price is available.
+fn eth_to_strk(price: u128, eth_strk_price: &EthStrkPrice) -> anyhow::Result<Option<u128>> {
+    let (eth_strk_price, decimals) = match eth_strk_price {
+        Ok(Some(eth_strk_price)) => *eth_strk_price,
+        Ok(None) => return Ok(None),
+        Err(e) => anyhow::bail!("{e:#}"),
+    };
+    let strk_price = (BigDecimal::new(price.into(), decimals.into())
+        / BigDecimal::new(eth_strk_price.into(), decimals.into()))
+    .as_bigint_and_exponent();
+    Ok(Some(strk_price.0.to_str_radix(10).parse::<u128>()?))
+}
+
+/// Updates the L1 gas prices from the [source](GasPriceProvider::gas_price_source) of the gas price provider.
+async fn update_l1_gas_price(
+    eth_client: &EthereumClient,
+    l1_gas_provider: &GasPriceProvider,
+    eth_strk_price: &EthStrkPrice,
+) -> anyhow::Result<()> {
+    let eth_gas_price = match l1_gas_provider.gas_price_source() {
+        L1GasPriceSource::BaseFee => fetch_base_fee_gas_price(eth_client, l1_gas_provider).await?,
+        L1GasPriceSource::OracleContract { address } => eth_client
+            .get_oracle_gas_price(Address::from_slice(address.as_bytes()))
+            .await
+            .with_context(|| format!("Getting the L1 gas price from the oracle contract at {address:#x}"))?,
+    };
+    let eth_gas_price = clamp_gas_price(eth_client, l1_gas_provider, "l1_gas_price", eth_gas_price);
+    l1_gas_provider.update_eth_l1_gas_price(eth_gas_price);
+
+    if let Some(strk_gas_price) =
+        eth_to_strk(eth_gas_price, eth_strk_price).context("failed to update strk l1 gas price")?
+    {
+        l1_gas_provider.update_strk_l1_gas_price(strk_gas_price);
     }
 
-    l1_gas_provider.update_last_update_timestamp();
+    l1_gas_provider.update_gas_price_last_update_timestamp();
+    Ok(())
+}
 
-    // Update block number separately to avoid holding the lock for too long
-    update_l1_block_metrics(eth_client, l1_gas_provider).await?;
+/// The base fee of the next L1 block, plus a percentile of the priority fees of the last [`FEE_HISTORY_BLOCK_COUNT`] L1
+/// blocks when the gas price provider has a [fee history percentile](GasPriceProvider::fee_history_percentile).
+async fn fetch_base_fee_gas_price(
+    eth_client: &EthereumClient,
+    l1_gas_provider: &GasPriceProvider,
+) -> anyhow::Result<u128> {
+    let block_number = eth_client.get_latest_block_number().await?;
+    let eth_gas_price = match l1_gas_provider.fee_history_percentile() {
+        Some(percentile) => {
+            record_block_range(
+                &tracing::Span::current(),
+                block_number.saturating_sub(FEE_HISTORY_BLOCK_COUNT - 1),
+                block_number,
+            );
+            let (base_fee, priority_fees) = eth_client
+                .get_fee_history(FEE_HISTORY_BLOCK_COUNT, block_number, percentile)
+                .await
+                .context("Getting the L1 fee history")?;
+            l1_gas_provider.gas_price_from_fee_history(base_fee, &priority_fees)
+        }
+        None => {
+            record_block_range(&tracing::Span::current(), block_number, block_number);
+            let fee_history =
+                eth_client.provider.get_fee_history(1, BlockNumberOrTag::Number(block_number), &[]).await?;
+            *fee_history.base_fee_per_gas.last().context("Getting eth gas price")?
+        }
+    };
+    Ok(eth_gas_price)
+}
 
+/// Updates the L1 data gas prices from the blob base fee of the next L1 block. Smoothing them over time is left to the
+/// gas price provider.
+async fn update_l1_data_gas_price(
+    eth_client: &EthereumClient,
+    l1_gas_provider: &GasPriceProvider,
+    eth_strk_price: &EthStrkPrice,
+) -> anyhow::Result<()> {
+    let blob_base_fee = eth_client.provider.get_blob_base_fee().await.context("Getting blob base fee")?;
+    let blob_base_fee = clamp_gas_price(eth_client, l1_gas_provider, "l1_data_gas_price", blob_base_fee);
+    l1_gas_provider.update_eth_l1_data_gas_price(blob_base_fee);
+
+    if let Some(strk_data_gas_price) =
+        eth_to_strk(blob_base_fee, eth_strk_price).context("failed to update strk l1 data gas price")?
+    {
+        l1_gas_provider.update_strk_l1_data_gas_price(strk_data_gas_price);
+    }
+
+    l1_gas_provider.update_data_gas_price_last_update_timestamp();
     Ok(())
 }
 
+/// Clamps a fetched gas price to the bounds configured on the gas price provider, as a safeguard against an L1 rpc
+/// returning absurd values.
+fn clamp_gas_price(
+    eth_client: &EthereumClient,
+    l1_gas_provider: &GasPriceProvider,
+    name: &'static str,
+    price: u128,
+) -> u128 {
+    let bounds = l1_gas_provider.gas_price_bounds();
+    let clamped = bounds.clamp(price);
+    if clamped != price {
+        tracing::warn!(
+            "Fetched {name} {price} is out of bounds [{}, {}], clamping it to {clamped}",
+            bounds.min,
+            bounds.max
+        );
+        eth_client.l1_block_metrics.l1_gas_price_clamped.add(1, &[KeyValue::new("price", name)]);
+    }
+    clamped
+}
+
 async fn update_l1_block_metrics(eth_client: &EthereumClient, l1_gas_provider: GasPriceProvider) -> anyhow::Result<()> {
     // Get the latest block number
     let latest_block_number = eth_client.get_latest_block_number().await?;
@@ -114,6 +229,10 @@ async fn update_l1_block_metrics(eth_client: &EthereumClient, l1_gas_provider: G
 
     eth_client.l1_block_metrics.l1_block_number.record(latest_block_number, &[]);
     eth_client.l1_block_metrics.l1_gas_price_wei.record(eth_gas_price as u64, &[]);
+    eth_client
+        .l1_block_metrics
+        .l1_gas_price_raw_wei
+        .record(l1_gas_provider.get_raw_gas_prices().eth_l1_gas_price as u64, &[]);
 
     // We're ignoring l1_gas_price_strk
 
@@ -124,8 +243,11 @@ async fn update_l1_block_metrics(eth_client: &EthereumClient, l1_gas_provider: G
 mod eth_client_gas_price_worker_test {
     use super::*;
     use crate::client::eth_client_getter_test::{create_ethereum_client, get_shared_anvil};
+    use crate::sync::{run_with_reconnect_and_warm_up, L1CircuitBreakerConfig, L1ReconnectConfig};
     use httpmock::{MockServer, Regex};
-    use mc_mempool::GasPriceProvider;
+    use mc_mempool::{GasPriceBounds, GasPriceProvider, MAX_GAS_PRICE_POLL_INTERVAL};
+    use mp_utils::service::PauseHandle;
+    use rand::SeedableRng;
     use serial_test::serial;
     use std::time::SystemTime;
     use tokio::task::JoinHandle;
@@ -142,15 +264,8 @@ mod eth_client_gas_price_worker_test {
         let worker_handle: JoinHandle<anyhow::Result<()>> = tokio::spawn({
             let eth_client = eth_client.clone();
             let l1_gas_provider = l1_gas_provider.clone();
-            async move {
-                gas_price_worker(
-                    &eth_client,
-                    l1_gas_provider,
-                    Duration::from_millis(200),
-                    ServiceContext::new_for_testing(),
-                )
-                .await
-            }
+            l1_gas_provider.set_poll_interval(Duration::from_millis(200)).unwrap();
+            async move { gas_price_worker(&eth_client, l1_gas_provider, ServiceContext::new_for_testing()).await }
         });
 
         // Wait for a short duration to allow the worker to run
@@ -175,6 +290,43 @@ mod eth_client_gas_price_worker_test {
         assert_eq!(updated_price.eth_l1_data_gas_price, 1);
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn gas_price_worker_picks_up_new_poll_interval() {
+        let anvil = get_shared_anvil();
+        let eth_client = create_ethereum_client(Some(anvil.endpoint().as_str()));
+        let l1_gas_provider = GasPriceProvider::new();
+        l1_gas_provider.set_poll_interval(Duration::from_millis(100)).unwrap();
+        let ctx = ServiceContext::new_for_testing();
+
+        let worker_handle: JoinHandle<anyhow::Result<()>> = tokio::spawn({
+            let eth_client = eth_client.clone();
+            let l1_gas_provider = l1_gas_provider.clone();
+            let ctx = ctx.clone();
+            async move { gas_price_worker(&eth_client, l1_gas_provider, ctx).await }
+        });
+
+        // The worker polls every 100ms.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let last_update = l1_gas_provider.get_gas_prices_last_update();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(l1_gas_provider.get_gas_prices_last_update() > last_update);
+
+        // Once its current wait is over, the worker waits for the new interval.
+        l1_gas_provider.set_poll_interval(MAX_GAS_PRICE_POLL_INTERVAL).unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let last_update = l1_gas_provider.get_gas_prices_last_update();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(l1_gas_provider.get_gas_prices_last_update(), last_update);
+
+        ctx.cancel_global();
+        timeout(Duration::from_secs(2), worker_handle)
+            .await
+            .expect("Gas price worker did not stop on cancellation")
+            .expect("Gas price worker panicked")
+            .expect("Gas price worker failed");
+    }
+
     #[serial]
     #[tokio::test]
     async fn gas_price_worker_when_infinite_loop_false_works() {
@@ -236,6 +388,41 @@ mod eth_client_gas_price_worker_test {
         assert_eq!(updated_price.eth_l1_data_gas_price, 20);
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn gas_price_worker_clamps_out_of_bounds_prices() {
+        let anvil = get_shared_anvil();
+        let eth_client = create_ethereum_client(Some(anvil.endpoint().as_str()));
+        let mut l1_gas_provider = GasPriceProvider::new();
+        // The fetched gas price is above the maximum, and the data gas price below the minimum.
+        l1_gas_provider.set_gas_price_bounds(GasPriceBounds { min: 10, max: 500_000_000 });
+
+        gas_price_worker_once(&eth_client, l1_gas_provider.clone(), Duration::from_millis(200))
+            .await
+            .expect("issue with the gas worker");
+
+        let updated_price = l1_gas_provider.get_gas_prices();
+        assert_eq!(updated_price.eth_l1_gas_price, 500_000_000);
+        assert_eq!(updated_price.eth_l1_data_gas_price, 10);
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn gas_price_worker_keeps_in_bounds_prices() {
+        let anvil = get_shared_anvil();
+        let eth_client = create_ethereum_client(Some(anvil.endpoint().as_str()));
+        let mut l1_gas_provider = GasPriceProvider::new();
+        l1_gas_provider.set_gas_price_bounds(GasPriceBounds { min: 1, max: 1_000_000_000 });
+
+        gas_price_worker_once(&eth_client, l1_gas_provider.clone(), Duration::from_millis(200))
+            .await
+            .expect("issue with the gas worker");
+
+        let updated_price = l1_gas_provider.get_gas_prices();
+        assert_eq!(updated_price.eth_l1_gas_price, 948082986);
+        assert_eq!(updated_price.eth_l1_data_gas_price, 1);
+    }
+
     #[serial]
     #[tokio::test]
     async fn gas_price_worker_when_eth_fee_history_fails_should_fails() {
@@ -247,7 +434,7 @@ mod eth_client_gas_price_worker_test {
             when.method("POST").path("/").json_body_obj(&serde_json::json!({
                 "jsonrpc": "2.0",
                 "method": "eth_feeHistory",
-                "params": ["0x12c", "0x137368e", []],
+                "params": ["0x1", "0x137368e", []],
                 "id": 1
             }));
             then.status(500).json_body_obj(&serde_json::json!({
@@ -266,6 +453,7 @@ mod eth_client_gas_price_worker_test {
         });
 
         let l1_gas_provider = GasPriceProvider::new();
+        l1_gas_provider.set_poll_interval(Duration::from_millis(200)).unwrap();
 
         l1_gas_provider.update_last_update_timestamp();
 
@@ -273,12 +461,7 @@ mod eth_client_gas_price_worker_test {
 
         let result = timeout(
             timeout_duration,
-            gas_price_worker(
-                &eth_client,
-                l1_gas_provider.clone(),
-                Duration::from_millis(200),
-                ServiceContext::new_for_testing(),
-            ),
+            gas_price_worker(&eth_client, l1_gas_provider.clone(), ServiceContext::new_for_testing()),
         )
         .await;
 
@@ -300,6 +483,170 @@ mod eth_client_gas_price_worker_test {
         mock.assert();
     }
 
+    /// Mocks an L1 rpc where `eth_feeHistory` and `eth_blobBaseFee` either succeed or fail.
+    fn mock_l1(mock_server: &MockServer, fee_history_ok: bool, blob_base_fee_ok: bool) {
+        let error = serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32000, "message": "Internal Server Error" },
+            "id": 1
+        });
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_blockNumber");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":1,"result":"0x0137368e"}));
+        });
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_feeHistory");
+            if fee_history_ok {
+                then.status(200).json_body_obj(&serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "result": {
+                        "oldestBlock": "0x137368e",
+                        "baseFeePerGas": ["0x64", "0xc8"],
+                        "gasUsedRatio": [0.5],
+                        "baseFeePerBlobGas": ["0x1", "0x1"],
+                        "blobGasUsedRatio": [0.5]
+                    },
+                    "id": 1
+                }));
+            } else {
+                then.status(500).json_body_obj(&error);
+            }
+        });
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_blobBaseFee");
+            if blob_base_fee_ok {
+                then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":1,"result":"0x2a"}));
+            } else {
+                then.status(500).json_body_obj(&error);
+            }
+        });
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn gas_price_worker_updates_data_gas_price_when_gas_price_fails() {
+        let mock_server = MockServer::start();
+        mock_l1(&mock_server, false, true);
+        let eth_client = create_ethereum_client(Some(&format!("http://{}", mock_server.address())));
+        let l1_gas_provider = GasPriceProvider::new();
+        let gas_last_update = l1_gas_provider.get_gas_prices_last_update();
+        let data_gas_last_update = l1_gas_provider.get_data_gas_prices_last_update();
+
+        gas_price_worker_once(&eth_client, l1_gas_provider.clone(), Duration::from_secs(10))
+            .await
+            .expect("issue with the gas worker");
+
+        let updated_price = l1_gas_provider.get_gas_prices();
+        assert_eq!(updated_price.eth_l1_gas_price, 0);
+        assert_eq!(updated_price.eth_l1_data_gas_price, 42);
+        assert_eq!(l1_gas_provider.get_gas_prices_last_update(), gas_last_update);
+        assert!(l1_gas_provider.get_data_gas_prices_last_update() > data_gas_last_update);
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn gas_price_worker_updates_gas_price_when_data_gas_price_fails() {
+        let mock_server = MockServer::start();
+        mock_l1(&mock_server, true, false);
+        let eth_client = create_ethereum_client(Some(&format!("http://{}", mock_server.address())));
+        let l1_gas_provider = GasPriceProvider::new();
+        let gas_last_update = l1_gas_provider.get_gas_prices_last_update();
+        let data_gas_last_update = l1_gas_provider.get_data_gas_prices_last_update();
+
+        gas_price_worker_once(&eth_client, l1_gas_provider.clone(), Duration::from_secs(10))
+            .await
+            .expect("issue with the gas worker");
+
+        let updated_price = l1_gas_provider.get_gas_prices();
+        assert_eq!(updated_price.eth_l1_gas_price, 200);
+        assert_eq!(updated_price.eth_l1_data_gas_price, 0);
+        assert!(l1_gas_provider.get_gas_prices_last_update() > gas_last_update);
+        assert_eq!(l1_gas_provider.get_data_gas_prices_last_update(), data_gas_last_update);
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn update_gas_price_from_fee_history_percentile() {
+        let mock_server = MockServer::start();
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_blockNumber");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":1,"result":"0x0137368e"}));
+        });
+        // The 50th percentile of the priority fees of each of the last 4 blocks, and the base fee of the next one.
+        let fee_history = mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_feeHistory").body_contains("[50.0]");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "result": {
+                    "oldestBlock": "0x137368b",
+                    "baseFeePerGas": ["0x64", "0x64", "0x64", "0x64", "0xc8"],
+                    "gasUsedRatio": [0.5, 0.5, 0.5, 0.5],
+                    "reward": [["0x1e"], ["0xa"], ["0x32"], ["0x14"]]
+                },
+                "id": 1
+            }));
+        });
+        let eth_client = create_ethereum_client(Some(&format!("http://{}", mock_server.address())));
+        let mut l1_gas_provider = GasPriceProvider::new();
+        l1_gas_provider.set_fee_history_percentile(Some(50.0));
+
+        update_l1_gas_price(&eth_client, &l1_gas_provider, &Ok(None)).await.expect("Failed to update gas prices");
+
+        // The base fee of the next block plus the median of the priority fees [10, 20, 30, 50].
+        assert_eq!(l1_gas_provider.get_gas_prices().eth_l1_gas_price, 200 + 20);
+        fee_history.assert();
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn update_gas_price_from_oracle_contract() {
+        let mock_server = MockServer::start();
+        let oracle = Address::repeat_byte(0x42);
+        // `gasPrice()` of the oracle contract returns 10000 wei.
+        let oracle_call = mock_server.mock(|when, then| {
+            when.method("POST")
+                .path("/")
+                .body_contains("eth_call")
+                .body_contains("42".repeat(20))
+                .body_contains("fe173b97");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("0x{:064x}", 10_000)
+            }));
+        });
+        let fee_history = mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_feeHistory");
+            then.status(500);
+        });
+        let eth_client = create_ethereum_client(Some(&format!("http://{}", mock_server.address())));
+        let mut l1_gas_provider = GasPriceProvider::new();
+        l1_gas_provider.set_gas_price_source(L1GasPriceSource::OracleContract { address: oracle.0 .0.into() });
+
+        update_l1_gas_price(&eth_client, &l1_gas_provider, &Ok(None)).await.expect("Failed to update gas prices");
+
+        assert_eq!(l1_gas_provider.get_gas_prices().eth_l1_gas_price, 10_000);
+        oracle_call.assert();
+        assert_eq!(fee_history.hits(), 0, "The base fee should not be fetched");
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn gas_price_worker_fails_when_data_gas_price_is_stale() {
+        let mock_server = MockServer::start();
+        mock_l1(&mock_server, true, false);
+        let eth_client = create_ethereum_client(Some(&format!("http://{}", mock_server.address())));
+        let l1_gas_provider = GasPriceProvider::new();
+
+        // Only the data gas price is stale.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let err = gas_price_worker_once(&eth_client, l1_gas_provider.clone(), Duration::from_millis(1))
+            .await
+            .expect_err("the data gas price should be stale");
+        assert!(err.to_string().starts_with("Data gas prices have not been updated"), "Got: {err}");
+        assert_eq!(l1_gas_provider.get_gas_prices().eth_l1_gas_price, 200);
+    }
+
     #[serial]
     #[tokio::test]
     async fn update_gas_price_works() {
@@ -310,7 +657,10 @@ mod eth_client_gas_price_worker_test {
         l1_gas_provider.update_last_update_timestamp();
 
         // Update gas prices
-        update_gas_price(&eth_client, l1_gas_provider.clone()).await.expect("Failed to update gas prices");
+        update_l1_gas_price(&eth_client, &l1_gas_provider, &Ok(None)).await.expect("Failed to update gas prices");
+        update_l1_data_gas_price(&eth_client, &l1_gas_provider, &Ok(None))
+            .await
+            .expect("Failed to update data gas prices");
 
         // Access the updated gas prices
         let updated_prices = l1_gas_provider.get_gas_prices();
@@ -331,4 +681,81 @@ mod eth_client_gas_price_worker_test {
 
         assert!(time_since_last_update.as_secs() < 60, "Last update timestamp should be within the last minute");
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn gas_prices_stay_fresh_across_endpoint_rotation() {
+        let old_server = MockServer::start();
+        mock_l1(&old_server, true, true);
+        let new_server = MockServer::start();
+        mock_l1(&new_server, true, true);
+        // The new endpoint is checked for the L1 core contract before it is used.
+        let get_code = new_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_getCode");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x6080"}));
+        });
+        let eth_client = create_ethereum_client(Some(&old_server.url("/")));
+        let l1_endpoints = eth_client.l1_endpoints();
+        let l1_gas_provider = GasPriceProvider::new();
+        let poll_interval = Duration::from_millis(100);
+        l1_gas_provider.set_poll_interval(poll_interval).unwrap();
+        let ctx = ServiceContext::new_for_testing();
+
+        let warm_up = {
+            let l1_gas_provider = l1_gas_provider.clone();
+            move |eth_client: EthereumClient| {
+                let l1_gas_provider = l1_gas_provider.clone();
+                async move { gas_price_worker_once(&eth_client, l1_gas_provider, poll_interval).await }
+            }
+        };
+        let worker = {
+            let l1_gas_provider = l1_gas_provider.clone();
+            move |eth_client: EthereumClient, ctx: ServiceContext| {
+                let l1_gas_provider = l1_gas_provider.clone();
+                async move { gas_price_worker(&eth_client, l1_gas_provider, ctx).await }
+            }
+        };
+        let config = L1ReconnectConfig {
+            max_retries: 0,
+            backoff: Duration::from_millis(10),
+            circuit_breaker: L1CircuitBreakerConfig { failure_threshold: 0, cooldown: Duration::ZERO },
+        };
+        let run = run_with_reconnect_and_warm_up(eth_client, config, PauseHandle::new(), ctx.clone(), warm_up, worker);
+        let sync = tokio::spawn(run);
+        tokio::time::sleep(3 * poll_interval).await;
+
+        // The old endpoint stops answering as soon as the rotation is requested.
+        l1_endpoints.set(vec![new_server.url("/").parse().unwrap()]);
+        old_server.reset();
+
+        let mut max_staleness = Duration::ZERO;
+        for _ in 0..100 {
+            let last_update = l1_gas_provider.get_gas_prices_last_update();
+            max_staleness = max_staleness.max(SystemTime::now().duration_since(last_update).unwrap_or_default());
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(max_staleness < 3 * poll_interval, "The gas prices were stale for {max_staleness:?}");
+        assert!(get_code.hits() >= 1, "The new endpoint should have been connected to");
+
+        ctx.cancel_global();
+        timeout(Duration::from_secs(2), sync)
+            .await
+            .expect("The L1 sync did not stop on cancellation")
+            .expect("The L1 sync panicked")
+            .expect("The L1 sync failed");
+    }
+
+    #[test]
+    fn jittered_interval_stays_within_bounds() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let interval = Duration::from_secs(10);
+        let intervals: Vec<_> = (0..100).map(|_| jittered_interval(interval, 0.2, &mut rng)).collect();
+
+        assert!(intervals.iter().all(|wait| (Duration::from_secs(8)..=Duration::from_secs(12)).contains(wait)));
+        // Consecutive waits vary, so that nodes started at the same time drift apart.
+        assert!(intervals.windows(2).all(|pair| pair[0] != pair[1]));
+
+        // No jitter keeps the interval as is.
+        assert_eq!(jittered_interval(interval, 0.0, &mut rng), interval);
+    }
 }
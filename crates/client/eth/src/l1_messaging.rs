@@ -1,19 +1,26 @@
 use crate::client::StarknetCoreContract::LogMessageToL2;
 use crate::client::{EthereumClient, StarknetCoreContract};
+use crate::sync::{iteration_span, record_block_range};
 use crate::utils::u256_to_felt;
 use alloy::eips::BlockNumberOrTag;
 use alloy::primitives::{keccak256, FixedBytes, U256};
+use alloy::rpc::types::Log;
 use alloy::sol_types::SolValue;
+use alloy::transports::RpcError;
 use anyhow::Context;
-use futures::StreamExt;
-use mc_db::{l1_db::LastSyncedEventBlock, MadaraBackend};
-use mc_mempool::{Mempool, MempoolProvider};
+use futures::{Stream, StreamExt};
+use mc_db::l1_db::{L1MessageOrigin, LastSyncedEventBlock};
+use mc_db::MadaraBackend;
+use mc_mempool::Mempool;
 use mp_utils::channel_wait_or_graceful_shutdown;
 use mp_utils::service::ServiceContext;
 use starknet_api::core::{ChainId, ContractAddress, EntryPointSelector, Nonce};
 use starknet_api::transaction::{Calldata, L1HandlerTransaction, TransactionVersion};
 use starknet_types_core::felt::Felt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
 
 impl EthereumClient {
     /// Get cancellation status of an L1 to L2 message
@@ -36,130 +43,382 @@ impl EthereumClient {
     }
 }
 
+/// How often the L1 blocks the consumed messages come from are checked for reorgs, when no new message arrives. This is
+/// about the L1 block time.
+const L1_REORG_CHECK_INTERVAL: Duration = Duration::from_secs(12);
+
+/// L1 blocks are finalized after two epochs, reorgs deeper than this are not possible.
+const MAX_L1_REORG_DEPTH: u64 = 128;
+
+/// Fragments of the error messages endpoints answer an `eth_getLogs` request over their block range or response size
+/// cap with. There is no standard error code for it.
+const RANGE_TOO_LARGE_MESSAGES: &[&str] =
+    &["range too large", "block range", "too many blocks", "query returned more than", "response size exceeded"];
+
+/// How the L1 messages of the finalized L1 blocks are fetched when catching up with the L1.
+#[derive(Clone, Copy, Debug)]
+pub struct L1LogFetchConfig {
+    /// Number of L1 blocks whose messages are fetched in a single `eth_getLogs` request. Endpoints usually cap the
+    /// range of a request: when a range is rejected as too large, it is halved until the endpoint accepts it.
+    pub range: u64,
+    /// Number of `eth_getLogs` requests in flight.
+    pub concurrency: usize,
+    /// L1 block the messages are fetched from on a fresh database, instead of the genesis. It is ignored once the
+    /// database holds some messaging progress, so that no L1 block after the already processed ones is ever skipped.
+    pub start_block: Option<u64>,
+}
+
+impl Default for L1LogFetchConfig {
+    fn default() -> Self {
+        Self { range: 1000, concurrency: 4, start_block: None }
+    }
+}
+
+/// The first L1 block whose messages are not processed yet.
+fn first_unprocessed_l1_block(backend: &MadaraBackend, start_block: Option<u64>) -> anyhow::Result<u64> {
+    let last_synced_event_block = backend
+        .messaging_last_synced_l1_block_with_event()?
+        .context("Getting the last synced L1 block with a message")?
+        .block_number;
+    let last_processed_block = backend.messaging_last_processed_l1_block()?;
+    let from_block = match last_processed_block {
+        Some(last_processed_block) => last_synced_event_block.max(last_processed_block + 1),
+        None => last_synced_event_block,
+    };
+
+    match start_block {
+        // A fresh database: nothing was processed yet, the L1 blocks before the start block are skipped.
+        Some(start_block) if last_processed_block.is_none() && last_synced_event_block == 0 => Ok(start_block),
+        Some(start_block) if start_block > from_block => {
+            tracing::warn!(
+                "⟠ Ignoring the L1 start block {start_block}: some L1 messages are already processed, resuming from block {from_block}"
+            );
+            Ok(from_block)
+        }
+        _ => Ok(from_block),
+    }
+}
+
+/// Splits the L1 blocks `from..=to` into ranges of at most `range` blocks.
+fn block_ranges(from: u64, to: u64, range: u64) -> impl Iterator<Item = (u64, u64)> {
+    let range = range.max(1);
+    (from..=to).step_by(range as usize).map(move |start| (start, start.saturating_add(range - 1).min(to)))
+}
+
+/// Whether the endpoint rejected an `eth_getLogs` request because its block range is over the endpoint cap.
+fn is_range_too_large(err: &alloy::contract::Error) -> bool {
+    let alloy::contract::Error::TransportError(RpcError::ErrorResp(payload)) = err else {
+        return false;
+    };
+    let message = payload.message.to_lowercase();
+    RANGE_TOO_LARGE_MESSAGES.iter().any(|fragment| message.contains(fragment))
+}
+
+/// Fetches the L1 messages of the L1 blocks `from_block..=to_block`, in block and log order. Ranges of more than
+/// `max_range` blocks are split, and a range rejected as too large is halved: `max_range` is then lowered, so that the
+/// following requests do not hit the endpoint cap again.
+async fn fetch_l1_messages(
+    client: &EthereumClient,
+    from_block: u64,
+    to_block: u64,
+    max_range: &AtomicU64,
+) -> anyhow::Result<Vec<(LogMessageToL2, Log)>> {
+    let mut messages = vec![];
+    // The ranges left to fetch, the next one is on top.
+    let mut ranges = vec![(from_block, to_block)];
+    while let Some((from_block, to_block)) = ranges.pop() {
+        let range = to_block - from_block + 1;
+        let max = max_range.load(Ordering::Relaxed);
+        if range > max {
+            ranges.extend([(from_block + max, to_block), (from_block, from_block + max - 1)]);
+            continue;
+        }
+
+        let filter = client.l1_core_contract.event_filter::<LogMessageToL2>().from_block(from_block).to_block(to_block);
+        match filter.query().await {
+            Ok(batch) => messages.extend(batch),
+            Err(err) if range > 1 && is_range_too_large(&err) => {
+                let half = range / 2;
+                if max_range.fetch_min(half, Ordering::Relaxed) > half {
+                    tracing::debug!(
+                        "⟠ The L1 endpoint rejected a range of {range} blocks, fetching the L1 messages {half} blocks at a time"
+                    );
+                }
+                ranges.extend([(from_block + half, to_block), (from_block, from_block + half - 1)]);
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Fetching the L1 messages from block {from_block} to {to_block}"));
+            }
+        }
+    }
+    Ok(messages)
+}
+
+/// Fetches the L1 messages of the L1 blocks `from_block..=to_block`, with up to [`L1LogFetchConfig::concurrency`]
+/// requests in flight. The batches are yielded in block order with the last L1 block they cover, and the messages of a
+/// batch are in block and log order.
+fn l1_message_batches(
+    client: &EthereumClient,
+    from_block: u64,
+    to_block: u64,
+    log_fetch: L1LogFetchConfig,
+) -> impl Stream<Item = anyhow::Result<(u64, Vec<(LogMessageToL2, Log)>)>> + '_ {
+    // Shared by the requests in flight, so that they all use the range the endpoint accepts once one finds it.
+    let max_range = Arc::new(AtomicU64::new(log_fetch.range.max(1)));
+    futures::stream::iter(block_ranges(from_block, to_block, log_fetch.range))
+        .map(move |(from_block, to_block)| {
+            let max_range = Arc::clone(&max_range);
+            async move { Ok((to_block, fetch_l1_messages(client, from_block, to_block, &max_range).await?)) }
+        })
+        // Unlike `buffer_unordered`, this yields the results in the order of the ranges.
+        .buffered(log_fetch.concurrency.max(1))
+}
+
 pub async fn sync(
     backend: &MadaraBackend,
     client: &EthereumClient,
     chain_id: &ChainId,
     mempool: Arc<Mempool>,
+    log_fetch: L1LogFetchConfig,
     ctx: ServiceContext,
 ) -> anyhow::Result<()> {
     tracing::info!("⟠ Starting L1 Messages Syncing...");
 
-    let last_synced_event_block = match backend.messaging_last_synced_l1_block_with_event() {
-        Ok(Some(blk)) => blk,
-        Ok(None) => {
-            unreachable!("Should never be None")
+    // The L1 block of the last processed message, to detect reorgs.
+    let mut last_origin = backend.messaging_l1_message_origins()?.pop();
+    let mut reorg_check_interval = tokio::time::interval(L1_REORG_CHECK_INTERVAL);
+
+    // The event stream is restarted from the fork point after an L1 reorg.
+    'watch: loop {
+        // Catch up with the finalized L1 blocks: their messages are fetched in parallel batches, and processed in
+        // order. The L1 blocks at or below the last processed one are skipped, such as the ones fetched again after a
+        // restart in the middle of a range.
+        let finalized_block = client.get_finalized_block_number().await?;
+        let mut watch_from_block = first_unprocessed_l1_block(backend, log_fetch.start_block)
+            .inspect_err(|e| tracing::error!("⟠ Madara Messaging DB unavailable: {e:#}"))?;
+        if finalized_block > watch_from_block {
+            let span = iteration_span("l1_messaging");
+            record_block_range(&span, watch_from_block, finalized_block);
+            span.in_scope(|| {
+                tracing::info!("⟠ Catching up with the L1 messages from block {watch_from_block} to {finalized_block}")
+            });
+            let mut batches = l1_message_batches(client, watch_from_block, finalized_block, log_fetch);
+            while let Some(batch) =
+                channel_wait_or_graceful_shutdown(batches.next(), &ctx).instrument(span.clone()).await
+            {
+                let (batch_to_block, batch) = batch?;
+                for (event, meta) in batch {
+                    let origin = message_origin(&meta)?;
+                    // A message from the same block as the last consumed message doesn't need to be checked again.
+                    if Some(origin) != last_origin
+                        && rollback_l1_reorg(backend, client, &mempool, &mut last_origin)
+                            .instrument(span.clone())
+                            .await?
+                    {
+                        continue 'watch;
+                    }
+                    last_origin = Some(origin);
+                    handle_l1_message(backend, client, chain_id, &mempool, &event, &meta, origin)
+                        .instrument(span.clone())
+                        .await?;
+                }
+                // The finalized blocks of the batch cannot be reorged, they are never processed again.
+                backend.messaging_update_last_processed_l1_block(batch_to_block)?;
+            }
+            if ctx.is_cancelled() {
+                break 'watch;
+            }
+            watch_from_block = finalized_block + 1;
         }
-        Err(e) => {
-            tracing::error!("⟠ Madara Messaging DB unavailable: {:?}", e);
-            return Err(e.into());
+
+        let event_filter = client.l1_core_contract.event_filter::<StarknetCoreContract::LogMessageToL2>();
+
+        let mut event_stream = event_filter
+            .from_block(watch_from_block)
+            .to_block(BlockNumberOrTag::Finalized)
+            .watch()
+            .await
+            .context(
+                "Failed to watch event filter - Ensure you are using an L1 RPC endpoint that points to an archive node",
+            )?
+            .into_stream();
+
+        loop {
+            let next_event = async {
+                tokio::select! {
+                    event = event_stream.next() => event.map(Some),
+                    _ = reorg_check_interval.tick() => Some(None),
+                }
+            };
+            let Some(event_result) = channel_wait_or_graceful_shutdown(next_event, &ctx).await else { break 'watch };
+
+            if let Some(Err(err)) = &event_result {
+                client.l1_block_metrics.l1_rpc_errors.add(1, &[]);
+                tracing::debug!("⟠ Error while listening for L1 Messages: {err:#}");
+            }
+            let new_origin = match &event_result {
+                Some(Ok((_, meta))) => Some(message_origin(meta)?),
+                _ => None,
+            };
+            let span = iteration_span("l1_messaging");
+            if let Some(origin) = new_origin {
+                record_block_range(&span, origin.block_number, origin.block_number);
+            }
+            // A message from the same block as the last consumed message doesn't need to be checked again.
+            if (new_origin.is_none() || new_origin != last_origin)
+                && rollback_l1_reorg(backend, client, &mempool, &mut last_origin).instrument(span.clone()).await?
+            {
+                continue 'watch;
+            }
+
+            let (Some(Ok((event, meta))), Some(origin)) = (event_result, new_origin) else { continue };
+            last_origin = Some(origin);
+            handle_l1_message(backend, client, chain_id, &mempool, &event, &meta, origin).instrument(span).await?;
         }
-    };
-    let event_filter = client.l1_core_contract.event_filter::<StarknetCoreContract::LogMessageToL2>();
-
-    let mut event_stream = event_filter
-        .from_block(last_synced_event_block.block_number)
-        .to_block(BlockNumberOrTag::Finalized)
-        .watch()
-        .await
-        .context(
-            "Failed to watch event filter - Ensure you are using an L1 RPC endpoint that points to an archive node",
-        )?
-        .into_stream();
-    while let Some(event_result) = channel_wait_or_graceful_shutdown(event_stream.next(), &ctx).await {
-        if let Ok((event, meta)) = event_result {
+    }
+
+    Ok(())
+}
+
+fn message_origin(meta: &Log) -> anyhow::Result<L1MessageOrigin> {
+    Ok(L1MessageOrigin::new(
+        meta.block_number.context("L1 Message without a block number")?,
+        meta.block_hash.context("L1 Message without a block hash")?.0,
+    ))
+}
+
+/// Submits the L1 handler transaction of an L1 message to the mempool, unless the message was cancelled.
+async fn handle_l1_message(
+    backend: &MadaraBackend,
+    client: &EthereumClient,
+    chain_id: &ChainId,
+    mempool: &Arc<Mempool>,
+    event: &LogMessageToL2,
+    meta: &Log,
+    origin: L1MessageOrigin,
+) -> anyhow::Result<()> {
+    tracing::info!(
+        "⟠ Processing L1 Message from block: {:?}, transaction_hash: {:?}, log_index: {:?}, fromAddress: {:?}",
+        meta.block_number,
+        meta.transaction_hash,
+        meta.log_index,
+        event.fromAddress
+    );
+
+    // Check if cancellation was initiated
+    let event_hash = get_l1_to_l2_msg_hash(event)?;
+    tracing::info!("⟠ Checking for cancelation, event hash : {:?}", event_hash);
+    let cancellation_timestamp = client.get_l1_to_l2_message_cancellations(event_hash).await?;
+    if cancellation_timestamp != Felt::ZERO {
+        tracing::info!("⟠ L1 Message was cancelled in block at timestamp : {:?}", cancellation_timestamp);
+        let tx_nonce = Nonce(u256_to_felt(event.nonce)?);
+        // cancelled message nonce should be inserted to avoid reprocessing
+        match backend.has_l1_messaging_nonce(tx_nonce) {
+            Ok(false) => {
+                backend.set_l1_messaging_nonce(tx_nonce, origin)?;
+            }
+            Ok(true) => {}
+            Err(e) => {
+                tracing::error!("⟠ Unexpected DB error: {:?}", e);
+                return Err(e.into());
+            }
+        };
+        return Ok(());
+    }
+
+    match process_l1_message(backend, event, origin, &meta.log_index, chain_id, mempool.clone()).await {
+        Ok(Some(tx_hash)) => {
             tracing::info!(
-                "⟠ Processing L1 Message from block: {:?}, transaction_hash: {:?}, log_index: {:?}, fromAddress: {:?}",
+                "⟠ L1 Message from block: {:?}, transaction_hash: {:?}, log_index: {:?} submitted, \
+                transaction hash on L2: {:?}",
                 meta.block_number,
                 meta.transaction_hash,
                 meta.log_index,
-                event.fromAddress
+                tx_hash
             );
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!(
+                "⟠ Unexpected error while processing L1 Message from block: {:?}, transaction_hash: {:?}, \
+            log_index: {:?}, error: {:?}",
+                meta.block_number,
+                meta.transaction_hash,
+                meta.log_index,
+                e
+            )
+        }
+    }
+    Ok(())
+}
 
-            // Check if cancellation was initiated
-            let event_hash = get_l1_to_l2_msg_hash(&event)?;
-            tracing::info!("⟠ Checking for cancelation, event hash : {:?}", event_hash);
-            let cancellation_timestamp = client.get_l1_to_l2_message_cancellations(event_hash).await?;
-            if cancellation_timestamp != Felt::ZERO {
-                tracing::info!("⟠ L1 Message was cancelled in block at timestamp : {:?}", cancellation_timestamp);
-                let tx_nonce = Nonce(u256_to_felt(event.nonce)?);
-                // cancelled message nonce should be inserted to avoid reprocessing
-                match backend.has_l1_messaging_nonce(tx_nonce) {
-                    Ok(false) => {
-                        backend.set_l1_messaging_nonce(tx_nonce)?;
-                    }
-                    Ok(true) => {}
-                    Err(e) => {
-                        tracing::error!("⟠ Unexpected DB error: {:?}", e);
-                        return Err(e.into());
-                    }
-                };
-                continue;
-            }
+/// Checks that the L1 block the last message was consumed from is still part of the canonical L1 chain. When it is
+/// not, the L1 reorged: the messages consumed from the reorged blocks are rolled back in the db, and their L1 handler
+/// transactions are removed from the mempool. They are processed again if they are part of the new canonical chain.
+///
+/// Returns whether a reorg was rolled back.
+async fn rollback_l1_reorg(
+    backend: &MadaraBackend,
+    client: &EthereumClient,
+    mempool: &Mempool,
+    last_origin: &mut Option<L1MessageOrigin>,
+) -> anyhow::Result<bool> {
+    let Some(origin) = *last_origin else { return Ok(false) };
+    if client.get_block_hash(origin.block_number).await? == Some(origin.block_hash) {
+        return Ok(false);
+    }
 
-            match process_l1_message(backend, &event, &meta.block_number, &meta.log_index, chain_id, mempool.clone())
-                .await
-            {
-                Ok(Some(tx_hash)) => {
-                    tracing::info!(
-                        "⟠ L1 Message from block: {:?}, transaction_hash: {:?}, log_index: {:?} submitted, \
-                        transaction hash on L2: {:?}",
-                        meta.block_number,
-                        meta.transaction_hash,
-                        meta.log_index,
-                        tx_hash
-                    );
-                }
-                Ok(None) => {}
-                Err(e) => {
-                    tracing::error!(
-                        "⟠ Unexpected error while processing L1 Message from block: {:?}, transaction_hash: {:?}, \
-                    log_index: {:?}, error: {:?}",
-                        meta.block_number,
-                        meta.transaction_hash,
-                        meta.log_index,
-                        e
-                    )
-                }
-            }
+    // Find the fork point: the latest block we consumed messages from that is still canonical.
+    let mut origins = backend.messaging_l1_message_origins()?;
+    let mut fork_point = None;
+    while let Some(candidate) = origins.pop() {
+        if candidate.block_number + MAX_L1_REORG_DEPTH < origin.block_number
+            || client.get_block_hash(candidate.block_number).await? == Some(candidate.block_hash)
+        {
+            fork_point = Some(candidate);
+            break;
         }
     }
+    let fork_block_number =
+        fork_point.map_or(origin.block_number.saturating_sub(MAX_L1_REORG_DEPTH), |fork| fork.block_number);
+
+    let rolled_back = backend.messaging_rollback_after_l1_block(fork_block_number)?;
+    let removed = mempool.remove_l1_handler_txs(&rolled_back.iter().copied().collect())?;
+    // The L1 handler transactions that already made it into a block can't be rolled back here.
+    tracing::warn!(
+        "⟠ L1 reorg detected after block {}: rolled back {} L1 messages, removed {} L1 handler transactions from the \
+        mempool",
+        fork_block_number,
+        rolled_back.len(),
+        removed.len()
+    );
 
-    Ok(())
+    *last_origin = fork_point;
+    Ok(true)
 }
 
 async fn process_l1_message(
     backend: &MadaraBackend,
     event: &LogMessageToL2,
-    l1_block_number: &Option<u64>,
+    origin: L1MessageOrigin,
     event_index: &Option<u64>,
     _chain_id: &ChainId,
     mempool: Arc<Mempool>,
 ) -> anyhow::Result<Option<Felt>> {
     let transaction = parse_handle_l1_message_transaction(event)?;
-    let tx_nonce = transaction.nonce;
     let fees: u128 = event.fee.try_into()?;
 
-    // Ensure that L1 message has not been executed
-    match backend.has_l1_messaging_nonce(tx_nonce) {
-        Ok(false) => {
-            backend.set_l1_messaging_nonce(tx_nonce)?;
-        }
-        Ok(true) => {
-            tracing::debug!("⟠ Event already processed: {:?}", transaction);
-            return Ok(None);
-        }
-        Err(e) => {
-            tracing::error!("⟠ Unexpected DB error: {:?}", e);
-            return Err(e.into());
-        }
+    // A message that was already consumed is flagged as a duplicate by the mempool instead of being executed again.
+    let Some(accepted) = mempool.accept_l1_message(transaction.clone().into(), fees, origin)? else {
+        tracing::debug!("⟠ Event already processed: {:?}", transaction);
+        return Ok(None);
     };
-
-    let res = mempool.accept_l1_handler_tx(transaction.into(), fees)?;
+    let res = accepted.result;
 
     // TODO: remove unwraps
     // Ques: shall it panic if no block number of event_index?
-    let block_sent = LastSyncedEventBlock::new(l1_block_number.unwrap(), event_index.unwrap());
+    let block_sent = LastSyncedEventBlock::new(origin.block_number, event_index.unwrap());
     backend.messaging_update_last_synced_l1_block_with_event(block_sent)?;
 
     Ok(Some(res.transaction_hash))
@@ -214,11 +473,17 @@ fn get_l1_to_l2_msg_hash(event: &LogMessageToL2) -> anyhow::Result<FixedBytes<32
 #[cfg(test)]
 mod l1_messaging_tests {
 
-    use std::{sync::Arc, time::Duration};
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
-    use crate::l1_messaging::sync;
+    use crate::l1_messaging::{
+        block_ranges, first_unprocessed_l1_block, l1_message_batches, sync, L1LogFetchConfig, L1_REORG_CHECK_INTERVAL,
+    };
     use crate::{
         client::{
+            eth_client_getter_test::create_ethereum_client,
             EthereumClient, L1BlockMetrics,
             StarknetCoreContract::{self, LogMessageToL2},
         },
@@ -229,13 +494,15 @@ mod l1_messaging_tests {
         hex::FromHex,
         node_bindings::{Anvil, AnvilInstance},
         primitives::{Address, U256},
-        providers::{ProviderBuilder, RootProvider},
+        providers::{Provider, ProviderBuilder, RootProvider},
         sol,
         transports::http::{Client, Http},
     };
+    use futures::TryStreamExt;
+    use httpmock::{HttpMockRequest, MockServer};
     use mc_db::DatabaseService;
     use mc_mempool::{GasPriceProvider, L1DataProvider, Mempool, MempoolLimits};
-    use mp_chain_config::ChainConfig;
+    use mp_chain_config::{ChainConfig, L1CoreAddress};
     use mp_utils::service::ServiceContext;
     use rstest::*;
     use starknet_api::core::Nonce;
@@ -368,7 +635,7 @@ mod l1_messaging_tests {
 
         // Set up provider
         let rpc_url: Url = anvil.endpoint().parse().expect("issue while parsing");
-        let provider = ProviderBuilder::new().on_http(rpc_url);
+        let provider = ProviderBuilder::new().on_http(rpc_url.clone());
 
         // Set up dummy contract
         let contract = DummyContract::deploy(provider.clone()).await.unwrap();
@@ -379,6 +646,13 @@ mod l1_messaging_tests {
             provider: Arc::new(provider.clone()),
             l1_core_contract: core_contract.clone(),
             l1_block_metrics: l1_block_metrics.clone(),
+            core_address: L1CoreAddress::new(contract.address().0 .0.into()),
+            endpoints: [rpc_url].into(),
+            active_endpoint: 0,
+            headers: Default::default(),
+            request_timeout: None,
+            block_tag: Default::default(),
+            head_subscription: None,
         };
 
         TestRunner { anvil, chain_config, db_service: db, dummy_contract: contract, eth_client, mempool }
@@ -407,8 +681,15 @@ mod l1_messaging_tests {
         let worker_handle = {
             let db = Arc::clone(&db);
             tokio::spawn(async move {
-                sync(db.backend(), &eth_client, &chain_config.chain_id, mempool, ServiceContext::new_for_testing())
-                    .await
+                sync(
+                    db.backend(),
+                    &eth_client,
+                    &chain_config.chain_id,
+                    mempool,
+                    L1LogFetchConfig::default(),
+                    ServiceContext::new_for_testing(),
+                )
+                .await
             })
         };
 
@@ -462,8 +743,15 @@ mod l1_messaging_tests {
         let worker_handle = {
             let db = Arc::clone(&db);
             tokio::spawn(async move {
-                sync(db.backend(), &eth_client, &chain_config.chain_id, mempool, ServiceContext::new_for_testing())
-                    .await
+                sync(
+                    db.backend(),
+                    &eth_client,
+                    &chain_config.chain_id,
+                    mempool,
+                    L1LogFetchConfig::default(),
+                    ServiceContext::new_for_testing(),
+                )
+                .await
             })
         };
 
@@ -512,8 +800,15 @@ mod l1_messaging_tests {
         let worker_handle = {
             let db = Arc::clone(&db);
             tokio::spawn(async move {
-                sync(db.backend(), &eth_client, &chain_config.chain_id, mempool, ServiceContext::new_for_testing())
-                    .await
+                sync(
+                    db.backend(),
+                    &eth_client,
+                    &chain_config.chain_id,
+                    mempool,
+                    L1LogFetchConfig::default(),
+                    ServiceContext::new_for_testing(),
+                )
+                .await
             })
         };
 
@@ -532,6 +827,284 @@ mod l1_messaging_tests {
         worker_handle.abort();
     }
 
+    /// Test the rollback of l1 -> l2 messages after an L1 reorg
+    ///
+    /// This test performs the following steps:
+    /// 1. Sets up test environemment
+    /// 2. Snapshots the L1 state
+    /// 3. Starts worker
+    /// 4. Fires a Message event from the dummy contract
+    /// 5. Waits for event to be processed
+    /// 6. Reverts the L1 to the snapshot, which reorgs out the block of the message
+    /// 7. Assert that the message set and the mempool are restored to their pre-reorg state
+    #[rstest]
+    #[traced_test]
+    #[tokio::test]
+    async fn e2e_test_l1_reorg_rolls_back_messages(#[future] setup_test_env: TestRunner) {
+        let TestRunner { chain_config, db_service: db, dummy_contract: contract, eth_client, anvil: _anvil, mempool } =
+            setup_test_env.await;
+        let provider = Arc::clone(&eth_client.provider);
+
+        let _ = contract.setIsCanceled(false).send().await.expect("Failed to send tx").watch().await;
+        let snapshot: U256 = provider.raw_request("evm_snapshot".into(), ()).await.expect("Failed to snapshot L1");
+
+        // Start worker
+        let worker_handle = {
+            let db = Arc::clone(&db);
+            let mempool = Arc::clone(&mempool);
+            tokio::spawn(async move {
+                sync(
+                    db.backend(),
+                    &eth_client,
+                    &chain_config.chain_id,
+                    mempool,
+                    L1LogFetchConfig::default(),
+                    ServiceContext::new_for_testing(),
+                )
+                .await
+            })
+        };
+
+        let _ = contract.fireEvent().send().await.expect("Failed to fire event");
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let nonce = Nonce(Felt::from_dec_str("10000000000000000").expect("failed to parse nonce string"));
+        assert!(db.backend().has_l1_messaging_nonce(nonce).unwrap());
+        assert!(!mempool.is_empty());
+
+        // Reorg the block of the message out of the L1. New blocks are mined on top of the snapshot.
+        let reverted: bool = provider.raw_request("evm_revert".into(), (snapshot,)).await.expect("Failed to revert L1");
+        assert!(reverted);
+        tokio::time::sleep(L1_REORG_CHECK_INTERVAL + Duration::from_secs(5)).await;
+
+        assert!(logs_contain("L1 reorg detected"));
+        assert!(!db.backend().has_l1_messaging_nonce(nonce).unwrap());
+        assert!(mempool.is_empty());
+        let last_block =
+            db.backend().messaging_last_synced_l1_block_with_event().expect("failed to retrieve block").unwrap();
+        assert_eq!(last_block.block_number, 0);
+
+        worker_handle.abort();
+    }
+
+    /// Fires a message, then mines `gap` empty L1 blocks on top of it. Returns the L1 block of the message.
+    async fn fire_event_with_gap(
+        contract: &DummyContractInstance<Http<Client>, RootProvider<Http<Client>>>,
+        provider: &RootProvider<Http<Client>>,
+        gap: u64,
+    ) -> u64 {
+        let receipt = contract
+            .fireEvent()
+            .send()
+            .await
+            .expect("Failed to fire event")
+            .get_receipt()
+            .await
+            .expect("Failed to get receipt");
+        let _: () = provider.raw_request("anvil_mine".into(), (U256::from(gap),)).await.expect("Failed to mine blocks");
+        receipt.block_number.expect("Receipt without a block number")
+    }
+
+    /// Test the catch-up of the l1 -> l2 messages of a large L1 block range
+    ///
+    /// This test performs the following steps:
+    /// 1. Sets up test environemment
+    /// 2. Fires Message events separated by large L1 block gaps
+    /// 3. Fetches the messages of the whole block range sequentially, then with parallel requests
+    /// 4. Assert that both return the messages in L1 block order, and logs their catch-up times
+    #[rstest]
+    #[traced_test]
+    #[tokio::test]
+    async fn e2e_test_catch_up_fetches_messages_in_order(#[future] setup_test_env: TestRunner) {
+        let TestRunner { dummy_contract: contract, eth_client, anvil: _anvil, .. } = setup_test_env.await;
+
+        let mut event_blocks = vec![];
+        for _ in 0..5 {
+            event_blocks.push(fire_event_with_gap(&contract, &eth_client.provider, 500).await);
+        }
+        let latest_block = eth_client.get_latest_block_number().await.expect("Failed to get latest block");
+
+        for concurrency in [1, 8] {
+            let log_fetch = L1LogFetchConfig { range: 50, concurrency, ..Default::default() };
+            let started_at = Instant::now();
+            let batches: Vec<_> = l1_message_batches(&eth_client, 0, latest_block, log_fetch)
+                .try_collect()
+                .await
+                .expect("Failed to fetch the L1 messages");
+            tracing::info!(
+                "Fetched the L1 messages of {latest_block} blocks with {concurrency} requests in flight in {:?}",
+                started_at.elapsed()
+            );
+
+            let message_blocks: Vec<u64> =
+                batches.into_iter().flat_map(|(_, batch)| batch).map(|(_, meta)| meta.block_number.unwrap()).collect();
+            assert_eq!(message_blocks, event_blocks);
+        }
+    }
+
+    /// Test that the messages sent before the worker starts are processed
+    ///
+    /// This test performs the following steps:
+    /// 1. Sets up test environemment
+    /// 2. Fires a Message event from the dummy contract, and mines a large L1 block gap on top of it
+    /// 3. Starts worker
+    /// 4. Assert that the worker caught up with the event
+    #[rstest]
+    #[traced_test]
+    #[tokio::test]
+    async fn e2e_test_catch_up_processes_past_messages(#[future] setup_test_env: TestRunner) {
+        let TestRunner { chain_config, db_service: db, dummy_contract: contract, eth_client, anvil: _anvil, mempool } =
+            setup_test_env.await;
+
+        let _ = contract.setIsCanceled(false).send().await.expect("Failed to send tx").watch().await;
+        fire_event_with_gap(&contract, &eth_client.provider, 500).await;
+
+        // Start worker
+        let worker_handle = {
+            let db = Arc::clone(&db);
+            let mempool = Arc::clone(&mempool);
+            tokio::spawn(async move {
+                sync(
+                    db.backend(),
+                    &eth_client,
+                    &chain_config.chain_id,
+                    mempool,
+                    L1LogFetchConfig { range: 20, concurrency: 4, ..Default::default() },
+                    ServiceContext::new_for_testing(),
+                )
+                .await
+            })
+        };
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        assert!(logs_contain("Catching up with the L1 messages"));
+        let nonce = Nonce(Felt::from_dec_str("10000000000000000").expect("failed to parse nonce string"));
+        assert!(db.backend().has_l1_messaging_nonce(nonce).unwrap());
+        assert!(!mempool.is_empty());
+
+        worker_handle.abort();
+    }
+
+    /// Test that a restarted worker does not process the L1 blocks it already processed again
+    ///
+    /// This test performs the following steps:
+    /// 1. Sets up test environemment
+    /// 2. Fires a Message event from the dummy contract, and mines a large L1 block gap on top of it
+    /// 3. Starts worker, and stops it once it caught up with the event
+    /// 4. Restarts worker
+    /// 5. Assert that the event was only processed by the first worker
+    #[rstest]
+    #[traced_test]
+    #[tokio::test]
+    async fn e2e_test_restart_skips_processed_blocks(#[future] setup_test_env: TestRunner) {
+        let TestRunner { chain_config, db_service: db, dummy_contract: contract, eth_client, anvil: _anvil, mempool } =
+            setup_test_env.await;
+
+        let _ = contract.setIsCanceled(false).send().await.expect("Failed to send tx").watch().await;
+        let event_block = fire_event_with_gap(&contract, &eth_client.provider, 500).await;
+
+        for _ in 0..2 {
+            let ctx = ServiceContext::new_for_testing();
+            let worker_handle = {
+                let db = Arc::clone(&db);
+                let chain_config = Arc::clone(&chain_config);
+                let eth_client = eth_client.clone();
+                let mempool = Arc::clone(&mempool);
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    sync(
+                        db.backend(),
+                        &eth_client,
+                        &chain_config.chain_id,
+                        mempool,
+                        L1LogFetchConfig { range: 20, concurrency: 4, ..Default::default() },
+                        ctx,
+                    )
+                    .await
+                })
+            };
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            ctx.cancel_global();
+            worker_handle.await.expect("The worker should not panic").expect("The worker should stop gracefully");
+
+            let last_processed_block = db.backend().messaging_last_processed_l1_block().unwrap();
+            assert!(last_processed_block.is_some_and(|last_processed_block| last_processed_block > event_block));
+        }
+
+        logs_assert(|lines: &[&str]| {
+            match lines.iter().filter(|line| line.contains("Processing L1 Message from block")).count() {
+                1 => Ok(()),
+                n => Err(format!("The L1 message was processed {n} times")),
+            }
+        });
+    }
+
+    #[test]
+    fn test_block_ranges() {
+        assert_eq!(block_ranges(0, 9, 4).collect::<Vec<_>>(), [(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(block_ranges(5, 5, 4).collect::<Vec<_>>(), [(5, 5)]);
+        // A range of zero blocks is fetched block by block.
+        assert_eq!(block_ranges(0, 2, 0).collect::<Vec<_>>(), [(0, 0), (1, 1), (2, 2)]);
+    }
+
+    /// Block range of an `eth_getLogs` request.
+    fn get_logs_range(req: &HttpMockRequest) -> Option<u64> {
+        let body: serde_json::Value = serde_json::from_slice(req.body.as_deref()?).ok()?;
+        let filter = &body["params"][0];
+        let block = |key: &str| u64::from_str_radix(filter[key].as_str()?.trim_start_matches("0x"), 16).ok();
+        Some(block("toBlock")? - block("fromBlock")? + 1)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_halves_range_too_large() {
+        let mock_server = MockServer::start();
+        let too_large = mock_server.mock(|when, then| {
+            when.method("POST")
+                .body_contains("eth_getLogs")
+                .matches(|req| get_logs_range(req).is_some_and(|range| range > 100));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "error": { "code": -32600, "message": "Block range too large, the maximum is 100 blocks" }
+            }));
+        });
+        let accepted = mock_server.mock(|when, then| {
+            when.method("POST")
+                .body_contains("eth_getLogs")
+                .matches(|req| get_logs_range(req).is_some_and(|range| range <= 100));
+            then.status(200).json_body_obj(&serde_json::json!({ "jsonrpc": "2.0", "id": 0, "result": [] }));
+        });
+
+        let eth_client = create_ethereum_client(Some(&mock_server.url("/")));
+        let log_fetch = L1LogFetchConfig { range: 400, concurrency: 1, ..Default::default() };
+        let batches: Vec<_> =
+            l1_message_batches(&eth_client, 0, 799, log_fetch).try_collect().await.expect("The fetch should succeed");
+        assert_eq!(batches.into_iter().map(|(to_block, _)| to_block).collect::<Vec<_>>(), [399, 799]);
+
+        // The first range is halved twice, after which the second range is fetched 100 blocks at a time right away.
+        assert_eq!(too_large.hits(), 2);
+        assert_eq!(accepted.hits(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_watch_from_start_block() {
+        let temp_dir = TempDir::new().expect("issue while creating temporary directory");
+        let chain_config = Arc::new(ChainConfig::madara_test());
+        let db = DatabaseService::new(&temp_dir.path().join("data"), None, false, chain_config, Default::default())
+            .await
+            .expect("Failed to create database service");
+        let backend = db.backend();
+
+        // A fresh database starts from the start block.
+        assert_eq!(first_unprocessed_l1_block(backend, None).unwrap(), 0);
+        assert_eq!(first_unprocessed_l1_block(backend, Some(100)).unwrap(), 100);
+
+        // Once some blocks are processed, the sync resumes after them: the start block never skips L1 blocks.
+        backend.messaging_update_last_processed_l1_block(50).unwrap();
+        assert_eq!(first_unprocessed_l1_block(backend, Some(100)).unwrap(), 51);
+        assert_eq!(first_unprocessed_l1_block(backend, Some(10)).unwrap(), 51);
+        assert_eq!(first_unprocessed_l1_block(backend, None).unwrap(), 51);
+    }
+
     /// Test taken from starknet.rs to ensure consistency
     /// https://github.com/xJonathanLEI/starknet-rs/blob/2ddc69479d326ed154df438d22f2d720fbba746e/starknet-core/src/types/msg.rs#L96
     #[test]
@@ -2,6 +2,7 @@ pub mod client;
 pub mod error;
 pub mod l1_gas_price;
 pub mod l1_messaging;
+pub mod state_submission;
 pub mod state_update;
 pub mod sync;
 pub mod utils;
@@ -0,0 +1,165 @@
+//! Parsing of the L1 transactions which submit Starknet state updates to the core contract.
+//! The state diff is either posted as calldata with `updateState`, or since EIP-4844 in a blob with
+//! `updateStateKzgDA`. A single L1 block can contain submissions of both kinds.
+
+use crate::client::StarknetCoreContract::{updateStateCall, updateStateKzgDACall};
+use crate::state_update::L1StateUpdate;
+use crate::utils::u256_to_felt;
+use alloy::primitives::{Address, B256, U256};
+use alloy::rpc::types::{Block, Transaction};
+use alloy::sol_types::SolCall;
+use anyhow::{bail, ensure, Context};
+use starknet_types_core::felt::Felt;
+
+/// Version byte of the EIP-4844 versioned hashes of KZG commitments.
+const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+// Offsets in the Starknet OS program output.
+const OUTPUT_FINAL_ROOT_OFFSET: usize = 1;
+const OUTPUT_NEW_BLOCK_NUMBER_OFFSET: usize = 3;
+const OUTPUT_NEW_BLOCK_HASH_OFFSET: usize = 5;
+const OUTPUT_USE_KZG_DA_OFFSET: usize = 8;
+const OUTPUT_HEADER_SIZE: usize = 10;
+/// Offset of the number of blobs in the KZG segment, which follows the header when the KZG DA is used.
+const OUTPUT_KZG_N_BLOBS_OFFSET: usize = OUTPUT_HEADER_SIZE + 1;
+
+/// Where the state diff of a state update was posted on the L1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DataAvailability {
+    Calldata,
+    /// The state diff is in the blobs of the transaction, identified by their versioned hashes.
+    Blob {
+        versioned_hashes: Vec<B256>,
+    },
+}
+
+/// A state update submitted to the core contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateUpdateSubmission {
+    pub tx_hash: B256,
+    pub program_output: Vec<U256>,
+    pub data_availability: DataAvailability,
+}
+
+impl StateUpdateSubmission {
+    /// Parses a transaction calling the core contract at `core_contract`. Returns `None` for transactions which do
+    /// not submit a state update.
+    pub fn from_transaction(tx: &Transaction, core_contract: Address) -> anyhow::Result<Option<Self>> {
+        if tx.to != Some(core_contract) {
+            return Ok(None);
+        }
+        let Some(selector) = tx.input.get(..4) else { return Ok(None) };
+        let versioned_hashes = tx.blob_versioned_hashes.clone().unwrap_or_default();
+
+        let (program_output, data_availability) = if selector == updateStateKzgDACall::SELECTOR {
+            let call = updateStateKzgDACall::abi_decode(&tx.input, true).context("Decoding updateStateKzgDA call")?;
+            ensure!(!versioned_hashes.is_empty(), "updateStateKzgDA transaction {} without blobs", tx.hash);
+            (call.programOutput, DataAvailability::Blob { versioned_hashes })
+        } else if selector == updateStateCall::SELECTOR {
+            let call = updateStateCall::abi_decode(&tx.input, true).context("Decoding updateState call")?;
+            (call.programOutput, DataAvailability::Calldata)
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self { tx_hash: tx.hash, program_output, data_availability }))
+    }
+
+    /// Checks that this submission is the one of `state_update`, and that its program output commits to the data
+    /// availability mode it was posted with.
+    pub fn verify(&self, state_update: &L1StateUpdate) -> anyhow::Result<()> {
+        ensure!(self.program_output.len() >= OUTPUT_HEADER_SIZE, "Program output is shorter than its header");
+        let output_felt = |offset: usize| u256_to_felt(self.program_output[offset]);
+
+        ensure!(
+            output_felt(OUTPUT_FINAL_ROOT_OFFSET)? == state_update.global_root,
+            "Program output state root does not match the state update"
+        );
+        ensure!(
+            output_felt(OUTPUT_NEW_BLOCK_NUMBER_OFFSET)? == Felt::from(state_update.block_number),
+            "Program output block number does not match the state update"
+        );
+        ensure!(
+            output_felt(OUTPUT_NEW_BLOCK_HASH_OFFSET)? == state_update.block_hash,
+            "Program output block hash does not match the state update"
+        );
+
+        let use_kzg_da = self.program_output[OUTPUT_USE_KZG_DA_OFFSET];
+        match &self.data_availability {
+            DataAvailability::Calldata => {
+                ensure!(use_kzg_da.is_zero(), "Blob state update posted as calldata");
+            }
+            DataAvailability::Blob { versioned_hashes } => {
+                ensure!(use_kzg_da == U256::from(1), "Calldata state update posted in a blob");
+                if let Some(hash) = versioned_hashes.iter().find(|hash| hash[0] != VERSIONED_HASH_VERSION_KZG) {
+                    bail!("Blob versioned hash {hash} is not a KZG commitment hash");
+                }
+                let n_blobs =
+                    self.program_output.get(OUTPUT_KZG_N_BLOBS_OFFSET).context("Program output without KZG segment")?;
+                ensure!(
+                    *n_blobs == U256::from(versioned_hashes.len()),
+                    "Program output commits to {n_blobs} blobs, but the transaction carries {}",
+                    versioned_hashes.len()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses all the state update submissions of a block fetched with its full transactions.
+pub fn state_update_submissions(block: &Block, core_contract: Address) -> anyhow::Result<Vec<StateUpdateSubmission>> {
+    let txs = block.transactions.as_transactions().context("Block fetched without its full transactions")?;
+    txs.iter().filter_map(|tx| StateUpdateSubmission::from_transaction(tx, core_contract).transpose()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const CORE_CONTRACT: &str = "0xc662c410C0ECf747543f5bA90660f6ABeBD9C8c4";
+    const BLOB_VERSIONED_HASH: &str = "0x01c1dfa573acb2783172c35d98a582e999384acb5250c35634b461d8d795cde2";
+
+    /// An L1 block with an unrelated transfer, a blob state update submission and a calldata one.
+    fn fixture_submissions() -> Vec<StateUpdateSubmission> {
+        let block: Block = serde_json::from_str(include_str!("../resources/block_with_blob_submission.json")).unwrap();
+        state_update_submissions(&block, Address::from_str(CORE_CONTRACT).unwrap()).unwrap()
+    }
+
+    fn state_update(block_number: u64, global_root: u64, block_hash: u64) -> L1StateUpdate {
+        L1StateUpdate { block_number, global_root: Felt::from(global_root), block_hash: Felt::from(block_hash) }
+    }
+
+    #[test]
+    fn parses_mixed_calldata_and_blob_submissions() {
+        let submissions = fixture_submissions();
+        let [blob, calldata] = &submissions[..] else { panic!("Expected 2 submissions, got {submissions:?}") };
+
+        let versioned_hashes = vec![B256::from_str(BLOB_VERSIONED_HASH).unwrap()];
+        assert_eq!(blob.data_availability, DataAvailability::Blob { versioned_hashes });
+        blob.verify(&state_update(100, 0x1100, 0x2100)).unwrap();
+
+        assert_eq!(calldata.data_availability, DataAvailability::Calldata);
+        calldata.verify(&state_update(101, 0x1200, 0x2200)).unwrap();
+    }
+
+    #[test]
+    fn verify_rejects_mismatching_submission() {
+        let blob = &fixture_submissions()[0];
+
+        blob.verify(&state_update(101, 0x1100, 0x2100)).unwrap_err();
+        blob.verify(&state_update(100, 0x1200, 0x2100)).unwrap_err();
+
+        // The program output commits to a single blob.
+        let mut two_blobs = blob.clone();
+        if let DataAvailability::Blob { versioned_hashes } = &mut two_blobs.data_availability {
+            versioned_hashes.push(versioned_hashes[0]);
+        }
+        two_blobs.verify(&state_update(100, 0x1100, 0x2100)).unwrap_err();
+
+        // A blob state update announced as calldata.
+        let as_calldata = StateUpdateSubmission { data_availability: DataAvailability::Calldata, ..blob.clone() };
+        as_calldata.verify(&state_update(100, 0x1100, 0x2100)).unwrap_err();
+    }
+}
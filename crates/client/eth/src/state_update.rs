@@ -1,9 +1,13 @@
-use crate::client::{L1BlockMetrics, StarknetCoreContract};
+use crate::client::{L1BlockMetrics, L1BlockTag, StarknetCoreContract};
+use crate::sync::{iteration_span, record_block_range};
 use crate::{
     client::EthereumClient,
     utils::{convert_log_state_update, trim_hash},
 };
+use alloy::eips::BlockId;
+use alloy::primitives::B256;
 use anyhow::Context;
+use futures::stream::BoxStream;
 use futures::StreamExt;
 use mc_db::MadaraBackend;
 use mp_convert::ToFelt;
@@ -13,6 +17,13 @@ use mp_utils::service::ServiceContext;
 use serde::Deserialize;
 use starknet_api::core::ChainId;
 use starknet_types_core::felt::Felt;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tracing::Instrument;
+
+/// How often the L1 head is polled to find out whether the buffered state updates are confirmed. This is about
+/// the L1 block time.
+const CONFIRMATIONS_POLL_INTERVAL: Duration = Duration::from_secs(12);
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
 pub struct L1StateUpdate {
@@ -21,22 +32,99 @@ pub struct L1StateUpdate {
     pub block_hash: Felt,
 }
 
-/// Get the last Starknet state update verified on the L1
-pub async fn get_initial_state(client: &EthereumClient) -> anyhow::Result<L1StateUpdate> {
-    let block_number = client.get_last_verified_block_number().await?;
-    let block_hash = client.get_last_verified_block_hash().await?;
-    let global_root = client.get_last_state_root().await?;
+/// Get the last Starknet state update verified on the L1, as of the last L1 block with `l1_confirmations`
+/// confirmations below the block of the client's [`L1BlockTag`](crate::client::L1BlockTag).
+pub async fn get_initial_state(client: &EthereumClient, l1_confirmations: u64) -> anyhow::Result<L1StateUpdate> {
+    let l1_head = client.get_verification_block_number().await?;
+    let at = BlockId::number(l1_head.saturating_sub(l1_confirmations));
+
+    let block_number = client.get_last_verified_block_number(at).await?;
+    let block_hash = client.get_last_verified_block_hash(at).await?;
+    let global_root = client.get_last_state_root(at).await?;
 
     Ok(L1StateUpdate { global_root, block_number, block_hash })
 }
 
+/// State updates seen on the L1 that do not have enough confirmations yet, ordered by L1 block number.
+#[derive(Debug, Default)]
+struct UnconfirmedStateUpdates(VecDeque<(u64, L1StateUpdate)>);
+
+impl UnconfirmedStateUpdates {
+    fn push(&mut self, l1_block_number: u64, state_update: L1StateUpdate) {
+        self.0.push_back((l1_block_number, state_update));
+    }
+
+    /// Removes the state updates that are at least `l1_confirmations` blocks below `l1_head`, and returns the latest
+    /// one. The older ones are superseded by it.
+    fn pop_confirmed(&mut self, l1_head: u64, l1_confirmations: u64) -> Option<L1StateUpdate> {
+        let mut latest = None;
+        while self
+            .0
+            .front()
+            .is_some_and(|(l1_block_number, _)| l1_head.saturating_sub(*l1_block_number) >= l1_confirmations)
+        {
+            latest = self.0.pop_front().map(|(_, state_update)| state_update);
+        }
+        latest
+    }
+}
+
+/// What triggers the confirmation of the buffered state updates.
+pub(crate) enum L1Heads {
+    /// The L1 head is polled every [`CONFIRMATIONS_POLL_INTERVAL`].
+    Poll(tokio::time::Interval),
+    /// The new L1 heads are pushed by a subscription, with their block number.
+    Subscription(BoxStream<'static, u64>),
+}
+
+impl L1Heads {
+    /// Subscribes to the new L1 heads when the client has a head subscription endpoint, and falls back to polling
+    /// otherwise.
+    async fn new(eth_client: &EthereumClient) -> Self {
+        if let Some(ws_endpoint) = &eth_client.head_subscription {
+            match EthereumClient::subscribe_heads(ws_endpoint).await {
+                Ok(heads) => return Self::Subscription(heads),
+                Err(err) => tracing::warn!("Polling the L1 head, the new L1 heads cannot be subscribed to: {err:#}"),
+            }
+        }
+        Self::Poll(tokio::time::interval(CONFIRMATIONS_POLL_INTERVAL))
+    }
+
+    /// Waits for the next L1 head, and returns its block number when it was pushed. Returns `None` once the
+    /// subscription has ended.
+    async fn next(&mut self) -> Option<Option<u64>> {
+        match self {
+            Self::Poll(interval) => {
+                interval.tick().await;
+                Some(None)
+            }
+            Self::Subscription(heads) => heads.next().await.map(Some),
+        }
+    }
+}
+
 /// Subscribes to the LogStateUpdate event from the Starknet core contract and store latest
-/// verified state
+/// verified state, once the event is `l1_confirmations` blocks below the L1 block of the client's block tag
 pub async fn listen_and_update_state(
     eth_client: &EthereumClient,
     backend: &MadaraBackend,
     block_metrics: &L1BlockMetrics,
     chain_id: ChainId,
+    l1_confirmations: u64,
+    ctx: ServiceContext,
+) -> anyhow::Result<()> {
+    let heads = L1Heads::new(eth_client).await;
+    listen_and_update_state_with_heads(eth_client, backend, block_metrics, chain_id, l1_confirmations, heads, ctx).await
+}
+
+/// See [`listen_and_update_state`]. The buffered state updates are confirmed on every new L1 head of `heads`.
+async fn listen_and_update_state_with_heads(
+    eth_client: &EthereumClient,
+    backend: &MadaraBackend,
+    block_metrics: &L1BlockMetrics,
+    chain_id: ChainId,
+    l1_confirmations: u64,
+    mut heads: L1Heads,
     ctx: ServiceContext,
 ) -> anyhow::Result<()> {
     let event_filter = eth_client.l1_core_contract.event_filter::<StarknetCoreContract::LogStateUpdate>();
@@ -49,16 +137,79 @@ pub async fn listen_and_update_state(
         )?
         .into_stream();
 
-    while let Some(event_result) = channel_wait_or_graceful_shutdown(event_stream.next(), &ctx).await {
-        let log = event_result.context("listening for events")?;
-        let format_event: L1StateUpdate =
-            convert_log_state_update(log.0.clone()).context("formatting event into an L1StateUpdate")?;
-        update_l1(backend, format_event, block_metrics, chain_id.clone())?;
+    let mut unconfirmed = UnconfirmedStateUpdates::default();
+    // First L1 block whose state updates have not reached the confirmation depth yet.
+    let mut confirmed_from = None;
+
+    loop {
+        let next_event = async {
+            tokio::select! {
+                event = event_stream.next() => event.map(|event| Ok((Some(event), None))),
+                head = heads.next() => {
+                    Some(head.context("The L1 head subscription has ended").map(|head| (None, head)))
+                }
+            }
+        };
+        let Some(next) = channel_wait_or_graceful_shutdown(next_event, &ctx).await else { break };
+        let (event_result, pushed_head) = next?;
+
+        let span = iteration_span("state_update");
+        let iteration = async {
+            if let Some(event_result) = event_result {
+                let log = event_result.context("listening for events")?;
+                let l1_block_number = log.1.block_number.context("LogStateUpdate event without a block number")?;
+                let format_event: L1StateUpdate =
+                    convert_log_state_update(log.0.clone()).context("formatting event into an L1StateUpdate")?;
+                let tx_hash = log.1.transaction_hash.context("LogStateUpdate event without a transaction hash")?;
+                verify_submission(eth_client, l1_block_number, tx_hash, &format_event).await?;
+                unconfirmed.push(l1_block_number, format_event);
+            }
+
+            // A pushed head is the latest L1 block, the other block tags are still requested.
+            let l1_head = match pushed_head {
+                Some(head) if eth_client.block_tag == L1BlockTag::Latest => head,
+                _ => eth_client.get_verification_block_number().await.context("Getting the L1 head")?,
+            };
+            // The state updates of these L1 blocks reach the confirmation depth in this iteration.
+            let confirmed_to = l1_head.saturating_sub(l1_confirmations);
+            record_block_range(&span, confirmed_from.unwrap_or(confirmed_to), confirmed_to);
+            if let Some(state_update) = unconfirmed.pop_confirmed(l1_head, l1_confirmations) {
+                update_l1(backend, state_update, block_metrics, chain_id.clone())?;
+            }
+            // Every state update up to the confirmation depth has been applied.
+            block_metrics.record_sync_progress(l1_head, confirmed_to);
+            confirmed_from = Some(confirmed_to + 1);
+            anyhow::Ok(())
+        };
+        iteration.instrument(span.clone()).await?;
     }
 
     Ok(())
 }
 
+/// Verifies a state update against the L1 transaction which submitted it, which posts the state diff either as calldata
+/// or in blobs. State updates submitted through another contract, such as a multisig, are not verified.
+async fn verify_submission(
+    eth_client: &EthereumClient,
+    l1_block_number: u64,
+    tx_hash: B256,
+    state_update: &L1StateUpdate,
+) -> anyhow::Result<()> {
+    let submissions = eth_client
+        .get_state_update_submissions(l1_block_number)
+        .await
+        .context("Getting the state update submissions")?;
+    match submissions.iter().find(|submission| submission.tx_hash == tx_hash) {
+        Some(submission) => submission
+            .verify(state_update)
+            .with_context(|| format!("Verifying the state update submitted in L1 transaction {tx_hash}")),
+        None => {
+            tracing::debug!("State update of L1 transaction {tx_hash} was not submitted to the core contract directly");
+            Ok(())
+        }
+    }
+}
+
 pub fn update_l1(
     backend: &MadaraBackend,
     state_update: L1StateUpdate,
@@ -91,6 +242,7 @@ pub async fn state_update_worker(
     backend: &MadaraBackend,
     eth_client: &EthereumClient,
     chain_id: ChainId,
+    l1_confirmations: u64,
     ctx: ServiceContext,
 ) -> anyhow::Result<()> {
     // Clear L1 confirmed block at startup
@@ -100,11 +252,12 @@ pub async fn state_update_worker(
     tracing::info!("🚀 Subscribed to L1 state verification");
     // ideally here there would be one service which will update the l1 gas prices and another one for messages and one that's already present is state update
     // Get and store the latest verified state
-    let initial_state = get_initial_state(eth_client).await.context("Getting initial ethereum state")?;
+    let initial_state =
+        get_initial_state(eth_client, l1_confirmations).await.context("Getting initial ethereum state")?;
     update_l1(backend, initial_state, &eth_client.l1_block_metrics, chain_id.clone())?;
 
     // Listen to LogStateUpdate (0x77552641) update and send changes continusly
-    listen_and_update_state(eth_client, backend, &eth_client.l1_block_metrics, chain_id, ctx)
+    listen_and_update_state(eth_client, backend, &eth_client.l1_block_metrics, chain_id, l1_confirmations, ctx)
         .await
         .context("Subscribing to the LogStateUpdate event")?;
 
@@ -116,9 +269,10 @@ mod eth_client_event_subscription_test {
     use super::*;
     use std::{sync::Arc, time::Duration};
 
+    use crate::client::eth_client_getter_test::{block_json, create_ethereum_client};
     use alloy::{node_bindings::Anvil, providers::ProviderBuilder, sol};
     use mc_db::DatabaseService;
-    use mp_chain_config::ChainConfig;
+    use mp_chain_config::{ChainConfig, L1CoreAddress};
     use rstest::*;
     use tempfile::TempDir;
     use url::Url;
@@ -140,6 +294,7 @@ mod eth_client_event_subscription_test {
 
     const L2_BLOCK_NUMBER: u64 = 662703;
     const ANOTHER_ANVIL_PORT: u16 = 8548;
+    const SHUTDOWN_ANVIL_PORT: u16 = 8549;
     const EVENT_PROCESSING_TIME: u64 = 2; // Time to allow for event processing in seconds
 
     /// Test the event subscription and state update functionality
@@ -182,13 +337,23 @@ mod eth_client_event_subscription_test {
         let l1_block_metrics = L1BlockMetrics::register().unwrap();
 
         let rpc_url: Url = anvil.endpoint().parse().expect("issue while parsing");
-        let provider = ProviderBuilder::new().on_http(rpc_url);
+        let provider = ProviderBuilder::new().on_http(rpc_url.clone());
 
         let contract = DummyContract::deploy(provider.clone()).await.unwrap();
         let core_contract = StarknetCoreContract::new(*contract.address(), provider.clone());
 
-        let eth_client =
-            EthereumClient { provider: Arc::new(provider), l1_core_contract: core_contract.clone(), l1_block_metrics };
+        let eth_client = EthereumClient {
+            provider: Arc::new(provider),
+            l1_core_contract: core_contract.clone(),
+            l1_block_metrics,
+            core_address: L1CoreAddress::new(contract.address().0 .0.into()),
+            endpoints: [rpc_url].into(),
+            active_endpoint: 0,
+            headers: Default::default(),
+            request_timeout: None,
+            block_tag: Default::default(),
+            head_subscription: None,
+        };
 
         // Start listening for state updates
         let listen_handle = {
@@ -199,6 +364,7 @@ mod eth_client_event_subscription_test {
                     db.backend(),
                     &eth_client.l1_block_metrics,
                     chain_info.chain_id.clone(),
+                    0,
                     ServiceContext::new_for_testing(),
                 )
                 .await
@@ -218,4 +384,326 @@ mod eth_client_event_subscription_test {
         listen_handle.abort();
         assert_eq!(block_in_db, Some(L2_BLOCK_NUMBER), "Block in DB does not match expected L2 block number");
     }
+
+    /// Test that the state update listener stops gracefully when the service is cancelled
+    ///
+    /// This test performs the following steps:
+    /// 1. Sets up a mock Ethereum environment using Anvil and starts listening for state updates
+    /// 2. Fires an event from the dummy contract and waits for it to be processed
+    /// 3. Cancels the service context
+    /// 4. Asserts that the listener returns `Ok` on its own, with the state update written, instead of having to be
+    ///    aborted
+    #[rstest]
+    #[tokio::test]
+    async fn listen_and_update_state_stops_on_cancellation() {
+        let anvil = Anvil::new()
+            .block_time(1)
+            .chain_id(1337)
+            .port(SHUTDOWN_ANVIL_PORT)
+            .try_spawn()
+            .expect("failed to spawn anvil instance");
+        let chain_info = Arc::new(ChainConfig::madara_test());
+        let temp_dir = TempDir::new().expect("issue while creating temporary directory");
+        let db = Arc::new(
+            DatabaseService::new(&temp_dir.path().join("data"), None, false, chain_info.clone(), Default::default())
+                .await
+                .expect("Failed to create database service"),
+        );
+
+        let rpc_url: Url = anvil.endpoint().parse().expect("issue while parsing");
+        let provider = ProviderBuilder::new().on_http(rpc_url.clone());
+        let contract = DummyContract::deploy(provider.clone()).await.unwrap();
+        let eth_client = EthereumClient {
+            provider: Arc::new(provider.clone()),
+            l1_core_contract: StarknetCoreContract::new(*contract.address(), provider),
+            l1_block_metrics: L1BlockMetrics::register().unwrap(),
+            core_address: L1CoreAddress::new(contract.address().0 .0.into()),
+            endpoints: [rpc_url].into(),
+            active_endpoint: 0,
+            headers: Default::default(),
+            request_timeout: None,
+            block_tag: Default::default(),
+            head_subscription: None,
+        };
+
+        let ctx = ServiceContext::new_for_testing();
+        let listen_handle = {
+            let db = Arc::clone(&db);
+            let ctx = ctx.clone();
+            tokio::spawn(async move {
+                listen_and_update_state(
+                    &eth_client,
+                    db.backend(),
+                    &eth_client.l1_block_metrics,
+                    chain_info.chain_id.clone(),
+                    0,
+                    ctx,
+                )
+                .await
+            })
+        };
+
+        let _ = contract.fireEvent().send().await.expect("Failed to fire event");
+        tokio::time::sleep(Duration::from_secs(EVENT_PROCESSING_TIME)).await;
+
+        ctx.cancel_global();
+        let res = tokio::time::timeout(Duration::from_secs(5), listen_handle)
+            .await
+            .expect("The listener should stop once cancelled")
+            .expect("The listener should not be aborted");
+        assert!(res.is_ok(), "The listener should stop gracefully: {res:?}");
+
+        let block_in_db =
+            db.backend().get_l1_last_confirmed_block().expect("Failed to get L1 last confirmed block number");
+        assert_eq!(block_in_db, Some(L2_BLOCK_NUMBER));
+    }
+
+    fn state_update(block_number: u64) -> L1StateUpdate {
+        L1StateUpdate { block_number, global_root: Felt::from(block_number), block_hash: Felt::from(block_number) }
+    }
+
+    #[test]
+    fn unconfirmed_state_updates_ignores_blocks_within_confirmation_window() {
+        let mut unconfirmed = UnconfirmedStateUpdates::default();
+        unconfirmed.push(100, state_update(1));
+        unconfirmed.push(105, state_update(2));
+
+        // Mocked L1 head: both events are less than 10 blocks deep.
+        assert_eq!(unconfirmed.pop_confirmed(109, 10), None);
+        assert!(!unconfirmed.0.is_empty());
+
+        // The first event reaches the confirmation depth, the second one is still within the window.
+        assert_eq!(unconfirmed.pop_confirmed(110, 10), Some(state_update(1)));
+        assert_eq!(unconfirmed.pop_confirmed(114, 10), None);
+
+        assert_eq!(unconfirmed.pop_confirmed(115, 10), Some(state_update(2)));
+        assert!(unconfirmed.0.is_empty());
+    }
+
+    #[test]
+    fn unconfirmed_state_updates_returns_latest_confirmed() {
+        let mut unconfirmed = UnconfirmedStateUpdates::default();
+        unconfirmed.push(100, state_update(1));
+        unconfirmed.push(101, state_update(2));
+        unconfirmed.push(120, state_update(3));
+
+        assert_eq!(unconfirmed.pop_confirmed(112, 10), Some(state_update(2)));
+        assert_eq!(unconfirmed.pop_confirmed(112, 10), None);
+        assert!(!unconfirmed.0.is_empty());
+    }
+
+    #[test]
+    fn unconfirmed_state_updates_no_confirmations() {
+        let mut unconfirmed = UnconfirmedStateUpdates::default();
+        unconfirmed.push(100, state_update(1));
+
+        assert_eq!(unconfirmed.pop_confirmed(100, 0), Some(state_update(1)));
+    }
+
+    /// Fields of an `l1_sync_iteration` span.
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    struct IterationSpanFields {
+        worker: String,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+    }
+
+    impl tracing::field::Visit for IterationSpanFields {
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            match field.name() {
+                "from_block" => self.from_block = Some(value),
+                "to_block" => self.to_block = Some(value),
+                _ => {}
+            }
+        }
+
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            if field.name() == "worker" {
+                self.worker = value.to_string();
+            }
+        }
+
+        fn record_debug(&mut self, _field: &tracing::field::Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    #[derive(Default)]
+    struct CapturedSpans {
+        /// Index in `spans` of the open spans.
+        open: std::collections::HashMap<tracing::span::Id, usize>,
+        /// The fields of every span, and whether it was entered.
+        spans: Vec<(IterationSpanFields, bool)>,
+    }
+
+    /// Captures the `l1_sync_iteration` spans.
+    #[derive(Clone, Default)]
+    struct IterationSpans(Arc<std::sync::Mutex<CapturedSpans>>);
+
+    impl IterationSpans {
+        fn entered(&self) -> Vec<IterationSpanFields> {
+            let captured = self.0.lock().unwrap();
+            captured.spans.iter().filter(|(_, entered)| *entered).map(|(fields, _)| fields.clone()).collect()
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for IterationSpans {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if attrs.metadata().name() != "l1_sync_iteration" {
+                return;
+            }
+            let mut fields = IterationSpanFields::default();
+            attrs.record(&mut fields);
+            let mut captured = self.0.lock().unwrap();
+            captured.spans.push((fields, false));
+            let index = captured.spans.len() - 1;
+            captured.open.insert(id.clone(), index);
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut captured = self.0.lock().unwrap();
+            if let Some(&index) = captured.open.get(id) {
+                values.record(&mut captured.spans[index].0);
+            }
+        }
+
+        fn on_enter(&self, id: &tracing::span::Id, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let mut captured = self.0.lock().unwrap();
+            if let Some(&index) = captured.open.get(id) {
+                captured.spans[index].1 = true;
+            }
+        }
+
+        fn on_close(&self, id: tracing::span::Id, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+            self.0.lock().unwrap().open.remove(&id);
+        }
+    }
+
+    /// Checks that an iteration of the state update listener runs in its own span, with the L1 block range it
+    /// confirms. The L1 is mocked: no state update is ever emitted, and the head is at block 100.
+    #[tokio::test]
+    async fn listen_and_update_state_enters_a_span_per_iteration() {
+        use tracing_subscriber::layer::SubscriberExt;
+        use tracing_subscriber::util::SubscriberInitExt;
+
+        let mock_server = httpmock::MockServer::start();
+        let rpc_result = |result: serde_json::Value| serde_json::json!({"jsonrpc":"2.0","id":0,"result":result});
+        mock_server.mock(|when, then| {
+            when.method("POST").body_contains("eth_newFilter");
+            then.status(200).json_body_obj(&rpc_result("0x1".into()));
+        });
+        mock_server.mock(|when, then| {
+            when.method("POST").body_contains("eth_getFilterChanges");
+            then.status(200).json_body_obj(&rpc_result(serde_json::json!([])));
+        });
+        let head = mock_server.mock(|when, then| {
+            when.method("POST").body_contains("eth_getBlockByNumber");
+            then.status(200).json_body_obj(&rpc_result(block_json(100)));
+        });
+
+        let spans = IterationSpans::default();
+        let _subscriber = tracing_subscriber::registry().with(spans.clone()).set_default();
+
+        let chain_info = Arc::new(ChainConfig::madara_test());
+        let temp_dir = TempDir::new().expect("issue while creating temporary directory");
+        let db =
+            DatabaseService::new(&temp_dir.path().join("data"), None, false, chain_info.clone(), Default::default())
+                .await
+                .expect("Failed to create database service");
+        let eth_client = create_ethereum_client(Some(&mock_server.url("/")));
+
+        // The first iteration runs right away, the next one only after the confirmations poll interval.
+        let ctx = ServiceContext::new_for_testing();
+        let (res, ()) = tokio::join!(
+            listen_and_update_state(
+                &eth_client,
+                db.backend(),
+                &eth_client.l1_block_metrics,
+                chain_info.chain_id.clone(),
+                10,
+                ctx.clone(),
+            ),
+            async {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                ctx.cancel_global();
+            }
+        );
+        res.expect("The listener should stop gracefully");
+
+        head.assert();
+        let expected = IterationSpanFields { worker: "state_update".into(), from_block: Some(90), to_block: Some(90) };
+        assert_eq!(spans.entered(), [expected]);
+    }
+
+    /// Checks that the heads pushed by a subscription trigger an iteration each, with the pushed head number instead
+    /// of polling the L1 head.
+    #[tokio::test]
+    async fn listen_and_update_state_processes_subscribed_heads() {
+        use tracing_subscriber::layer::SubscriberExt;
+        use tracing_subscriber::util::SubscriberInitExt;
+
+        let mock_server = httpmock::MockServer::start();
+        let rpc_result = |result: serde_json::Value| serde_json::json!({"jsonrpc":"2.0","id":0,"result":result});
+        mock_server.mock(|when, then| {
+            when.method("POST").body_contains("eth_newFilter");
+            then.status(200).json_body_obj(&rpc_result("0x1".into()));
+        });
+        mock_server.mock(|when, then| {
+            when.method("POST").body_contains("eth_getFilterChanges");
+            then.status(200).json_body_obj(&rpc_result(serde_json::json!([])));
+        });
+        let head = mock_server.mock(|when, then| {
+            when.method("POST").body_contains("eth_getBlockByNumber");
+            then.status(200).json_body_obj(&rpc_result(block_json(100)));
+        });
+
+        let spans = IterationSpans::default();
+        let _subscriber = tracing_subscriber::registry().with(spans.clone()).set_default();
+
+        let chain_info = Arc::new(ChainConfig::madara_test());
+        let temp_dir = TempDir::new().expect("issue while creating temporary directory");
+        let db =
+            DatabaseService::new(&temp_dir.path().join("data"), None, false, chain_info.clone(), Default::default())
+                .await
+                .expect("Failed to create database service");
+        let eth_client = create_ethereum_client(Some(&mock_server.url("/")));
+
+        // The mock subscription pushes three heads, and then stays open.
+        let heads =
+            L1Heads::Subscription(futures::stream::iter([100, 101, 102]).chain(futures::stream::pending()).boxed());
+        let ctx = ServiceContext::new_for_testing();
+        let (res, ()) = tokio::join!(
+            listen_and_update_state_with_heads(
+                &eth_client,
+                db.backend(),
+                &eth_client.l1_block_metrics,
+                chain_info.chain_id.clone(),
+                10,
+                heads,
+                ctx.clone(),
+            ),
+            async {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                ctx.cancel_global();
+            }
+        );
+        res.expect("The listener should stop gracefully");
+
+        // The L1 head is never polled.
+        head.assert_hits(0);
+        let iteration = |block| IterationSpanFields {
+            worker: "state_update".into(),
+            from_block: Some(block),
+            to_block: Some(block),
+        };
+        assert_eq!(spans.entered(), [iteration(90), iteration(91), iteration(92)]);
+    }
 }
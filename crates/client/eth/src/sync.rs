@@ -1,15 +1,45 @@
-use crate::client::EthereumClient;
+use crate::client::{redact_url, EthereumClient};
 use crate::l1_gas_price::gas_price_worker;
-use crate::l1_messaging::sync;
+use crate::l1_messaging::{sync, L1LogFetchConfig};
 use crate::state_update::state_update_worker;
+use anyhow::Context;
+use futures::future::OptionFuture;
 use mc_mempool::{GasPriceProvider, Mempool};
-use mp_utils::service::ServiceContext;
+use mp_utils::service::{PauseHandle, ServiceContext};
+use mp_utils::wait_or_graceful_shutdown;
 use starknet_api::core::ChainId;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use url::Url;
 
 use mc_db::MadaraBackend;
 
+/// Id of the next L1 sync iteration span, unique across the workers.
+static NEXT_ITERATION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Span of one iteration of an L1 sync worker. The logs of the iteration, including the ones of the [`EthereumClient`]
+/// calls it makes, carry its `iteration` id so that they can be correlated. The L1 block range the iteration processes
+/// is recorded with [`record_block_range`] once known.
+pub(crate) fn iteration_span(worker: &'static str) -> tracing::Span {
+    tracing::info_span!(
+        "l1_sync_iteration",
+        worker,
+        iteration = NEXT_ITERATION_ID.fetch_add(1, Ordering::Relaxed),
+        from_block = tracing::field::Empty,
+        to_block = tracing::field::Empty,
+    )
+}
+
+/// Records the L1 blocks `from_block..=to_block` as the range processed by the iteration of `span`.
+pub(crate) fn record_block_range(span: &tracing::Span, from_block: u64, to_block: u64) {
+    span.record("from_block", from_block);
+    span.record("to_block", to_block);
+}
+
+/// Runs the L1 sync workers until `ctx` is cancelled. The workers only observe the cancellation while waiting for their
+/// next iteration: an in-flight iteration, and its db writes, always completes before this returns.
 #[allow(clippy::too_many_arguments)]
 pub async fn l1_sync_worker(
     backend: &MadaraBackend,
@@ -17,20 +47,574 @@ pub async fn l1_sync_worker(
     chain_id: ChainId,
     l1_gas_provider: GasPriceProvider,
     gas_price_sync_disabled: bool,
-    gas_price_poll_ms: Duration,
+    l1_confirmations: u64,
     mempool: Arc<Mempool>,
+    log_fetch: L1LogFetchConfig,
     ctx: ServiceContext,
 ) -> anyhow::Result<()> {
     tokio::try_join!(
-        state_update_worker(backend, eth_client, chain_id.clone(), ctx.clone()),
+        state_update_worker(backend, eth_client, chain_id.clone(), l1_confirmations, ctx.clone()),
         async {
             if !gas_price_sync_disabled {
-                gas_price_worker(eth_client, l1_gas_provider, gas_price_poll_ms, ctx.clone()).await?;
+                gas_price_worker(eth_client, l1_gas_provider, ctx.clone()).await?;
             }
             Ok(())
         },
-        sync(backend, eth_client, &chain_id, mempool, ctx.clone())
+        sync(backend, eth_client, &chain_id, mempool, log_fetch, ctx.clone())
     )?;
 
     Ok(())
 }
+
+/// How the L1 sync recovers from a failing L1 endpoint.
+#[derive(Clone, Debug)]
+pub struct L1ReconnectConfig {
+    /// Consecutive reconnection attempts before giving up.
+    pub max_retries: u32,
+    /// Backoff before the first reconnection attempt, doubled after every attempt up to [`MAX_RECONNECT_BACKOFF`].
+    pub backoff: Duration,
+    pub circuit_breaker: L1CircuitBreakerConfig,
+}
+
+pub const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+/// A worker that has run for this long is considered healthy: the retries start over, and the circuit breaker closes.
+const HEALTHY_RUN_DURATION: Duration = Duration::from_secs(60);
+
+/// See [`L1CircuitBreaker`].
+#[derive(Clone, Debug)]
+pub struct L1CircuitBreakerConfig {
+    /// Consecutive L1 failures which open the circuit breaker, `0` disables it.
+    pub failure_threshold: u32,
+    /// How long the circuit breaker stays open before an L1 request is let through again.
+    pub cooldown: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// The L1 requests go through.
+    Closed,
+    /// The L1 endpoint failed repeatedly, no request is made until the cooldown is over.
+    Open,
+    /// The cooldown is over, a single request is let through to probe the L1 endpoint.
+    HalfOpen,
+}
+
+/// Keeps the L1 sync from retrying a failing L1 endpoint in a tight loop. Once `failure_threshold` consecutive L1
+/// failures are recorded, the breaker opens: the L1 sync makes no request for `cooldown`. It then half-opens, and the
+/// next request closes it when it succeeds, or opens it again when it fails.
+///
+/// While the breaker is open, the L1 gas prices are not updated: block production pauses once they go stale.
+#[derive(Debug)]
+pub struct L1CircuitBreaker {
+    config: L1CircuitBreakerConfig,
+    consecutive_failures: u32,
+    /// Set while the breaker is open or half-open.
+    open_until: Option<Instant>,
+}
+
+impl L1CircuitBreaker {
+    pub fn new(config: L1CircuitBreakerConfig) -> Self {
+        Self { config, consecutive_failures: 0, open_until: None }
+    }
+
+    pub fn state(&self, now: Instant) -> CircuitState {
+        match self.open_until {
+            None => CircuitState::Closed,
+            Some(open_until) if now < open_until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Records a failed L1 request, and returns whether it opened the breaker.
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let opens = match self.state(now) {
+            CircuitState::Closed => {
+                self.config.failure_threshold > 0 && self.consecutive_failures >= self.config.failure_threshold
+            }
+            // The probe failed.
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        if opens {
+            self.open_until = Some(now + self.config.cooldown);
+        }
+        opens
+    }
+
+    /// Records a successful L1 request, which closes the breaker.
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    /// Time left before the breaker half-opens, zero when it is not open.
+    pub fn remaining_cooldown(&self, now: Instant) -> Duration {
+        self.open_until.map_or(Duration::ZERO, |open_until| open_until.saturating_duration_since(now))
+    }
+}
+
+/// Runs `worker`, reconnecting to the L1 and restarting it when it fails. The error is only returned once
+/// `max_retries` consecutive reconnection attempts have failed. When several L1 endpoints are configured, reconnecting
+/// fails over to the next working one, see [`EthereumClient::failover`]. Whether the L1 is connected is reported in the status
+/// of the service.
+///
+/// The worker and reconnection failures are recorded in an [`L1CircuitBreaker`]: while it is open, the next
+/// reconnection attempt waits for the end of its cooldown instead of the backoff, and the service reports the breaker
+/// as open in its status. A successful reconnection does not close the breaker, the worker must first run without
+/// failing for a while: a worker which fails right after every reconnection still opens it.
+///
+/// The worker is also restarted when the address of the L1 core contract changes, see
+/// [`EthereumClient::core_address`]. It is stopped through the context it is given, so that its in-flight iteration
+/// completes first.
+///
+/// The worker is stopped the same way while `pause` is set, and started again once it is cleared. The L1 client is kept
+/// in the meantime. Since the gas prices are not updated while paused, they go stale once paused for longer than the
+/// gas price max age.
+///
+/// When the endpoints of [`EthereumClient::l1_endpoints`] are rotated, a client is connected to the new endpoints while
+/// the worker keeps running on the current one. The worker is only restarted on the new client once it is connected.
+/// The current client is kept when the new endpoints cannot be connected to.
+pub async fn run_with_reconnect<F, Fut>(
+    eth_client: EthereumClient,
+    config: L1ReconnectConfig,
+    pause: PauseHandle,
+    ctx: ServiceContext,
+    worker: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(EthereumClient, ServiceContext) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    run_with_reconnect_and_warm_up(eth_client, config, pause, ctx, |_| async { anyhow::Ok(()) }, worker).await
+}
+
+/// Like [`run_with_reconnect`], and the client connected to rotated endpoints is only swapped in once `warm_up`
+/// succeeds on it, such as a gas price fetch: the gas prices are then updated right before the worker restarts, and
+/// they never go stale during the rotation.
+pub async fn run_with_reconnect_and_warm_up<W, WFut, F, Fut>(
+    mut eth_client: EthereumClient,
+    config: L1ReconnectConfig,
+    pause: PauseHandle,
+    ctx: ServiceContext,
+    warm_up: W,
+    mut worker: F,
+) -> anyhow::Result<()>
+where
+    W: Fn(EthereumClient) -> WFut,
+    WFut: Future<Output = anyhow::Result<()>>,
+    F: FnMut(EthereumClient, ServiceContext) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let mut retries = 0;
+    let mut backoff = config.backoff;
+    let mut breaker = L1CircuitBreaker::new(config.circuit_breaker.clone());
+    let mut core_address = eth_client.core_address().subscribe();
+    let mut endpoints = eth_client.l1_endpoints().subscribe();
+    let mut paused = pause.subscribe();
+    loop {
+        if *paused.borrow_and_update() {
+            tracing::info!("⏸️ L1 sync paused");
+            ctx.report_status(|status| status.paused = Some(true));
+            let resumed = async {
+                let _ = paused.wait_for(|is_paused| !*is_paused).await;
+            };
+            if wait_or_graceful_shutdown(resumed, &ctx).await.is_none() {
+                return Ok(());
+            }
+            tracing::info!("▶️ L1 sync resumed");
+            ctx.report_status(|status| status.paused = Some(false));
+        }
+
+        ctx.report_status(|status| status.connected = Some(true));
+        let run_ctx = ctx.child();
+        let run = worker(eth_client.clone(), run_ctx.clone());
+        tokio::pin!(run);
+        let healthy = tokio::time::sleep(HEALTHY_RUN_DURATION);
+        tokio::pin!(healthy);
+        let mut is_healthy = false;
+        let mut core_address_changed = false;
+        let mut pausing = false;
+        let mut rotation = std::pin::pin!(OptionFuture::from(None));
+        let mut rotating = false;
+        let mut rotated = None;
+        let res = loop {
+            tokio::select! {
+                res = &mut run => break res,
+                () = &mut healthy, if !is_healthy => {
+                    is_healthy = true;
+                    retries = 0;
+                    backoff = config.backoff;
+                    breaker.record_success();
+                    ctx.report_status(|status| status.circuit_open = Some(false));
+                }
+                Ok(()) = core_address.changed(), if !core_address_changed => {
+                    core_address_changed = true;
+                    run_ctx.cancel_local();
+                }
+                Ok(()) = paused.changed(), if !pausing => {
+                    if *paused.borrow_and_update() {
+                        pausing = true;
+                        run_ctx.cancel_local();
+                    }
+                }
+                // The rotations are made one at a time, the latest endpoints are connected to after the current one.
+                Ok(()) = endpoints.changed(), if !rotating && rotated.is_none() => {
+                    let urls = endpoints.borrow_and_update().clone();
+                    let connecting = connect_and_warm_up(eth_client.clone(), urls, &warm_up);
+                    rotating = true;
+                    rotation.set(OptionFuture::from(Some(connecting)));
+                }
+                Some(res) = rotation.as_mut(), if rotating => {
+                    rotating = false;
+                    rotation.set(OptionFuture::from(None));
+                    match res {
+                        Ok(new_client) => {
+                            rotated = Some(new_client);
+                            run_ctx.cancel_local();
+                        }
+                        Err(err) => {
+                            tracing::warn!("Could not rotate the L1 endpoints, keeping the current ones: {err:#}")
+                        }
+                    }
+                }
+            }
+        };
+        if rotating {
+            // The worker stopped before the rotation was complete, it is made again on the next run.
+            endpoints.mark_changed();
+        }
+        let rotated = if let Some(new_client) = rotated {
+            tracing::info!(
+                "🔁 L1 endpoints rotated from {} to {}",
+                redact_url(&eth_client.endpoints[eth_client.active_endpoint]),
+                redact_url(&new_client.endpoints[new_client.active_endpoint])
+            );
+            eth_client = new_client;
+            eth_client.l1_block_metrics.l1_active_endpoint.record(eth_client.active_endpoint as u64, &[]);
+            true
+        } else {
+            false
+        };
+        let err = match res {
+            Ok(()) if core_address_changed && !ctx.is_cancelled() => {
+                let address = *core_address.borrow_and_update();
+                tracing::info!("🔁 L1 core contract address changed to {address:#x}, restarting the L1 sync");
+                match eth_client.reload_core_contract().await {
+                    Ok(()) => continue,
+                    Err(err) => err,
+                }
+            }
+            // Waits for the resume at the start of the next run.
+            Ok(()) if (pausing || rotated) && !ctx.is_cancelled() => continue,
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        ctx.report_status(|status| status.connected = Some(false));
+        eth_client.l1_block_metrics.l1_rpc_errors.add(1, &[]);
+        record_failure(&mut breaker, &ctx);
+
+        loop {
+            if retries >= config.max_retries {
+                return Err(err.context(format!("L1 sync failed after {retries} reconnection attempts")));
+            }
+            retries += 1;
+            let wait = match breaker.state(Instant::now()) {
+                CircuitState::Open => breaker.remaining_cooldown(Instant::now()),
+                CircuitState::Closed | CircuitState::HalfOpen => backoff,
+            };
+            tracing::warn!(
+                "L1 sync failed, reconnecting in {wait:?} (attempt {retries}/{}): {err:#}",
+                config.max_retries
+            );
+            if wait_or_graceful_shutdown(tokio::time::sleep(wait), &ctx).await.is_none() {
+                return Ok(());
+            }
+            backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+
+            match eth_client.reconnect().await {
+                // The breaker is closed once the worker has run for long enough.
+                Ok(()) => break,
+                Err(reconnect_err) => {
+                    eth_client.l1_block_metrics.l1_rpc_errors.add(1, &[]);
+                    tracing::warn!("Could not reconnect to the L1: {reconnect_err:#}");
+                    record_failure(&mut breaker, &ctx);
+                }
+            }
+        }
+    }
+}
+
+/// Connects a client to the endpoints `urls`, and runs `warm_up` on it.
+async fn connect_and_warm_up<W, WFut>(
+    eth_client: EthereumClient,
+    urls: Vec<Url>,
+    warm_up: &W,
+) -> anyhow::Result<EthereumClient>
+where
+    W: Fn(EthereumClient) -> WFut,
+    WFut: Future<Output = anyhow::Result<()>>,
+{
+    let new_client = eth_client.connect_to(urls).await?;
+    warm_up(new_client.clone()).await.context("Warming up the client of the new L1 endpoints")?;
+    Ok(new_client)
+}
+
+/// Records an L1 failure in `breaker`, and reports the breaker as open in the service status when it opens.
+fn record_failure(breaker: &mut L1CircuitBreaker, ctx: &ServiceContext) {
+    if breaker.record_failure(Instant::now()) {
+        tracing::warn!(
+            "🔌 The L1 endpoint failed {} times in a row, pausing the L1 requests for {:?}",
+            breaker.consecutive_failures,
+            breaker.config.cooldown
+        );
+        ctx.report_status(|status| status.circuit_open = Some(true));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::eth_client_getter_test::{create_ethereum_client, get_shared_anvil};
+    use crate::client::StarknetCoreContract::LogMessageToL2;
+    use alloy::primitives::Address;
+    use mp_chain_config::parse_l1_core_address;
+    use serial_test::serial;
+    use std::sync::atomic::AtomicU32;
+
+    fn reconnect_config(max_retries: u32) -> L1ReconnectConfig {
+        L1ReconnectConfig {
+            max_retries,
+            backoff: Duration::from_millis(10),
+            circuit_breaker: L1CircuitBreakerConfig { failure_threshold: 0, cooldown: Duration::ZERO },
+        }
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn run_with_reconnect_resumes_after_dropped_connection() {
+        let anvil = get_shared_anvil();
+        let eth_client = create_ethereum_client(Some(anvil.endpoint().as_str()));
+        let runs = AtomicU32::new(0);
+
+        let ctx = ServiceContext::new_for_testing();
+        run_with_reconnect(eth_client, reconnect_config(3), PauseHandle::new(), ctx, |eth_client, _| {
+            let run = runs.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if run == 0 {
+                    anyhow::bail!("connection dropped");
+                }
+                // The reconnected client works.
+                eth_client.get_latest_block_number().await?;
+                Ok(())
+            }
+        })
+        .await
+        .expect("The worker should resume after reconnecting");
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn run_with_reconnect_gives_up_after_max_retries() {
+        // Nothing listens on this port, reconnecting always fails.
+        let eth_client = create_ethereum_client(Some("http://127.0.0.1:1"));
+        let runs = AtomicU32::new(0);
+
+        let ctx = ServiceContext::new_for_testing();
+        let res = run_with_reconnect(eth_client, reconnect_config(3), PauseHandle::new(), ctx, |_, _| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            async { anyhow::bail!("connection dropped") }
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn run_with_reconnect_watches_new_core_address() {
+        let mock_server = httpmock::MockServer::start();
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_getCode");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x6080"}));
+        });
+        let eth_client = create_ethereum_client(Some(&mock_server.url("/")));
+        let core_address = eth_client.core_address();
+        let new_address = parse_l1_core_address("0xE2Bb56ee936fd6433DC0F6e7e3b8365C906AA057").unwrap();
+        let runs = AtomicU32::new(0);
+
+        let ctx = ServiceContext::new_for_testing();
+        run_with_reconnect(eth_client, reconnect_config(0), PauseHandle::new(), ctx, |eth_client, ctx| {
+            let run = runs.fetch_add(1, Ordering::SeqCst);
+            let core_address = core_address.clone();
+            async move {
+                let filter = eth_client.l1_core_contract.event_filter::<LogMessageToL2>().filter;
+                if run == 0 {
+                    assert!(!filter.address.matches(&Address::from_slice(new_address.as_bytes())));
+                    core_address.set(new_address);
+                    // The worker is stopped to watch the new address.
+                    ctx.cancelled().await;
+                    return Ok(());
+                }
+                assert!(filter.address.matches(&Address::from_slice(new_address.as_bytes())));
+                Ok(())
+            }
+        })
+        .await
+        .expect("The worker should restart on the new address");
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    /// Waits for the L1 head synced by a test worker to move past its current value.
+    async fn wait_for_l1_head_advance(l1_head: &AtomicU64) {
+        let from = l1_head.load(Ordering::SeqCst);
+        let advanced = async {
+            while l1_head.load(Ordering::SeqCst) <= from {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), advanced).await.expect("The L1 head should advance");
+    }
+
+    #[tokio::test]
+    async fn run_with_reconnect_pauses_and_resumes() {
+        // The worker makes no L1 request.
+        let eth_client = create_ethereum_client(Some("http://127.0.0.1:1"));
+        let pause = PauseHandle::new();
+        let ctx = ServiceContext::new_for_testing();
+        let l1_head = Arc::new(AtomicU64::new(0));
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let worker = {
+            let l1_head = Arc::clone(&l1_head);
+            let runs = Arc::clone(&runs);
+            move |_: EthereumClient, ctx: ServiceContext| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                let l1_head = Arc::clone(&l1_head);
+                async move {
+                    // One L1 block every tick, until the worker is stopped.
+                    let tick = Duration::from_millis(5);
+                    while wait_or_graceful_shutdown(tokio::time::sleep(tick), &ctx).await.is_some() {
+                        l1_head.fetch_add(1, Ordering::SeqCst);
+                    }
+                    Ok(())
+                }
+            }
+        };
+        let run = run_with_reconnect(eth_client, reconnect_config(0), pause.clone(), ctx.clone(), worker);
+        let sync = tokio::spawn(run);
+        wait_for_l1_head_advance(&l1_head).await;
+
+        assert!(!pause.pause());
+        let reported_paused = async {
+            while ctx.service_status(ctx.id()).paused != Some(true) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), reported_paused).await.expect("The pause should be reported");
+        let paused_l1_head = l1_head.load(Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(l1_head.load(Ordering::SeqCst), paused_l1_head);
+
+        // The worker is started again.
+        assert!(pause.resume());
+        wait_for_l1_head_advance(&l1_head).await;
+        assert_eq!(ctx.service_status(ctx.id()).paused, Some(false));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        ctx.cancel_global();
+        sync.await.unwrap().expect("The L1 sync should stop cleanly");
+    }
+
+    #[test]
+    fn circuit_breaker_opens_then_half_opens() {
+        let cooldown = Duration::from_secs(30);
+        let mut breaker = L1CircuitBreaker::new(L1CircuitBreakerConfig { failure_threshold: 3, cooldown });
+        let start = Instant::now();
+
+        assert!(!breaker.record_failure(start));
+        assert!(!breaker.record_failure(start));
+        assert_eq!(breaker.state(start), CircuitState::Closed);
+        assert!(breaker.record_failure(start));
+        assert_eq!(breaker.state(start), CircuitState::Open);
+        assert_eq!(breaker.remaining_cooldown(start), cooldown);
+
+        // The breaker half-opens after the cooldown, a failed probe opens it again.
+        let after_cooldown = start + cooldown;
+        assert_eq!(breaker.state(after_cooldown), CircuitState::HalfOpen);
+        assert!(breaker.record_failure(after_cooldown));
+        assert_eq!(breaker.state(after_cooldown), CircuitState::Open);
+
+        // A successful probe closes it.
+        let after_second_cooldown = after_cooldown + cooldown;
+        assert_eq!(breaker.state(after_second_cooldown), CircuitState::HalfOpen);
+        breaker.record_success();
+        assert_eq!(breaker.state(after_second_cooldown), CircuitState::Closed);
+        assert!(!breaker.record_failure(after_second_cooldown));
+    }
+
+    #[test]
+    fn circuit_breaker_disabled() {
+        let mut breaker =
+            L1CircuitBreaker::new(L1CircuitBreakerConfig { failure_threshold: 0, cooldown: Duration::from_secs(30) });
+        for _ in 0..100 {
+            assert!(!breaker.record_failure(Instant::now()));
+        }
+        assert_eq!(breaker.state(Instant::now()), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn run_with_reconnect_waits_for_circuit_breaker_cooldown() {
+        // Nothing listens on this port, reconnecting always fails.
+        let eth_client = create_ethereum_client(Some("http://127.0.0.1:1"));
+        let cooldown = Duration::from_millis(200);
+        let config = L1ReconnectConfig {
+            circuit_breaker: L1CircuitBreakerConfig { failure_threshold: 1, cooldown },
+            ..reconnect_config(3)
+        };
+
+        let started_at = Instant::now();
+        let ctx = ServiceContext::new_for_testing();
+        let res = run_with_reconnect(eth_client, config, PauseHandle::new(), ctx, |_, _| async {
+            anyhow::bail!("connection dropped")
+        })
+        .await;
+
+        assert!(res.is_err());
+        // Every reconnection attempt waited for the breaker to half-open, instead of the 10ms backoff.
+        assert!(started_at.elapsed() >= cooldown * 3);
+    }
+
+    #[tokio::test]
+    async fn run_with_reconnect_opens_circuit_breaker_when_worker_fails_after_reconnecting() {
+        let mock_server = httpmock::MockServer::start();
+        mock_server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_getCode");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x6080"}));
+        });
+        // Reconnecting always succeeds.
+        let eth_client = create_ethereum_client(Some(&mock_server.url("/")));
+        let cooldown = Duration::from_millis(200);
+        let config = L1ReconnectConfig {
+            circuit_breaker: L1CircuitBreakerConfig { failure_threshold: 2, cooldown },
+            ..reconnect_config(3)
+        };
+        let runs = AtomicU32::new(0);
+
+        let started_at = Instant::now();
+        let ctx = ServiceContext::new_for_testing();
+        let res = run_with_reconnect(eth_client, config, PauseHandle::new(), ctx.clone(), |_, _| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            async { anyhow::bail!("connection dropped") }
+        })
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        // The breaker opened on the second failure, and the following attempts waited for its cooldown.
+        assert!(started_at.elapsed() >= cooldown * 2);
+        assert_eq!(ctx.service_status(ctx.id()).circuit_open, Some(true));
+    }
+}
@@ -351,7 +351,8 @@ async fn declare_transaction(
     add_transaction_provider: Arc<dyn AddTransactionProvider>,
 ) -> Response<String> {
     match add_transaction_provider.add_declare_transaction(tx.into()).await {
-        Ok(result) => create_json_response(hyper::StatusCode::OK, &result),
+        // The gateway response format has no backpressure signal.
+        Ok(submitted) => create_json_response(hyper::StatusCode::OK, &submitted.result),
         Err(e) => create_json_response(hyper::StatusCode::OK, &e),
     }
 }
@@ -361,7 +362,7 @@ async fn deploy_account_transaction(
     add_transaction_provider: Arc<dyn AddTransactionProvider>,
 ) -> Response<String> {
     match add_transaction_provider.add_deploy_account_transaction(tx.into()).await {
-        Ok(result) => create_json_response(hyper::StatusCode::OK, &result),
+        Ok(submitted) => create_json_response(hyper::StatusCode::OK, &submitted.result),
         Err(e) => create_json_response(hyper::StatusCode::OK, &e),
     }
 }
@@ -371,7 +372,7 @@ async fn invoke_transaction(
     add_transaction_provider: Arc<dyn AddTransactionProvider>,
 ) -> Response<String> {
     match add_transaction_provider.add_invoke_transaction(tx.into()).await {
-        Ok(result) => create_json_response(hyper::StatusCode::OK, &result),
+        Ok(submitted) => create_json_response(hyper::StatusCode::OK, &submitted.result),
         Err(e) => create_json_response(hyper::StatusCode::OK, &e),
     }
 }
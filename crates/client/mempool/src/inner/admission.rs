@@ -0,0 +1,30 @@
+use super::{MempoolLimiter, MempoolTransaction};
+use std::fmt;
+
+/// Why an [`AdmissionPolicy`] refused a transaction. The message is returned to the submitter.
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("{0}")]
+pub struct RejectReason(pub String);
+
+impl RejectReason {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self(reason.into())
+    }
+}
+
+/// Deployment specific admission rules, such as sender allowlists or custom fee rules. The mempool consults the policy
+/// after the built-in limits have accepted a transaction, and before inserting it. Transactions added back to the
+/// mempool by block production are not checked again.
+pub trait AdmissionPolicy: fmt::Debug + Send + Sync {
+    fn admit(&self, tx: &MempoolTransaction, limiter: &MempoolLimiter) -> Result<(), RejectReason>;
+}
+
+/// Admits every transaction. This is the default admission policy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AdmitAll;
+
+impl AdmissionPolicy for AdmitAll {
+    fn admit(&self, _tx: &MempoolTransaction, _limiter: &MempoolLimiter) -> Result<(), RejectReason> {
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::time::{Instant, SystemTime};
+
+/// Source of the current time for the mempool: the arrival timestamps of the transactions and their age checks.
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The wall clock, [`SystemTime::now`]. This is the default mempool clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock which reads the wall clock once when created, and then only moves forward with a monotonic [`Instant`].
+/// Adjustments of the system clock while the node is running do not make transactions expire early or late.
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicClock {
+    start: SystemTime,
+    started_at: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self { start: SystemTime::now(), started_at: Instant::now() }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> SystemTime {
+        self.start + self.started_at.elapsed()
+    }
+}
+
+/// A clock which only moves when told to, for tests.
+#[cfg(any(test, feature = "testing"))]
+#[derive(Clone, Debug)]
+pub struct FakeClock(std::sync::Arc<std::sync::Mutex<SystemTime>>);
+
+#[cfg(any(test, feature = "testing"))]
+impl FakeClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self(std::sync::Arc::new(std::sync::Mutex::new(now)))
+    }
+
+    pub fn advance(&self, duration: std::time::Duration) {
+        *self.0.lock().expect("Poisoned lock") += duration;
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.0.lock().expect("Poisoned lock") = now;
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().expect("Poisoned lock")
+    }
+}
@@ -0,0 +1,47 @@
+use super::DropReason;
+use starknet_types_core::felt::Felt;
+use std::collections::{BTreeMap, HashMap};
+
+/// Bounded LRU map of the recently dropped transaction hashes to why they were dropped, so that users can find out
+/// what happened to a transaction which left the mempool without being included. Past the capacity, the transactions
+/// dropped the longest ago are forgotten.
+#[derive(Debug)]
+pub(crate) struct RecentlyDropped {
+    capacity: usize,
+    /// Drop reason of each transaction, and when it was dropped.
+    reasons: HashMap<Felt, (DropReason, u64)>,
+    /// Transactions by drop order, the least recently dropped first.
+    by_drop: BTreeMap<u64, Felt>,
+    next_drop: u64,
+}
+
+impl RecentlyDropped {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, reasons: HashMap::new(), by_drop: BTreeMap::new(), next_drop: 0 }
+    }
+
+    pub fn record(&mut self, tx_hash: Felt, reason: DropReason) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.forget(tx_hash);
+        while self.reasons.len() >= self.capacity {
+            let Some((_, oldest)) = self.by_drop.pop_first() else { break };
+            self.reasons.remove(&oldest);
+        }
+        self.reasons.insert(tx_hash, (reason, self.next_drop));
+        self.by_drop.insert(self.next_drop, tx_hash);
+        self.next_drop += 1;
+    }
+
+    /// Forgets a transaction, for when it is added back to the mempool.
+    pub fn forget(&mut self, tx_hash: Felt) {
+        if let Some((_, dropped_at)) = self.reasons.remove(&tx_hash) {
+            self.by_drop.remove(&dropped_at);
+        }
+    }
+
+    pub fn get(&self, tx_hash: &Felt) -> Option<DropReason> {
+        self.reasons.get(tx_hash).map(|(reason, _)| *reason)
+    }
+}
@@ -1,44 +1,343 @@
+use std::collections::{hash_map, HashMap, HashSet};
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
+use blockifier::transaction::account_transaction::AccountTransaction;
+use blockifier::transaction::transaction_execution::Transaction;
 use blockifier::transaction::transaction_types::TransactionType;
 use mc_exec::execution::TxInfo;
-use mp_chain_config::ChainConfig;
+use mp_chain_config::{ChainConfig, MempoolDeclareLimitPolicy, MempoolExpiredTxPolicy};
+use mp_convert::ToFelt;
+#[cfg(feature = "metrics")]
+use opentelemetry::KeyValue;
+use serde::{Deserialize, Serialize};
+use starknet_api::core::ContractAddress;
+use starknet_types_core::felt::Felt;
 
+use super::clock::{Clock, SystemClock};
+use super::dropped::RecentlyDropped;
+use super::throughput::{EventRate, MempoolThroughput};
+#[cfg(feature = "metrics")]
+use crate::metrics::MempoolMetrics;
 use crate::MempoolTransaction;
 
+/// Number of consecutive full blocks, or blocks which were not full, after which the auto-tuned transaction limit
+/// moves by one step. See [`MempoolLimiter::record_block_fullness`].
+pub const AUTO_TUNE_BLOCKS: u32 = 3;
+/// Step of the auto-tuned transaction limit, in percent of the current limit.
+pub const AUTO_TUNE_STEP_PERCENT: usize = 10;
+
+/// Bounds [`MempoolLimits::max_transactions`] is auto-tuned within, see [`MempoolLimiter::record_block_fullness`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MempoolAutoTune {
+    pub min_transactions: usize,
+    pub max_transactions: usize,
+}
+
 #[derive(Debug)]
 pub struct MempoolLimits {
     pub max_transactions: usize,
     pub max_declare_transactions: usize,
-    pub max_age: Duration,
+    /// Whether the declare transactions over `max_declare_transactions` are rejected, or accepted and served last.
+    pub declare_limit_policy: MempoolDeclareLimitPolicy,
+    pub max_transactions_per_sender: usize,
+    /// Part of `max_transactions` that only L1 handler transactions can use.
+    pub reserved_l1_handler_transactions: usize,
+    /// Part of `max_transactions` that only deploy account transactions can use.
+    pub reserved_deploy_account_transactions: usize,
+    /// `None` disables the age limit: the transactions never expire.
+    pub max_age: Option<Duration>,
+    /// Whether the submitted transactions older than `max_age` are rejected, or accepted and left to the age sweeper.
+    pub expired_tx_policy: MempoolExpiredTxPolicy,
+    /// Evict the lowest-tip transaction instead of rejecting an incoming transaction when the mempool is full.
+    pub eviction_enabled: bool,
+    /// Minimum tip increase, in percent, for a transaction to replace another one with the same sender and nonce.
+    pub replacement_bump_percent: u64,
+    /// Minimum time between two replacements of the transaction with the same sender and nonce. Zero disables it.
+    pub replacement_min_interval: Duration,
+    /// Fraction of `max_transactions` above which the mempool is considered near capacity.
+    pub near_capacity_watermark: f64,
+    /// Senders which bypass `max_transactions` and `max_declare_transactions`, such as trusted relayers. The other
+    /// limits still apply to them.
+    pub privileged_senders: HashSet<ContractAddress>,
+    /// Limit of the cumulative [`MempoolTransaction::encoded_size`] of the transactions in the mempool, as a few
+    /// declare transactions with huge classes could exhaust the memory well before `max_transactions` is reached.
+    /// L1 handler transactions are not limited.
+    pub max_total_bytes: usize,
+    /// Limit of the code size of the class of a declare transaction, in bytes. See
+    /// [`ClassInfo::code_size`](blockifier::execution::contract_class::ClassInfo::code_size).
+    pub max_declare_bytecode_size: usize,
+    /// Limit of the cumulative L2 gas max amount of the V3 transactions in the mempool, so that it does not hold more
+    /// work than block production could ever execute. The older transactions and L1 handler transactions do not
+    /// declare an L2 gas amount.
+    pub max_total_l2_gas: u64,
+    /// Transactions with a nonce more than this above the nonce of their account are rejected, so that a sender cannot
+    /// fill the mempool with transactions buffered behind a huge nonce gap.
+    pub max_nonce_distance: u64,
+    /// V3 transactions with a tip below the current STRK L1 gas price times this multiplier are rejected. `0` disables
+    /// the minimum.
+    pub min_tip_multiplier: f64,
+    /// Whether the account transactions which pay no fee are accepted, see [`MempoolTransaction::is_zero_fee`]. L1
+    /// handler transactions are paid on L1, they are always accepted.
+    pub allow_zero_fee_transactions: bool,
+    /// Whether resubmitting a transaction already in the mempool refreshes its arrival time instead of being reported
+    /// as [`InsertOutcome::AlreadyKnown`](super::InsertOutcome::AlreadyKnown). The transaction is not counted twice.
+    pub refresh_on_resubmit: bool,
+    /// Number of recently dropped transactions whose drop reason is remembered, see
+    /// [`MempoolLimiter::drop_reason`].
+    pub dropped_transactions_capacity: usize,
+    /// Auto-tunes `max_transactions` within these bounds from the fullness of the blocks produced. `None` keeps it
+    /// fixed.
+    pub auto_tune: Option<MempoolAutoTune>,
 }
 
 impl MempoolLimits {
     pub fn new(chain_config: &ChainConfig) -> Self {
+        // A zero ceiling in the chain config disables the auto-tuning.
+        let auto_tune = (chain_config.mempool_tx_limit_max > 0).then_some(MempoolAutoTune {
+            min_transactions: chain_config.mempool_tx_limit_min,
+            max_transactions: chain_config.mempool_tx_limit_max,
+        });
+        let max_transactions = match auto_tune {
+            Some(bounds) => chain_config.mempool_tx_limit.max(bounds.min_transactions).min(bounds.max_transactions),
+            None => chain_config.mempool_tx_limit,
+        };
         Self {
-            max_transactions: chain_config.mempool_tx_limit,
+            max_transactions,
             max_declare_transactions: chain_config.mempool_declare_tx_limit,
-            max_age: chain_config.mempool_tx_max_age,
+            declare_limit_policy: chain_config.mempool_declare_limit_policy,
+            max_transactions_per_sender: chain_config.mempool_tx_limit_per_sender,
+            reserved_l1_handler_transactions: chain_config.mempool_l1_handler_tx_reserved,
+            reserved_deploy_account_transactions: chain_config.mempool_deploy_account_tx_reserved,
+            // A zero max age in the chain config disables the limit.
+            max_age: Some(chain_config.mempool_tx_max_age).filter(|max_age| !max_age.is_zero()),
+            expired_tx_policy: chain_config.mempool_expired_tx_policy,
+            eviction_enabled: chain_config.mempool_eviction_enabled,
+            replacement_bump_percent: chain_config.mempool_replacement_bump_percent,
+            replacement_min_interval: chain_config.mempool_replacement_min_interval,
+            near_capacity_watermark: chain_config.mempool_near_capacity_watermark,
+            privileged_senders: chain_config.mempool_privileged_senders.iter().copied().collect(),
+            max_total_bytes: chain_config.mempool_max_total_bytes,
+            max_declare_bytecode_size: chain_config.max_declare_bytecode_size,
+            max_total_l2_gas: chain_config.mempool_max_total_l2_gas,
+            max_nonce_distance: chain_config.mempool_max_nonce_distance,
+            min_tip_multiplier: chain_config.mempool_min_tip_multiplier,
+            allow_zero_fee_transactions: chain_config.allow_zero_fee_transactions,
+            refresh_on_resubmit: chain_config.mempool_refresh_on_resubmit,
+            dropped_transactions_capacity: chain_config.mempool_dropped_tx_lru_size,
+            auto_tune,
         }
     }
     #[cfg(any(test, feature = "testing"))]
     pub fn for_testing() -> Self {
         Self {
-            max_age: Duration::from_secs(10000000),
+            max_age: Some(Duration::from_secs(10000000)),
+            expired_tx_policy: MempoolExpiredTxPolicy::Reject,
             max_declare_transactions: usize::MAX,
+            declare_limit_policy: MempoolDeclareLimitPolicy::Reject,
             max_transactions: usize::MAX,
+            max_transactions_per_sender: usize::MAX,
+            reserved_l1_handler_transactions: 0,
+            reserved_deploy_account_transactions: 0,
+            eviction_enabled: false,
+            replacement_bump_percent: 10,
+            replacement_min_interval: Duration::ZERO,
+            near_capacity_watermark: 0.9,
+            privileged_senders: HashSet::new(),
+            max_total_bytes: usize::MAX,
+            max_declare_bytecode_size: usize::MAX,
+            max_total_l2_gas: u64::MAX,
+            max_nonce_distance: u64::MAX,
+            min_tip_multiplier: 0.0,
+            allow_zero_fee_transactions: true,
+            refresh_on_resubmit: false,
+            dropped_transactions_capacity: 10_000,
+            auto_tune: None,
+        }
+    }
+
+    fn reserved(&self, reservation: Reservation) -> usize {
+        match reservation {
+            Reservation::L1Handler => self.reserved_l1_handler_transactions,
+            Reservation::DeployAccount => self.reserved_deploy_account_transactions,
+        }
+    }
+
+    fn total_reserved(&self) -> usize {
+        self.reserved_l1_handler_transactions.saturating_add(self.reserved_deploy_account_transactions)
+    }
+
+    /// Capacity shared by all transactions, including the reserved ones once their reservation is full.
+    fn unreserved_transactions(&self) -> usize {
+        self.max_transactions.saturating_sub(self.total_reserved())
+    }
+}
+
+/// Mempool limits which can be changed at runtime. `None` keeps the current limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MempoolLimitsUpdate {
+    pub max_transactions: Option<usize>,
+    pub max_declare_transactions: Option<usize>,
+    pub max_age: Option<Duration>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum InvalidMempoolLimits {
+    #[error("The transaction limit must be at least {min}, the reserved capacity plus one")]
+    MaxTransactionsTooLow { min: usize },
+    #[error("The transaction max age must not be zero")]
+    ZeroMaxAge,
+}
+
+/// Occupancy counters of the mempool limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolCounters {
+    pub transactions: usize,
+    pub declare_transactions: usize,
+    /// Cumulative [`MempoolTransaction::encoded_size`] of the transactions.
+    pub bytes: usize,
+    /// Transactions popped for block production which have been neither included nor re-added yet. They are also
+    /// counted in `transactions`.
+    #[serde(default)]
+    pub in_flight_transactions: usize,
+    /// Cumulative L2 gas max amount of the V3 transactions.
+    #[serde(default)]
+    pub l2_gas: u64,
+}
+
+/// Transaction counter of the mempool limits which does not match the transactions actually in the mempool, see
+/// [`MempoolLimiter::check_counter_consistency`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterDivergence {
+    /// Transactions counted against the limits.
+    pub counted: usize,
+    /// Transactions actually in the mempool.
+    pub in_mempool: usize,
+    /// Transactions popped for block production, which are counted but no longer in the mempool.
+    pub in_flight: usize,
+}
+
+impl CounterDivergence {
+    /// Number of transactions by which the counter is off the expected window.
+    pub fn gap(&self) -> usize {
+        if self.counted < self.in_mempool {
+            self.in_mempool - self.counted
+        } else {
+            self.counted - (self.in_mempool + self.in_flight)
         }
     }
 }
 
-/// Note: when a transaction is poped from the mempool by block prod, the limits will not be updated until the full
-/// tick has been executed and excess transactions are added back into the mempool.
-/// This means that the inner mempool may have fewer transactions than what the limits says at a given time.
+/// Counters of the transactions of a [`TransactionType`] in the mempool.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransactionTypeCounters {
+    /// Transactions of this type currently in the mempool.
+    pub current: usize,
+    /// Transactions of this type inserted since the mempool was created, including the replacements and the popped
+    /// transactions added back.
+    pub inserted: u64,
+    /// Transactions of this type removed since the mempool was created, whether they were included in a block,
+    /// replaced, evicted or expired.
+    pub removed: u64,
+}
+
+/// Why a transaction was dropped from the mempool without being included in a block, or never entered it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    /// The transaction exceeded the max age or its deadline.
+    Expired,
+    /// The transaction was evicted to make room for a higher priority one.
+    Evicted,
+    /// The transaction was replaced by another one with the same sender and nonce.
+    Replaced,
+    /// The transaction was rejected on insertion because of a [`MempoolLimitReached`].
+    RejectedOverLimit,
+    /// The transaction was removed by the node operator.
+    RemovedByOperator,
+}
+
+impl DropReason {
+    /// Label used for this reason in metrics.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Expired => "expired",
+            Self::Evicted => "evicted",
+            Self::Replaced => "replaced",
+            Self::RejectedOverLimit => "rejected_over_limit",
+            Self::RemovedByOperator => "removed_by_operator",
+        }
+    }
+}
+
+/// Label of a transaction type in metrics.
+#[cfg(feature = "metrics")]
+fn tx_type_label(tx_type: TransactionType) -> &'static str {
+    match tx_type {
+        TransactionType::InvokeFunction => "invoke",
+        TransactionType::Declare => "declare",
+        TransactionType::DeployAccount => "deploy_account",
+        TransactionType::L1Handler => "l1_handler",
+    }
+}
+
+/// Transaction types with a reserved part of the mempool capacity, so that other transactions cannot starve them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Reservation {
+    L1Handler,
+    DeployAccount,
+}
+
+impl Reservation {
+    const ALL: [Self; 2] = [Self::L1Handler, Self::DeployAccount];
+}
+
+/// Note: a transaction popped from the mempool by block prod is reserved for the block with
+/// [`MempoolLimiter::reserve_for_block`]: it is still counted until the full tick has been executed, and the
+/// reservation is released with [`MempoolLimiter::release_reservation`] when the transaction is included or added back.
+/// This means that the inner mempool may have fewer transactions than what the limits says at a given time, but new
+/// transactions cannot fill the room of the transactions being executed.
 #[derive(Debug)]
-pub(crate) struct MempoolLimiter {
+pub struct MempoolLimiter {
     pub config: MempoolLimits,
     current_transactions: usize,
     current_declare_transactions: usize,
+    current_l1_handler_transactions: usize,
+    current_deploy_account_transactions: usize,
+    /// Cumulative encoded size of the transactions in the mempool.
+    current_bytes: usize,
+    /// Cumulative L2 gas max amount of the transactions in the mempool.
+    current_l2_gas: u64,
+    /// Transactions reserved for block production, see [`MempoolLimiter::reserve_for_block`].
+    current_in_flight_transactions: usize,
+    /// Number of transactions in the mempool for each sender. Senders with no transactions are pruned from the map.
+    current_transactions_per_sender: HashMap<ContractAddress, usize>,
+    /// Counters of each transaction type, types never inserted are absent.
+    per_type_counters: HashMap<TransactionType, TransactionTypeCounters>,
+    /// Transactions dropped since the mempool was created, for each reason. Reasons never hit are absent.
+    dropped_transactions: HashMap<DropReason, u64>,
+    /// Drop reason of the recently dropped transactions.
+    recently_dropped: RecentlyDropped,
+    /// Minimum tip of the V3 transactions, derived from the L1 gas price by [`MempoolLimiter::update_min_tip`].
+    min_tip: u64,
+    /// Number of [`MempoolLimiter::mark_removed`] calls for transactions the counters did not account for.
+    counter_underflows: u64,
+    /// Number of [`MempoolLimiter::check_counter_consistency`] calls which found a divergence.
+    counter_divergences: u64,
+    /// Number of consecutive full blocks reported to [`MempoolLimiter::record_block_fullness`], since the last step
+    /// of the auto-tuned transaction limit.
+    consecutive_full_blocks: u32,
+    /// Same as `consecutive_full_blocks`, for the blocks which were not full.
+    consecutive_underutilized_blocks: u32,
+    /// Transactions accepted by [`MempoolInner::insert_tx`](super::MempoolInner::insert_tx), forced insertions
+    /// excluded.
+    accepted: EventRate,
+    /// Transactions popped for block production.
+    popped: EventRate,
+    /// Occupancy metrics, only published when set.
+    #[cfg(feature = "metrics")]
+    metrics: Option<MempoolMetrics>,
+    /// Source of the current time for the age checks.
+    clock: Arc<dyn Clock>,
 }
 
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
@@ -47,48 +346,166 @@ pub enum MempoolLimitReached {
     MaxTransactions { max: usize },
     #[error("The mempool has reached the limit of {max} declare transactions")]
     MaxDeclareTransactions { max: usize },
+    #[error("The mempool has reached the limit of {max} transactions outside of the capacity reserved for L1 handler and deploy account transactions")]
+    MaxUnreservedTransactions { max: usize },
+    #[error("The mempool has reached the limit of {max} bytes")]
+    MaxBytes { max: usize },
+    #[error("The mempool has reached the limit of {max} L2 gas")]
+    MaxL2Gas { max: u64 },
+    #[error("The declared class has a code size of {size} bytes, which is greater than the limit of {max} bytes")]
+    DeclareBytecodeTooLarge { size: usize, max: usize },
+    #[error("The transaction pays no fee, which this chain does not accept")]
+    ZeroFee,
+    #[error("The transaction tip of {tip} is below the current minimum tip of {min_tip}")]
+    TipTooLow { tip: u64, min_tip: u64 },
+    #[error("The mempool has reached the limit of {max} transactions for sender {sender:#x}")]
+    MaxPerSender { sender: Felt, max: usize },
     #[error("The transaction age is greater than the limit of {max:?}")]
     Age { max: Duration },
+    #[error("The transaction deadline of {deadline:?} has passed")]
+    DeadlinePassed { deadline: SystemTime },
+}
+
+impl MempoolLimitReached {
+    /// Stable name of this limit, used as the label of the rejection metrics and in the RPC error data.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::MaxTransactions { .. } => "max_transactions",
+            Self::MaxDeclareTransactions { .. } => "max_declare_transactions",
+            Self::MaxUnreservedTransactions { .. } => "max_unreserved_transactions",
+            Self::MaxBytes { .. } => "max_bytes",
+            Self::MaxL2Gas { .. } => "max_l2_gas",
+            Self::DeclareBytecodeTooLarge { .. } => "max_declare_bytecode_size",
+            Self::ZeroFee => "zero_fee",
+            Self::TipTooLow { .. } => "min_tip",
+            Self::MaxPerSender { .. } => "max_per_sender",
+            Self::Age { .. } => "age",
+            Self::DeadlinePassed { .. } => "deadline",
+        }
+    }
+
+    /// Stable JSON-RPC error code of this limit. Each limit has its own code, so that clients can react to them
+    /// differently: a declare rejected with [`MempoolLimitReached::MaxDeclareTransactions`] should not be retried right
+    /// away, while an invoke can still go through. The codes are listed in the README, and must not be reused.
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::MaxTransactions { .. } => 10100,
+            Self::MaxDeclareTransactions { .. } => 10101,
+            Self::MaxUnreservedTransactions { .. } => 10102,
+            Self::MaxBytes { .. } => 10103,
+            Self::MaxL2Gas { .. } => 10104,
+            Self::DeclareBytecodeTooLarge { .. } => 10105,
+            Self::ZeroFee => 10106,
+            Self::TipTooLow { .. } => 10107,
+            Self::MaxPerSender { .. } => 10108,
+            Self::Age { .. } => 10109,
+            Self::DeadlinePassed { .. } => 10110,
+        }
+    }
 }
 
 pub(crate) struct TransactionCheckedLimits {
+    tx_hash: Felt,
+    tx_type: TransactionType,
     check_tx_limit: bool,
     check_declare_limit: bool,
+    check_bytes_limit: bool,
     check_age: bool,
+    /// Reserved capacity this transaction can use before falling back to the unreserved capacity.
+    reservation: Option<Reservation>,
+    /// L1 handler transactions do not have a sender, so they are not tracked per sender.
+    sender: Option<ContractAddress>,
     tx_arrived_at: SystemTime,
+    /// See [`MempoolTransaction::deadline`].
+    deadline: Option<SystemTime>,
+    encoded_size: usize,
+    /// L2 gas max amount of the resource bounds, zero for the transactions without one.
+    l2_gas: u64,
+    /// Code size of the declared class, only set for declare transactions.
+    declare_bytecode_size: Option<usize>,
+    /// Only V3 transactions have a tip, the minimum tip does not apply to the other transactions.
+    tip: Option<u64>,
+    /// Whether the transaction pays no fee, always false for L1 handler transactions.
+    zero_fee: bool,
 }
 
 impl TransactionCheckedLimits {
     // Returns which limits apply for this transaction.
     // This struct is also used to update the limits after insertion, without having to keep a clone of the transaction around.
     // We can add more limits here as needed :)
-    pub fn limits_for(tx: &MempoolTransaction) -> Self {
+    pub fn limits_for(tx: &MempoolTransaction, privileged_senders: &HashSet<ContractAddress>) -> Self {
+        // Like L1 handler transactions, the privileged senders are exempt from the capacity limits.
+        let privileged = || privileged_senders.contains(&tx.contract_address());
         match tx.tx.tx_type() {
             TransactionType::Declare => TransactionCheckedLimits {
-                check_tx_limit: true,
-                check_declare_limit: true,
+                tx_type: TransactionType::Declare,
+                check_tx_limit: !privileged(),
+                check_declare_limit: !privileged(),
+                check_bytes_limit: true,
                 check_age: true,
+                reservation: None,
+                sender: Some(tx.contract_address()),
                 tx_arrived_at: tx.arrived_at,
+                tx_hash: tx.tx_hash().to_felt(),
+                deadline: tx.deadline,
+                encoded_size: tx.encoded_size,
+                l2_gas: tx.max_l2_gas_amount().unwrap_or(0),
+                declare_bytecode_size: declare_bytecode_size(tx),
+                tip: tx.v3_tip(),
+                zero_fee: tx.is_zero_fee(),
             },
             TransactionType::DeployAccount => TransactionCheckedLimits {
-                check_tx_limit: true,
+                tx_type: TransactionType::DeployAccount,
+                check_tx_limit: !privileged(),
                 check_declare_limit: false,
+                check_bytes_limit: true,
                 check_age: true,
+                reservation: Some(Reservation::DeployAccount),
+                sender: Some(tx.contract_address()),
                 tx_arrived_at: tx.arrived_at,
+                tx_hash: tx.tx_hash().to_felt(),
+                deadline: tx.deadline,
+                encoded_size: tx.encoded_size,
+                l2_gas: tx.max_l2_gas_amount().unwrap_or(0),
+                declare_bytecode_size: None,
+                tip: tx.v3_tip(),
+                zero_fee: tx.is_zero_fee(),
             },
             TransactionType::InvokeFunction => TransactionCheckedLimits {
-                check_tx_limit: true,
+                tx_type: TransactionType::InvokeFunction,
+                check_tx_limit: !privileged(),
                 check_declare_limit: false,
+                check_bytes_limit: true,
                 check_age: true,
+                reservation: None,
+                sender: Some(tx.contract_address()),
                 tx_arrived_at: tx.arrived_at,
+                tx_hash: tx.tx_hash().to_felt(),
+                deadline: tx.deadline,
+                encoded_size: tx.encoded_size,
+                l2_gas: tx.max_l2_gas_amount().unwrap_or(0),
+                declare_bytecode_size: None,
+                tip: tx.v3_tip(),
+                zero_fee: tx.is_zero_fee(),
             },
             // L1 handler transactions are transactions added into the L1 core contract. We don't want to miss
             // any of those if possible.
             TransactionType::L1Handler => TransactionCheckedLimits {
+                tx_type: TransactionType::L1Handler,
                 check_tx_limit: false,
                 check_declare_limit: false,
+                check_bytes_limit: false,
                 check_age: false,
+                reservation: Some(Reservation::L1Handler),
+                sender: None,
                 tx_arrived_at: tx.arrived_at,
+                tx_hash: tx.tx_hash().to_felt(),
+                deadline: tx.deadline,
+                encoded_size: tx.encoded_size,
+                l2_gas: 0,
+                declare_bytecode_size: None,
+                tip: None,
+                zero_fee: false,
             },
         }
     }
@@ -96,52 +513,581 @@ impl TransactionCheckedLimits {
 
 impl MempoolLimiter {
     pub fn new(limits: MempoolLimits) -> Self {
-        Self { config: limits, current_transactions: 0, current_declare_transactions: 0 }
+        Self {
+            recently_dropped: RecentlyDropped::new(limits.dropped_transactions_capacity),
+            config: limits,
+            current_transactions: 0,
+            current_declare_transactions: 0,
+            current_l1_handler_transactions: 0,
+            current_deploy_account_transactions: 0,
+            current_bytes: 0,
+            current_l2_gas: 0,
+            current_in_flight_transactions: 0,
+            current_transactions_per_sender: HashMap::new(),
+            per_type_counters: HashMap::new(),
+            dropped_transactions: HashMap::new(),
+            min_tip: 0,
+            counter_underflows: 0,
+            counter_divergences: 0,
+            consecutive_full_blocks: 0,
+            consecutive_underutilized_blocks: 0,
+            accepted: EventRate::default(),
+            popped: EventRate::default(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            clock: Arc::new(SystemClock),
+        }
     }
 
-    pub fn check_insert_limits(&self, to_check: &TransactionCheckedLimits) -> Result<(), MempoolLimitReached> {
-        // tx limit
-        if to_check.check_tx_limit && self.current_transactions >= self.config.max_transactions {
-            return Err(MempoolLimitReached::MaxTransactions { max: self.config.max_transactions });
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    /// Publishes the age of the oldest transaction in the mempool, `None` when it is empty.
+    #[cfg(feature = "metrics")]
+    pub fn record_oldest_transaction_age(&self, age: Option<Duration>) {
+        if let Some(metrics) = &self.metrics {
+            metrics.oldest_transaction_age.record(age.unwrap_or_default().as_secs_f64(), &[]);
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics(&mut self, metrics: MempoolMetrics) {
+        self.metrics = Some(metrics);
+        self.publish_metrics();
+    }
+
+    /// See [`TransactionCheckedLimits::limits_for`].
+    pub(crate) fn limits_for(&self, tx: &MempoolTransaction) -> TransactionCheckedLimits {
+        TransactionCheckedLimits::limits_for(tx, &self.config.privileged_senders)
+    }
+
+    pub fn counters(&self) -> MempoolCounters {
+        MempoolCounters {
+            transactions: self.current_transactions,
+            declare_transactions: self.current_declare_transactions,
+            bytes: self.current_bytes,
+            in_flight_transactions: self.current_in_flight_transactions,
+            l2_gas: self.current_l2_gas,
+        }
+    }
+
+    /// Counters of the transactions of `tx_type`, zero for a type never inserted.
+    pub fn type_counters(&self, tx_type: TransactionType) -> TransactionTypeCounters {
+        self.per_type_counters.get(&tx_type).copied().unwrap_or_default()
+    }
+
+    /// Transactions dropped for this `reason` since the mempool was created.
+    pub fn dropped_transactions(&self, reason: DropReason) -> u64 {
+        self.dropped_transactions.get(&reason).copied().unwrap_or_default()
+    }
+
+    /// Why the transaction with this hash was dropped, if it was dropped recently and not added back since. The drop
+    /// reasons of the [`MempoolLimits::dropped_transactions_capacity`] most recently dropped transactions are kept.
+    pub fn drop_reason(&self, tx_hash: &Felt) -> Option<DropReason> {
+        self.recently_dropped.get(tx_hash)
+    }
+
+    /// Ratio of transactions in the mempool against the transaction limit.
+    pub fn utilization(&self) -> f64 {
+        utilization(self.current_transactions, self.config.max_transactions)
+    }
+
+    /// Whether there are more transactions in the mempool than the transaction limit, which happens when the limit is
+    /// lowered at runtime. Evicting a single transaction then does not make room for another one.
+    pub fn is_over_tx_limit(&self) -> bool {
+        self.current_transactions > self.config.max_transactions
+    }
+
+    /// Changes the limits at runtime, and returns the previous ones. Lowering a limit below the current occupancy does
+    /// not remove any transaction: the new transactions are rejected until the mempool has drained below it.
+    pub fn update_limits(&mut self, update: MempoolLimitsUpdate) -> Result<MempoolLimitsUpdate, InvalidMempoolLimits> {
+        let min = self.config.total_reserved().saturating_add(1);
+        if update.max_transactions.is_some_and(|max| max < min) {
+            return Err(InvalidMempoolLimits::MaxTransactionsTooLow { min });
+        }
+        if update.max_age == Some(Duration::ZERO) {
+            return Err(InvalidMempoolLimits::ZeroMaxAge);
+        }
+
+        let previous = MempoolLimitsUpdate {
+            max_transactions: Some(self.config.max_transactions),
+            max_declare_transactions: Some(self.config.max_declare_transactions),
+            max_age: self.config.max_age,
+        };
+        self.config.max_transactions = update.max_transactions.unwrap_or(self.config.max_transactions);
+        self.config.max_declare_transactions =
+            update.max_declare_transactions.unwrap_or(self.config.max_declare_transactions);
+        self.config.max_age = update.max_age.or(self.config.max_age);
+        self.publish_metrics();
+        Ok(previous)
+    }
+
+    /// Auto-tunes [`MempoolLimits::max_transactions`] from the fullness of the blocks produced, when
+    /// [`MempoolLimits::auto_tune`] is set. After [`AUTO_TUNE_BLOCKS`] consecutive full blocks, the limit is raised by
+    /// [`AUTO_TUNE_STEP_PERCENT`] toward the ceiling; after as many blocks which were not full, it settles back toward
+    /// the floor the same way. It never goes below the reserved capacity plus one. Like
+    /// [`MempoolLimiter::update_limits`], lowering the limit does not evict anything. Returns the current limit.
+    pub fn record_block_fullness(&mut self, full: bool) -> usize {
+        let Some(bounds) = self.config.auto_tune else { return self.config.max_transactions };
+        let streak = if full {
+            self.consecutive_underutilized_blocks = 0;
+            &mut self.consecutive_full_blocks
+        } else {
+            self.consecutive_full_blocks = 0;
+            &mut self.consecutive_underutilized_blocks
+        };
+        *streak += 1;
+        if *streak < AUTO_TUNE_BLOCKS {
+            return self.config.max_transactions;
+        }
+        *streak = 0;
+
+        let current = self.config.max_transactions;
+        let step = (current / 100 * AUTO_TUNE_STEP_PERCENT).max(1);
+        let tuned = if full {
+            current.saturating_add(step).min(bounds.max_transactions)
+        } else {
+            current.saturating_sub(step).max(bounds.min_transactions)
+        };
+        let tuned = tuned.max(self.config.total_reserved().saturating_add(1));
+        if tuned != current {
+            tracing::debug!("Auto-tuned the mempool transaction limit from {current} to {tuned}");
+            self.config.max_transactions = tuned;
+            self.publish_metrics();
+        }
+        tuned
+    }
+
+    /// Derives the minimum tip of the V3 transactions from the current STRK L1 gas price, and returns it. The minimum
+    /// follows the L1 gas price both ways: tips rejected while it was high are accepted again once it goes down.
+    pub fn update_min_tip(&mut self, l1_gas_price: u128) -> u64 {
+        // Float to int casts saturate.
+        self.min_tip = (l1_gas_price as f64 * self.config.min_tip_multiplier) as u64;
+        self.min_tip
+    }
+
+    pub fn min_tip(&self) -> u64 {
+        self.min_tip
+    }
+
+    /// Whether the mempool is above its high-watermark. This is only advisory, nothing is rejected because of it.
+    pub fn is_near_capacity(&self) -> bool {
+        self.utilization() >= self.config.near_capacity_watermark
+    }
+
+    /// Transactions per second accepted and popped over the last [`THROUGHPUT_WINDOW`](super::THROUGHPUT_WINDOW).
+    pub fn throughput(&self) -> MempoolThroughput {
+        let now = self.now();
+        MempoolThroughput {
+            accepted_per_second: self.accepted.per_second(now),
+            popped_per_second: self.popped.per_second(now),
+        }
+    }
+
+    /// Records a transaction accepted in the mempool for the throughput. Transactions added back by block production
+    /// are not accepted again.
+    pub fn record_accepted(&mut self) {
+        self.accepted.record(self.now());
+        self.publish_throughput();
+    }
+
+    fn publish_throughput(&self) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            let throughput = self.throughput();
+            metrics.accepted_transactions_per_second.record(throughput.accepted_per_second, &[]);
+            metrics.popped_transactions_per_second.record(throughput.popped_per_second, &[]);
+        }
+    }
+
+    fn publish_metrics(&self) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.current_transactions.record(self.current_transactions as u64, &[]);
+            metrics.current_declare_transactions.record(self.current_declare_transactions as u64, &[]);
+            metrics.current_bytes.record(self.current_bytes as u64, &[]);
+            metrics.transactions_utilization.record(self.utilization(), &[]);
+            metrics
+                .declare_transactions_utilization
+                .record(utilization(self.current_declare_transactions, self.config.max_declare_transactions), &[]);
+            for (tx_type, counters) in &self.per_type_counters {
+                metrics
+                    .current_transactions_by_type
+                    .record(counters.current as u64, &[KeyValue::new("type", tx_type_label(*tx_type))]);
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub fn record_rejected(&mut self, limit: &MempoolLimitReached, tx_hash: Felt) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.rejected_transaction_counter.add(1, &[KeyValue::new("reason", limit.reason())]);
+        }
+        self.record_dropped(DropReason::RejectedOverLimit, tx_hash);
+    }
+
+    fn record_dropped(&mut self, reason: DropReason, tx_hash: Felt) {
+        *self.dropped_transactions.entry(reason).or_insert(0) += 1;
+        self.recently_dropped.record(tx_hash, reason);
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.dropped_transaction_counter.add(1, &[KeyValue::new("reason", reason.label())]);
+        }
+    }
+
+    fn current_reserved(&self, reservation: Reservation) -> usize {
+        match reservation {
+            Reservation::L1Handler => self.current_l1_handler_transactions,
+            Reservation::DeployAccount => self.current_deploy_account_transactions,
+        }
+    }
+
+    fn current_reserved_mut(&mut self, reservation: Reservation) -> &mut usize {
+        match reservation {
+            Reservation::L1Handler => &mut self.current_l1_handler_transactions,
+            Reservation::DeployAccount => &mut self.current_deploy_account_transactions,
+        }
+    }
+
+    /// `replacing` is the transaction that will be replaced by this one, if any. Its room is considered free.
+    pub(crate) fn check_insert_limits(
+        &self,
+        to_check: &TransactionCheckedLimits,
+        replacing: Option<&TransactionCheckedLimits>,
+    ) -> Result<(), MempoolLimitReached> {
+        let current_transactions = self.current_transactions.saturating_sub(usize::from(replacing.is_some()));
+
+        // declared class size
+        // This does not depend on the mempool occupancy, it is checked first so that an oversized class is never
+        // reported as a full mempool.
+        if let Some(size) = to_check.declare_bytecode_size {
+            if size > self.config.max_declare_bytecode_size {
+                return Err(MempoolLimitReached::DeclareBytecodeTooLarge {
+                    size,
+                    max: self.config.max_declare_bytecode_size,
+                });
+            }
+        }
+
+        // zero fee
+        if to_check.zero_fee && !self.config.allow_zero_fee_transactions {
+            return Err(MempoolLimitReached::ZeroFee);
+        }
+
+        // min tip
+        // Like the class size, this does not depend on the mempool occupancy and never triggers eviction.
+        if let Some(tip) = to_check.tip {
+            if tip < self.min_tip {
+                return Err(MempoolLimitReached::TipTooLow { tip, min_tip: self.min_tip });
+            }
         }
 
         // declare tx limit
-        if to_check.check_declare_limit && self.current_declare_transactions >= self.config.max_declare_transactions {
+        // Under the deprioritize policy, the declare transactions over the limit are accepted and served last instead.
+        if self.config.declare_limit_policy == MempoolDeclareLimitPolicy::Reject
+            && self.exceeds_declare_limit(to_check, replacing)
+        {
             return Err(MempoolLimitReached::MaxDeclareTransactions { max: self.config.max_declare_transactions });
         }
 
+        // per sender tx limit
+        if let Some(sender) = &to_check.sender {
+            let current = self
+                .current_transactions_per_sender
+                .get(sender)
+                .copied()
+                .unwrap_or(0)
+                .saturating_sub(usize::from(replacing.is_some_and(|r| r.sender.as_ref() == Some(sender))));
+            if current >= self.config.max_transactions_per_sender {
+                return Err(MempoolLimitReached::MaxPerSender {
+                    sender: sender.to_felt(),
+                    max: self.config.max_transactions_per_sender,
+                });
+            }
+        }
+
         // age
-        if self.tx_age_exceeded(to_check) {
-            return Err(MempoolLimitReached::Age { max: self.config.max_age });
+        // Admitted age-exceeded transactions are removed by the next sweep, or skipped when popped.
+        if let Some(max_age) = self.config.max_age {
+            if self.config.expired_tx_policy == MempoolExpiredTxPolicy::Reject && self.tx_age_exceeded(to_check) {
+                return Err(MempoolLimitReached::Age { max: max_age });
+            }
         }
 
-        Ok(())
-    }
+        // deadline
+        // Unlike the age, a passed deadline is always rejected: the sender asked for the transaction to be dropped.
+        if let Some(deadline) = to_check.deadline {
+            if self.tx_deadline_passed(to_check) {
+                return Err(MempoolLimitReached::DeadlinePassed { deadline });
+            }
+        }
 
-    pub fn tx_age_exceeded(&self, to_check: &TransactionCheckedLimits) -> bool {
-        if to_check.check_age {
-            let current_time = SystemTime::now();
-            if to_check.tx_arrived_at < current_time.checked_sub(self.config.max_age).unwrap_or(SystemTime::UNIX_EPOCH)
-            {
-                return true;
+        // byte limit
+        // Evicting a single transaction may not free enough bytes, this limit does not trigger eviction.
+        let current_bytes = self.current_bytes.saturating_sub(replacing.map_or(0, |r| r.encoded_size));
+        if to_check.check_bytes_limit
+            && current_bytes.saturating_add(to_check.encoded_size) > self.config.max_total_bytes
+        {
+            return Err(MempoolLimitReached::MaxBytes { max: self.config.max_total_bytes });
+        }
+
+        // l2 gas limit
+        // Like the byte limit, this does not trigger eviction.
+        let current_l2_gas = self.current_l2_gas.saturating_sub(replacing.map_or(0, |r| r.l2_gas));
+        if to_check.l2_gas > 0 && current_l2_gas.saturating_add(to_check.l2_gas) > self.config.max_total_l2_gas {
+            return Err(MempoolLimitReached::MaxL2Gas { max: self.config.max_total_l2_gas });
+        }
+
+        // reserved capacity
+        // Reaching the unreserved capacity does not trigger eviction: the evicted transaction could be holding a
+        // reserved slot, which would not make room for this one.
+        let current_reserved = |reservation: Reservation| {
+            self.current_reserved(reservation)
+                .saturating_sub(usize::from(replacing.is_some_and(|r| r.reservation == Some(reservation))))
+        };
+        let fits_in_reservation = to_check
+            .reservation
+            .is_some_and(|reservation| current_reserved(reservation) < self.config.reserved(reservation));
+        if to_check.check_tx_limit && !fits_in_reservation && self.config.total_reserved() > 0 {
+            // Reserved transactions past their reservation use the unreserved capacity.
+            let in_reservations: usize = Reservation::ALL
+                .into_iter()
+                .map(|reservation| current_reserved(reservation).min(self.config.reserved(reservation)))
+                .sum();
+            if current_transactions.saturating_sub(in_reservations) >= self.config.unreserved_transactions() {
+                return Err(MempoolLimitReached::MaxUnreservedTransactions {
+                    max: self.config.unreserved_transactions(),
+                });
             }
         }
-        false
+
+        // tx limit
+        // This one is checked last: when eviction is enabled, reaching it means that the transaction can be inserted
+        // once room has been made for it.
+        if to_check.check_tx_limit && !fits_in_reservation && current_transactions >= self.config.max_transactions {
+            return Err(MempoolLimitReached::MaxTransactions { max: self.config.max_transactions });
+        }
+
+        Ok(())
+    }
+
+    /// Whether inserting the transaction would go over the declare limit. `replacing` is the transaction that will be
+    /// replaced by this one, if any.
+    pub(crate) fn exceeds_declare_limit(
+        &self,
+        to_check: &TransactionCheckedLimits,
+        replacing: Option<&TransactionCheckedLimits>,
+    ) -> bool {
+        let current_declare_transactions = self
+            .current_declare_transactions
+            .saturating_sub(usize::from(replacing.is_some_and(|r| r.check_declare_limit)));
+        to_check.check_declare_limit && current_declare_transactions >= self.config.max_declare_transactions
     }
 
-    pub fn update_tx_limits(&mut self, limits: &TransactionCheckedLimits) {
+    /// Whether the transaction is past its max age or its deadline. Expired transactions are removed by the sweeper,
+    /// and skipped when popped.
+    pub(crate) fn tx_expired(&self, to_check: &TransactionCheckedLimits) -> bool {
+        self.tx_age_exceeded(to_check) || self.tx_deadline_passed(to_check)
+    }
+
+    pub(crate) fn tx_deadline_passed(&self, to_check: &TransactionCheckedLimits) -> bool {
+        to_check.deadline.is_some_and(|deadline| deadline <= self.clock.now())
+    }
+
+    pub(crate) fn tx_age_exceeded(&self, to_check: &TransactionCheckedLimits) -> bool {
+        let Some(max_age) = self.config.max_age else {
+            // The age limit is disabled.
+            return false;
+        };
+        to_check.check_age && self.tx_age(to_check) > max_age
+    }
+
+    /// Time since the transaction arrived. A transaction which arrived after now means the system clock went
+    /// backwards: it is treated as just arrived, so that it is neither kept forever nor dropped spuriously.
+    fn tx_age(&self, to_check: &TransactionCheckedLimits) -> Duration {
+        self.clock.now().duration_since(to_check.tx_arrived_at).unwrap_or_else(|err| {
+            tracing::debug!(
+                "Transaction {:#x} arrived {:?} in the future, the system clock went backwards",
+                to_check.tx_hash,
+                err.duration()
+            );
+            Duration::ZERO
+        })
+    }
+
+    pub(crate) fn update_tx_limits(&mut self, limits: &TransactionCheckedLimits) {
+        // A transaction added back is no longer dropped.
+        self.recently_dropped.forget(limits.tx_hash);
         // We want all transactions to count toward the limit, not just those where the limit is checked.
         self.current_transactions += 1;
+        self.current_bytes += limits.encoded_size;
+        self.current_l2_gas = self.current_l2_gas.saturating_add(limits.l2_gas);
         if limits.check_declare_limit {
             self.current_declare_transactions += 1;
         }
+        if let Some(reservation) = limits.reservation {
+            *self.current_reserved_mut(reservation) += 1;
+        }
+        if let Some(sender) = limits.sender {
+            *self.current_transactions_per_sender.entry(sender).or_insert(0) += 1;
+        }
+        let type_counters = self.per_type_counters.entry(limits.tx_type).or_default();
+        type_counters.current += 1;
+        type_counters.inserted += 1;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.inserted_transaction_counter.add(1, &[KeyValue::new("type", tx_type_label(limits.tx_type))]);
+        }
+        self.publish_metrics();
+    }
+
+    /// Updates the counters for a transaction leaving the mempool. `dropped` is the reason it was dropped, `None` when
+    /// it leaves the mempool for any other reason, such as being included in a block.
+    pub(crate) fn mark_removed(&mut self, to_update: &TransactionCheckedLimits, dropped: Option<DropReason>) {
+        // These should not underflow unless block prod marks transactions as consumed even though they have not been
+        // popped. The counters then floor at zero, and the anomaly is reported.
+        let mut underflowed = vec![];
+        if saturating_decrement(&mut self.current_transactions, 1) {
+            underflowed.push("transactions");
+        }
+        if saturating_decrement(&mut self.current_bytes, to_update.encoded_size) {
+            underflowed.push("bytes");
+        }
+        if self.current_l2_gas < to_update.l2_gas {
+            underflowed.push("l2_gas");
+        }
+        self.current_l2_gas = self.current_l2_gas.saturating_sub(to_update.l2_gas);
+        if to_update.check_declare_limit && saturating_decrement(&mut self.current_declare_transactions, 1) {
+            underflowed.push("declare_transactions");
+        }
+        if let Some(reservation) = to_update.reservation {
+            if saturating_decrement(self.current_reserved_mut(reservation), 1) {
+                underflowed.push("reserved_transactions");
+            }
+        }
+        if let Some(sender) = to_update.sender {
+            match self.current_transactions_per_sender.entry(sender) {
+                hash_map::Entry::Occupied(mut entry) => {
+                    *entry.get_mut() -= 1;
+                    if *entry.get() == 0 {
+                        // Prune the entry so that the map does not grow unbounded.
+                        entry.remove();
+                    }
+                }
+                hash_map::Entry::Vacant(_) => underflowed.push("transactions_per_sender"),
+            }
+        }
+        let type_counters = self.per_type_counters.entry(to_update.tx_type).or_default();
+        if saturating_decrement(&mut type_counters.current, 1) {
+            underflowed.push("transactions_by_type");
+        } else {
+            type_counters.removed += 1;
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = &self.metrics {
+                metrics.removed_transaction_counter.add(1, &[KeyValue::new("type", tx_type_label(to_update.tx_type))]);
+            }
+        }
+        if !underflowed.is_empty() {
+            self.record_counter_underflow(&underflowed);
+        }
+        if let Some(reason) = dropped {
+            self.record_dropped(reason, to_update.tx_hash);
+        }
+        self.publish_metrics();
+    }
+
+    /// Reserves the room of a transaction popped for block production. The transaction stays counted against the
+    /// limits while it is being executed, until [`MempoolLimiter::release_reservation`].
+    pub fn reserve_for_block(&mut self) {
+        self.current_in_flight_transactions += 1;
+        self.popped.record(self.now());
+        self.publish_throughput();
+    }
+
+    /// Releases the reservation of a popped transaction once it is included in a block, dropped, or about to be
+    /// inserted again. It is no longer counted.
+    pub(crate) fn release_reservation(&mut self, to_update: &TransactionCheckedLimits) {
+        if saturating_decrement(&mut self.current_in_flight_transactions, 1) {
+            self.record_counter_underflow(&["in_flight_transactions"]);
+        }
+        self.mark_removed(to_update, None);
     }
 
-    pub fn mark_removed(&mut self, to_update: &TransactionCheckedLimits) {
-        // These should not overflow unless block prod marks transactions as consumed even though they have not been popped.
-        self.current_transactions -= 1;
-        if to_update.check_declare_limit {
-            self.current_declare_transactions -= 1;
+    fn record_counter_underflow(&mut self, underflowed: &[&str]) {
+        tracing::warn!("Mempool transaction marked as removed but not accounted for in the {underflowed:?} counters");
+        self.counter_underflows += 1;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.counter_underflow_counter.add(1, &[]);
         }
     }
+
+    /// Number of [`MempoolLimiter::mark_removed`] calls for transactions the counters did not account for.
+    pub fn counter_underflows(&self) -> u64 {
+        self.counter_underflows
+    }
+
+    /// Compares the transaction counter against the `in_mempool` transactions actually in the inner mempool. The
+    /// transactions popped for block production are still counted until their reservation is released, so the counter
+    /// is expected to be between `in_mempool` and `in_mempool` plus the in-flight transactions. A counter outside of
+    /// this window means the accounting has drifted: it is reported, and the limits are enforced against a wrong
+    /// occupancy until the mempool restarts.
+    pub fn check_counter_consistency(&mut self, in_mempool: usize) -> Option<CounterDivergence> {
+        let divergence = CounterDivergence {
+            counted: self.current_transactions,
+            in_mempool,
+            in_flight: self.current_in_flight_transactions,
+        };
+        let in_window = (in_mempool..=in_mempool.saturating_add(divergence.in_flight)).contains(&divergence.counted);
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            let gap = if in_window { 0 } else { divergence.gap() };
+            metrics.counter_divergence.record(gap as u64, &[]);
+        }
+        if in_window {
+            return None;
+        }
+        tracing::warn!(
+            "Mempool transaction counter diverged: {} transactions counted, {} in the mempool and {} in flight",
+            divergence.counted,
+            divergence.in_mempool,
+            divergence.in_flight
+        );
+        self.counter_divergences += 1;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.counter_divergence_counter.add(1, &[]);
+        }
+        Some(divergence)
+    }
+
+    /// Number of [`MempoolLimiter::check_counter_consistency`] calls which found a divergence.
+    pub fn counter_divergences(&self) -> u64 {
+        self.counter_divergences
+    }
+}
+
+/// Subtracts `by` from `counter`, flooring at zero. Returns whether it would have underflowed.
+fn saturating_decrement(counter: &mut usize, by: usize) -> bool {
+    let underflow = *counter < by;
+    *counter = counter.saturating_sub(by);
+    underflow
+}
+
+fn declare_bytecode_size(tx: &MempoolTransaction) -> Option<usize> {
+    match &tx.tx {
+        Transaction::AccountTransaction(AccountTransaction::Declare(tx)) => Some(tx.class_info.code_size()),
+        _ => None,
+    }
+}
+
+/// Ratio of `current` against `max`, without dividing by zero when a limit is set to 0.
+fn utilization(current: usize, max: usize) -> f64 {
+    current as f64 / max.max(1) as f64
 }
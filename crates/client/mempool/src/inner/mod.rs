@@ -3,58 +3,79 @@
 //! Insertion and popping should be O(log n).
 //! We also really don't want to poison the lock by panicking.
 
+#[cfg(feature = "metrics")]
+use crate::metrics::MempoolMetrics;
 use blockifier::transaction::account_transaction::AccountTransaction;
 use blockifier::transaction::transaction_execution::Transaction;
+use blockifier::transaction::transaction_types::TransactionType;
 use deployed_contracts::DeployedContracts;
+use mc_exec::execution::TxInfo;
+use mp_chain_config::{MempoolOrdering, MempoolUnderpricedPolicy};
 use mp_convert::ToFelt;
-use nonce_chain::{InsertedPosition, NonceChain, NonceChainNewState, ReplacedState};
-use starknet_api::core::ContractAddress;
-use starknet_types_core::felt::Felt;
-use std::{
-    cmp,
-    collections::{hash_map, BTreeSet, HashMap},
+use nonce_chain::{
+    check_replacement, InsertedPosition, NonceChain, NonceChainNewState, OrderMempoolTransactionByNonce, ReplacedState,
 };
+use serde::{Deserialize, Serialize};
+use starknet_api::core::{ContractAddress, Nonce};
+use starknet_types_core::felt::Felt;
+use std::collections::{btree_map, hash_map, BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+use tx_queue::{QueuedAccount, TxPriority, TxQueue};
 
+mod admission;
+mod clock;
 mod deployed_contracts;
+mod dropped;
 mod limits;
 mod nonce_chain;
 mod proptest;
+mod tests;
+mod throughput;
 mod tx;
+mod tx_queue;
 
+pub use admission::*;
+pub use clock::*;
 pub use limits::*;
+pub use throughput::*;
 pub use tx::*;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct AccountOrderedByTimestamp {
-    contract_addr: Felt,
-    timestamp: ArrivedAtTimestamp,
-}
-
-impl Ord for AccountOrderedByTimestamp {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        // Important: Fallback on contract addr here.
-        // There can be timestamp collisions.
-        self.timestamp.cmp(&other.timestamp).then_with(|| self.contract_addr.cmp(&other.contract_addr))
-    }
-}
-impl PartialOrd for AccountOrderedByTimestamp {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 #[derive(Debug)]
 /// Invariants:
 /// - Every nonce chain in `nonce_chains` should have a one to one match with `tx_queue`.
 /// - Every [`AccountTransaction::DeployAccount`] transaction should have a one to one match with `deployed_contracts`.
+/// - No pending transactions map in `pending_by_sender` should be empty, and the lowest pending nonce of a sender should
+///   leave a gap after its nonce chain.
+/// - `tx_hashes` should have a one to one match with the ready and pending transactions.
 /// - See [`NonceChain`] invariants.
 pub(crate) struct MempoolInner {
-    /// We have one nonce chain per contract address.
+    /// We have one nonce chain per contract address. Nonce chains only hold the ready transactions.
     nonce_chains: HashMap<Felt, NonceChain>,
-    /// FCFS queue.
-    tx_queue: BTreeSet<AccountOrderedByTimestamp>,
+    /// Future transactions, which have a nonce gap with the ready transactions of their sender. They are promoted to
+    /// the nonce chains when the gap is filled.
+    pending_by_sender: HashMap<ContractAddress, BTreeMap<Nonce, MempoolTransaction>>,
+    /// Ready transactions, ordered by the [`MempoolOrdering`] strategy. Ties are first-come first-served.
+    tx_queue: TxQueue,
     deployed_contracts: DeployedContracts,
+    /// Hashes of the ready and pending transactions, to short-circuit the insertion of a transaction already in the
+    /// mempool.
+    tx_hashes: HashSet<Felt>,
+    /// Deadlines and hashes of the transactions inserted with a [`MempoolTransaction::deadline`], so that the sweeper
+    /// only looks for them once a deadline has passed. The entries of the transactions removed since are only dropped
+    /// once their deadline has passed.
+    deadlines: BTreeSet<(SystemTime, Felt)>,
+    /// Arrival times and hashes of the ready and pending transactions, to find the oldest one. Like `deadlines`, the
+    /// entries of the removed transactions are only dropped once they are the oldest.
+    arrivals: BTreeSet<(SystemTime, Felt)>,
+    /// When the transaction with this sender and nonce was last replaced, to enforce
+    /// [`MempoolLimits::replacement_min_interval`]. Entries are dropped once the interval has passed.
+    last_replacements: HashMap<(ContractAddress, Nonce), SystemTime>,
     limiter: MempoolLimiter,
+    /// Consulted after the built-in limits for the transactions which are not force-inserted.
+    admission_policy: Arc<dyn AdmissionPolicy>,
+    events: Option<broadcast::Sender<MempoolEvent>>,
 }
 
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
@@ -63,17 +84,136 @@ pub enum TxInsersionError {
     NonceConflict,
     #[error("A transaction with this hash already exists in the transaction pool")]
     DuplicateTxn,
+    #[error("Replacement transaction underpriced: its tip of {tip} should be at least {min_tip}")]
+    ReplacementUnderpriced { tip: u64, min_tip: u128 },
+    #[error("The transaction nonce {nonce:#x} is more than {max_distance} above the account nonce {account_nonce:#x}")]
+    NonceTooFar { nonce: Felt, account_nonce: Felt, max_distance: u64 },
+    #[error("The transaction hash {tx_hash:#x} was not computed for the chain id of this chain")]
+    ChainIdMismatch { tx_hash: Felt },
+    #[error("The priority hint {hint} is above the maximum of {max}")]
+    InvalidPriorityHint { hint: u8, max: u8 },
+    #[error("Replacement too frequent: the transaction with this sender and nonce can be replaced in {remaining:?}")]
+    ReplacementCooldown { remaining: Duration },
+    #[error("The transaction was rejected by the mempool admission policy: {0}")]
+    Rejected(RejectReason),
+    #[error("The class {class_hash:#x} of the account is not declared")]
+    UndeclaredClass { class_hash: Felt },
     #[error(transparent)]
     Limit(#[from] MempoolLimitReached),
 }
 
+/// What the insertion of a transaction did to the mempool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InsertOutcome {
+    /// The transaction was added to the mempool.
+    Added,
+    /// The transaction replaced the transaction with this hash, which had the same sender and nonce.
+    Replaced(Felt),
+    /// The mempool was full: the transaction with this hash was evicted to make room for this one.
+    EvictedToFit(Felt),
+    /// A transaction with the same hash is already in the mempool, nothing was changed.
+    AlreadyKnown,
+    /// A transaction with the same hash is already in the mempool, its arrival time was refreshed so that it does not
+    /// expire. See [`MempoolLimits::refresh_on_resubmit`].
+    Refreshed,
+}
+
+/// Why a transaction was removed from the mempool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemovalReason {
+    /// The transaction was included in a block.
+    Included,
+    /// The transaction stayed in the mempool for longer than the max age, or past its deadline.
+    Expired,
+    /// The mempool was full: the transaction was evicted to make room for a higher priority one.
+    Evicted,
+    /// The L1 block the L1 handler transaction comes from was reorged out.
+    L1Reorg,
+    /// The L1 gas max price of the transaction was below the L1 gas price when block production popped it, see
+    /// [`MempoolUnderpricedPolicy::Drop`].
+    Underpriced,
+    /// The transaction was removed by the node operator, see [`MempoolInner::remove_tx_by_hash`].
+    RemovedByOperator,
+}
+
+/// A change to the content of the mempool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MempoolEvent {
+    /// The transaction was added to the mempool.
+    Added { tx_hash: Felt },
+    /// The transaction replaced the `previous` transaction, which had the same sender and nonce. No
+    /// [`MempoolEvent::Removed`] event is emitted for the previous transaction.
+    Replaced { tx_hash: Felt, previous: Felt },
+    /// The transaction was removed from the mempool.
+    Removed { tx_hash: Felt, reason: RemovalReason },
+}
+
 impl MempoolInner {
     pub fn new(limits_config: MempoolLimits) -> Self {
         Self {
             nonce_chains: Default::default(),
+            pending_by_sender: Default::default(),
             tx_queue: Default::default(),
             deployed_contracts: Default::default(),
+            tx_hashes: Default::default(),
+            deadlines: Default::default(),
+            arrivals: Default::default(),
+            last_replacements: Default::default(),
             limiter: MempoolLimiter::new(limits_config),
+            admission_policy: Arc::new(AdmitAll),
+            events: None,
+        }
+    }
+
+    /// Order the ready transactions with this strategy instead of [`MempoolOrdering::FeePriority`]. This must be set
+    /// before any transaction is inserted.
+    pub fn with_ordering(mut self, ordering: MempoolOrdering) -> Self {
+        debug_assert!(self.tx_queue.is_empty(), "Mempool ordering changed after transactions were inserted");
+        self.tx_queue = TxQueue::new(ordering);
+        self
+    }
+
+    /// Publish the mempool occupancy and rejections as metrics.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: MempoolMetrics) -> Self {
+        self.limiter.set_metrics(metrics);
+        self
+    }
+
+    /// Read the current time from this clock instead of [`SystemClock`].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.limiter.set_clock(clock);
+        self
+    }
+
+    /// Consult this policy instead of [`AdmitAll`] before inserting a transaction.
+    pub fn with_admission_policy(mut self, admission_policy: Arc<dyn AdmissionPolicy>) -> Self {
+        self.admission_policy = admission_policy;
+        self
+    }
+
+    /// The current time, according to the clock of the mempool.
+    pub fn now(&self) -> SystemTime {
+        self.limiter.now()
+    }
+
+    /// Send the [`MempoolEvent`]s to this channel.
+    pub fn with_events(mut self, events: broadcast::Sender<MempoolEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    fn emit(&self, event: MempoolEvent) {
+        if let Some(events) = &self.events {
+            // Sending fails when there are no subscribers, which is fine.
+            let _ = events.send(event);
+        }
+    }
+
+    fn emit_removed<'a>(&self, txs: impl IntoIterator<Item = &'a MempoolTransaction>, reason: RemovalReason) {
+        for tx in txs {
+            self.emit(MempoolEvent::Removed { tx_hash: tx.tx_hash().to_felt(), reason });
         }
     }
 
@@ -82,32 +222,98 @@ impl MempoolInner {
         self.nonce_chains.values().for_each(NonceChain::check_invariants);
         let mut tx_queue = self.tx_queue.clone();
         for (k, v) in &self.nonce_chains {
-            assert!(tx_queue.remove(&AccountOrderedByTimestamp { contract_addr: *k, timestamp: v.front_arrived_at }))
+            assert!(tx_queue.remove(&QueuedAccount {
+                contract_addr: *k,
+                timestamp: v.front_arrived_at,
+                priority: v.front_priority
+            }))
+        }
+        assert!(tx_queue.is_empty());
+        for (sender, pending) in &self.pending_by_sender {
+            let (&lowest_nonce, _) = pending.first_key_value().expect("Empty pending transactions");
+            if let Some(chain) = self.nonce_chains.get(&sender.to_felt()) {
+                assert!(lowest_nonce > next_nonce(chain.last().nonce()));
+            }
         }
-        assert_eq!(tx_queue, Default::default());
         let mut deployed_contracts = self.deployed_contracts.clone();
-        for (contract, _) in self.nonce_chains.values().flat_map(|chain| &chain.transactions) {
-            if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &contract.0.tx {
+        for tx in self.transactions() {
+            if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &tx.tx {
                 deployed_contracts.decrement(tx.contract_address)
             }
         }
         assert!(deployed_contracts.is_empty(), "remaining deployed_contracts: {deployed_contracts:?}");
+        let tx_hashes: HashSet<Felt> = self.transactions().map(|tx| tx.tx_hash().to_felt()).collect();
+        assert_eq!(tx_hashes.len(), self.transactions().count(), "duplicate transaction hashes");
+        assert_eq!(tx_hashes, self.tx_hashes);
+        for tx in self.transactions() {
+            assert!(self.arrivals.contains(&(tx.arrived_at, tx.tx_hash().to_felt())), "missing arrival");
+        }
     }
 
     /// When `force` is `true`, this function should never return any error.
-    pub fn insert_tx(&mut self, mempool_tx: MempoolTransaction, force: bool) -> Result<(), TxInsersionError> {
+    ///
+    /// A transaction already in the mempool is not inserted again, this returns [`InsertOutcome::AlreadyKnown`], or
+    /// [`InsertOutcome::Refreshed`] when [`MempoolLimits::refresh_on_resubmit`] is set. A transaction with the same
+    /// sender and nonce but a different hash is a replacement.
+    ///
+    /// `account_nonce` is the current nonce of the sender. Transactions with a nonce gap after the account nonce and the
+    /// ready transactions of the sender are buffered in [`MempoolInner::pending_by_sender`] until the gap is filled.
+    /// Forced transactions are always ready.
+    pub fn insert_tx(
+        &mut self,
+        mempool_tx: MempoolTransaction,
+        force: bool,
+        account_nonce: Nonce,
+    ) -> Result<InsertOutcome, TxInsersionError> {
         // delete age-exceeded txs from the mempool
         // todo(perf): this may want to limit this check once every few seconds to avoid it being in the hot path?
-        self.remove_age_exceeded_txs();
+        let _removed = self.remove_age_exceeded_txs();
 
-        // check limits
-        let limits_for_tx = TransactionCheckedLimits::limits_for(&mempool_tx);
+        let tx_hash = mempool_tx.tx_hash().to_felt();
+        if !force && self.tx_hashes.contains(&tx_hash) {
+            if self.limiter.config.refresh_on_resubmit && self.refresh_arrival(&mempool_tx) {
+                return Ok(InsertOutcome::Refreshed);
+            }
+            return Ok(InsertOutcome::AlreadyKnown);
+        }
         if !force {
-            self.limiter.check_insert_limits(&limits_for_tx)?;
+            self.check_nonce_distance(&mempool_tx, account_nonce)?;
         }
-
         let contract_addr = mempool_tx.contract_address().to_felt();
+        let sender = mempool_tx.contract_address();
+        let nonce = mempool_tx.nonce();
+        let tip = mempool_tx.tip();
+        let deadline = mempool_tx.deadline;
         let arrived_at = mempool_tx.arrived_at;
+
+        // A transaction replacing a pending one stays pending.
+        let pending_same_nonce = self.pending_by_sender.get(&sender).and_then(|pending| pending.get(&nonce));
+        let is_pending = pending_same_nonce.is_some() || (!force && !self.is_ready(&mempool_tx, account_nonce));
+
+        // check limits
+        let limits_for_tx = self.limiter.limits_for(&mempool_tx);
+        let mut mempool_tx = OrderMempoolTransactionByNonce(mempool_tx);
+        let mut evict = false;
+        if !force {
+            let replacing = self.replacing(&mempool_tx, is_pending, pending_same_nonce);
+            if replacing.is_some() {
+                self.check_replacement_cooldown(sender, nonce)?;
+            }
+            match self.check_limits(&limits_for_tx, replacing, tip, contract_addr) {
+                Ok(evicted) => evict = evicted.is_some(),
+                Err(limit) => {
+                    self.limiter.record_rejected(&limit, tx_hash);
+                    return Err(limit.into());
+                }
+            }
+            self.admission_policy.admit(&mempool_tx.0, &self.limiter).map_err(TxInsersionError::Rejected)?;
+            // The limits passed, so a transaction over the declare limit is accepted under the deprioritize policy.
+            // Forced insertions keep the flag they had when they were first inserted.
+            let replacing_limits = replacing.map(|tx| self.limiter.limits_for(tx));
+            mempool_tx.0.deprioritized = self.limiter.exceeds_declare_limit(&limits_for_tx, replacing_limits.as_ref());
+        }
+
+        let mempool_tx = mempool_tx.0;
         let deployed_contract_address =
             if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &mempool_tx.tx {
                 Some(tx.contract_address)
@@ -115,34 +321,277 @@ impl MempoolInner {
                 None
             };
 
-        let is_replaced = match self.nonce_chains.entry(contract_addr) {
+        let is_replaced =
+            if is_pending { self.insert_pending(mempool_tx, force)? } else { self.insert_ready(mempool_tx, force)? };
+
+        let replaced = if let ReplacedState::Replaced { previous } = is_replaced {
+            // Mark the previous transaction as deleted
+            self.limiter.mark_removed(&self.limiter.limits_for(&previous), Some(DropReason::Replaced));
+            self.tx_hashes.remove(&previous.tx_hash().to_felt());
+            if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &previous.tx {
+                self.deployed_contracts.decrement(tx.contract_address);
+            }
+            if !force && !self.limiter.config.replacement_min_interval.is_zero() {
+                self.last_replacements.insert((sender, nonce), self.now());
+            }
+            Some(previous)
+        } else {
+            None
+        };
+        if let Some(contract_address) = &deployed_contract_address {
+            self.deployed_contracts.increment(*contract_address)
+        }
+        self.tx_hashes.insert(tx_hash);
+        if let Some(deadline) = deadline {
+            self.deadlines.insert((deadline, tx_hash));
+        }
+        self.arrivals.insert((arrived_at, tx_hash));
+
+        // Evict only once the insertion has succeeded, so that a rejected transaction never evicts anything.
+        let evicted = if evict { self.evict_lowest_priority(tip, contract_addr) } else { None };
+        debug_assert_eq!(evict, evicted.is_some());
+
+        // Update transaction limits
+        self.limiter.update_tx_limits(&limits_for_tx);
+        if !force {
+            self.limiter.record_accepted();
+        }
+
+        // This transaction may have filled a nonce gap.
+        self.promote_pending(sender, account_nonce);
+
+        self.emit_removed(&evicted, RemovalReason::Evicted);
+        let outcome = match (replaced, evicted) {
+            (Some(previous), _) => InsertOutcome::Replaced(previous.tx_hash().to_felt()),
+            (None, Some(evicted)) => InsertOutcome::EvictedToFit(evicted.tx_hash().to_felt()),
+            (None, None) => InsertOutcome::Added,
+        };
+        match outcome {
+            InsertOutcome::Replaced(previous) => self.emit(MempoolEvent::Replaced { tx_hash, previous }),
+            // Forced insertions are re-added transactions, which were never removed.
+            _ if force => {}
+            _ => self.emit(MempoolEvent::Added { tx_hash }),
+        }
+
+        Ok(outcome)
+    }
+
+    /// Dry run of [`MempoolInner::insert_tx`]: returns what inserting the transaction would do, or why it would be
+    /// rejected, without modifying the mempool. Unlike an insertion, the age-exceeded transactions are not removed
+    /// first, so this may report a full mempool that an insertion would have made room in.
+    pub fn check_insert_tx(
+        &self,
+        mempool_tx: MempoolTransaction,
+        account_nonce: Nonce,
+    ) -> Result<InsertOutcome, TxInsersionError> {
+        if self.tx_hashes.contains(&mempool_tx.tx_hash().to_felt()) {
+            if self.limiter.config.refresh_on_resubmit && self.stored_arrival(&mempool_tx).is_some() {
+                return Ok(InsertOutcome::Refreshed);
+            }
+            return Ok(InsertOutcome::AlreadyKnown);
+        }
+        self.check_nonce_distance(&mempool_tx, account_nonce)?;
+        let contract_addr = mempool_tx.contract_address().to_felt();
+        let tip = mempool_tx.tip();
+        let pending_same_nonce = self
+            .pending_by_sender
+            .get(&mempool_tx.contract_address())
+            .and_then(|pending| pending.get(&mempool_tx.nonce()));
+        let is_pending = pending_same_nonce.is_some() || !self.is_ready(&mempool_tx, account_nonce);
+
+        let limits_for_tx = self.limiter.limits_for(&mempool_tx);
+        let mempool_tx = OrderMempoolTransactionByNonce(mempool_tx);
+        let replacing = self.replacing(&mempool_tx, is_pending, pending_same_nonce);
+        let evicted = self.check_limits(&limits_for_tx, replacing, tip, contract_addr)?;
+        self.admission_policy.admit(&mempool_tx.0, &self.limiter).map_err(TxInsersionError::Rejected)?;
+
+        if let Some(previous) = replacing {
+            if previous.tx_hash() == mempool_tx.0.tx_hash() {
+                return Err(TxInsersionError::DuplicateTxn);
+            }
+            self.check_replacement_cooldown(mempool_tx.0.contract_address(), mempool_tx.0.nonce())?;
+            check_replacement(previous, &mempool_tx.0, self.limiter.config.replacement_bump_percent)?;
+            return Ok(InsertOutcome::Replaced(previous.tx_hash().to_felt()));
+        }
+        Ok(match evicted {
+            Some(evicted) => InsertOutcome::EvictedToFit(self.nonce_chains[&evicted].last().tx_hash().to_felt()),
+            None => InsertOutcome::Added,
+        })
+    }
+
+    /// Rejects the transactions with a nonce more than [`MempoolLimits::max_nonce_distance`] above the account nonce.
+    /// L1 handler nonces are not related to the nonce of the target contract, they are not checked.
+    fn check_nonce_distance(
+        &self,
+        mempool_tx: &MempoolTransaction,
+        account_nonce: Nonce,
+    ) -> Result<(), TxInsersionError> {
+        let nonce = mempool_tx.nonce();
+        let max_distance = self.limiter.config.max_nonce_distance;
+        if mempool_tx.tx.tx_type() != TransactionType::L1Handler
+            && nonce.0 > account_nonce.0
+            && nonce.0 - account_nonce.0 > Felt::from(max_distance)
+        {
+            return Err(TxInsersionError::NonceTooFar { nonce: nonce.0, account_nonce: account_nonce.0, max_distance });
+        }
+        Ok(())
+    }
+
+    /// Rejects the replacement of the transaction with this sender and nonce when it was already replaced less than
+    /// [`MempoolLimits::replacement_min_interval`] ago.
+    fn check_replacement_cooldown(&self, sender: ContractAddress, nonce: Nonce) -> Result<(), TxInsersionError> {
+        let Some(last_replaced) = self.last_replacements.get(&(sender, nonce)) else { return Ok(()) };
+        let elapsed = self.now().duration_since(*last_replaced).unwrap_or_default();
+        match self.limiter.config.replacement_min_interval.checked_sub(elapsed) {
+            Some(remaining) if !remaining.is_zero() => Err(TxInsersionError::ReplacementCooldown { remaining }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The transaction with the same sender and nonce, which inserting `mempool_tx` would replace.
+    fn replacing<'a>(
+        &'a self,
+        mempool_tx: &OrderMempoolTransactionByNonce,
+        is_pending: bool,
+        pending_same_nonce: Option<&'a MempoolTransaction>,
+    ) -> Option<&'a MempoolTransaction> {
+        if is_pending {
+            pending_same_nonce
+        } else {
+            self.nonce_chains
+                .get(&mempool_tx.0.contract_address().to_felt())
+                .and_then(|chain| chain.get_same_nonce(mempool_tx))
+        }
+    }
+
+    /// Checks the limits for inserting a transaction, which replaces `replacing` if any: a replacement transaction frees
+    /// the room of the transaction it replaces. When the mempool is full but eviction can make room for it, returns the
+    /// account whose last transaction would be evicted.
+    fn check_limits(
+        &self,
+        limits_for_tx: &TransactionCheckedLimits,
+        replacing: Option<&MempoolTransaction>,
+        tip: u64,
+        contract_addr: Felt,
+    ) -> Result<Option<Felt>, MempoolLimitReached> {
+        let replacing_limits = replacing.map(|tx| self.limiter.limits_for(tx));
+        match self.limiter.check_insert_limits(limits_for_tx, replacing_limits.as_ref()) {
+            // The tx limit is checked last, so every other limit is fine if we get here.
+            Err(limit @ MempoolLimitReached::MaxTransactions { .. }) => {
+                if !self.limiter.config.eviction_enabled || self.limiter.is_over_tx_limit() {
+                    return Err(limit);
+                }
+                self.lowest_priority_evictable(tip, contract_addr).map(Some).ok_or(limit)
+            }
+            Err(limit) => Err(limit),
+            Ok(()) => Ok(None),
+        }
+    }
+
+    /// A transaction is ready when there is no nonce gap between it and either the account nonce or the ready
+    /// transactions of its sender.
+    fn is_ready(&self, mempool_tx: &MempoolTransaction, account_nonce: Nonce) -> bool {
+        let nonce = mempool_tx.nonce();
+        // L1 handler nonces are the L1 messaging nonces, they are not related to the nonce of the target contract.
+        mempool_tx.tx.tx_type() == TransactionType::L1Handler
+            || nonce <= account_nonce
+            || self
+                .nonce_chains
+                .get(&mempool_tx.contract_address().to_felt())
+                .is_some_and(|chain| nonce <= next_nonce(chain.last().nonce()))
+    }
+
+    /// Arrival time of the stored transaction with the same hash as `mempool_tx`, when it arrived before it.
+    fn stored_arrival(&self, mempool_tx: &MempoolTransaction) -> Option<ArrivedAtTimestamp> {
+        let sender = mempool_tx.contract_address();
+        let stored = match self.pending_by_sender.get(&sender).and_then(|pending| pending.get(&mempool_tx.nonce())) {
+            Some(pending) => pending,
+            None => self
+                .nonce_chains
+                .get(&sender.to_felt())?
+                .get_same_nonce(&OrderMempoolTransactionByNonce(mempool_tx.clone()))?,
+        };
+        let is_older_duplicate = stored.tx_hash() == mempool_tx.tx_hash() && stored.arrived_at < mempool_tx.arrived_at;
+        is_older_duplicate.then_some(stored.arrived_at)
+    }
+
+    /// Moves the arrival time of the stored transaction with the same hash as `mempool_tx` to the arrival time of
+    /// `mempool_tx`, so that its age starts over. The transaction is updated in place: it is not counted again by the
+    /// limiter. A ready transaction at the front of its nonce chain is queued again with its new arrival time. Returns
+    /// `false` when there was nothing to refresh.
+    fn refresh_arrival(&mut self, mempool_tx: &MempoolTransaction) -> bool {
+        let Some(previous) = self.stored_arrival(mempool_tx) else { return false };
+        let arrived_at = mempool_tx.arrived_at;
+        let sender = mempool_tx.contract_address();
+        let nonce = mempool_tx.nonce();
+
+        if let Some(pending) = self.pending_by_sender.get_mut(&sender).and_then(|pending| pending.get_mut(&nonce)) {
+            pending.arrived_at = arrived_at;
+        } else {
+            let contract_addr = sender.to_felt();
+            let chain = self.nonce_chains.get_mut(&contract_addr).expect("Checked by stored_arrival");
+            // The nonce chain is keyed by the transactions themselves, the stored one is taken out to be updated.
+            let (mut stored, _) = chain
+                .transactions
+                .remove_entry(&OrderMempoolTransactionByNonce(mempool_tx.clone()))
+                .expect("Checked by stored_arrival");
+            stored.0.arrived_at = arrived_at;
+            let inserted = chain.transactions.insert(stored, ());
+            debug_assert!(inserted.is_none());
+
+            if chain.front_nonce == nonce {
+                chain.front_arrived_at = arrived_at;
+                let priority = chain.front_priority;
+                let removed = self.tx_queue.remove(&QueuedAccount { contract_addr, timestamp: previous, priority });
+                debug_assert!(removed);
+                let inserted = self.tx_queue.insert(QueuedAccount { contract_addr, timestamp: arrived_at, priority });
+                debug_assert!(inserted);
+            }
+        }
+
+        let tx_hash = mempool_tx.tx_hash().to_felt();
+        self.arrivals.remove(&(previous, tx_hash));
+        self.arrivals.insert((arrived_at, tx_hash));
+        true
+    }
+
+    /// Inserts a ready transaction into the nonce chain of its sender, and updates the tx queue.
+    fn insert_ready(&mut self, mempool_tx: MempoolTransaction, force: bool) -> Result<ReplacedState, TxInsersionError> {
+        let contract_addr = mempool_tx.contract_address().to_felt();
+        let arrived_at = mempool_tx.arrived_at;
+        let priority = TxPriority::of(&mempool_tx);
+
+        match self.nonce_chains.entry(contract_addr) {
             hash_map::Entry::Occupied(mut entry) => {
                 // Handle nonce collision.
                 let chain: &mut NonceChain = entry.get_mut();
-                let (position, is_replaced) = match chain.insert(mempool_tx, force) {
-                    Ok(position) => position,
-                    Err(nonce_collision_or_duplicate_hash) => {
-                        debug_assert!(!force); // "Force add should never error
-                        return Err(nonce_collision_or_duplicate_hash);
-                    }
-                };
+                let (position, is_replaced) =
+                    match chain.insert(mempool_tx, force, self.limiter.config.replacement_bump_percent) {
+                        Ok(position) => position,
+                        Err(nonce_collision_or_duplicate_hash) => {
+                            debug_assert!(!force); // "Force add should never error
+                            return Err(nonce_collision_or_duplicate_hash);
+                        }
+                    };
 
                 match position {
-                    InsertedPosition::Front { former_head_arrived_at } => {
+                    InsertedPosition::Front { former_head_arrived_at, former_head_priority } => {
                         // If we inserted at the front, it has invalidated the tx queue. Update the tx queue.
-                        let removed = self
-                            .tx_queue
-                            .remove(&AccountOrderedByTimestamp { contract_addr, timestamp: former_head_arrived_at });
+                        let removed = self.tx_queue.remove(&QueuedAccount {
+                            contract_addr,
+                            timestamp: former_head_arrived_at,
+                            priority: former_head_priority,
+                        });
                         debug_assert!(removed);
                         let inserted =
-                            self.tx_queue.insert(AccountOrderedByTimestamp { contract_addr, timestamp: arrived_at });
+                            self.tx_queue.insert(QueuedAccount { contract_addr, timestamp: arrived_at, priority });
                         debug_assert!(inserted);
                     }
                     InsertedPosition::Other => {
                         // No need to update the tx queue.
                     }
                 }
-                is_replaced
+                Ok(is_replaced)
             }
             hash_map::Entry::Vacant(entry) => {
                 // Insert the new nonce chain
@@ -150,31 +599,144 @@ impl MempoolInner {
                 entry.insert(nonce_chain);
 
                 // Also update the tx queue.
-                let inserted = self.tx_queue.insert(AccountOrderedByTimestamp { contract_addr, timestamp: arrived_at });
+                let inserted = self.tx_queue.insert(QueuedAccount { contract_addr, timestamp: arrived_at, priority });
                 debug_assert!(inserted);
 
-                ReplacedState::NotReplaced
+                Ok(ReplacedState::NotReplaced)
+            }
+        }
+    }
+
+    /// Buffers a transaction with a nonce gap, until [`MempoolInner::promote_pending`] makes it ready.
+    fn insert_pending(
+        &mut self,
+        mempool_tx: MempoolTransaction,
+        force: bool,
+    ) -> Result<ReplacedState, TxInsersionError> {
+        let pending = self.pending_by_sender.entry(mempool_tx.contract_address()).or_default();
+        match pending.entry(mempool_tx.nonce()) {
+            btree_map::Entry::Occupied(mut entry) => {
+                if !force {
+                    if entry.get().tx_hash() == mempool_tx.tx_hash() {
+                        return Err(TxInsersionError::DuplicateTxn);
+                    }
+                    check_replacement(entry.get(), &mempool_tx, self.limiter.config.replacement_bump_percent)?;
+                }
+                Ok(ReplacedState::Replaced { previous: entry.insert(mempool_tx) })
+            }
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(mempool_tx);
+                Ok(ReplacedState::NotReplaced)
+            }
+        }
+    }
+
+    /// Moves the pending transactions of `sender` that no longer have a nonce gap to its ready transactions, in nonce
+    /// order.
+    fn promote_pending(&mut self, sender: ContractAddress, account_nonce: Nonce) {
+        while let Some(pending) = self.pending_by_sender.get_mut(&sender) {
+            let (&nonce, _) = pending.first_key_value().expect("Pending transactions should not be empty");
+            let next_ready_nonce =
+                self.nonce_chains.get(&sender.to_felt()).map(|chain| next_nonce(chain.last().nonce()));
+            if nonce > account_nonce && !next_ready_nonce.is_some_and(|next| nonce <= next) {
+                break;
             }
+
+            let (_, mempool_tx) = pending.pop_first().expect("Checked just above");
+            if pending.is_empty() {
+                self.pending_by_sender.remove(&sender);
+            }
+            tracing::debug!("Promoting pending tx_hash={:#x}", mempool_tx.tx_hash().to_felt());
+            let force = true;
+            let is_replaced = self.insert_ready(mempool_tx, force).expect("Force insert tx should not error");
+            debug_assert!(matches!(is_replaced, ReplacedState::NotReplaced));
+        }
+    }
+
+    /// Finds the account whose last transaction has the lowest tip, strictly lower than `incoming_tip`.
+    /// The incoming transaction's own account is never considered, as evicting from it could create a nonce gap
+    /// right before the incoming transaction.
+    // todo(perf): this is O(n) in the number of accounts in the mempool. A tip-ordered index of the nonce chain tails
+    // would make this O(log n), but this is only hit when the mempool is full.
+    fn lowest_priority_evictable(&self, incoming_tip: u64, incoming_contract_addr: Felt) -> Option<Felt> {
+        self.nonce_chains
+            .iter()
+            .filter(|(contract_addr, _)| **contract_addr != incoming_contract_addr)
+            .map(|(contract_addr, chain)| (contract_addr, chain.last()))
+            // L1 handler transactions are never evicted, we don't want to miss any of those.
+            .filter(|(_, tx)| tx.tx.tx_type() != TransactionType::L1Handler)
+            .map(|(contract_addr, tx)| (*contract_addr, tx.tip()))
+            // Equal tips are not evicted, to avoid churn.
+            .filter(|(_, tip)| *tip < incoming_tip)
+            .min_by_key(|(_, tip)| *tip)
+            .map(|(contract_addr, _)| contract_addr)
+    }
+
+    /// Evicts the lowest-tip transaction of the mempool to make room for an incoming transaction with a strictly
+    /// higher tip. Only the last transaction of every nonce chain is a candidate, so that eviction never creates a nonce
+    /// gap.
+    pub fn evict_lowest_priority(
+        &mut self,
+        incoming_tip: u64,
+        incoming_contract_addr: Felt,
+    ) -> Option<MempoolTransaction> {
+        let contract_addr = self.lowest_priority_evictable(incoming_tip, incoming_contract_addr)?;
+
+        // Update nonce chain.
+        let nonce_chain = self.nonce_chains.get_mut(&contract_addr).expect("Evictable account without a nonce chain");
+        let front = QueuedAccount {
+            contract_addr,
+            timestamp: nonce_chain.front_arrived_at,
+            priority: nonce_chain.front_priority,
         };
+        let (mempool_tx, nonce_chain_new_state) = nonce_chain.pop_last();
+        if nonce_chain_new_state == NonceChainNewState::Empty {
+            // Remove the nonce chain and its tx queue entry.
+            let removed = self.nonce_chains.remove(&contract_addr);
+            debug_assert!(removed.is_some());
+            let removed = self.tx_queue.remove(&front);
+            debug_assert!(removed);
+        }
 
-        if let ReplacedState::Replaced { previous } = is_replaced {
-            // Mark the previous transaction as deleted
-            self.limiter.mark_removed(&TransactionCheckedLimits::limits_for(&previous));
-        } else if let Some(contract_address) = &deployed_contract_address {
-            self.deployed_contracts.increment(*contract_address)
+        // Update deployed contracts.
+        if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &mempool_tx.tx {
+            self.deployed_contracts.decrement(tx.contract_address);
         }
 
-        // Update transaction limits
-        self.limiter.update_tx_limits(&limits_for_tx);
+        self.limiter.mark_removed(&self.limiter.limits_for(&mempool_tx), Some(DropReason::Evicted));
+        self.tx_hashes.remove(&mempool_tx.tx_hash().to_felt());
+        Some(mempool_tx)
+    }
 
-        Ok(())
+    /// All the transactions in the mempool, ready and pending, in no particular order.
+    pub fn transactions(&self) -> impl Iterator<Item = &MempoolTransaction> {
+        let ready_txs = self.nonce_chains.values().flat_map(|chain| chain.transactions.keys().map(|tx| &tx.0));
+        ready_txs.chain(self.pending_by_sender.values().flat_map(BTreeMap::values))
+    }
+
+    /// The ready and pending transactions of `sender`, ordered by nonce. The nonce chain and the pending transactions
+    /// already index the transactions by sender, and the pending nonces are all above the ones of the nonce chain.
+    pub fn transactions_of(&self, sender: ContractAddress) -> impl Iterator<Item = &MempoolTransaction> {
+        let ready_txs =
+            self.nonce_chains.get(&sender.to_felt()).into_iter().flat_map(|chain| chain.transactions.keys());
+        let pending_txs = self.pending_by_sender.get(&sender).into_iter().flat_map(BTreeMap::values);
+        ready_txs.map(|tx| &tx.0).chain(pending_txs)
     }
 
     pub fn has_deployed_contract(&self, addr: &ContractAddress) -> bool {
         self.deployed_contracts.contains(addr)
     }
 
-    fn pop_tx_queue_account(&mut self, tx_queue_account: &AccountOrderedByTimestamp) -> MempoolTransaction {
+    /// Whether a declare transaction of the mempool declares this class.
+    pub fn declares_class(&self, class_hash: &Felt) -> bool {
+        self.transactions().any(|tx| match &tx.tx {
+            Transaction::AccountTransaction(AccountTransaction::Declare(tx)) => *tx.class_hash() == *class_hash,
+            _ => false,
+        })
+    }
+
+    /// The account must have been removed from the tx queue already.
+    fn pop_tx_queue_account(&mut self, tx_queue_account: &QueuedAccount) -> MempoolTransaction {
         // Update nonce chain.
         let nonce_chain =
             self.nonce_chains.get_mut(&tx_queue_account.contract_addr).expect("Nonce chain does not match tx queue");
@@ -187,9 +749,10 @@ impl MempoolInner {
             }
             NonceChainNewState::NotEmpty => {
                 // Re-add to tx queue.
-                let inserted = self.tx_queue.insert(AccountOrderedByTimestamp {
+                let inserted = self.tx_queue.insert(QueuedAccount {
                     contract_addr: tx_queue_account.contract_addr,
                     timestamp: nonce_chain.front_arrived_at,
+                    priority: nonce_chain.front_priority,
                 });
                 debug_assert!(inserted);
             }
@@ -199,51 +762,379 @@ impl MempoolInner {
         if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &mempool_tx.tx {
             self.deployed_contracts.decrement(tx.contract_address);
         }
+        self.tx_hashes.remove(&mempool_tx.tx_hash().to_felt());
 
         mempool_tx
     }
 
-    pub fn remove_age_exceeded_txs(&mut self) {
-        // Pop tx queue.
-        // too bad there's no first_entry api, we should check if hashbrown has it to avoid the double lookup.
-        while let Some(tx_queue_account) = self.tx_queue.first() {
-            let tx_queue_account = tx_queue_account.clone(); // clone is cheap for this struct
+    /// Removes the transactions past the max age or their deadline. Returns the removed transactions.
+    pub fn remove_age_exceeded_txs(&mut self) -> Vec<MempoolTransaction> {
+        let mut removed = vec![];
+        // Pop the oldest ready transactions.
+        while let Some(tx_queue_account) = self.tx_queue.oldest() {
+            let tx_queue_account = *tx_queue_account;
             let nonce_chain = self
                 .nonce_chains
                 .get_mut(&tx_queue_account.contract_addr)
                 .expect("Nonce chain does not match tx queue");
             let (k, _v) = nonce_chain.transactions.first_key_value().expect("Nonce chain without a tx");
 
-            if self.limiter.tx_age_exceeded(&TransactionCheckedLimits::limits_for(&k.0)) {
+            if self.limiter.tx_age_exceeded(&self.limiter.limits_for(&k.0)) {
+                let removed = self.tx_queue.remove(&tx_queue_account);
+                debug_assert!(removed);
                 let tx = self.pop_tx_queue_account(&tx_queue_account);
-                let _res = self.tx_queue.pop_first().expect("Cannot be empty, checked just above");
-                self.limiter.mark_removed(&TransactionCheckedLimits::limits_for(&tx));
+                self.limiter.mark_removed(&self.limiter.limits_for(&tx), Some(DropReason::Expired));
+                if tx.tx.tx_type() != TransactionType::L1Handler {
+                    self.demote_following(tx_queue_account.contract_addr, tx.nonce());
+                }
+                removed.push(tx);
             } else {
                 break;
             }
         }
+
+        // Pending transactions are not in the tx queue.
+        // todo(perf): this is O(n) in the number of pending transactions.
+        for pending in self.pending_by_sender.values_mut() {
+            let (expired, kept): (BTreeMap<_, _>, BTreeMap<_, _>) = std::mem::take(pending)
+                .into_iter()
+                .partition(|(_, tx)| self.limiter.tx_expired(&self.limiter.limits_for(tx)));
+            *pending = kept;
+            for (_, tx) in expired {
+                if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &tx.tx {
+                    self.deployed_contracts.decrement(tx.contract_address);
+                }
+                self.limiter.mark_removed(&self.limiter.limits_for(&tx), Some(DropReason::Expired));
+                self.tx_hashes.remove(&tx.tx_hash().to_felt());
+                removed.push(tx);
+            }
+        }
+        self.pending_by_sender.retain(|_, pending| !pending.is_empty());
+
+        // The deadlines do not follow the arrival order, the ready transactions past theirs can be anywhere in the
+        // nonce chains. The following transactions of their senders are kept as pending transactions, until the
+        // nonce gap is filled.
+        let now = self.now();
+        let mut passed = HashSet::new();
+        while let Some((_, tx_hash)) = self.deadlines.first().filter(|(deadline, _)| *deadline <= now) {
+            passed.insert(*tx_hash);
+            self.deadlines.pop_first();
+        }
+        passed.retain(|tx_hash| self.tx_hashes.contains(tx_hash));
+        if !passed.is_empty() {
+            let is_passed = |tx: &MempoolTransaction| {
+                passed.contains(&tx.tx_hash().to_felt()) && tx.deadline.is_some_and(|deadline| deadline <= now)
+            };
+            removed.extend(self.remove_ready_matching(is_passed, Some(DropReason::Expired)));
+        }
+
+        let min_interval = self.limiter.config.replacement_min_interval;
+        self.last_replacements
+            .retain(|_, last_replaced| now.duration_since(*last_replaced).is_ok_and(|elapsed| elapsed < min_interval));
+
+        #[cfg(feature = "metrics")]
+        self.limiter.record_oldest_transaction_age(self.oldest_transaction_age());
+
+        self.emit_removed(&removed, RemovalReason::Expired);
+        removed
+    }
+
+    /// Time since the arrival of the oldest ready or pending transaction, `None` when the mempool is empty. This is
+    /// published as a metric every time the age-exceeded transactions are removed.
+    pub fn oldest_transaction_age(&mut self) -> Option<Duration> {
+        while self.arrivals.first().is_some_and(|(_, tx_hash)| !self.tx_hashes.contains(tx_hash)) {
+            self.arrivals.pop_first();
+        }
+        let (oldest, _) = self.arrivals.first()?;
+        Some(self.now().duration_since(*oldest).unwrap_or_default())
+    }
+
+    /// Removes the L1 handler transactions of the L1 messages with these nonces, for when the L1 blocks they come from
+    /// were reorged out. Returns the removed transactions.
+    // todo(perf): this is O(n) in the number of transactions in the mempool, but L1 reorgs are rare.
+    pub fn remove_l1_handler_txs(&mut self, l1_message_nonces: &HashSet<Nonce>) -> Vec<MempoolTransaction> {
+        let is_reorged = |tx: &MempoolTransaction| {
+            tx.tx.tx_type() == TransactionType::L1Handler && l1_message_nonces.contains(&tx.nonce())
+        };
+        // L1 handler transactions are always ready, they are never in `pending_by_sender`.
+        let removed = self.remove_ready_matching(is_reorged, None);
+        self.emit_removed(&removed, RemovalReason::L1Reorg);
+        removed
+    }
+
+    /// Removes the transaction with this hash, ready or pending. The following transactions of its sender are kept as
+    /// pending transactions, until the nonce gap is filled. Returns the removed transaction, if it was found.
+    // todo(perf): this is O(n) in the number of transactions in the mempool, but operator removals are rare.
+    pub fn remove_tx_by_hash(&mut self, tx_hash: Felt) -> Option<MempoolTransaction> {
+        if !self.tx_hashes.contains(&tx_hash) {
+            return None;
+        }
+        let is_tx = |tx: &MempoolTransaction| tx.tx_hash().to_felt() == tx_hash;
+
+        let pending = self
+            .pending_by_sender
+            .iter()
+            .find_map(|(sender, pending)| pending.iter().find(|(_, tx)| is_tx(tx)).map(|(nonce, _)| (*sender, *nonce)));
+        let removed = if let Some((sender, nonce)) = pending {
+            let pending = self.pending_by_sender.get_mut(&sender).expect("Sender without pending transactions");
+            let tx = pending.remove(&nonce).expect("Pending transaction not found");
+            if pending.is_empty() {
+                self.pending_by_sender.remove(&sender);
+            }
+            if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &tx.tx {
+                self.deployed_contracts.decrement(tx.contract_address);
+            }
+            self.limiter.mark_removed(&self.limiter.limits_for(&tx), Some(DropReason::RemovedByOperator));
+            self.tx_hashes.remove(&tx_hash);
+            tx
+        } else {
+            let mut removed = self.remove_ready_matching(is_tx, Some(DropReason::RemovedByOperator));
+            debug_assert_eq!(removed.len(), 1);
+            removed.pop()?
+        };
+
+        self.emit_removed([&removed], RemovalReason::RemovedByOperator);
+        Some(removed)
+    }
+
+    /// Removes the ready transactions matching `f`, from anywhere in the nonce chains. The following transactions of
+    /// their senders are moved back to the pending transactions, behind the nonce gap. Returns the removed
+    /// transactions, which are counted as `dropped`.
+    // todo(perf): this is O(n) in the number of ready transactions.
+    fn remove_ready_matching(
+        &mut self,
+        f: impl Fn(&MempoolTransaction) -> bool,
+        dropped: Option<DropReason>,
+    ) -> Vec<MempoolTransaction> {
+        let contract_addrs: Vec<Felt> = self
+            .nonce_chains
+            .iter()
+            .filter(|(_, chain)| chain.transactions.keys().any(|tx| f(&tx.0)))
+            .map(|(contract_addr, _)| *contract_addr)
+            .collect();
+
+        let mut removed = vec![];
+        for contract_addr in contract_addrs {
+            let nonce_chain = self.nonce_chains.get_mut(&contract_addr).expect("Contract addr without a nonce chain");
+            let front = QueuedAccount {
+                contract_addr,
+                timestamp: nonce_chain.front_arrived_at,
+                priority: nonce_chain.front_priority,
+            };
+            let (txs, nonce_chain_new_state) = nonce_chain.remove_matching(&f);
+
+            // The front of the chain may have changed, re-queue the account.
+            let removed_from_queue = self.tx_queue.remove(&front);
+            debug_assert!(removed_from_queue);
+            match nonce_chain_new_state {
+                NonceChainNewState::Empty => {
+                    let removed = self.nonce_chains.remove(&contract_addr);
+                    debug_assert!(removed.is_some());
+                }
+                NonceChainNewState::NotEmpty => {
+                    let inserted = self.tx_queue.insert(QueuedAccount {
+                        contract_addr,
+                        timestamp: nonce_chain.front_arrived_at,
+                        priority: nonce_chain.front_priority,
+                    });
+                    debug_assert!(inserted);
+                }
+            }
+
+            // L1 handler nonces are not account nonces, they have no gap to wait for.
+            let gap = txs.iter().filter(|tx| tx.tx.tx_type() != TransactionType::L1Handler).map(|tx| tx.nonce()).min();
+            if let Some(gap) = gap {
+                self.demote_following(contract_addr, gap);
+            }
+
+            for tx in txs {
+                if let Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) = &tx.tx {
+                    self.deployed_contracts.decrement(tx.contract_address);
+                }
+                self.limiter.mark_removed(&self.limiter.limits_for(&tx), dropped);
+                self.tx_hashes.remove(&tx.tx_hash().to_felt());
+                removed.push(tx);
+            }
+        }
+        removed
+    }
+
+    /// Moves the ready transactions of `contract_addr` with a nonce above `gap` back to the pending transactions, for
+    /// when the transaction with the `gap` nonce was removed from the mempool. They wait there until the nonce gap is
+    /// filled, like the transactions inserted with a nonce gap.
+    fn demote_following(&mut self, contract_addr: Felt, gap: Nonce) {
+        let Some(nonce_chain) = self.nonce_chains.get_mut(&contract_addr) else { return };
+        let front = QueuedAccount {
+            contract_addr,
+            timestamp: nonce_chain.front_arrived_at,
+            priority: nonce_chain.front_priority,
+        };
+
+        let mut demoted = vec![];
+        let mut nonce_chain_new_state = NonceChainNewState::NotEmpty;
+        while nonce_chain_new_state == NonceChainNewState::NotEmpty && nonce_chain.last().nonce() > gap {
+            let (tx, new_state) = nonce_chain.pop_last();
+            nonce_chain_new_state = new_state;
+            demoted.push(tx);
+        }
+        if nonce_chain_new_state == NonceChainNewState::Empty {
+            // Remove the nonce chain and its tx queue entry.
+            let removed = self.nonce_chains.remove(&contract_addr);
+            debug_assert!(removed.is_some());
+            let removed = self.tx_queue.remove(&front);
+            debug_assert!(removed);
+        }
+
+        for tx in demoted {
+            tracing::debug!("Demoting tx_hash={:#x} behind a nonce gap", tx.tx_hash().to_felt());
+            let force = true;
+            let is_replaced = self.insert_pending(tx, force).expect("Force insert tx should not error");
+            debug_assert!(matches!(is_replaced, ReplacedState::NotReplaced));
+        }
     }
 
+    /// Pops the next ready transaction. The expired transactions found on the way are removed, use
+    /// [`MempoolInner::pop_next_chunk`] to get them.
     pub fn pop_next(&mut self) -> Option<MempoolTransaction> {
+        self.pop_next_skipping_expired(&mut vec![])
+    }
+
+    /// Like [`MempoolInner::pop_next`], and pushes the removed expired transactions to `expired`.
+    fn pop_next_skipping_expired(&mut self, expired: &mut Vec<MempoolTransaction>) -> Option<MempoolTransaction> {
         // Pop tx queue.
         let mempool_tx = loop {
             let tx_queue_account = self.tx_queue.pop_first()?; // Bubble up None if the mempool is empty.
             let mempool_tx = self.pop_tx_queue_account(&tx_queue_account);
 
-            let limits = TransactionCheckedLimits::limits_for(&mempool_tx);
-            if !self.limiter.tx_age_exceeded(&limits) {
+            let limits = self.limiter.limits_for(&mempool_tx);
+            if !self.limiter.tx_expired(&limits) {
                 break mempool_tx;
             }
 
-            self.limiter.mark_removed(&limits);
+            self.limiter.mark_removed(&limits, Some(DropReason::Expired));
+            self.emit_removed([&mempool_tx], RemovalReason::Expired);
+            if mempool_tx.tx.tx_type() != TransactionType::L1Handler {
+                self.demote_following(tx_queue_account.contract_addr, mempool_tx.nonce());
+            }
+            expired.push(mempool_tx);
         };
 
-        // do not update mempool limits, block prod will update it with re-add txs.
+        // The transaction stays counted until block prod re-adds it or marks it as consumed.
+        self.limiter.reserve_for_block();
         Some(mempool_tx)
     }
 
-    pub fn pop_next_chunk(&mut self, dest: &mut impl Extend<MempoolTransaction>, n: usize) {
-        dest.extend((0..n).map_while(|_| self.pop_next()))
+    /// The first `n` ready transactions, in the order [`MempoolInner::pop_next`] would pop them, without removing them
+    /// or changing the counters. Like `pop_next`, the expired transactions are skipped.
+    pub fn peek_ready(&self, n: usize) -> Vec<&MempoolTransaction> {
+        self.peek_ready_iter().take(n).collect()
+    }
+
+    /// The transactions [`MempoolInner::pop_next_chunk_priced`] would take with the same `n` and `l1_gas_price`, in
+    /// the same order, without removing them or changing the counters. The skipped transactions are not returned.
+    pub fn peek_next_chunk_priced(&self, n: usize, l1_gas_price: u128) -> Vec<&MempoolTransaction> {
+        let mut underpriced_senders = HashSet::new();
+        self.peek_ready_iter()
+            .filter(|tx| {
+                let is_underpriced = tx.max_l1_gas_price().is_some_and(|max_price| max_price < l1_gas_price);
+                if is_underpriced || underpriced_senders.contains(&tx.contract_address()) {
+                    underpriced_senders.insert(tx.contract_address());
+                    return false;
+                }
+                true
+            })
+            .take(n)
+            .collect()
+    }
+
+    /// The ready transactions in the order [`MempoolInner::pop_next`] would pop them, skipping the expired ones.
+    fn peek_ready_iter(&self) -> impl Iterator<Item = &MempoolTransaction> + '_ {
+        let mut queue = self.tx_queue.peek();
+        // The transactions of each peeked account which have not been peeked yet, in nonce order.
+        let mut remaining = HashMap::new();
+        std::iter::from_fn(move || loop {
+            let tx_queue_account = queue.pop_first()?;
+            let txs = remaining.entry(tx_queue_account.contract_addr).or_insert_with(|| {
+                let nonce_chain = self
+                    .nonce_chains
+                    .get(&tx_queue_account.contract_addr)
+                    .expect("Nonce chain does not match tx queue");
+                nonce_chain.transactions.keys().map(|tx| &tx.0).peekable()
+            });
+            let mempool_tx = txs.next().expect("Nonce chain does not match tx queue");
+            let expired = self.limiter.tx_expired(&self.limiter.limits_for(mempool_tx));
+            // Like popping it, peeking a transaction makes the next one of the nonce chain ready, unless it is expired:
+            // the following transactions of its sender are then behind a nonce gap.
+            let demotes_following = expired && mempool_tx.tx.tx_type() != TransactionType::L1Handler;
+            if let Some(next_tx) = txs.peek().filter(|_| !demotes_following) {
+                queue.insert(QueuedAccount {
+                    contract_addr: tx_queue_account.contract_addr,
+                    timestamp: next_tx.arrived_at,
+                    priority: TxPriority::of(next_tx),
+                });
+            }
+
+            if !expired {
+                return Some(mempool_tx);
+            }
+        })
+    }
+
+    /// Pops up to `n` ready transactions. Returns the expired transactions removed on the way.
+    pub fn pop_next_chunk(&mut self, dest: &mut impl Extend<MempoolTransaction>, n: usize) -> Vec<MempoolTransaction> {
+        let mut expired = vec![];
+        dest.extend((0..n).map_while(|_| self.pop_next_skipping_expired(&mut expired)));
+        expired
+    }
+
+    /// Like [`MempoolInner::pop_next_chunk`], but skips the V3 transactions whose L1 gas max price is below
+    /// `l1_gas_price`. Once a transaction of a sender is skipped, the following transactions of that sender are skipped
+    /// too, since they cannot be executed before it. Depending on the `policy`, the skipped transactions are put back in
+    /// the mempool or removed from it.
+    ///
+    /// Returns the removed transactions: the expired ones, and the underpriced ones under the drop policy.
+    pub fn pop_next_chunk_priced(
+        &mut self,
+        dest: &mut impl Extend<MempoolTransaction>,
+        n: usize,
+        l1_gas_price: u128,
+        policy: MempoolUnderpricedPolicy,
+    ) -> Vec<MempoolTransaction> {
+        let mut expired = vec![];
+        let mut underpriced = vec![];
+        let mut underpriced_senders = HashSet::new();
+        let mut taken = 0;
+        while taken < n {
+            let Some(tx) = self.pop_next_skipping_expired(&mut expired) else { break };
+            let is_underpriced = tx.max_l1_gas_price().is_some_and(|max_price| max_price < l1_gas_price);
+            if is_underpriced || underpriced_senders.contains(&tx.contract_address()) {
+                underpriced_senders.insert(tx.contract_address());
+                underpriced.push(tx);
+            } else {
+                dest.extend([tx]);
+                taken += 1;
+            }
+        }
+
+        match policy {
+            MempoolUnderpricedPolicy::Requeue => {
+                for tx in underpriced {
+                    // The popped transactions are still counted: they are counted again on insertion.
+                    self.limiter.release_reservation(&self.limiter.limits_for(&tx));
+                    let force = true;
+                    let nonce = tx.nonce();
+                    self.insert_tx(tx, force, nonce).expect("Force insert tx should not error");
+                }
+            }
+            MempoolUnderpricedPolicy::Drop => {
+                for tx in &underpriced {
+                    self.limiter.release_reservation(&self.limiter.limits_for(tx));
+                }
+                self.emit_removed(&underpriced, RemovalReason::Underpriced);
+                expired.extend(underpriced);
+            }
+        }
+        expired
     }
 
     /// This is called by the block production after a batch of transaction is executed.
@@ -254,16 +1145,82 @@ impl MempoolInner {
         consumed_txs: impl IntoIterator<Item = MempoolTransaction>,
     ) {
         for tx in consumed_txs {
-            self.limiter.mark_removed(&TransactionCheckedLimits::limits_for(&tx))
+            self.limiter.release_reservation(&self.limiter.limits_for(&tx));
+            self.emit_removed([&tx], RemovalReason::Included);
+            // The account nonce is now past the consumed transaction.
+            self.promote_pending(tx.contract_address(), next_nonce(tx.nonce()));
         }
         for tx in txs {
+            // The popped transactions are still counted: they are counted again on insertion.
+            self.limiter.release_reservation(&self.limiter.limits_for(&tx));
             let force = true;
-            self.insert_tx(tx, force).expect("Force insert tx should not error");
+            let nonce = tx.nonce();
+            self.insert_tx(tx, force, nonce).expect("Force insert tx should not error");
         }
     }
 
+    /// See [`MempoolLimiter::update_limits`].
+    pub fn update_limits(&mut self, update: MempoolLimitsUpdate) -> Result<MempoolLimitsUpdate, InvalidMempoolLimits> {
+        self.limiter.update_limits(update)
+    }
+
+    /// See [`MempoolLimiter::record_block_fullness`].
+    pub fn record_block_fullness(&mut self, full: bool) -> usize {
+        self.limiter.record_block_fullness(full)
+    }
+
+    /// See [`MempoolLimiter::is_near_capacity`].
+    pub fn is_near_capacity(&self) -> bool {
+        self.limiter.is_near_capacity()
+    }
+
+    /// See [`MempoolLimiter::update_min_tip`].
+    pub fn update_min_tip(&mut self, l1_gas_price: u128) -> u64 {
+        self.limiter.update_min_tip(l1_gas_price)
+    }
+
+    /// Whether the minimum tip is enabled, see [`MempoolLimits::min_tip_multiplier`].
+    pub fn has_min_tip(&self) -> bool {
+        self.limiter.config.min_tip_multiplier > 0.0
+    }
+
+    /// See [`MempoolLimiter::counters`].
+    pub fn counters(&self) -> MempoolCounters {
+        self.limiter.counters()
+    }
+
+    /// See [`MempoolLimiter::type_counters`].
+    pub fn type_counters(&self, tx_type: TransactionType) -> TransactionTypeCounters {
+        self.limiter.type_counters(tx_type)
+    }
+
+    /// See [`MempoolLimiter::dropped_transactions`].
+    pub fn dropped_transactions(&self, reason: DropReason) -> u64 {
+        self.limiter.dropped_transactions(reason)
+    }
+
+    /// See [`MempoolLimiter::drop_reason`].
+    pub fn drop_reason(&self, tx_hash: &Felt) -> Option<DropReason> {
+        self.limiter.drop_reason(tx_hash)
+    }
+
+    /// See [`MempoolLimiter::throughput`].
+    pub fn throughput(&self) -> MempoolThroughput {
+        self.limiter.throughput()
+    }
+
+    /// See [`MempoolLimiter::check_counter_consistency`].
+    pub fn check_counter_consistency(&mut self) -> Option<CounterDivergence> {
+        let in_mempool = self.transactions().count();
+        self.limiter.check_counter_consistency(in_mempool)
+    }
+
     #[cfg(any(test, feature = "testing"))]
     pub fn is_empty(&self) -> bool {
-        self.tx_queue.is_empty()
+        self.tx_queue.is_empty() && self.pending_by_sender.is_empty()
     }
 }
+
+fn next_nonce(nonce: Nonce) -> Nonce {
+    Nonce(nonce.0 + Felt::ONE)
+}
@@ -1,7 +1,10 @@
 use super::tx::{ArrivedAtTimestamp, MempoolTransaction};
+use super::tx_queue::TxPriority;
 use crate::TxInsersionError;
+use blockifier::transaction::transaction_types::TransactionType;
+use mc_exec::execution::TxInfo;
 use starknet_api::{core::Nonce, transaction::TransactionHash};
-use std::collections::{btree_map, BTreeMap};
+use std::collections::BTreeMap;
 use std::{cmp, iter};
 
 #[derive(Debug, Clone)]
@@ -25,7 +28,7 @@ impl PartialOrd for OrderMempoolTransactionByNonce {
 }
 
 /// Invariants:
-/// - front_nonce, front_arrived_at and front_tx_hash must match the front transaction timestamp.
+/// - front_nonce, front_arrived_at, front_tx_hash and front_priority must match the front transaction.
 /// - No nonce chain should ever be empty in the mempool.
 #[derive(Debug)]
 pub struct NonceChain {
@@ -36,11 +39,12 @@ pub struct NonceChain {
     pub(crate) front_arrived_at: ArrivedAtTimestamp,
     pub(crate) front_nonce: Nonce,
     pub(crate) front_tx_hash: TransactionHash,
+    pub(crate) front_priority: TxPriority,
 }
 
 #[derive(Eq, PartialEq, Debug)]
 pub enum InsertedPosition {
-    Front { former_head_arrived_at: ArrivedAtTimestamp },
+    Front { former_head_arrived_at: ArrivedAtTimestamp, former_head_priority: TxPriority },
     Other,
 }
 
@@ -62,6 +66,7 @@ impl NonceChain {
             front_arrived_at: tx.arrived_at,
             front_tx_hash: tx.tx_hash(),
             front_nonce: tx.nonce(),
+            front_priority: TxPriority::of(&tx),
             transactions: iter::once((OrderMempoolTransactionByNonce(tx), ())).collect(),
         }
     }
@@ -73,18 +78,28 @@ impl NonceChain {
         assert_eq!(front.0.tx_hash(), self.front_tx_hash);
         assert_eq!(front.0.nonce(), self.front_nonce);
         assert_eq!(front.0.arrived_at, self.front_arrived_at);
+        assert_eq!(TxPriority::of(&front.0), self.front_priority);
+    }
+
+    /// Returns the transaction with the same nonce as `mempool_tx`, if any.
+    pub fn get_same_nonce(&self, mempool_tx: &OrderMempoolTransactionByNonce) -> Option<&MempoolTransaction> {
+        self.transactions.get_key_value(mempool_tx).map(|(tx, _)| &tx.0)
     }
 
     /// Returns where in the chain it was inserted.
+    /// When `force` is `false`, a transaction with the same nonce as an existing one replaces it only if its tip is
+    /// bumped by at least `replacement_bump_percent`.
     /// When `force` is `true`, this function should never return any error.
     pub fn insert(
         &mut self,
         mempool_tx: MempoolTransaction,
         force: bool,
+        replacement_bump_percent: u64,
     ) -> Result<(InsertedPosition, ReplacedState), TxInsersionError> {
         let mempool_tx_arrived_at = mempool_tx.arrived_at;
         let mempool_tx_nonce = mempool_tx.nonce();
         let mempool_tx_hash = mempool_tx.tx_hash();
+        let mempool_tx_priority = TxPriority::of(&mempool_tx);
 
         let replaced = if force {
             // double lookup here unfortunately.. that's because we're using the keys in a hacky way and can't update the
@@ -101,19 +116,24 @@ impl NonceChain {
                 ReplacedState::NotReplaced
             }
         } else {
-            match self.transactions.entry(OrderMempoolTransactionByNonce(mempool_tx)) {
-                btree_map::Entry::Occupied(entry) => {
-                    // duplicate nonce, either it's because the hash is duplicated or nonce conflict with another tx.
-                    if entry.key().0.tx_hash() == mempool_tx_hash {
-                        return Err(TxInsersionError::DuplicateTxn);
-                    } else {
-                        return Err(TxInsersionError::NonceConflict);
-                    }
+            let mempool_tx = OrderMempoolTransactionByNonce(mempool_tx);
+            if let Some((previous, _)) = self.transactions.get_key_value(&mempool_tx) {
+                // duplicate nonce, either it's because the hash is duplicated or this tx wants to replace another one.
+                if previous.0.tx_hash() == mempool_tx_hash {
+                    return Err(TxInsersionError::DuplicateTxn);
                 }
-                btree_map::Entry::Vacant(entry) => *entry.insert(()),
-            }
+                check_replacement(&previous.0, &mempool_tx.0, replacement_bump_percent)?;
 
-            ReplacedState::NotReplaced
+                // same double lookup as above.
+                let (previous, _) = self.transactions.remove_entry(&mempool_tx).expect("Checked just above");
+                let inserted = self.transactions.insert(mempool_tx, ());
+                debug_assert!(inserted.is_none());
+                ReplacedState::Replaced { previous: previous.0 }
+            } else {
+                let inserted = self.transactions.insert(mempool_tx, ());
+                debug_assert!(inserted.is_none());
+                ReplacedState::NotReplaced
+            }
         };
 
         let position = if self.front_nonce >= mempool_tx_nonce {
@@ -121,7 +141,8 @@ impl NonceChain {
             let former_head_arrived_at = core::mem::replace(&mut self.front_arrived_at, mempool_tx_arrived_at);
             self.front_nonce = mempool_tx_nonce;
             self.front_tx_hash = mempool_tx_hash;
-            InsertedPosition::Front { former_head_arrived_at }
+            let former_head_priority = core::mem::replace(&mut self.front_priority, mempool_tx_priority);
+            InsertedPosition::Front { former_head_arrived_at, former_head_priority }
         } else {
             InsertedPosition::Other
         };
@@ -137,13 +158,70 @@ impl NonceChain {
     pub fn pop(&mut self) -> (MempoolTransaction, NonceChainNewState) {
         // TODO(perf): avoid double lookup
         let (tx, _) = self.transactions.pop_first().expect("Nonce chain should not be empty");
+        (tx.0, self.update_front())
+    }
+
+    /// Removes the transactions matching `f`, from anywhere in the chain.
+    pub fn remove_matching(
+        &mut self,
+        mut f: impl FnMut(&MempoolTransaction) -> bool,
+    ) -> (Vec<MempoolTransaction>, NonceChainNewState) {
+        let (removed, kept): (BTreeMap<_, _>, BTreeMap<_, _>) =
+            std::mem::take(&mut self.transactions).into_iter().partition(|(tx, _)| f(&tx.0));
+        self.transactions = kept;
+        (removed.into_keys().map(|tx| tx.0).collect(), self.update_front())
+    }
+
+    fn update_front(&mut self) -> NonceChainNewState {
         if let Some((new_front, _)) = self.transactions.first_key_value() {
             self.front_arrived_at = new_front.0.arrived_at;
             self.front_tx_hash = new_front.0.tx_hash();
             self.front_nonce = new_front.0.nonce();
-            (tx.0, NonceChainNewState::NotEmpty)
+            self.front_priority = TxPriority::of(&new_front.0);
+            NonceChainNewState::NotEmpty
         } else {
+            NonceChainNewState::Empty
+        }
+    }
+
+    /// Returns the transaction with the highest nonce, which is the only one that can be removed without creating a
+    /// nonce gap in the chain.
+    pub fn last(&self) -> &MempoolTransaction {
+        &self.transactions.last_key_value().expect("Nonce chain should not be empty").0 .0
+    }
+
+    /// Removes the transaction with the highest nonce. The front of the chain is unchanged unless the chain becomes
+    /// empty.
+    pub fn pop_last(&mut self) -> (MempoolTransaction, NonceChainNewState) {
+        let (tx, _) = self.transactions.pop_last().expect("Nonce chain should not be empty");
+        if self.transactions.is_empty() {
             (tx.0, NonceChainNewState::Empty)
+        } else {
+            (tx.0, NonceChainNewState::NotEmpty)
         }
     }
 }
+
+/// Minimum tip for a transaction to replace one with `previous_tip`. The tip always has to be strictly greater, even
+/// when the bump percent is 0.
+pub(crate) fn min_replacement_tip(previous_tip: u64, replacement_bump_percent: u64) -> u128 {
+    let bumped = (u128::from(previous_tip) * (100 + u128::from(replacement_bump_percent))).div_ceil(100);
+    bumped.max(u128::from(previous_tip) + 1)
+}
+
+pub(crate) fn check_replacement(
+    previous: &MempoolTransaction,
+    mempool_tx: &MempoolTransaction,
+    replacement_bump_percent: u64,
+) -> Result<(), TxInsersionError> {
+    // L1 handler transactions come from the core contract, they can neither be replaced nor replace anything.
+    if previous.tx.tx_type() == TransactionType::L1Handler || mempool_tx.tx.tx_type() == TransactionType::L1Handler {
+        return Err(TxInsersionError::NonceConflict);
+    }
+
+    let min_tip = min_replacement_tip(previous.tip(), replacement_bump_percent);
+    if u128::from(mempool_tx.tip()) < min_tip {
+        return Err(TxInsersionError::ReplacementUnderpriced { tip: mempool_tx.tip(), min_tip });
+    }
+    Ok(())
+}
@@ -23,7 +23,7 @@ use starknet_types_core::felt::Felt;
 use blockifier::abi::abi_utils::selector_from_name;
 use starknet_api::transaction::Fee;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt,
     time::{Duration, SystemTime},
 };
@@ -160,7 +160,7 @@ impl Arbitrary for Insert {
                 let tx = Transaction::from_api(tx, tx_hash, Some(DUMMY_CLASS.clone()), l1_gas_paid, deployed, false)
                     .unwrap();
 
-                Insert(MempoolTransaction { tx, arrived_at, converted_class: None }, force)
+                Insert(MempoolTransaction::new(tx, arrived_at, None), force)
             })
             .boxed()
     }
@@ -181,18 +181,18 @@ impl MempoolInvariantsProblem {
         mempool.check_invariants();
 
         let mut inserted = HashSet::new();
-        let mut inserted_contract_nonce_pairs = HashSet::new();
+        let mut inserted_contract_nonce_pairs = HashMap::new();
         let mut new_contracts = HashSet::new();
 
         let handle_pop = |res: Option<MempoolTransaction>,
                           inserted: &mut HashSet<TransactionHash>,
-                          inserted_contract_nonce_pairs: &mut HashSet<(Nonce, ContractAddress)>,
+                          inserted_contract_nonce_pairs: &mut HashMap<(Nonce, ContractAddress), TransactionType>,
                           new_contracts: &mut HashSet<ContractAddress>| {
             if let Some(res) = &res {
                 let removed = inserted.remove(&res.tx_hash());
                 assert!(removed);
                 let removed = inserted_contract_nonce_pairs.remove(&(res.nonce(), res.contract_address()));
-                assert!(removed);
+                assert!(removed.is_some());
 
                 if res.tx.tx_type() == TransactionType::DeployAccount {
                     let _removed = new_contracts.remove(&res.contract_address());
@@ -210,18 +210,24 @@ impl MempoolInvariantsProblem {
                 Operation::Insert(insert) => {
                     let force = insert.1;
                     tracing::trace!("Insert {:?}", insert);
-                    let res = mempool.insert_tx(insert.0.clone(), insert.1);
+                    let res = mempool
+                        .insert_tx(insert.0.clone(), insert.1, insert.0.nonce())
+                        .map(|outcome| outcome == InsertOutcome::AlreadyKnown);
 
-                    let expected = if !force
-                        && inserted_contract_nonce_pairs.contains(&(insert.0.nonce(), insert.0.contract_address()))
-                    {
-                        if inserted.contains(&insert.0.tx_hash()) {
-                            Err(TxInsersionError::DuplicateTxn)
-                        } else {
+                    let previous_ty =
+                        inserted_contract_nonce_pairs.get(&(insert.0.nonce(), insert.0.contract_address()));
+                    let expected = match previous_ty {
+                        Some(_) if !force && inserted.contains(&insert.0.tx_hash()) => Ok(true),
+                        Some(previous_ty)
+                            if !force
+                                && (*previous_ty == TransactionType::L1Handler
+                                    || insert.0.tx.tx_type() == TransactionType::L1Handler) =>
+                        {
                             Err(TxInsersionError::NonceConflict)
                         }
-                    } else {
-                        Ok(())
+                        // All the generated transactions have a tip of 0, so they can never replace each other.
+                        Some(_) if !force => Err(TxInsersionError::ReplacementUnderpriced { tip: 0, min_tip: 1 }),
+                        _ => Ok(false),
                     };
 
                     assert_eq!(expected, res);
@@ -231,7 +237,8 @@ impl MempoolInvariantsProblem {
                             new_contracts.insert(insert.0.contract_address());
                         }
                         inserted.insert(insert.0.tx_hash());
-                        inserted_contract_nonce_pairs.insert((insert.0.nonce(), insert.0.contract_address()));
+                        inserted_contract_nonce_pairs
+                            .insert((insert.0.nonce(), insert.0.contract_address()), insert.0.tx.tx_type());
                     }
 
                     tracing::trace!("Result {:?}", res);
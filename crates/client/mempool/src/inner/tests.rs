@@ -0,0 +1,2380 @@
+#![cfg(test)]
+
+use super::*;
+use assert_matches::assert_matches;
+use blockifier::abi::abi_utils::selector_from_name;
+use blockifier::transaction::transaction_types::TransactionType;
+use blockifier::{
+    execution::contract_class::ClassInfo,
+    test_utils::{contracts::FeatureContract, CairoVersion},
+    transaction::transaction_execution::Transaction,
+};
+use mp_chain_config::{MempoolDeclareLimitPolicy, MempoolExpiredTxPolicy};
+use starknet_api::{
+    core::{ChainId, Nonce},
+    data_availability::DataAvailabilityMode,
+    transaction::{
+        DeclareTransactionV3, Fee, InvokeTransactionV3, Resource, ResourceBounds, ResourceBoundsMapping, Tip,
+        TransactionHasher, TransactionVersion,
+    },
+};
+use std::time::{Duration, SystemTime};
+
+lazy_static::lazy_static! {
+    static ref DUMMY_CLASS: ClassInfo = {
+        let dummy_contract_class = FeatureContract::TestContract(CairoVersion::Cairo1);
+        ClassInfo::new(&dummy_contract_class.get_class(), 100, 100).unwrap()
+    };
+}
+
+pub(crate) enum TestTxTy {
+    Invoke,
+    Declare,
+    L1Handler,
+}
+
+/// Makes a V3 transaction for the given sender and nonce. The transaction hash is computed from the transaction
+/// content, so transactions that only differ by their tip will have a different hash. L1 handler transactions have no
+/// tip.
+pub(crate) fn make_tx(ty: TestTxTy, sender: u64, nonce: u64, tip: u64) -> MempoolTransaction {
+    make_tx_with_l1_gas_price(ty, sender, nonce, tip, 5)
+}
+
+/// Like [`make_tx`], with an L1 gas max price per unit of `max_l1_gas_price`.
+pub(crate) fn make_tx_with_l1_gas_price(
+    ty: TestTxTy,
+    sender: u64,
+    nonce: u64,
+    tip: u64,
+    max_l1_gas_price: u128,
+) -> MempoolTransaction {
+    make_tx_with_resource_bounds(ty, sender, nonce, tip, max_l1_gas_price, 5)
+}
+
+/// Like [`make_tx`], with an L2 gas max amount of `max_l2_gas`.
+pub(crate) fn make_tx_with_l2_gas(
+    ty: TestTxTy,
+    sender: u64,
+    nonce: u64,
+    tip: u64,
+    max_l2_gas: u64,
+) -> MempoolTransaction {
+    make_tx_with_resource_bounds(ty, sender, nonce, tip, 5, max_l2_gas)
+}
+
+fn make_tx_with_resource_bounds(
+    ty: TestTxTy,
+    sender: u64,
+    nonce: u64,
+    tip: u64,
+    max_l1_gas_price: u128,
+    max_l2_gas: u64,
+) -> MempoolTransaction {
+    let sender_address = ContractAddress::try_from(Felt::from(sender)).unwrap();
+    let nonce = Nonce(Felt::from(nonce));
+    let resource_bounds = ResourceBoundsMapping(
+        [
+            (Resource::L1Gas, ResourceBounds { max_amount: 5, max_price_per_unit: max_l1_gas_price }),
+            (Resource::L2Gas, ResourceBounds { max_amount: max_l2_gas, max_price_per_unit: 5 }),
+        ]
+        .into(),
+    );
+
+    let (tx, class_info) = match ty {
+        TestTxTy::Invoke => (
+            starknet_api::transaction::Transaction::Invoke(starknet_api::transaction::InvokeTransaction::V3(
+                InvokeTransactionV3 {
+                    resource_bounds,
+                    tip: Tip(tip),
+                    signature: Default::default(),
+                    nonce,
+                    sender_address,
+                    calldata: Default::default(),
+                    nonce_data_availability_mode: DataAvailabilityMode::L1,
+                    fee_data_availability_mode: DataAvailabilityMode::L1,
+                    paymaster_data: Default::default(),
+                    account_deployment_data: Default::default(),
+                },
+            )),
+            None,
+        ),
+        TestTxTy::Declare => (
+            starknet_api::transaction::Transaction::Declare(starknet_api::transaction::DeclareTransaction::V3(
+                DeclareTransactionV3 {
+                    resource_bounds,
+                    tip: Tip(tip),
+                    signature: Default::default(),
+                    nonce,
+                    class_hash: Default::default(),
+                    compiled_class_hash: Default::default(),
+                    sender_address,
+                    nonce_data_availability_mode: DataAvailabilityMode::L1,
+                    fee_data_availability_mode: DataAvailabilityMode::L1,
+                    paymaster_data: Default::default(),
+                    account_deployment_data: Default::default(),
+                },
+            )),
+            Some(DUMMY_CLASS.clone()),
+        ),
+        TestTxTy::L1Handler => (
+            starknet_api::transaction::Transaction::L1Handler(starknet_api::transaction::L1HandlerTransaction {
+                version: TransactionVersion::ZERO,
+                nonce,
+                contract_address: sender_address,
+                entry_point_selector: selector_from_name("l1_handler_set_value"),
+                calldata: Default::default(),
+            }),
+            None,
+        ),
+    };
+
+    let paid_fee_on_l1 = matches!(tx, starknet_api::transaction::Transaction::L1Handler(_)).then_some(Fee(1));
+    let tx_hash = tx.calculate_transaction_hash(&ChainId::Mainnet, &TransactionVersion::THREE).unwrap();
+    let tx = Transaction::from_api(tx, tx_hash, class_info, paid_fee_on_l1, None, false).unwrap();
+
+    MempoolTransaction::new(tx, SystemTime::now(), None)
+}
+
+#[test]
+fn mempool_limit_per_sender() {
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { max_transactions_per_sender: 3, ..MempoolLimits::for_testing() });
+
+    for nonce in 0..3 {
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, nonce, 0), false, Nonce(Felt::ZERO)).unwrap();
+    }
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 3, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxPerSender { sender, max: 3 })) if sender == Felt::ONE
+    );
+
+    // Another sender is not affected by the limit.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_limit_per_sender_released_on_consumed() {
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { max_transactions_per_sender: 1, ..MempoolLimits::for_testing() });
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert!(mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 1, 0), false, Nonce(Felt::ZERO)).is_err());
+
+    // Block production pops and consumes the transaction: the sender can send a new one.
+    let popped = mempool.pop_next().unwrap();
+    mempool.re_add_txs([], [popped]);
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 1, 0), false, Nonce(Felt::ONE)).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_limit_per_sender_counts_declares() {
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { max_transactions_per_sender: 2, ..MempoolLimits::for_testing() });
+
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 1, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 2, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxPerSender { sender, max: 2 })) if sender == Felt::ONE
+    );
+    mempool.check_invariants();
+}
+
+fn mempool_with_eviction(max_transactions: usize) -> MempoolInner {
+    MempoolInner::new(MempoolLimits { max_transactions, eviction_enabled: true, ..MempoolLimits::for_testing() })
+}
+
+#[test]
+fn mempool_eviction_evicts_lowest_tip() {
+    let mut mempool = mempool_with_eviction(3);
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 10), false, Nonce(Felt::ZERO)).unwrap();
+    let cheapest = make_tx(TestTxTy::Invoke, 2, 0, 5);
+    let cheapest_hash = cheapest.tx_hash().to_felt();
+    mempool.insert_tx(cheapest, false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 20), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Added)
+    );
+
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 15), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::EvictedToFit(cheapest_hash))
+    );
+    mempool.check_invariants();
+
+    let mut remaining = vec![];
+    mempool.pop_next_chunk(&mut remaining, usize::MAX);
+    let mut remaining_senders: Vec<_> = remaining.iter().map(|tx| tx.contract_address().to_felt()).collect();
+    remaining_senders.sort();
+    assert_eq!(remaining_senders, [Felt::from(1), Felt::from(3), Felt::from(4)]);
+}
+
+#[test]
+fn mempool_eviction_rejects_lower_or_equal_tip() {
+    let mut mempool = mempool_with_eviction(2);
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 10), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 10), false, Nonce(Felt::ZERO)).unwrap();
+
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 5), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 2 }))
+    );
+    // Equal tips do not evict, to avoid churn.
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 10), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 2 }))
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_eviction_only_evicts_chain_tail() {
+    let mut mempool = mempool_with_eviction(3);
+
+    // Sender 1 has the cheapest transaction at the front of its chain, but only its last transaction can be evicted
+    // without creating a nonce gap.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 1), false, Nonce(Felt::ZERO)).unwrap();
+    let tail = make_tx(TestTxTy::Invoke, 1, 1, 8);
+    let tail_hash = tail.tx_hash().to_felt();
+    mempool.insert_tx(tail, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 9), false, Nonce(Felt::ZERO)).unwrap();
+
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 10), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::EvictedToFit(tail_hash))
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_eviction_disabled() {
+    let mut mempool = MempoolInner::new(MempoolLimits { max_transactions: 1, ..MempoolLimits::for_testing() });
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 100), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 1 }))
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_lowered_limits() {
+    let mut mempool = mempool_with_eviction(4);
+    for sender in 0..3 {
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, sender, 0, 10), false, Nonce(Felt::ZERO)).unwrap();
+    }
+
+    let update = MempoolLimitsUpdate { max_transactions: Some(2), ..Default::default() };
+    let previous = mempool.update_limits(update).unwrap();
+    assert_eq!(previous.max_transactions, Some(4));
+
+    // Nothing is evicted, even for a higher tip: the mempool has to drain first.
+    assert_eq!(mempool.transactions().count(), 3);
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 100), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 2 }))
+    );
+    assert_eq!(mempool.transactions().count(), 3);
+    mempool.check_invariants();
+
+    // Once drained back to the limit, eviction makes room again.
+    let popped = mempool.pop_next().unwrap();
+    mempool.re_add_txs([], [popped]);
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 100), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::EvictedToFit(_))
+    );
+
+    // Restoring the previous limits.
+    mempool.update_limits(previous).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 10), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_invalid_limits_update() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        reserved_l1_handler_transactions: 2,
+        reserved_deploy_account_transactions: 1,
+        ..MempoolLimits::for_testing()
+    });
+
+    assert_eq!(
+        mempool.update_limits(MempoolLimitsUpdate { max_transactions: Some(3), ..Default::default() }),
+        Err(InvalidMempoolLimits::MaxTransactionsTooLow { min: 4 })
+    );
+    assert_eq!(
+        mempool.update_limits(MempoolLimitsUpdate { max_age: Some(Duration::ZERO), ..Default::default() }),
+        Err(InvalidMempoolLimits::ZeroMaxAge)
+    );
+    // A rejected update changes nothing.
+    assert_eq!(mempool.limiter.config.max_transactions, usize::MAX);
+}
+
+#[test]
+fn mempool_auto_tune_follows_block_fullness() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 1_000,
+        auto_tune: Some(MempoolAutoTune { min_transactions: 900, max_transactions: 1_150 }),
+        ..MempoolLimits::for_testing()
+    });
+    let report = |mempool: &mut MempoolInner, full: bool, blocks: u32| {
+        (0..blocks).fold(0, |_, _| mempool.record_block_fullness(full))
+    };
+
+    // The limit only moves after enough consecutive full blocks.
+    assert_eq!(report(&mut mempool, true, AUTO_TUNE_BLOCKS - 1), 1_000);
+    assert_eq!(report(&mut mempool, false, 1), 1_000);
+    assert_eq!(report(&mut mempool, true, AUTO_TUNE_BLOCKS - 1), 1_000);
+    assert_eq!(report(&mut mempool, true, 1), 1_100);
+    // Up to the ceiling.
+    assert_eq!(report(&mut mempool, true, AUTO_TUNE_BLOCKS), 1_150);
+    assert_eq!(report(&mut mempool, true, AUTO_TUNE_BLOCKS), 1_150);
+
+    // Blocks which are not full let it settle back, down to the floor.
+    assert_eq!(report(&mut mempool, false, AUTO_TUNE_BLOCKS), 1_040);
+    assert_eq!(report(&mut mempool, false, AUTO_TUNE_BLOCKS), 940);
+    assert_eq!(report(&mut mempool, false, AUTO_TUNE_BLOCKS), 900);
+    assert_eq!(report(&mut mempool, false, 10 * AUTO_TUNE_BLOCKS), 900);
+    assert_eq!(mempool.limiter.config.max_transactions, 900);
+
+    // Without auto-tuning, the limit is fixed.
+    let mut mempool = MempoolInner::new(MempoolLimits { max_transactions: 1_000, ..MempoolLimits::for_testing() });
+    assert_eq!(report(&mut mempool, true, 10 * AUTO_TUNE_BLOCKS), 1_000);
+}
+
+fn oversized_declare(sender: u64, tip: u64, encoded_size: usize) -> MempoolTransaction {
+    MempoolTransaction { encoded_size, ..make_tx(TestTxTy::Declare, sender, 0, tip) }
+}
+
+#[test]
+fn mempool_byte_limit() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 10,
+        max_declare_transactions: 10,
+        max_total_bytes: 1_000_000,
+        eviction_enabled: true,
+        ..MempoolLimits::for_testing()
+    });
+
+    mempool.insert_tx(oversized_declare(1, 0, 400_000), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(oversized_declare(2, 0, 400_000), false, Nonce(Felt::ZERO)).unwrap();
+    // Neither the transaction nor the declare limit is reached, and evicting a single transaction would not be enough.
+    assert_eq!(
+        mempool.insert_tx(oversized_declare(3, 100, 400_000), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxBytes { max: 1_000_000 }))
+    );
+    // Smaller transactions still fit in the byte budget.
+    let small = make_tx(TestTxTy::Invoke, 4, 0, 0);
+    assert!(small.encoded_size > 0 && small.encoded_size < 200_000);
+    mempool.insert_tx(small, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+
+    // Consumed transactions release their bytes.
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, usize::MAX);
+    mempool.re_add_txs([], popped);
+    mempool.insert_tx(oversized_declare(3, 100, 400_000), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_declare_bytecode_at_limit() {
+    let code_size = DUMMY_CLASS.code_size();
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { max_declare_bytecode_size: code_size, ..MempoolLimits::for_testing() });
+
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 0), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Added)
+    );
+    // Other transaction types have no class.
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Added)
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_declare_bytecode_too_large() {
+    let code_size = DUMMY_CLASS.code_size();
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { max_declare_bytecode_size: code_size - 1, ..MempoolLimits::for_testing() });
+
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::DeclareBytecodeTooLarge {
+            size: code_size,
+            max: code_size - 1
+        }))
+    );
+    // The rejected transaction does not take a slot.
+    assert!(mempool.is_empty());
+    assert_eq!(mempool.limiter.utilization(), 0.0);
+    mempool.check_invariants();
+}
+
+fn zero_fee_invoke(sender: u64) -> MempoolTransaction {
+    make_tx_with_resource_bounds(TestTxTy::Invoke, sender, 0, 0, 0, 0)
+}
+
+#[test]
+fn mempool_zero_fee_allowed() {
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { allow_zero_fee_transactions: true, ..MempoolLimits::for_testing() });
+
+    let tx = zero_fee_invoke(1);
+    assert!(tx.is_zero_fee());
+    assert_eq!(mempool.insert_tx(tx, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_zero_fee_rejected() {
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { allow_zero_fee_transactions: false, ..MempoolLimits::for_testing() });
+
+    assert_eq!(
+        mempool.insert_tx(zero_fee_invoke(1), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::ZeroFee))
+    );
+    assert!(mempool.is_empty());
+    // Transactions paying a fee, and L1 handler transactions which are paid on L1, are still accepted.
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Added)
+    );
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::L1Handler, 3, 0, 0), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Added)
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_mark_removed_floors_counters_at_zero() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let declare = make_tx(TestTxTy::Declare, 1, 0, 0);
+    let limits = mempool.limiter.limits_for(&declare);
+
+    mempool.limiter.update_tx_limits(&limits);
+    mempool.limiter.mark_removed(&limits, None);
+    assert_eq!(mempool.limiter.counter_underflows(), 0);
+
+    // Block production marked the transaction as consumed twice.
+    mempool.limiter.mark_removed(&limits, None);
+    mempool.limiter.mark_removed(&limits, None);
+    assert_eq!(mempool.counters(), MempoolCounters::default());
+    assert_eq!(mempool.limiter.counter_underflows(), 2);
+
+    // The counters are still accurate afterwards.
+    mempool.limiter.update_tx_limits(&limits);
+    assert_eq!(
+        mempool.counters(),
+        MempoolCounters { transactions: 1, declare_transactions: 1, bytes: declare.encoded_size, ..Default::default() }
+    );
+}
+
+#[test]
+fn mempool_type_counters() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 3, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 4, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+
+    let counters = |current, inserted, removed| TransactionTypeCounters { current, inserted, removed };
+    assert_eq!(mempool.type_counters(TransactionType::InvokeFunction), counters(2, 2, 0));
+    assert_eq!(mempool.type_counters(TransactionType::Declare), counters(1, 1, 0));
+    assert_eq!(mempool.type_counters(TransactionType::L1Handler), counters(1, 1, 0));
+    assert_eq!(mempool.type_counters(TransactionType::DeployAccount), counters(0, 0, 0));
+
+    // Block production consumes all the transactions.
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, 4);
+    assert_eq!(popped.len(), 4);
+    mempool.re_add_txs([], popped);
+    assert_eq!(mempool.type_counters(TransactionType::InvokeFunction), counters(0, 2, 2));
+    assert_eq!(mempool.type_counters(TransactionType::Declare), counters(0, 1, 1));
+    assert_eq!(mempool.type_counters(TransactionType::L1Handler), counters(0, 1, 1));
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 1, 0), false, Nonce(Felt::ONE)).unwrap();
+    assert_eq!(mempool.type_counters(TransactionType::InvokeFunction), counters(1, 3, 2));
+    assert_eq!(mempool.counters().transactions, 1);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_dropped_transactions_by_reason() {
+    let clock = FakeClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 2,
+        eviction_enabled: true,
+        replacement_bump_percent: 10,
+        max_age: Some(Duration::from_secs(60)),
+        ..MempoolLimits::for_testing()
+    })
+    .with_clock(Arc::new(clock.clone()));
+    let tx = |mempool: &MempoolInner, sender, tip| MempoolTransaction {
+        arrived_at: mempool.now(),
+        ..make_tx(TestTxTy::Invoke, sender, 0, tip)
+    };
+    let dropped = |mempool: &MempoolInner| {
+        [DropReason::Expired, DropReason::Evicted, DropReason::Replaced, DropReason::RejectedOverLimit]
+            .map(|reason| mempool.dropped_transactions(reason))
+    };
+
+    mempool.insert_tx(tx(&mempool, 1, 100), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(tx(&mempool, 2, 5), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(dropped(&mempool), [0, 0, 0, 0]);
+
+    assert_matches!(mempool.insert_tx(tx(&mempool, 1, 110), false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Replaced(_)));
+    assert_eq!(dropped(&mempool), [0, 0, 1, 0]);
+
+    clock.advance(Duration::from_secs(30));
+    assert_matches!(
+        mempool.insert_tx(tx(&mempool, 3, 20), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::EvictedToFit(_))
+    );
+    assert_eq!(dropped(&mempool), [0, 1, 1, 0]);
+
+    assert!(mempool.insert_tx(tx(&mempool, 4, 1), false, Nonce(Felt::ZERO)).is_err());
+    assert_eq!(dropped(&mempool), [0, 1, 1, 1]);
+
+    // Only the replacement of sender 1 is older than the max age.
+    clock.advance(Duration::from_secs(31));
+    assert_eq!(mempool.remove_age_exceeded_txs().len(), 1);
+    assert_eq!(dropped(&mempool), [1, 1, 1, 1]);
+
+    // Transactions included in a block are not dropped.
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, usize::MAX);
+    mempool.re_add_txs([], popped);
+    assert!(mempool.is_empty());
+    assert_eq!(dropped(&mempool), [1, 1, 1, 1]);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_drop_reason() {
+    let clock = FakeClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 2,
+        eviction_enabled: true,
+        max_age: Some(Duration::from_secs(60)),
+        ..MempoolLimits::for_testing()
+    })
+    .with_clock(Arc::new(clock.clone()));
+    let tx = |mempool: &MempoolInner, sender, tip| MempoolTransaction {
+        arrived_at: mempool.now(),
+        ..make_tx(TestTxTy::Invoke, sender, 0, tip)
+    };
+    let hash = |tx: &MempoolTransaction| tx.tx_hash().to_felt();
+
+    let (replaced, evicted) = (tx(&mempool, 1, 100), tx(&mempool, 2, 5));
+    mempool.insert_tx(replaced.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(evicted.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.drop_reason(&hash(&replaced)), None);
+
+    let expired = tx(&mempool, 1, 110);
+    mempool.insert_tx(expired.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    clock.advance(Duration::from_secs(30));
+    let included = tx(&mempool, 3, 20);
+    mempool.insert_tx(included.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    let rejected = tx(&mempool, 4, 1);
+    assert!(mempool.insert_tx(rejected.clone(), false, Nonce(Felt::ZERO)).is_err());
+    clock.advance(Duration::from_secs(31));
+    assert_eq!(mempool.remove_age_exceeded_txs().len(), 1);
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, usize::MAX);
+    mempool.re_add_txs([], popped);
+
+    assert_eq!(mempool.drop_reason(&hash(&replaced)), Some(DropReason::Replaced));
+    assert_eq!(mempool.drop_reason(&hash(&evicted)), Some(DropReason::Evicted));
+    assert_eq!(mempool.drop_reason(&hash(&rejected)), Some(DropReason::RejectedOverLimit));
+    assert_eq!(mempool.drop_reason(&hash(&expired)), Some(DropReason::Expired));
+    // Transactions included in a block are not dropped.
+    assert_eq!(mempool.drop_reason(&hash(&included)), None);
+
+    // A dropped transaction submitted again is no longer dropped.
+    let rejected = MempoolTransaction { arrived_at: mempool.now(), ..rejected };
+    mempool.insert_tx(rejected.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.drop_reason(&hash(&rejected)), None);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_drop_reason_forgets_least_recently_dropped() {
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { dropped_transactions_capacity: 2, ..MempoolLimits::for_testing() });
+    let txs: Vec<_> = (1..=3).map(|sender| make_tx(TestTxTy::Invoke, sender, 0, 0)).collect();
+    for tx in &txs {
+        mempool.insert_tx(tx.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    }
+    for tx in &txs {
+        assert!(mempool.remove_tx_by_hash(tx.tx_hash().to_felt()).is_some());
+    }
+
+    let drop_reasons: Vec<_> = txs.iter().map(|tx| mempool.drop_reason(&tx.tx_hash().to_felt())).collect();
+    assert_eq!(drop_reasons, [None, Some(DropReason::RemovedByOperator), Some(DropReason::RemovedByOperator)]);
+}
+
+#[test]
+fn mempool_privileged_sender_bypasses_capacity_limits() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 2,
+        max_declare_transactions: 1,
+        privileged_senders: [ContractAddress::try_from(Felt::ONE).unwrap()].into(),
+        ..MempoolLimits::for_testing()
+    });
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 3, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+
+    // The mempool is full, but not for the privileged sender.
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 2 }))
+    );
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 1, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 5, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxDeclareTransactions { max: 1 }))
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_privileged_sender_age_limit() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 2,
+        max_declare_transactions: 1,
+        privileged_senders: [ContractAddress::try_from(Felt::ONE).unwrap()].into(),
+        ..MempoolLimits::for_testing()
+    });
+    mempool.limiter.config.max_age = Some(Duration::from_secs(60));
+
+    let old = MempoolTransaction {
+        arrived_at: SystemTime::now() - Duration::from_secs(120),
+        ..make_tx(TestTxTy::Invoke, 1, 0, 0)
+    };
+    assert_matches!(
+        mempool.insert_tx(old, false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::Age { .. }))
+    );
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_near_capacity_watermark() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 10,
+        near_capacity_watermark: 0.5,
+        ..MempoolLimits::for_testing()
+    });
+
+    for sender in 0..4 {
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, sender, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    }
+    assert_eq!(mempool.limiter.utilization(), 0.4);
+    assert!(!mempool.is_near_capacity());
+
+    // The flag flips once the watermark is reached, but nothing is rejected.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.limiter.utilization(), 0.5);
+    assert!(mempool.is_near_capacity());
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 5, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert!(mempool.is_near_capacity());
+
+    // Popped transactions keep their room until block production consumes them.
+    let popped = [mempool.pop_next().unwrap(), mempool.pop_next().unwrap()];
+    assert!(mempool.is_near_capacity());
+    mempool.re_add_txs([], popped);
+    assert!(!mempool.is_near_capacity());
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_remove_age_exceeded_txs() {
+    let max_age = Duration::from_millis(100);
+    let mut mempool = MempoolInner::new(MempoolLimits { max_age: Some(max_age), ..MempoolLimits::for_testing() });
+
+    let expired = make_tx(TestTxTy::Invoke, 1, 0, 0);
+    let expired_hash = expired.tx_hash();
+    mempool.insert_tx(expired, false, Nonce(Felt::ZERO)).unwrap();
+    assert!(mempool.remove_age_exceeded_txs().is_empty());
+
+    std::thread::sleep(max_age);
+    let removed: Vec<_> = mempool.remove_age_exceeded_txs().iter().map(|tx| tx.tx_hash()).collect();
+    assert_eq!(removed, [expired_hash]);
+    assert!(mempool.is_empty());
+    mempool.check_invariants();
+}
+
+fn received_events(events: &mut broadcast::Receiver<MempoolEvent>) -> Vec<MempoolEvent> {
+    std::iter::from_fn(|| events.try_recv().ok()).collect()
+}
+
+#[test]
+fn mempool_events_insertion() {
+    let (sender, mut events) = broadcast::channel(16);
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 2,
+        eviction_enabled: true,
+        replacement_bump_percent: 10,
+        ..MempoolLimits::for_testing()
+    })
+    .with_events(sender);
+
+    let previous = make_tx(TestTxTy::Invoke, 1, 0, 100);
+    let previous_hash = previous.tx_hash().to_felt();
+    let replacement = make_tx(TestTxTy::Invoke, 1, 0, 110);
+    let replacement_hash = replacement.tx_hash().to_felt();
+    let cheap = make_tx(TestTxTy::Invoke, 2, 0, 10);
+    let cheap_hash = cheap.tx_hash().to_felt();
+    let pricey = make_tx(TestTxTy::Invoke, 3, 0, 1000);
+    let pricey_hash = pricey.tx_hash().to_felt();
+
+    mempool.insert_tx(previous, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(replacement, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(cheap, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(pricey, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+
+    assert_eq!(
+        received_events(&mut events),
+        [
+            MempoolEvent::Added { tx_hash: previous_hash },
+            MempoolEvent::Replaced { tx_hash: replacement_hash, previous: previous_hash },
+            MempoolEvent::Added { tx_hash: cheap_hash },
+            MempoolEvent::Removed { tx_hash: cheap_hash, reason: RemovalReason::Evicted },
+            MempoolEvent::Added { tx_hash: pricey_hash },
+        ]
+    );
+}
+
+#[test]
+fn mempool_events_removal() {
+    let (sender, mut events) = broadcast::channel(16);
+    let max_age = Duration::from_millis(100);
+    let mut mempool =
+        MempoolInner::new(MempoolLimits { max_age: Some(max_age), ..MempoolLimits::for_testing() }).with_events(sender);
+
+    let included = make_tx(TestTxTy::Invoke, 1, 0, 0);
+    let included_hash = included.tx_hash().to_felt();
+    let not_included = make_tx(TestTxTy::Invoke, 2, 0, 0);
+    mempool.insert_tx(included, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(not_included, false, Nonce(Felt::ZERO)).unwrap();
+    received_events(&mut events);
+
+    // Re-adding the transactions which were not included in the block is not a change to the mempool.
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, 2);
+    let (consumed, re_added): (Vec<_>, Vec<_>) =
+        popped.into_iter().partition(|tx| tx.tx_hash().to_felt() == included_hash);
+    mempool.re_add_txs(re_added, consumed);
+    assert_eq!(
+        received_events(&mut events),
+        [MempoolEvent::Removed { tx_hash: included_hash, reason: RemovalReason::Included }]
+    );
+
+    let l1_handler = make_tx(TestTxTy::L1Handler, 3, 0, 0);
+    let l1_handler_hash = l1_handler.tx_hash().to_felt();
+    mempool.insert_tx(l1_handler, false, Nonce(Felt::ZERO)).unwrap();
+    received_events(&mut events);
+    mempool.remove_l1_handler_txs(&[Nonce(Felt::ZERO)].into());
+    assert_eq!(
+        received_events(&mut events),
+        [MempoolEvent::Removed { tx_hash: l1_handler_hash, reason: RemovalReason::L1Reorg }]
+    );
+
+    let expired = mempool.transactions().next().unwrap().tx_hash().to_felt();
+    std::thread::sleep(max_age);
+    mempool.remove_age_exceeded_txs();
+    assert_eq!(
+        received_events(&mut events),
+        [MempoolEvent::Removed { tx_hash: expired, reason: RemovalReason::Expired }]
+    );
+    assert!(mempool.is_empty());
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_events_lagged_subscriber() {
+    let (sender, mut events) = broadcast::channel(2);
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing()).with_events(sender);
+
+    for contract in 1..=3 {
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, contract, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    }
+
+    // The subscriber missed the oldest event, and then resumes from the oldest kept one.
+    assert_eq!(events.try_recv(), Err(broadcast::error::TryRecvError::Lagged(1)));
+    assert_eq!(received_events(&mut events).len(), 2);
+}
+
+#[test]
+fn mempool_replacement() {
+    let mut mempool = MempoolInner::new(MempoolLimits { replacement_bump_percent: 10, ..MempoolLimits::for_testing() });
+
+    let previous = make_tx(TestTxTy::Invoke, 1, 0, 100);
+    let previous_hash = previous.tx_hash().to_felt();
+    assert_eq!(mempool.insert_tx(previous, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+
+    let replacement = make_tx(TestTxTy::Invoke, 1, 0, 110);
+    let replacement_hash = replacement.tx_hash();
+    assert_eq!(mempool.insert_tx(replacement, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Replaced(previous_hash)));
+    mempool.check_invariants();
+
+    assert_eq!(mempool.pop_next().map(|tx| tx.tx_hash()), Some(replacement_hash));
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_replacement_with_zeroed_counters() {
+    let mut mempool = MempoolInner::new(MempoolLimits { replacement_bump_percent: 10, ..MempoolLimits::for_testing() });
+
+    let previous = make_tx(TestTxTy::Declare, 1, 0, 100);
+    let previous_hash = previous.tx_hash().to_felt();
+    let limits = mempool.limiter.limits_for(&previous);
+    assert_eq!(mempool.insert_tx(previous, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+
+    // The counters forget the transaction which is about to be replaced.
+    mempool.limiter.mark_removed(&limits, None);
+    assert_eq!(mempool.counters(), MempoolCounters::default());
+
+    let replacement = make_tx(TestTxTy::Declare, 1, 0, 110);
+    let replacement_hash = replacement.tx_hash();
+    assert_eq!(mempool.insert_tx(replacement, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Replaced(previous_hash)));
+    assert_eq!(mempool.pop_next().map(|tx| tx.tx_hash()), Some(replacement_hash));
+}
+
+#[test]
+fn mempool_duplicate_tx() {
+    let mut mempool = MempoolInner::new(MempoolLimits { max_transactions: 4, ..MempoolLimits::for_testing() });
+
+    let ready = make_tx(TestTxTy::Invoke, 1, 0, 10);
+    let pending = make_tx(TestTxTy::Invoke, 1, 2, 10);
+    assert_eq!(mempool.insert_tx(ready.clone(), false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    assert_eq!(mempool.insert_tx(pending.clone(), false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    assert_eq!(mempool.limiter.utilization(), 0.5);
+
+    // Duplicates are not counted twice.
+    assert_eq!(mempool.insert_tx(ready, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::AlreadyKnown));
+    assert_eq!(mempool.insert_tx(pending, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::AlreadyKnown));
+    assert_eq!(mempool.limiter.utilization(), 0.5);
+    assert_eq!(mempool.transactions().count(), 2);
+    mempool.check_invariants();
+
+    // The same sender and nonce with a different hash is still a replacement.
+    let ready_hash = make_tx(TestTxTy::Invoke, 1, 0, 10).tx_hash().to_felt();
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 20), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Replaced(ready_hash))
+    );
+    assert_eq!(mempool.limiter.utilization(), 0.5);
+    mempool.check_invariants();
+
+    // A popped transaction is no longer known by the mempool.
+    let popped = mempool.pop_next().unwrap();
+    mempool.re_add_txs([], [popped.clone()]);
+    assert_eq!(mempool.insert_tx(popped, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_replacement_underpriced() {
+    let mut mempool = MempoolInner::new(MempoolLimits { replacement_bump_percent: 10, ..MempoolLimits::for_testing() });
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 100), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 109), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::ReplacementUnderpriced { tip: 109, min_tip: 110 })
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_replacement_min_interval() {
+    let clock = FakeClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let limits = MempoolLimits {
+        max_age: None,
+        replacement_min_interval: Duration::from_secs(10),
+        ..MempoolLimits::for_testing()
+    };
+    let mut mempool = MempoolInner::new(limits).with_clock(Arc::new(clock.clone()));
+
+    let first = make_tx(TestTxTy::Invoke, 1, 0, 100);
+    let first_hash = first.tx_hash().to_felt();
+    mempool.insert_tx(first, false, Nonce(Felt::ZERO)).unwrap();
+    let second = make_tx(TestTxTy::Invoke, 1, 0, 200);
+    let second_hash = second.tx_hash().to_felt();
+    assert_eq!(mempool.insert_tx(second, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Replaced(first_hash)));
+
+    // The second replacement comes too soon after the first one.
+    clock.advance(Duration::from_secs(4));
+    let third = make_tx(TestTxTy::Invoke, 1, 0, 300);
+    assert_eq!(
+        mempool.check_insert_tx(third.clone(), Nonce(Felt::ZERO)),
+        Err(TxInsersionError::ReplacementCooldown { remaining: Duration::from_secs(6) })
+    );
+    assert_eq!(
+        mempool.insert_tx(third.clone(), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::ReplacementCooldown { remaining: Duration::from_secs(6) })
+    );
+    mempool.check_invariants();
+
+    // Other senders and nonces are not affected.
+    let next_nonce = make_tx(TestTxTy::Invoke, 1, 1, 0);
+    assert_eq!(mempool.insert_tx(next_nonce, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+
+    clock.advance(Duration::from_secs(6));
+    assert_eq!(mempool.insert_tx(third, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Replaced(second_hash)));
+    mempool.check_invariants();
+}
+
+/// Rejects the transactions of one sender.
+#[derive(Debug)]
+struct DenySender(ContractAddress);
+
+impl AdmissionPolicy for DenySender {
+    fn admit(&self, tx: &MempoolTransaction, _limiter: &MempoolLimiter) -> Result<(), RejectReason> {
+        if tx.contract_address() == self.0 {
+            return Err(RejectReason::new("sender is not allowed"));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn mempool_admission_policy() {
+    let denied = make_tx(TestTxTy::Invoke, 1, 0, 100);
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing())
+        .with_admission_policy(Arc::new(DenySender(denied.contract_address())));
+
+    let expected = Err(TxInsersionError::Rejected(RejectReason::new("sender is not allowed")));
+    assert_eq!(mempool.check_insert_tx(denied.clone(), Nonce(Felt::ZERO)), expected);
+    assert_eq!(mempool.insert_tx(denied.clone(), false, Nonce(Felt::ZERO)), expected);
+    assert!(mempool.is_empty());
+    assert_eq!(mempool.counters().transactions, 0);
+
+    // Other senders are admitted.
+    let allowed = make_tx(TestTxTy::Invoke, 2, 0, 100);
+    assert_eq!(mempool.insert_tx(allowed, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+
+    // Forced insertions are not checked.
+    assert_eq!(mempool.insert_tx(denied, true, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_replacement_when_full() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 1,
+        max_declare_transactions: 1,
+        max_transactions_per_sender: 1,
+        ..MempoolLimits::for_testing()
+    });
+
+    // The replaced transaction frees its room for the replacement.
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 10), false, Nonce(Felt::ZERO)).unwrap();
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 20), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Replaced(_))
+    );
+    mempool.check_invariants();
+
+    // Declare count is still 1.
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 2, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxDeclareTransactions { max: 1 }))
+    );
+
+    // Once consumed, both declare and tx counts are released.
+    let popped = mempool.pop_next().unwrap();
+    mempool.re_add_txs([], [popped]);
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+}
+
+/// Makes a transaction arriving `arrived_after_ms` after `start`.
+fn make_tx_arrived_at(
+    ty: TestTxTy,
+    sender: u64,
+    nonce: u64,
+    tip: u64,
+    start: SystemTime,
+    arrived_after_ms: u64,
+) -> MempoolTransaction {
+    MempoolTransaction {
+        arrived_at: start + Duration::from_millis(arrived_after_ms),
+        ..make_tx(ty, sender, nonce, tip)
+    }
+}
+
+fn pop_all_senders(mempool: &mut MempoolInner) -> Vec<(Felt, Felt)> {
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, usize::MAX);
+    popped.iter().map(|tx| (tx.contract_address().to_felt(), tx.nonce().to_felt())).collect()
+}
+
+#[test]
+fn mempool_pops_by_tip() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let start = SystemTime::now();
+
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 0, 5, start, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 2, 0, 20, start, 1), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Declare, 3, 0, 10, start, 2), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 4, 0, 20, start, 3), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+
+    // Equal tips are first-come first-served.
+    assert_eq!(
+        pop_all_senders(&mut mempool),
+        [
+            (Felt::from(2), Felt::ZERO),
+            (Felt::from(4), Felt::ZERO),
+            (Felt::from(3), Felt::ZERO),
+            (Felt::ONE, Felt::ZERO)
+        ]
+    );
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_priority_hint_breaks_tip_ties() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let start = SystemTime::now();
+    let with_hint = |tx: MempoolTransaction, priority_hint| MempoolTransaction { priority_hint, ..tx };
+
+    let txs = [
+        with_hint(make_tx_arrived_at(TestTxTy::Invoke, 1, 0, 10, start, 0), 0),
+        with_hint(make_tx_arrived_at(TestTxTy::Invoke, 2, 0, 10, start, 1), 50),
+        with_hint(make_tx_arrived_at(TestTxTy::Invoke, 3, 0, 10, start, 2), 100),
+        // The hint never outweighs the tip.
+        with_hint(make_tx_arrived_at(TestTxTy::Invoke, 4, 0, 20, start, 3), 0),
+        with_hint(make_tx_arrived_at(TestTxTy::Invoke, 5, 0, 5, start, 4), 100),
+    ];
+    for tx in txs {
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+    }
+    mempool.check_invariants();
+
+    // Among equal tips, the highest hint goes first, even when it arrived last.
+    assert_eq!(
+        pop_all_senders(&mut mempool),
+        [
+            (Felt::from(4), Felt::ZERO),
+            (Felt::from(3), Felt::ZERO),
+            (Felt::from(2), Felt::ZERO),
+            (Felt::ONE, Felt::ZERO),
+            (Felt::from(5), Felt::ZERO)
+        ]
+    );
+}
+
+#[test]
+fn mempool_pops_by_tip_of_ready_tx() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let start = SystemTime::now();
+
+    // Sender 1's high-tip transaction is not ready until its cheap first transaction is popped.
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 0, 1, start, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 1, 100, start, 1), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 2, 0, 50, start, 2), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 3, 0, 75, start, 3), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+
+    let first = mempool.pop_next().unwrap();
+    assert_eq!(first.contract_address().to_felt(), Felt::from(3));
+    let second = mempool.pop_next().unwrap();
+    assert_eq!(second.contract_address().to_felt(), Felt::from(2));
+    mempool.check_invariants();
+
+    // Once popped, the front of sender 1 is its high-tip transaction.
+    assert_eq!(pop_all_senders(&mut mempool), [(Felt::ONE, Felt::ZERO), (Felt::ONE, Felt::ONE)]);
+}
+
+#[test]
+fn mempool_pops_l1_handlers_first() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let start = SystemTime::now();
+
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 0, 100, start, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::L1Handler, 2, 0, 0, start, 1), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::L1Handler, 3, 0, 0, start, 2), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+
+    // L1 handlers are untipped and keep being served first-come first-served, ahead of tipped transactions.
+    assert_eq!(
+        pop_all_senders(&mut mempool),
+        [(Felt::from(2), Felt::ZERO), (Felt::from(3), Felt::ZERO), (Felt::ONE, Felt::ZERO)]
+    );
+}
+
+/// Pops the same transactions, with tips that do not follow their arrival order, from a mempool with this ordering.
+fn pop_all_senders_with_ordering(ordering: MempoolOrdering) -> Vec<(Felt, Felt)> {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing()).with_ordering(ordering);
+    let start = SystemTime::now();
+
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 0, 5, start, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 2, 0, 20, start, 1), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Declare, 3, 0, 10, start, 2), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::L1Handler, 4, 0, 0, start, 3), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+
+    let popped = pop_all_senders(&mut mempool);
+    assert!(mempool.is_empty());
+    popped
+}
+
+#[test]
+fn mempool_ordering_fee_priority() {
+    assert_eq!(
+        pop_all_senders_with_ordering(MempoolOrdering::FeePriority),
+        [
+            (Felt::from(4), Felt::ZERO),
+            (Felt::from(2), Felt::ZERO),
+            (Felt::from(3), Felt::ZERO),
+            (Felt::ONE, Felt::ZERO)
+        ]
+    );
+}
+
+#[test]
+fn mempool_ordering_fifo() {
+    // The tips are ignored, but L1 handlers are still served first.
+    assert_eq!(
+        pop_all_senders_with_ordering(MempoolOrdering::Fifo),
+        [
+            (Felt::from(4), Felt::ZERO),
+            (Felt::ONE, Felt::ZERO),
+            (Felt::from(2), Felt::ZERO),
+            (Felt::from(3), Felt::ZERO)
+        ]
+    );
+}
+
+/// Pops the transactions of two busy senders from a mempool with this ordering. Sender 1 tips a bit more.
+fn pop_busy_senders_with_ordering(ordering: MempoolOrdering) -> Vec<Felt> {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing()).with_ordering(ordering);
+    let start = SystemTime::now();
+
+    for nonce in 0..5 {
+        let tx = make_tx_arrived_at(TestTxTy::Invoke, 1, nonce, 100, start, nonce);
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+        let tx = make_tx_arrived_at(TestTxTy::Invoke, 2, nonce, 90, start, 10 + nonce);
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+    }
+    mempool.check_invariants();
+
+    let popped = pop_all_senders(&mut mempool);
+    assert!(mempool.is_empty());
+    popped.into_iter().map(|(sender, _nonce)| sender).collect()
+}
+
+#[test]
+fn mempool_ordering_fair_share_interleaves_senders() {
+    let (one, two) = (Felt::ONE, Felt::TWO);
+    // The highest tip drains sender 1 first.
+    assert_eq!(
+        pop_busy_senders_with_ordering(MempoolOrdering::FeePriority),
+        [one, one, one, one, one, two, two, two, two, two]
+    );
+    // The senders take turns.
+    assert_eq!(
+        pop_busy_senders_with_ordering(MempoolOrdering::FairShare),
+        [one, two, one, two, one, two, one, two, one, two]
+    );
+}
+
+#[test]
+fn mempool_ordering_fair_share_weighted_by_tip() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing()).with_ordering(MempoolOrdering::FairShare);
+    let start = SystemTime::now();
+
+    // Sender 1 tips three times as much as sender 2, it gets three times as many turns.
+    for nonce in 0..6 {
+        let tx = make_tx_arrived_at(TestTxTy::Invoke, 1, nonce, 299, start, nonce);
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+    }
+    for nonce in 0..2 {
+        let tx = make_tx_arrived_at(TestTxTy::Invoke, 2, nonce, 99, start, 10 + nonce);
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+    }
+    mempool.check_invariants();
+
+    let senders: Vec<_> = pop_all_senders(&mut mempool).into_iter().map(|(sender, _nonce)| sender).collect();
+    let (one, two) = (Felt::ONE, Felt::TWO);
+    assert_eq!(senders, [one, one, one, two, one, one, one, two]);
+}
+
+#[rstest::rstest]
+#[case::fee_priority(MempoolOrdering::FeePriority)]
+#[case::fifo(MempoolOrdering::Fifo)]
+#[case::fair_share(MempoolOrdering::FairShare)]
+fn mempool_peek_ready_matches_pop_order(#[case] ordering: MempoolOrdering) {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing()).with_ordering(ordering);
+    let start = SystemTime::now();
+
+    // The second transaction of sender 1 is ready once the first one is popped, and outranks sender 2.
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 0, 30, start, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 1, 25, start, 1), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 2, 1, start, 2), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 2, 0, 20, start, 3), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Declare, 3, 0, 10, start, 4), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::L1Handler, 4, 0, 0, start, 5), false, Nonce(Felt::ZERO)).unwrap();
+    let counters = mempool.counters();
+
+    let peeked: Vec<_> = mempool.peek_ready(4).into_iter().map(MempoolTransaction::tx_hash).collect();
+    assert_eq!(peeked.len(), 4);
+    let peeked_all: Vec<_> = mempool.peek_ready(usize::MAX).into_iter().map(MempoolTransaction::tx_hash).collect();
+    assert_eq!(peeked_all[..4], peeked);
+    // Peeking does not change the mempool.
+    assert_eq!(mempool.counters(), counters);
+    mempool.check_invariants();
+
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, usize::MAX);
+    assert!(mempool.is_empty());
+    assert_eq!(popped.iter().map(MempoolTransaction::tx_hash).collect::<Vec<_>>(), peeked_all);
+}
+
+#[test]
+fn mempool_reserved_l1_handler_capacity() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 4,
+        reserved_l1_handler_transactions: 2,
+        ..MempoolLimits::for_testing()
+    });
+
+    // Invokes cannot use the capacity reserved for L1 handlers.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxUnreservedTransactions { max: 2 }))
+    );
+
+    // L1 handlers still fit in their reserved slots.
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 4, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 5, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_reserved_capacity_overflow_uses_unreserved() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 3,
+        reserved_l1_handler_transactions: 1,
+        ..MempoolLimits::for_testing()
+    });
+
+    // The second L1 handler does not fit in the reservation and takes an unreserved slot.
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxUnreservedTransactions { max: 2 }))
+    );
+
+    // Once an L1 handler is consumed, the remaining one moves back to the reservation.
+    let popped = mempool.pop_next().unwrap();
+    mempool.re_add_txs([], [popped]);
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_max_nonce_distance_rejects_beyond() {
+    let mut mempool = MempoolInner::new(MempoolLimits { max_nonce_distance: 3, ..MempoolLimits::for_testing() });
+    let account_nonce = Nonce(Felt::TWO);
+
+    let res = mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 2 + 3 + 1, 0), false, account_nonce);
+    assert_eq!(
+        res,
+        Err(TxInsersionError::NonceTooFar { nonce: Felt::from(6), account_nonce: Felt::TWO, max_distance: 3 })
+    );
+    assert!(mempool.is_empty());
+    // The dry run reports the same rejection.
+    let res = mempool.check_insert_tx(make_tx(TestTxTy::Invoke, 1, 2 + 3 + 1, 0), account_nonce);
+    assert_matches!(res, Err(TxInsersionError::NonceTooFar { .. }));
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_max_nonce_distance_accepts_boundary() {
+    let mut mempool = MempoolInner::new(MempoolLimits { max_nonce_distance: 3, ..MempoolLimits::for_testing() });
+    let account_nonce = Nonce(Felt::TWO);
+
+    // Buffered behind the gap at nonces 2 to 4.
+    let res = mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 2 + 3, 0), false, account_nonce);
+    assert_eq!(res, Ok(InsertOutcome::Added));
+    mempool.check_invariants();
+    // Forced transactions and L1 handlers are not checked.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 100, 0), true, account_nonce).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 3, 100, 0), false, account_nonce).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_buffers_nonce_gap() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let account_nonce = Nonce(Felt::ONE);
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 1, 0), false, account_nonce).unwrap();
+    // Nonce 2 is missing.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 3, 0), false, account_nonce).unwrap();
+    mempool.check_invariants();
+
+    // Only the transaction before the gap is ready.
+    assert_eq!(pop_all_senders(&mut mempool), [(Felt::ONE, Felt::ONE)]);
+    assert!(!mempool.is_empty());
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_promotes_when_nonce_gap_filled() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let account_nonce = Nonce(Felt::TWO);
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 4, 0), false, account_nonce).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 3, 0), false, account_nonce).unwrap();
+    mempool.check_invariants();
+    assert!(pop_all_senders(&mut mempool).is_empty());
+
+    // Nonce 2 fills the gap: the contiguous successors become ready.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 2, 0), false, account_nonce).unwrap();
+    mempool.check_invariants();
+    assert_eq!(
+        pop_all_senders(&mut mempool),
+        [(Felt::ONE, Felt::TWO), (Felt::ONE, Felt::THREE), (Felt::ONE, Felt::from(4))]
+    );
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_promotes_on_consumed() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    let popped = mempool.pop_next().unwrap();
+
+    // Nonce 0 is being executed, the account nonce has not been updated yet.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 1, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert!(mempool.pop_next().is_none());
+
+    mempool.re_add_txs([], [popped]);
+    mempool.check_invariants();
+    assert_eq!(pop_all_senders(&mut mempool), [(Felt::ONE, Felt::ONE)]);
+}
+
+#[test]
+fn mempool_remove_tx_by_hash() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let sender = ContractAddress::try_from(Felt::ONE).unwrap();
+    let nonces = |mempool: &MempoolInner| mempool.transactions_of(sender).map(|tx| tx.nonce().0).collect::<Vec<_>>();
+
+    // Nonces 0 and 1 are ready, 3 is pending behind the gap at nonce 2.
+    let txs = [0, 1, 3].map(|nonce| make_tx(TestTxTy::Invoke, 1, nonce, 0));
+    for tx in &txs {
+        mempool.insert_tx(tx.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    }
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.type_counters(TransactionType::InvokeFunction).current, 4);
+
+    // A ready transaction, the following one of the sender is kept behind the gap.
+    let ready_hash = txs[0].tx_hash().to_felt();
+    assert_eq!(mempool.remove_tx_by_hash(ready_hash).map(|tx| tx.tx_hash().to_felt()), Some(ready_hash));
+    assert!(!mempool.tx_hashes.contains(&ready_hash));
+    assert_eq!(nonces(&mempool), [Felt::ONE, Felt::THREE]);
+    assert_eq!(mempool.type_counters(TransactionType::InvokeFunction).current, 3);
+    assert_eq!(mempool.dropped_transactions(DropReason::RemovedByOperator), 1);
+    mempool.check_invariants();
+
+    // A pending transaction.
+    let pending_hash = txs[2].tx_hash().to_felt();
+    assert_eq!(mempool.remove_tx_by_hash(pending_hash).map(|tx| tx.tx_hash().to_felt()), Some(pending_hash));
+    assert_eq!(nonces(&mempool), [Felt::ONE]);
+    assert_eq!(mempool.type_counters(TransactionType::InvokeFunction).current, 2);
+    assert_eq!(mempool.dropped_transactions(DropReason::RemovedByOperator), 2);
+    mempool.check_invariants();
+
+    // A transaction which is not in the mempool.
+    assert!(mempool.remove_tx_by_hash(pending_hash).is_none());
+    assert_eq!(mempool.dropped_transactions(DropReason::RemovedByOperator), 2);
+    // Only the transaction of the other sender is ready.
+    assert_eq!(pop_all_senders(&mut mempool).len(), 1);
+}
+
+#[test]
+fn mempool_remove_tx_by_hash_demotes_following_nonces() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let txs = [0, 1, 2].map(|nonce| make_tx(TestTxTy::Invoke, 1, nonce, 0));
+    for tx in &txs {
+        mempool.insert_tx(tx.clone(), false, Nonce(Felt::ZERO)).unwrap();
+    }
+
+    // Removing nonce 1 leaves a gap before nonce 2.
+    mempool.remove_tx_by_hash(txs[1].tx_hash().to_felt()).unwrap();
+    mempool.check_invariants();
+    assert_eq!(mempool.pop_next().map(|tx| tx.tx_hash()), Some(txs[0].tx_hash()));
+    assert!(mempool.pop_next().is_none());
+    assert_eq!(mempool.transactions().count(), 1);
+
+    // Filling the gap makes nonce 2 ready again.
+    mempool.insert_tx(txs[1].clone(), false, Nonce(Felt::ONE)).unwrap();
+    mempool.check_invariants();
+    assert_eq!(mempool.pop_next().map(|tx| tx.tx_hash()), Some(txs[1].tx_hash()));
+    assert_eq!(mempool.pop_next().map(|tx| tx.tx_hash()), Some(txs[2].tx_hash()));
+}
+
+#[test]
+fn mempool_transactions_of_sender() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let account_nonce = Nonce(Felt::ONE);
+
+    // Nonces 1 and 2 are ready, 4 and 5 are pending behind the gap at nonce 3.
+    let txs = [
+        make_tx(TestTxTy::Invoke, 1, 5, 0),
+        make_tx(TestTxTy::Invoke, 1, 2, 0),
+        make_tx(TestTxTy::Invoke, 1, 4, 0),
+        make_tx(TestTxTy::Invoke, 1, 1, 0),
+    ];
+    for tx in &txs {
+        mempool.insert_tx(tx.clone(), false, account_nonce).unwrap();
+    }
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 1, 0), false, account_nonce).unwrap();
+    mempool.check_invariants();
+
+    let sender = ContractAddress::try_from(Felt::ONE).unwrap();
+    let listed: Vec<_> = mempool.transactions_of(sender).map(|tx| (tx.nonce(), tx.tx_hash())).collect();
+    let expected: Vec<_> =
+        [&txs[3], &txs[1], &txs[2], &txs[0]].into_iter().map(|tx| (tx.nonce(), tx.tx_hash())).collect();
+    assert_eq!(listed, expected);
+    assert!(listed.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+    let unknown_sender = ContractAddress::try_from(Felt::THREE).unwrap();
+    assert_eq!(mempool.transactions_of(unknown_sender).count(), 0);
+}
+
+#[test]
+fn mempool_remove_l1_handler_txs() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    let start = SystemTime::now();
+
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::Invoke, 1, 0, 0, start, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::L1Handler, 2, 0, 0, start, 1), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::L1Handler, 2, 1, 0, start, 2), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_arrived_at(TestTxTy::L1Handler, 3, 5, 0, start, 3), false, Nonce(Felt::ZERO)).unwrap();
+
+    // Only L1 handlers are removed: the invoke transaction with nonce 0 stays.
+    let removed = mempool.remove_l1_handler_txs(&[Nonce(Felt::ZERO), Nonce(Felt::from(5))].into());
+    let mut removed: Vec<_> =
+        removed.iter().map(|tx| (tx.contract_address().to_felt(), tx.nonce().to_felt())).collect();
+    removed.sort();
+    assert_eq!(removed, [(Felt::TWO, Felt::ZERO), (Felt::THREE, Felt::from(5))]);
+    mempool.check_invariants();
+
+    assert_eq!(pop_all_senders(&mut mempool), [(Felt::TWO, Felt::ONE), (Felt::ONE, Felt::ZERO)]);
+}
+
+/// Dry runs the insertion of the transaction made by `make_tx`, checks that it leaves the mempool untouched, then checks
+/// that actually inserting it does what the dry run reported.
+fn assert_dry_run(
+    mempool: &mut MempoolInner,
+    make_tx: impl Fn() -> MempoolTransaction,
+    account_nonce: Nonce,
+    expected: Result<InsertOutcome, TxInsersionError>,
+) {
+    let txs_before: Vec<_> = mempool.transactions().map(|tx| tx.tx_hash()).collect();
+    assert_eq!(mempool.check_insert_tx(make_tx(), account_nonce), expected);
+    let txs_after: Vec<_> = mempool.transactions().map(|tx| tx.tx_hash()).collect();
+    assert_eq!(txs_before, txs_after);
+    mempool.check_invariants();
+
+    assert_eq!(mempool.insert_tx(make_tx(), false, account_nonce), expected);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_check_insert_tx_outcomes() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 2,
+        eviction_enabled: true,
+        replacement_bump_percent: 10,
+        ..MempoolLimits::for_testing()
+    });
+
+    assert_dry_run(&mut mempool, || make_tx(TestTxTy::Invoke, 1, 0, 10), Nonce(Felt::ZERO), Ok(InsertOutcome::Added));
+    assert_dry_run(&mut mempool, || make_tx(TestTxTy::Invoke, 2, 0, 5), Nonce(Felt::ZERO), Ok(InsertOutcome::Added));
+
+    let replaced_hash = make_tx(TestTxTy::Invoke, 1, 0, 10).tx_hash().to_felt();
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Invoke, 1, 0, 11),
+        Nonce(Felt::ZERO),
+        Ok(InsertOutcome::Replaced(replaced_hash)),
+    );
+
+    let evicted_hash = make_tx(TestTxTy::Invoke, 2, 0, 5).tx_hash().to_felt();
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Invoke, 3, 0, 20),
+        Nonce(Felt::ZERO),
+        Ok(InsertOutcome::EvictedToFit(evicted_hash)),
+    );
+}
+
+#[test]
+fn mempool_check_insert_tx_nonce_rejections() {
+    let mut mempool = MempoolInner::new(MempoolLimits { replacement_bump_percent: 10, ..MempoolLimits::for_testing() });
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 100), false, Nonce(Felt::ZERO)).unwrap();
+
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Invoke, 1, 0, 100),
+        Nonce(Felt::ZERO),
+        Ok(InsertOutcome::AlreadyKnown),
+    );
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Invoke, 1, 0, 109),
+        Nonce(Felt::ZERO),
+        Err(TxInsersionError::ReplacementUnderpriced { tip: 109, min_tip: 110 }),
+    );
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::L1Handler, 1, 0, 0),
+        Nonce(Felt::ZERO),
+        Err(TxInsersionError::NonceConflict),
+    );
+}
+
+#[test]
+fn mempool_check_insert_tx_limit_rejections() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_transactions: 3,
+        max_declare_transactions: 1,
+        max_transactions_per_sender: 1,
+        reserved_l1_handler_transactions: 1,
+        max_age: Some(Duration::from_secs(60)),
+        ..MempoolLimits::for_testing()
+    });
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Invoke, 1, 1, 0),
+        Nonce(Felt::ZERO),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxPerSender { sender: Felt::ONE, max: 1 })),
+    );
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Declare, 2, 0, 0),
+        Nonce(Felt::ZERO),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxDeclareTransactions { max: 1 })),
+    );
+    assert_dry_run(
+        &mut mempool,
+        || MempoolTransaction {
+            arrived_at: SystemTime::now() - Duration::from_secs(120),
+            ..make_tx(TestTxTy::Invoke, 2, 0, 0)
+        },
+        Nonce(Felt::ZERO),
+        Err(TxInsersionError::Limit(MempoolLimitReached::Age { max: Duration::from_secs(60) })),
+    );
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Invoke, 3, 0, 0),
+        Nonce(Felt::ZERO),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxUnreservedTransactions { max: 2 })),
+    );
+
+    let mut mempool = MempoolInner::new(MempoolLimits { max_transactions: 3, ..MempoolLimits::for_testing() });
+    for sender in 1..=3 {
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, sender, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    }
+    assert_dry_run(
+        &mut mempool,
+        || make_tx(TestTxTy::Invoke, 4, 0, 0),
+        Nonce(Felt::ZERO),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 3 })),
+    );
+}
+
+/// Inserts transactions spanning an L1 gas price of 5: sender 1 pays more, sender 2 pays less for both of its
+/// transactions, sender 3 pays exactly 5, and the L1 handler transaction of sender 4 has no L1 gas price.
+fn insert_priced_txs(mempool: &mut MempoolInner) {
+    for tx in [
+        make_tx_with_l1_gas_price(TestTxTy::Invoke, 1, 0, 0, 10),
+        make_tx_with_l1_gas_price(TestTxTy::Invoke, 2, 0, 0, 3),
+        make_tx_with_l1_gas_price(TestTxTy::Invoke, 2, 1, 0, 8),
+        make_tx_with_l1_gas_price(TestTxTy::Invoke, 3, 0, 0, 5),
+        make_tx(TestTxTy::L1Handler, 4, 0, 0),
+    ] {
+        let nonce = tx.nonce();
+        mempool.insert_tx(tx, false, nonce).unwrap();
+    }
+    mempool.check_invariants();
+}
+
+fn senders(txs: &[MempoolTransaction]) -> Vec<(Felt, Felt)> {
+    let mut senders: Vec<_> = txs.iter().map(|tx| (tx.contract_address().to_felt(), tx.nonce().to_felt())).collect();
+    senders.sort();
+    senders
+}
+
+#[test]
+fn mempool_pop_priced_requeues_underpriced() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    insert_priced_txs(&mut mempool);
+
+    let mut popped = vec![];
+    let removed = mempool.pop_next_chunk_priced(&mut popped, usize::MAX, 5, MempoolUnderpricedPolicy::Requeue);
+    assert!(removed.is_empty());
+    assert_eq!(senders(&popped), [(Felt::ONE, Felt::ZERO), (Felt::from(3), Felt::ZERO), (Felt::from(4), Felt::ZERO)]);
+    // The second transaction of sender 2 pays enough, but it cannot be executed before the first one.
+    assert_eq!(mempool.counters().transactions, 5);
+    mempool.check_invariants();
+
+    // Both transactions of sender 2 are served once the L1 gas price goes down.
+    let mut popped = vec![];
+    mempool.pop_next_chunk_priced(&mut popped, usize::MAX, 3, MempoolUnderpricedPolicy::Requeue);
+    assert_eq!(senders(&popped), [(Felt::from(2), Felt::ZERO), (Felt::from(2), Felt::ONE)]);
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_pop_priced_drops_underpriced() {
+    let (sender, mut events) = broadcast::channel(16);
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing()).with_events(sender);
+    insert_priced_txs(&mut mempool);
+    while events.try_recv().is_ok() {}
+
+    let mut popped = vec![];
+    let removed = mempool.pop_next_chunk_priced(&mut popped, usize::MAX, 5, MempoolUnderpricedPolicy::Drop);
+    assert_eq!(senders(&popped), [(Felt::ONE, Felt::ZERO), (Felt::from(3), Felt::ZERO), (Felt::from(4), Felt::ZERO)]);
+    assert_eq!(senders(&removed), [(Felt::from(2), Felt::ZERO), (Felt::from(2), Felt::ONE)]);
+    assert!(mempool.is_empty());
+    // The popped transactions are still counted until block production marks them as consumed.
+    assert_eq!(mempool.counters().transactions, 3);
+
+    for tx in &removed {
+        assert_eq!(
+            events.try_recv().unwrap(),
+            MempoolEvent::Removed { tx_hash: tx.tx_hash().to_felt(), reason: RemovalReason::Underpriced }
+        );
+    }
+}
+
+#[test]
+fn mempool_pop_priced_takes_up_to_n() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    insert_priced_txs(&mut mempool);
+
+    // Skipped transactions do not count against `n`.
+    let mut popped = vec![];
+    mempool.pop_next_chunk_priced(&mut popped, 3, 5, MempoolUnderpricedPolicy::Requeue);
+    assert_eq!(senders(&popped), [(Felt::ONE, Felt::ZERO), (Felt::from(3), Felt::ZERO), (Felt::from(4), Felt::ZERO)]);
+    assert!(!mempool.is_empty());
+    mempool.check_invariants();
+}
+
+#[rstest::rstest]
+#[case::all(usize::MAX)]
+#[case::up_to_n(2)]
+fn mempool_peek_priced_matches_pop_priced(#[case] n: usize) {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    insert_priced_txs(&mut mempool);
+    let counters = mempool.counters();
+
+    let peeked: Vec<_> = mempool.peek_next_chunk_priced(n, 5).into_iter().map(MempoolTransaction::tx_hash).collect();
+    // Previewing does not change the mempool.
+    assert_eq!(mempool.counters(), counters);
+    mempool.check_invariants();
+
+    let mut popped = vec![];
+    mempool.pop_next_chunk_priced(&mut popped, n, 5, MempoolUnderpricedPolicy::Requeue);
+    assert_eq!(popped.iter().map(MempoolTransaction::tx_hash).collect::<Vec<_>>(), peeked);
+}
+
+#[test]
+fn mempool_min_tip_tracks_l1_gas_price() {
+    let mut mempool = MempoolInner::new(MempoolLimits { min_tip_multiplier: 0.5, ..MempoolLimits::for_testing() });
+
+    assert_eq!(mempool.update_min_tip(100), 50);
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 40), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::TipTooLow { tip: 40, min_tip: 50 }))
+    );
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 50), false, Nonce(Felt::ZERO)).unwrap();
+
+    // The L1 gas price goes up.
+    assert_eq!(mempool.update_min_tip(200), 100);
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 60), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::TipTooLow { tip: 60, min_tip: 100 }))
+    );
+    // L1 handler transactions have no tip.
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 3, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+
+    // The L1 gas price goes down, the previously rejected tips are accepted again.
+    assert_eq!(mempool.update_min_tip(80), 40);
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 2, 0, 60), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 40), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_min_tip_disabled() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+
+    assert!(!mempool.has_min_tip());
+    assert_eq!(mempool.update_min_tip(u128::MAX), 0);
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+}
+
+fn mempool_with_fake_clock(max_age: Duration) -> (MempoolInner, FakeClock) {
+    let clock = FakeClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let mempool = MempoolInner::new(MempoolLimits { max_age: Some(max_age), ..MempoolLimits::for_testing() })
+        .with_clock(Arc::new(clock.clone()));
+    (mempool, clock)
+}
+
+#[test]
+fn mempool_fake_clock_age_expiry() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(60));
+    let tx = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, 1, 0, 0) };
+    mempool.insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+
+    clock.advance(Duration::from_secs(60));
+    assert!(mempool.remove_age_exceeded_txs().is_empty());
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(mempool.remove_age_exceeded_txs().len(), 1);
+    assert!(mempool.is_empty());
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_fake_clock_pop_skips_expired() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(60));
+    let old = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, 1, 0, 0) };
+    mempool.insert_tx(old, false, Nonce(Felt::ZERO)).unwrap();
+    clock.advance(Duration::from_secs(30));
+    let recent = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, 2, 0, 0) };
+    mempool.insert_tx(recent, false, Nonce(Felt::ZERO)).unwrap();
+
+    clock.advance(Duration::from_secs(40));
+    let popped = mempool.pop_next().expect("The recent transaction is not expired");
+    assert_eq!(popped.contract_address().to_felt(), Felt::from(2));
+    assert!(mempool.pop_next().is_none());
+}
+
+#[test]
+fn mempool_fake_clock_rejects_expired_insert() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(60));
+    let tx = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, 1, 0, 0) };
+    clock.advance(Duration::from_secs(61));
+    assert_matches!(
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::Age { .. }))
+    );
+}
+
+#[test]
+fn mempool_fake_clock_arrived_in_the_future() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(60));
+    // The system clock went backwards after the transaction arrived: it is treated as just arrived.
+    let tx = MempoolTransaction {
+        arrived_at: mempool.now() + Duration::from_secs(3600),
+        ..make_tx(TestTxTy::Invoke, 1, 0, 0)
+    };
+    assert_eq!(mempool.insert_tx(tx, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    assert!(mempool.remove_age_exceeded_txs().is_empty());
+    clock.advance(Duration::from_secs(3600 + 60));
+    assert!(mempool.remove_age_exceeded_txs().is_empty());
+    mempool.check_invariants();
+
+    // Once the clock catches up, the transaction ages as usual.
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(mempool.remove_age_exceeded_txs().len(), 1);
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_age_limit_disabled() {
+    let clock = FakeClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let mut mempool = MempoolInner::new(MempoolLimits { max_age: None, ..MempoolLimits::for_testing() })
+        .with_clock(Arc::new(clock.clone()));
+
+    // A transaction as old as it gets is accepted, and never expires.
+    let tx = MempoolTransaction { arrived_at: SystemTime::UNIX_EPOCH, ..make_tx(TestTxTy::Invoke, 1, 0, 0) };
+    assert_eq!(mempool.insert_tx(tx, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    clock.advance(Duration::from_secs(1_000_000));
+    assert!(mempool.remove_age_exceeded_txs().is_empty());
+    mempool.check_invariants();
+    assert_eq!(pop_all_senders(&mut mempool), [(Felt::ONE, Felt::ZERO)]);
+}
+
+#[test]
+fn mempool_oldest_transaction_age() {
+    let clock = FakeClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let mut mempool = MempoolInner::new(MempoolLimits { max_age: None, ..MempoolLimits::for_testing() })
+        .with_clock(Arc::new(clock.clone()));
+    assert_eq!(mempool.oldest_transaction_age(), None);
+
+    let arrived_secs_ago = |mempool: &MempoolInner, secs, sender, nonce| MempoolTransaction {
+        arrived_at: mempool.now() - Duration::from_secs(secs),
+        ..make_tx(TestTxTy::Invoke, sender, nonce, 0)
+    };
+    // The transaction of sender 3 is pending behind a nonce gap.
+    let pending = arrived_secs_ago(&mempool, 20, 3, 2);
+    let pending_hash = pending.tx_hash().to_felt();
+    mempool.insert_tx(arrived_secs_ago(&mempool, 10, 1, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(arrived_secs_ago(&mempool, 30, 2, 0), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(pending, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.check_invariants();
+    assert_eq!(mempool.oldest_transaction_age(), Some(Duration::from_secs(30)));
+
+    // The oldest transaction is popped first.
+    assert_eq!(mempool.pop_next().map(|tx| tx.contract_address().to_felt()), Some(Felt::TWO));
+    assert_eq!(mempool.oldest_transaction_age(), Some(Duration::from_secs(20)));
+
+    mempool.remove_tx_by_hash(pending_hash).unwrap();
+    assert_eq!(mempool.oldest_transaction_age(), Some(Duration::from_secs(10)));
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(mempool.oldest_transaction_age(), Some(Duration::from_secs(15)));
+
+    mempool.pop_next().unwrap();
+    assert_eq!(mempool.oldest_transaction_age(), None);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_refresh_on_resubmit() {
+    let clock = FakeClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+    let limits = MempoolLimits {
+        max_age: Some(Duration::from_secs(60)),
+        refresh_on_resubmit: true,
+        ..MempoolLimits::for_testing()
+    };
+    let mut mempool = MempoolInner::new(limits).with_clock(Arc::new(clock.clone()));
+    let arrived_now = |mempool: &MempoolInner, sender, nonce| MempoolTransaction {
+        arrived_at: mempool.now(),
+        ..make_tx(TestTxTy::Invoke, sender, nonce, 0)
+    };
+    // The transaction of sender 2 is pending behind a nonce gap.
+    for (sender, nonce) in [(1, 0), (2, 1)] {
+        let tx = arrived_now(&mempool, sender, nonce);
+        assert_eq!(mempool.insert_tx(tx, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    }
+    let counters = mempool.counters();
+
+    // Both are resubmitted when about to expire: they are not counted twice, and their age starts over.
+    clock.advance(Duration::from_secs(50));
+    for (sender, nonce) in [(1, 0), (2, 1)] {
+        let tx = arrived_now(&mempool, sender, nonce);
+        assert_eq!(mempool.check_insert_tx(tx.clone(), Nonce(Felt::ZERO)), Ok(InsertOutcome::Refreshed));
+        assert_eq!(mempool.insert_tx(tx, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Refreshed));
+    }
+    assert_eq!(mempool.counters(), counters);
+    assert_eq!(mempool.oldest_transaction_age(), Some(Duration::ZERO));
+    mempool.check_invariants();
+
+    // They outlive their first arrival, and expire a max age after their resubmission.
+    clock.advance(Duration::from_secs(20));
+    assert!(mempool.remove_age_exceeded_txs().is_empty());
+    assert_eq!(mempool.oldest_transaction_age(), Some(Duration::from_secs(20)));
+    clock.advance(Duration::from_secs(41));
+    assert_eq!(mempool.remove_age_exceeded_txs().len(), 2);
+    assert!(mempool.is_empty());
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_resubmit_without_refresh() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(60));
+    let tx = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, 1, 0, 0) };
+    mempool.insert_tx(tx.clone(), false, Nonce(Felt::ZERO)).unwrap();
+
+    clock.advance(Duration::from_secs(50));
+    let resubmitted = MempoolTransaction { arrived_at: mempool.now(), ..tx };
+    assert_eq!(mempool.insert_tx(resubmitted, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::AlreadyKnown));
+    assert_eq!(mempool.oldest_transaction_age(), Some(Duration::from_secs(50)));
+    clock.advance(Duration::from_secs(11));
+    assert_eq!(mempool.remove_age_exceeded_txs().len(), 1);
+}
+
+fn tx_with_deadline(mempool: &MempoolInner, sender: u64, deadline_in: Duration) -> MempoolTransaction {
+    let now = mempool.now();
+    MempoolTransaction { arrived_at: now, deadline: Some(now + deadline_in), ..make_tx(TestTxTy::Invoke, sender, 0, 0) }
+}
+
+#[test]
+fn mempool_deadline_expiry() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(3600));
+    let without_deadline = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, 1, 0, 0) };
+    mempool.insert_tx(without_deadline, false, Nonce(Felt::ZERO)).unwrap();
+    let with_deadline = tx_with_deadline(&mempool, 2, Duration::from_secs(10));
+    let tx_hash = with_deadline.tx_hash();
+    mempool.insert_tx(with_deadline, false, Nonce(Felt::ZERO)).unwrap();
+
+    clock.advance(Duration::from_secs(9));
+    assert!(mempool.remove_age_exceeded_txs().is_empty());
+    // The deadline applies even though the transaction is far from the max age, and is not the oldest one.
+    clock.advance(Duration::from_secs(1));
+    let removed = mempool.remove_age_exceeded_txs();
+    assert_eq!(removed.iter().map(MempoolTransaction::tx_hash).collect::<Vec<_>>(), [tx_hash]);
+    assert_eq!(mempool.counters().transactions, 1);
+    mempool.check_invariants();
+    assert_eq!(pop_all_senders(&mut mempool), [(Felt::ONE, Felt::ZERO)]);
+}
+
+#[test]
+fn mempool_deadline_passed_rejected() {
+    let (mut mempool, _clock) = mempool_with_fake_clock(Duration::from_secs(3600));
+    let tx = tx_with_deadline(&mempool, 1, Duration::ZERO);
+    assert_matches!(
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::DeadlinePassed { .. }))
+    );
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_pop_skips_deadline_passed() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(3600));
+    mempool.insert_tx(tx_with_deadline(&mempool, 1, Duration::from_secs(10)), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(tx_with_deadline(&mempool, 2, Duration::from_secs(60)), false, Nonce(Felt::ZERO)).unwrap();
+
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(mempool.peek_ready(usize::MAX).len(), 1);
+    let popped = mempool.pop_next().expect("The second transaction is before its deadline");
+    assert_eq!(popped.contract_address().to_felt(), Felt::TWO);
+    assert!(mempool.pop_next().is_none());
+    // The skipped transaction is removed, the popped one stays counted until it is consumed.
+    assert_eq!(mempool.counters().transactions, 1);
+}
+
+#[test]
+fn mempool_pop_returns_expired_and_demotes_following_nonces() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(3600));
+    let expiring = tx_with_deadline(&mempool, 1, Duration::from_secs(10));
+    let expiring_hash = expiring.tx_hash();
+    let following = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, 1, 1, 0) };
+    mempool.insert_tx(expiring, false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(following, false, Nonce(Felt::ZERO)).unwrap();
+
+    clock.advance(Duration::from_secs(30));
+    assert!(mempool.peek_ready(usize::MAX).is_empty());
+    let mut popped = vec![];
+    let expired = mempool.pop_next_chunk(&mut popped, usize::MAX);
+    assert_eq!(expired.iter().map(MempoolTransaction::tx_hash).collect::<Vec<_>>(), [expiring_hash]);
+    // The following transaction of the sender waits behind the nonce gap.
+    assert!(popped.is_empty());
+    assert_eq!(mempool.transactions().count(), 1);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_block_reservation_keeps_capacity() {
+    let mut mempool = MempoolInner::new(MempoolLimits { max_transactions: 5, ..MempoolLimits::for_testing() });
+    for sender in 1..=5 {
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, sender, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    }
+
+    // Block production pops a batch: the popped transactions stay counted while they are executed.
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, 3);
+    assert_eq!(mempool.counters().transactions, 5);
+    assert_eq!(mempool.counters().in_flight_transactions, 3);
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 10, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 5 }))
+    );
+    assert!(mempool.counters().transactions <= 5);
+
+    // One transaction is included and the excess is re-added.
+    let consumed = popped.remove(0);
+    mempool.re_add_txs(popped, [consumed]);
+    assert_eq!(mempool.counters().transactions, 4);
+    assert_eq!(mempool.counters().in_flight_transactions, 0);
+    assert_eq!(mempool.limiter.counter_underflows(), 0);
+
+    // The room of the included transaction is freed.
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 10, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.counters().transactions, 5);
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, 11, 0, 0), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 5 }))
+    );
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_l2_gas_limit() {
+    let mut mempool = MempoolInner::new(MempoolLimits { max_total_l2_gas: 100, ..MempoolLimits::for_testing() });
+    mempool.insert_tx(make_tx_with_l2_gas(TestTxTy::Invoke, 1, 0, 0, 60), false, Nonce(Felt::ZERO)).unwrap();
+    mempool.insert_tx(make_tx_with_l2_gas(TestTxTy::Declare, 2, 0, 0, 40), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.counters().l2_gas, 100);
+
+    assert_matches!(
+        mempool.insert_tx(make_tx_with_l2_gas(TestTxTy::Invoke, 3, 0, 0, 1), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxL2Gas { max: 100 }))
+    );
+    // L1 handler transactions do not declare any L2 gas.
+    mempool.insert_tx(make_tx(TestTxTy::L1Handler, 4, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.counters().l2_gas, 100);
+
+    // Including a transaction frees its L2 gas.
+    let mut popped = vec![];
+    mempool.pop_next_chunk(&mut popped, usize::MAX);
+    let (consumed, re_added): (Vec<_>, Vec<_>) = popped.into_iter().partition(|tx| tx.max_l2_gas_amount() == Some(60));
+    mempool.re_add_txs(re_added, consumed);
+    assert_eq!(mempool.counters().l2_gas, 40);
+    mempool.insert_tx(make_tx_with_l2_gas(TestTxTy::Invoke, 3, 0, 0, 60), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.counters().l2_gas, 100);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_l2_gas_limit_replacement() {
+    let mut mempool = MempoolInner::new(MempoolLimits { max_total_l2_gas: 100, ..MempoolLimits::for_testing() });
+    mempool.insert_tx(make_tx_with_l2_gas(TestTxTy::Invoke, 1, 0, 0, 100), false, Nonce(Felt::ZERO)).unwrap();
+
+    // The replaced transaction's L2 gas is considered free.
+    let replacement = make_tx_with_l2_gas(TestTxTy::Invoke, 1, 0, 10, 80);
+    assert_matches!(mempool.insert_tx(replacement, false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Replaced(_)));
+    assert_eq!(mempool.counters().l2_gas, 80);
+    assert_matches!(
+        mempool.insert_tx(make_tx_with_l2_gas(TestTxTy::Invoke, 2, 0, 0, 21), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxL2Gas { max: 100 }))
+    );
+}
+
+fn expired_tx(sender: u64) -> MempoolTransaction {
+    let arrived_at = SystemTime::now() - Duration::from_secs(120);
+    MempoolTransaction { arrived_at, ..make_tx(TestTxTy::Invoke, sender, 0, 0) }
+}
+
+#[test]
+fn mempool_expired_tx_policy_reject() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_age: Some(Duration::from_secs(60)),
+        expired_tx_policy: MempoolExpiredTxPolicy::Reject,
+        ..MempoolLimits::for_testing()
+    });
+    assert_matches!(
+        mempool.insert_tx(expired_tx(1), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::Age { .. }))
+    );
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn mempool_expired_tx_policy_admit() {
+    let mut mempool = MempoolInner::new(MempoolLimits {
+        max_age: Some(Duration::from_secs(60)),
+        expired_tx_policy: MempoolExpiredTxPolicy::Admit,
+        ..MempoolLimits::for_testing()
+    });
+    assert_eq!(mempool.insert_tx(expired_tx(1), false, Nonce(Felt::ZERO)), Ok(InsertOutcome::Added));
+    assert_eq!(mempool.counters().transactions, 1);
+    mempool.check_invariants();
+
+    // The transaction is held until the sweeper removes it.
+    let removed = mempool.remove_age_exceeded_txs();
+    assert_eq!(removed.len(), 1);
+    assert!(mempool.is_empty());
+    assert_eq!(mempool.counters().transactions, 0);
+    mempool.check_invariants();
+}
+
+fn mempool_with_declare_limit_policy(declare_limit_policy: MempoolDeclareLimitPolicy) -> MempoolInner {
+    MempoolInner::new(MempoolLimits {
+        max_transactions: 3,
+        max_declare_transactions: 1,
+        declare_limit_policy,
+        ..MempoolLimits::for_testing()
+    })
+}
+
+#[test]
+fn mempool_declare_limit_policy_reject() {
+    let mut mempool = mempool_with_declare_limit_policy(MempoolDeclareLimitPolicy::Reject);
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 100), false, Nonce(Felt::ZERO)).unwrap();
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 2, 0, 100), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxDeclareTransactions { max: 1 }))
+    );
+    assert_eq!(mempool.counters().transactions, 1);
+    mempool.check_invariants();
+}
+
+#[test]
+fn mempool_declare_limit_policy_deprioritize() {
+    let mut mempool = mempool_with_declare_limit_policy(MempoolDeclareLimitPolicy::Deprioritize);
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 1, 0, 100), false, Nonce(Felt::ZERO)).unwrap();
+    // Over the declare limit, the declare transaction is accepted.
+    assert_eq!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 2, 0, 100), false, Nonce(Felt::ZERO)),
+        Ok(InsertOutcome::Added)
+    );
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 3, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert_eq!(mempool.counters().declare_transactions, 2);
+    mempool.check_invariants();
+
+    // The transaction limit still applies.
+    assert_matches!(
+        mempool.insert_tx(make_tx(TestTxTy::Declare, 4, 0, 100), false, Nonce(Felt::ZERO)),
+        Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 3 }))
+    );
+
+    // The declare transaction within the limit keeps its priority, the one over the limit is queued behind the invoke
+    // transaction despite its higher tip.
+    let (one, two, three) = (Felt::ONE, Felt::TWO, Felt::THREE);
+    assert_eq!(pop_all_senders(&mut mempool), [(one, Felt::ZERO), (three, Felt::ZERO), (two, Felt::ZERO)]);
+}
+
+#[test]
+fn mempool_declares_class() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    // The test declare transactions declare the zero class hash.
+    assert!(!mempool.declares_class(&Felt::ZERO));
+
+    mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert!(!mempool.declares_class(&Felt::ZERO));
+
+    mempool.insert_tx(make_tx(TestTxTy::Declare, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    assert!(mempool.declares_class(&Felt::ZERO));
+    assert!(!mempool.declares_class(&Felt::ONE));
+
+    // Once the declare transaction is taken by block production, the class is looked up in the db.
+    pop_all_senders(&mut mempool);
+    assert!(!mempool.declares_class(&Felt::ZERO));
+}
+
+#[test]
+fn mempool_throughput_over_window() {
+    let (mut mempool, clock) = mempool_with_fake_clock(Duration::from_secs(1000));
+    assert_eq!(mempool.throughput(), MempoolThroughput::default());
+
+    // One transaction per second for 30 seconds.
+    for sender in 1..=30 {
+        let tx = MempoolTransaction { arrived_at: mempool.now(), ..make_tx(TestTxTy::Invoke, sender, 0, 0) };
+        mempool.insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+        clock.advance(Duration::from_secs(1));
+    }
+    let popped: Vec<_> = (0..6).map(|_| mempool.pop_next().unwrap()).collect();
+    let per_second = |accepted: f64, popped: f64| MempoolThroughput {
+        accepted_per_second: accepted / THROUGHPUT_WINDOW.as_secs_f64(),
+        popped_per_second: popped / THROUGHPUT_WINDOW.as_secs_f64(),
+    };
+    assert_eq!(mempool.throughput(), per_second(30.0, 6.0));
+
+    // Transactions added back by block production are not accepted again.
+    mempool.re_add_txs(popped, []);
+    assert_eq!(mempool.throughput(), per_second(30.0, 6.0));
+
+    // The first transaction leaves the window.
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(mempool.throughput(), per_second(29.0, 6.0));
+
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(mempool.throughput(), MempoolThroughput::default());
+}
+
+#[test]
+fn mempool_limit_codes_are_stable_and_unique() {
+    let limits = [
+        (MempoolLimitReached::MaxTransactions { max: 10 }, 10100, "max_transactions"),
+        (MempoolLimitReached::MaxDeclareTransactions { max: 10 }, 10101, "max_declare_transactions"),
+        (MempoolLimitReached::MaxUnreservedTransactions { max: 10 }, 10102, "max_unreserved_transactions"),
+        (MempoolLimitReached::MaxBytes { max: 10 }, 10103, "max_bytes"),
+        (MempoolLimitReached::MaxL2Gas { max: 10 }, 10104, "max_l2_gas"),
+        (MempoolLimitReached::DeclareBytecodeTooLarge { size: 20, max: 10 }, 10105, "max_declare_bytecode_size"),
+        (MempoolLimitReached::ZeroFee, 10106, "zero_fee"),
+        (MempoolLimitReached::TipTooLow { tip: 1, min_tip: 10 }, 10107, "min_tip"),
+        (MempoolLimitReached::MaxPerSender { sender: Felt::ONE, max: 10 }, 10108, "max_per_sender"),
+        (MempoolLimitReached::Age { max: Duration::from_secs(10) }, 10109, "age"),
+        (MempoolLimitReached::DeadlinePassed { deadline: SystemTime::UNIX_EPOCH }, 10110, "deadline"),
+    ];
+    for (limit, code, reason) in &limits {
+        assert_eq!(limit.code(), *code, "{limit:?}");
+        assert_eq!(limit.reason(), *reason, "{limit:?}");
+    }
+
+    let codes: HashSet<_> = limits.iter().map(|(limit, _, _)| limit.code()).collect();
+    let reasons: HashSet<_> = limits.iter().map(|(limit, _, _)| limit.reason()).collect();
+    assert_eq!(codes.len(), limits.len());
+    assert_eq!(reasons.len(), limits.len());
+}
+
+#[test]
+fn mempool_counter_divergence_detected() {
+    let mut mempool = MempoolInner::new(MempoolLimits::for_testing());
+    for sender in 1..=3 {
+        mempool.insert_tx(make_tx(TestTxTy::Invoke, sender, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+    }
+    assert_eq!(mempool.check_counter_consistency(), None);
+
+    // A popped transaction is still counted while block production executes it.
+    let popped = mempool.pop_next().unwrap();
+    assert_eq!(mempool.counters().transactions, 3);
+    assert_eq!(mempool.check_counter_consistency(), None);
+    mempool.re_add_txs([popped], []);
+    assert_eq!(mempool.check_counter_consistency(), None);
+    assert_eq!(mempool.limiter.counter_divergences(), 0);
+
+    // The counters forget a transaction which is still in the mempool.
+    let tx = mempool.transactions().next().unwrap().clone();
+    mempool.limiter.mark_removed(&mempool.limiter.limits_for(&tx), None);
+    assert_eq!(
+        mempool.check_counter_consistency(),
+        Some(CounterDivergence { counted: 2, in_mempool: 3, in_flight: 0 })
+    );
+
+    // The counters account for a transaction twice.
+    let limits = mempool.limiter.limits_for(&tx);
+    mempool.limiter.update_tx_limits(&limits);
+    mempool.limiter.update_tx_limits(&limits);
+    let divergence = mempool.check_counter_consistency().unwrap();
+    assert_eq!(divergence, CounterDivergence { counted: 4, in_mempool: 3, in_flight: 0 });
+    assert_eq!(divergence.gap(), 1);
+    assert_eq!(mempool.limiter.counter_divergences(), 2);
+}
+
+#[cfg(feature = "metrics")]
+mod metrics {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::metrics::data::{Gauge, ResourceMetrics, Sum};
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::runtime;
+    use opentelemetry_sdk::testing::metrics::InMemoryMetricsExporter;
+
+    /// Mempool publishing its metrics to an in-memory exporter instead of the global meter.
+    struct MeteredMempool {
+        mempool: MempoolInner,
+        provider: SdkMeterProvider,
+        exporter: InMemoryMetricsExporter,
+    }
+
+    impl MeteredMempool {
+        fn new(limits: MempoolLimits) -> Self {
+            let exporter = InMemoryMetricsExporter::default();
+            let reader = PeriodicReader::builder(exporter.clone(), runtime::Tokio).build();
+            let provider = SdkMeterProvider::builder().with_reader(reader).build();
+            let metrics = MempoolMetrics::register_with_meter(&provider.meter("mempool"));
+            Self { mempool: MempoolInner::new(limits).with_metrics(metrics), provider, exporter }
+        }
+
+        /// Exports the metrics recorded so far.
+        fn collect(&self) -> Vec<ResourceMetrics> {
+            self.exporter.reset();
+            self.provider.force_flush().unwrap();
+            self.exporter.get_finished_metrics().unwrap()
+        }
+    }
+
+    fn metric_data<'a, T: 'static>(exported: &'a [ResourceMetrics], name: &str) -> &'a T {
+        exported
+            .iter()
+            .flat_map(|resource| &resource.scope_metrics)
+            .flat_map(|scope| &scope.metrics)
+            .find(|metric| metric.name == name)
+            .unwrap_or_else(|| panic!("Metric {name} was not exported"))
+            .data
+            .as_any()
+            .downcast_ref()
+            .unwrap_or_else(|| panic!("Metric {name} has an unexpected aggregation"))
+    }
+
+    fn gauge<T: Copy + 'static>(exported: &[ResourceMetrics], name: &str) -> T {
+        metric_data::<Gauge<T>>(exported, name).data_points[0].value
+    }
+
+    /// Value of the counter `name` for the data point with `attribute`, zero when it was never incremented.
+    fn counter(exported: &[ResourceMetrics], name: &str, attribute: KeyValue) -> u64 {
+        metric_data::<Sum<u64>>(exported, name)
+            .data_points
+            .iter()
+            .find(|point| point.attributes.contains(&attribute))
+            .map_or(0, |point| point.value)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mempool_metrics_occupancy() {
+        let mut metered = MeteredMempool::new(MempoolLimits {
+            max_transactions: 4,
+            max_declare_transactions: 2,
+            ..MempoolLimits::for_testing()
+        });
+        metered.mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+        metered.mempool.insert_tx(make_tx(TestTxTy::Declare, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+        let bytes = metered.mempool.counters().bytes;
+        assert!(bytes > 0);
+
+        let exported = metered.collect();
+        assert_eq!(gauge::<u64>(&exported, "mempool_transactions"), 2);
+        assert_eq!(gauge::<u64>(&exported, "mempool_declare_transactions"), 1);
+        assert_eq!(gauge::<u64>(&exported, "mempool_bytes"), bytes as u64);
+        assert_eq!(gauge::<f64>(&exported, "mempool_transactions_utilization"), 0.5);
+        assert_eq!(gauge::<f64>(&exported, "mempool_declare_transactions_utilization"), 0.5);
+
+        // Block production consumes all the transactions.
+        let mut popped = vec![];
+        metered.mempool.pop_next_chunk(&mut popped, usize::MAX);
+        metered.mempool.re_add_txs([], popped);
+
+        let exported = metered.collect();
+        assert_eq!(gauge::<u64>(&exported, "mempool_transactions"), 0);
+        assert_eq!(gauge::<u64>(&exported, "mempool_declare_transactions"), 0);
+        assert_eq!(gauge::<u64>(&exported, "mempool_bytes"), 0);
+        assert_eq!(gauge::<f64>(&exported, "mempool_transactions_utilization"), 0.0);
+        assert_eq!(gauge::<f64>(&exported, "mempool_declare_transactions_utilization"), 0.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mempool_metrics_rejections_by_reason() {
+        let mut metered = MeteredMempool::new(MempoolLimits {
+            max_transactions: 3,
+            max_declare_transactions: 1,
+            ..MempoolLimits::for_testing()
+        });
+        metered.mempool.insert_tx(make_tx(TestTxTy::Invoke, 1, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+        metered.mempool.insert_tx(make_tx(TestTxTy::Declare, 2, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+        assert_matches!(
+            metered.mempool.insert_tx(make_tx(TestTxTy::Declare, 3, 0, 0), false, Nonce(Felt::ZERO)),
+            Err(TxInsersionError::Limit(MempoolLimitReached::MaxDeclareTransactions { max: 1 }))
+        );
+        metered.mempool.insert_tx(make_tx(TestTxTy::Invoke, 4, 0, 0), false, Nonce(Felt::ZERO)).unwrap();
+        for sender in [5, 6] {
+            assert_matches!(
+                metered.mempool.insert_tx(make_tx(TestTxTy::Invoke, sender, 0, 0), false, Nonce(Felt::ZERO)),
+                Err(TxInsersionError::Limit(MempoolLimitReached::MaxTransactions { max: 3 }))
+            );
+        }
+
+        let exported = metered.collect();
+        let rejected =
+            |reason: &'static str| counter(&exported, "rejected_transaction_count", KeyValue::new("reason", reason));
+        assert_eq!(rejected("max_transactions"), 2);
+        assert_eq!(rejected("max_declare_transactions"), 1);
+        assert_eq!(rejected("max_bytes"), 0);
+    }
+}
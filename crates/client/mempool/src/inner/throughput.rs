@@ -0,0 +1,55 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// Sliding window over which the transaction rates are computed.
+pub const THROUGHPUT_WINDOW: Duration = Duration::from_secs(60);
+/// Past this many events in the window, the oldest ones are forgotten: the rates are capped at this many events per
+/// window, which bounds the memory of the ring buffer.
+pub const MAX_THROUGHPUT_SAMPLES: usize = 1 << 16;
+
+/// Transactions per second accepted in and popped from the mempool, over the last [`THROUGHPUT_WINDOW`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MempoolThroughput {
+    pub accepted_per_second: f64,
+    pub popped_per_second: f64,
+}
+
+/// Rate of events over a sliding window, from a ring buffer of their timestamps.
+#[derive(Clone, Debug)]
+pub(crate) struct EventRate {
+    window: Duration,
+    capacity: usize,
+    /// Timestamps of the events, the oldest first.
+    events: VecDeque<SystemTime>,
+}
+
+impl EventRate {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self { window, capacity, events: VecDeque::new() }
+    }
+
+    pub fn record(&mut self, now: SystemTime) {
+        let window_start = now.checked_sub(self.window).unwrap_or(SystemTime::UNIX_EPOCH);
+        while self.events.front().is_some_and(|at| *at <= window_start) {
+            self.events.pop_front();
+        }
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(now);
+    }
+
+    /// Events per second over the window ending at `now`. The events are recorded with the same clock, so they are
+    /// ordered, but an event after `now` is still counted.
+    pub fn per_second(&self, now: SystemTime) -> f64 {
+        let window_start = now.checked_sub(self.window).unwrap_or(SystemTime::UNIX_EPOCH);
+        let expired = self.events.partition_point(|at| *at <= window_start);
+        (self.events.len() - expired) as f64 / self.window.as_secs_f64()
+    }
+}
+
+impl Default for EventRate {
+    fn default() -> Self {
+        Self::new(THROUGHPUT_WINDOW, MAX_THROUGHPUT_SAMPLES)
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{clone_transaction, contract_addr, nonce, tx_hash};
+use crate::tx::blockifier_to_saved_tx;
+use crate::{
+    clone_transaction, contract_addr, is_zero_fee, max_l1_gas_price, max_l2_gas_amount, nonce, tip, tx_hash, v3_tip,
+};
 use blockifier::transaction::transaction_execution::Transaction;
 use mc_exec::execution::TxInfo;
 use mp_class::ConvertedClass;
@@ -11,10 +14,25 @@ use std::{fmt, time::SystemTime};
 
 pub type ArrivedAtTimestamp = SystemTime;
 
+/// Highest [`MempoolTransaction::priority_hint`] a submitter can set.
+pub const MAX_PRIORITY_HINT: u8 = 100;
+
 pub struct MempoolTransaction {
     pub tx: Transaction,
     pub arrived_at: ArrivedAtTimestamp,
     pub converted_class: Option<ConvertedClass>,
+    /// Size of the transaction and its class once serialized, counted against the mempool byte limit.
+    pub encoded_size: usize,
+    /// Set on the declare transactions accepted over the declare limit under
+    /// [`MempoolDeclareLimitPolicy::Deprioritize`](mp_chain_config::MempoolDeclareLimitPolicy::Deprioritize), which
+    /// are served after the other transactions.
+    pub deprioritized: bool,
+    /// Time after which the sender wants the transaction dropped if it has not been included, independently of the
+    /// mempool max age.
+    pub deadline: Option<SystemTime>,
+    /// Set by the submitter to order its transactions among the ones with the same tip, higher is popped first. It
+    /// does not override the tip, and is at most [`MAX_PRIORITY_HINT`].
+    pub priority_hint: u8,
 }
 
 impl fmt::Debug for MempoolTransaction {
@@ -35,11 +53,22 @@ impl Clone for MempoolTransaction {
             tx: clone_transaction(&self.tx),
             arrived_at: self.arrived_at,
             converted_class: self.converted_class.clone(),
+            encoded_size: self.encoded_size,
+            deprioritized: self.deprioritized,
+            deadline: self.deadline,
+            priority_hint: self.priority_hint,
         }
     }
 }
 
 impl MempoolTransaction {
+    pub fn new(tx: Transaction, arrived_at: ArrivedAtTimestamp, converted_class: Option<ConvertedClass>) -> Self {
+        // This is the size of the transaction as saved in the db.
+        let saved_tx = blockifier_to_saved_tx(&tx, arrived_at, None);
+        // Serializing these types to bincode cannot fail, they have no maps or sequences of unknown length.
+        let encoded_size = bincode::serialized_size(&(&saved_tx, &converted_class)).unwrap_or_default() as usize;
+        Self { tx, arrived_at, converted_class, encoded_size, deprioritized: false, deadline: None, priority_hint: 0 }
+    }
     pub fn clone_tx(&self) -> Transaction {
         clone_transaction(&self.tx)
     }
@@ -52,4 +81,19 @@ impl MempoolTransaction {
     pub fn tx_hash(&self) -> TransactionHash {
         tx_hash(&self.tx)
     }
+    pub fn tip(&self) -> u64 {
+        tip(&self.tx)
+    }
+    pub fn v3_tip(&self) -> Option<u64> {
+        v3_tip(&self.tx)
+    }
+    pub fn max_l1_gas_price(&self) -> Option<u128> {
+        max_l1_gas_price(&self.tx)
+    }
+    pub fn max_l2_gas_amount(&self) -> Option<u64> {
+        max_l2_gas_amount(&self.tx)
+    }
+    pub fn is_zero_fee(&self) -> bool {
+        is_zero_fee(&self.tx)
+    }
 }
@@ -0,0 +1,299 @@
+//! Queue of the accounts with a transaction ready to be executed, which is the front of their nonce chain.
+//! Accounts are popped by priority, and also indexed by arrival time so that age-exceeded transactions can be found
+//! without scanning the whole queue.
+//! The pop order depends on the [`MempoolOrdering`] of the chain.
+//!
+//! Under [`MempoolOrdering::FairShare`], the nonce chains are the per-sender ready queues and this queue schedules
+//! them with start-time fair queuing: every ready transaction gets a virtual start and finish time, and the account
+//! with the earliest virtual finish time is popped first. A transaction costs less virtual time the higher its tip,
+//! so the senders are served in turn, with a share of the pops weighted by their tips.
+
+use super::tx::{ArrivedAtTimestamp, MempoolTransaction};
+use blockifier::transaction::transaction_types::TransactionType;
+use mc_exec::execution::TxInfo;
+use mp_chain_config::MempoolOrdering;
+use starknet_types_core::felt::Felt;
+use std::iter::Peekable;
+use std::{cmp, collections::btree_set, collections::BTreeSet, collections::HashMap};
+
+/// Virtual time a transaction with no tip costs under [`MempoolOrdering::FairShare`]. A transaction with a tip of `tip`
+/// costs `FAIR_SHARE_QUANTUM / (tip + 1)`.
+const FAIR_SHARE_QUANTUM: u128 = u64::MAX as u128;
+
+/// Priority of a ready transaction, higher is popped first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TxPriority {
+    /// L1 handler transactions are not tipped: they are always served ahead of the tipped transactions, first-come
+    /// first-served.
+    is_l1_handler: bool,
+    /// False for the [`MempoolTransaction::deprioritized`] transactions, which are served after all the others.
+    is_prioritized: bool,
+    tip: u64,
+    /// Breaks the ties between equal tips, before the arrival time. See [`MempoolTransaction::priority_hint`].
+    priority_hint: u8,
+}
+
+impl TxPriority {
+    pub fn of(tx: &MempoolTransaction) -> Self {
+        Self {
+            is_l1_handler: tx.tx.tx_type() == TransactionType::L1Handler,
+            is_prioritized: !tx.deprioritized,
+            tip: tx.tip(),
+            priority_hint: tx.priority_hint,
+        }
+    }
+
+    /// The priority the queue is ordered by under this ordering strategy.
+    fn ordered_by(self, ordering: MempoolOrdering) -> Self {
+        match ordering {
+            MempoolOrdering::FeePriority => self,
+            // Ignoring the tip leaves the arrival time as the tie breaker.
+            MempoolOrdering::Fifo => Self { tip: 0, priority_hint: 0, ..self },
+            // The tip is accounted for in the virtual finish time instead.
+            MempoolOrdering::FairShare => Self { tip: 0, priority_hint: 0, ..self },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueuedAccount {
+    pub contract_addr: Felt,
+    /// Arrival time of the ready transaction.
+    pub timestamp: ArrivedAtTimestamp,
+    /// Priority of the ready transaction.
+    pub priority: TxPriority,
+}
+
+#[derive(Clone, Debug)]
+struct AccountOrderedByPriority {
+    /// Priority of the account under the queue ordering strategy.
+    rank: TxPriority,
+    /// Virtual finish time of the ready transaction under [`MempoolOrdering::FairShare`], zero otherwise.
+    virtual_finish: u128,
+    account: QueuedAccount,
+}
+
+impl AccountOrderedByPriority {
+    fn new(account: QueuedAccount, ordering: MempoolOrdering, virtual_finish: u128) -> Self {
+        Self { rank: account.priority.ordered_by(ordering), virtual_finish, account }
+    }
+}
+
+impl PartialEq for AccountOrderedByPriority {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+impl Eq for AccountOrderedByPriority {}
+impl Ord for AccountOrderedByPriority {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        // Highest priority first, then earliest virtual finish time, ties are broken by arrival time (FIFO).
+        // Important: Fallback on contract addr here.
+        // There can be timestamp collisions.
+        other
+            .rank
+            .cmp(&self.rank)
+            .then_with(|| self.virtual_finish.cmp(&other.virtual_finish))
+            .then_with(|| self.account.timestamp.cmp(&other.account.timestamp))
+            .then_with(|| self.account.contract_addr.cmp(&other.account.contract_addr))
+    }
+}
+impl PartialOrd for AccountOrderedByPriority {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AccountOrderedByTimestamp(QueuedAccount);
+
+impl PartialEq for AccountOrderedByTimestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+impl Eq for AccountOrderedByTimestamp {}
+impl Ord for AccountOrderedByTimestamp {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        // An account is only ever queued once, so the contract addr is enough to break timestamp collisions.
+        self.0.timestamp.cmp(&other.0.timestamp).then_with(|| self.0.contract_addr.cmp(&other.0.contract_addr))
+    }
+}
+impl PartialOrd for AccountOrderedByTimestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Virtual times of an account under [`MempoolOrdering::FairShare`].
+#[derive(Clone, Copy, Debug)]
+struct FairShareTag {
+    /// Virtual start time of the last transaction of the account that was queued.
+    start: u128,
+    /// Virtual finish time of the last transaction of the account that was queued, which is the earliest virtual start
+    /// time of its next transaction. Reset to `start` when the transaction is removed without being popped, so that a
+    /// replaced transaction keeps its turn.
+    finish: u128,
+    /// Whether the transaction is still in the queue.
+    queued: bool,
+}
+
+/// Virtual clock of [`MempoolOrdering::FairShare`]. L1 handler transactions are not scheduled by it: they are always
+/// served first.
+#[derive(Clone, Debug, Default)]
+struct FairShareClock {
+    /// Virtual start time of the last popped transaction.
+    virtual_time: u128,
+    /// Accounts whose virtual finish time is past `virtual_time`, and the queued accounts.
+    tags: HashMap<Felt, FairShareTag>,
+    /// Number of the `tags` which are queued.
+    queued: usize,
+}
+
+impl FairShareClock {
+    /// Schedules the ready transaction of `account`, returning its virtual finish time.
+    fn schedule(&mut self, account: &QueuedAccount) -> u128 {
+        if account.priority.is_l1_handler {
+            return 0;
+        }
+        let cost = (FAIR_SHARE_QUANTUM / (u128::from(account.priority.tip) + 1)).max(1);
+        let previous_finish = self.tags.get(&account.contract_addr).map_or(0, |tag| tag.finish);
+        let start = cmp::max(self.virtual_time, previous_finish);
+        let finish = start.saturating_add(cost);
+        self.tags.insert(account.contract_addr, FairShareTag { start, finish, queued: true });
+        self.queued += 1;
+        finish
+    }
+
+    /// The virtual finish time of the queued transaction of `account`.
+    fn queued_finish(&self, account: &QueuedAccount) -> u128 {
+        match self.tags.get(&account.contract_addr) {
+            Some(tag) if tag.queued && !account.priority.is_l1_handler => tag.finish,
+            _ => 0,
+        }
+    }
+
+    /// The queued transaction of `account` was removed without being popped.
+    fn unschedule(&mut self, account: &QueuedAccount) {
+        if let Some(tag) = self.tags.get_mut(&account.contract_addr).filter(|tag| tag.queued) {
+            *tag = FairShareTag { start: tag.start, finish: tag.start, queued: false };
+            self.queued -= 1;
+        }
+    }
+
+    /// The queued transaction of `account` was popped, advancing the virtual time to its start time.
+    fn served(&mut self, account: &QueuedAccount) {
+        let Some(tag) = self.tags.get_mut(&account.contract_addr).filter(|tag| tag.queued) else { return };
+        tag.queued = false;
+        self.queued -= 1;
+        self.virtual_time = cmp::max(self.virtual_time, tag.start);
+
+        // The accounts which are not queued and whose finish time is behind the virtual time are scheduled as if they
+        // had never been served, they can be forgotten.
+        let virtual_time = self.virtual_time;
+        if self.tags.len() > 2 * self.queued + 64 {
+            self.tags.retain(|_, tag| tag.queued || tag.finish > virtual_time);
+        }
+    }
+}
+
+/// Invariants:
+/// - `by_priority` and `by_age` contain the same accounts.
+#[derive(Clone, Debug, Default)]
+pub struct TxQueue {
+    ordering: MempoolOrdering,
+    by_priority: BTreeSet<AccountOrderedByPriority>,
+    by_age: BTreeSet<AccountOrderedByTimestamp>,
+    /// Only used under [`MempoolOrdering::FairShare`].
+    fair_share: FairShareClock,
+}
+
+impl TxQueue {
+    pub fn new(ordering: MempoolOrdering) -> Self {
+        Self { ordering, by_priority: Default::default(), by_age: Default::default(), fair_share: Default::default() }
+    }
+
+    pub fn insert(&mut self, account: QueuedAccount) -> bool {
+        let virtual_finish = match self.ordering {
+            MempoolOrdering::FairShare => self.fair_share.schedule(&account),
+            MempoolOrdering::FeePriority | MempoolOrdering::Fifo => 0,
+        };
+        let inserted = self.by_priority.insert(AccountOrderedByPriority::new(account, self.ordering, virtual_finish));
+        let inserted_by_age = self.by_age.insert(AccountOrderedByTimestamp(account));
+        debug_assert_eq!(inserted, inserted_by_age);
+        inserted
+    }
+
+    pub fn remove(&mut self, account: &QueuedAccount) -> bool {
+        let virtual_finish = self.fair_share.queued_finish(account);
+        let removed = self.by_priority.remove(&AccountOrderedByPriority::new(*account, self.ordering, virtual_finish));
+        let removed_by_age = self.by_age.remove(&AccountOrderedByTimestamp(*account));
+        debug_assert_eq!(removed, removed_by_age);
+        if removed {
+            self.fair_share.unschedule(account);
+        }
+        removed
+    }
+
+    /// Removes the account with the highest priority ready transaction.
+    pub fn pop_first(&mut self) -> Option<QueuedAccount> {
+        let AccountOrderedByPriority { account, .. } = self.by_priority.pop_first()?;
+        let removed = self.by_age.remove(&AccountOrderedByTimestamp(account));
+        debug_assert!(removed);
+        self.fair_share.served(&account);
+        Some(account)
+    }
+
+    /// A read-only view of the queue, to find out which accounts would be popped without popping them.
+    pub fn peek(&self) -> PeekedTxQueue<'_> {
+        PeekedTxQueue {
+            ordering: self.ordering,
+            queued: self.by_priority.iter().peekable(),
+            requeued: BTreeSet::new(),
+            fair_share: self.fair_share.clone(),
+        }
+    }
+
+    /// The account with the oldest ready transaction.
+    pub fn oldest(&self) -> Option<&QueuedAccount> {
+        self.by_age.first().map(|account| &account.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_priority.is_empty()
+    }
+}
+
+/// See [`TxQueue::peek`]. The popped accounts are not removed from the queue, and the inserted accounts are only
+/// queued in this view.
+pub struct PeekedTxQueue<'a> {
+    ordering: MempoolOrdering,
+    /// The accounts of the queue which have not been popped from this view yet.
+    queued: Peekable<btree_set::Iter<'a, AccountOrderedByPriority>>,
+    /// The accounts inserted in this view.
+    requeued: BTreeSet<AccountOrderedByPriority>,
+    /// The virtual clock of the queue, advanced by the pops of this view.
+    fair_share: FairShareClock,
+}
+
+impl PeekedTxQueue<'_> {
+    /// The account [`TxQueue::pop_first`] would return.
+    pub fn pop_first(&mut self) -> Option<QueuedAccount> {
+        let from_requeued = match (self.queued.peek(), self.requeued.first()) {
+            (Some(queued), Some(requeued)) => requeued < *queued,
+            (None, _) => true,
+            (Some(_), None) => false,
+        };
+        let account = if from_requeued { self.requeued.pop_first()? } else { self.queued.next()?.clone() };
+        self.fair_share.served(&account.account);
+        Some(account.account)
+    }
+
+    pub fn insert(&mut self, account: QueuedAccount) {
+        let virtual_finish = match self.ordering {
+            MempoolOrdering::FairShare => self.fair_share.schedule(&account),
+            MempoolOrdering::FeePriority | MempoolOrdering::Fifo => 0,
+        };
+        self.requeued.insert(AccountOrderedByPriority::new(account, self.ordering, virtual_finish));
+    }
+}
@@ -1,14 +1,157 @@
 //! TODO: this should be in the backend
 use mp_block::header::{GasPrices, L1DataAvailabilityMode};
+use mp_block::H160;
 use mp_oracle::Oracle;
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+/// Exponential moving average parameters used to smooth the L1 gas prices.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GasPriceSmoothing {
+    /// Weight of the newest sample, between 0 (excluded) and 1. A factor of 1 disables smoothing.
+    pub alpha: f64,
+    /// Number of most recent samples the average is computed over.
+    pub window: usize,
+}
+
+impl GasPriceSmoothing {
+    /// No smoothing: the smoothed price is always the latest sample.
+    pub const DISABLED: Self = Self { alpha: 1.0, window: 1 };
+
+    /// Computes the moving average over the samples, oldest first. The first sample seeds the average.
+    fn assert_valid(&self) {
+        assert!(self.alpha > 0.0 && self.alpha <= 1.0, "Gas price smoothing factor must be in (0, 1]");
+        assert!(self.window > 0, "Gas price smoothing window must not be empty");
+    }
+
+    fn ema(&self, samples: &VecDeque<u128>) -> u128 {
+        let mut samples = samples.iter();
+        let Some(first) = samples.next() else { return 0 };
+        let ema = samples.fold(*first as f64, |ema, sample| self.alpha * *sample as f64 + (1.0 - self.alpha) * ema);
+        ema.round() as u128
+    }
+}
+
+impl Default for GasPriceSmoothing {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+/// Bounds, in wei, the fetched L1 gas prices are clamped to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasPriceBounds {
+    pub min: u128,
+    pub max: u128,
+}
+
+impl GasPriceBounds {
+    pub const UNBOUNDED: Self = Self { min: 0, max: u128::MAX };
+
+    pub fn clamp(&self, price: u128) -> u128 {
+        price.clamp(self.min, self.max)
+    }
+}
+
+impl Default for GasPriceBounds {
+    fn default() -> Self {
+        Self::UNBOUNDED
+    }
+}
+
+/// Where the gas price worker fetches the L1 gas price from. The L1 data gas price always comes from the blob base fee.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum L1GasPriceSource {
+    /// The L1 base fee, plus a percentile of the priority fees when a
+    /// [fee history percentile](GasPriceProvider::fee_history_percentile) is set.
+    #[default]
+    BaseFee,
+    /// The `gasPrice()` view function of an L1 oracle contract, which returns the L1 gas price in wei. App chains use
+    /// it to derive their gas price from an L1 oracle rather than from the base fee.
+    OracleContract { address: H160 },
+}
+
+/// The `percentile`th percentile of `values`, with the nearest-rank method. Zero when there are no values.
+fn nearest_rank_percentile(values: &[u128], percentile: f64) -> u128 {
+    let mut values = values.to_vec();
+    values.sort_unstable();
+    let rank = (percentile / 100.0 * values.len() as f64).ceil() as usize;
+    values.get(rank.max(1) - 1).copied().unwrap_or(0)
+}
+
+/// Bounds of the interval at which the gas price worker fetches the L1 gas prices.
+pub const MIN_GAS_PRICE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+pub const MAX_GAS_PRICE_POLL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const DEFAULT_GAS_PRICE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error(
+    "Gas price poll interval {interval:?} is out of bounds, it must be between {MIN_GAS_PRICE_POLL_INTERVAL:?} and {MAX_GAS_PRICE_POLL_INTERVAL:?}"
+)]
+pub struct InvalidGasPricePollInterval {
+    pub interval: Duration,
+}
+
+#[derive(Clone, Copy)]
+enum GasPriceKind {
+    EthL1Gas,
+    EthL1DataGas,
+    StrkL1Gas,
+    StrkL1DataGas,
+}
+
+impl GasPriceKind {
+    fn price_mut(self, prices: &mut GasPrices) -> &mut u128 {
+        match self {
+            Self::EthL1Gas => &mut prices.eth_l1_gas_price,
+            Self::EthL1DataGas => &mut prices.eth_l1_data_gas_price,
+            Self::StrkL1Gas => &mut prices.strk_l1_gas_price,
+            Self::StrkL1DataGas => &mut prices.strk_l1_data_gas_price,
+        }
+    }
+
+    fn is_data_gas(self) -> bool {
+        matches!(self, Self::EthL1DataGas | Self::StrkL1DataGas)
+    }
+}
+
+/// A last update in the future (clock drift) counts as fresh.
+fn is_older_than(now: SystemTime, last_update: SystemTime, max_age: Duration) -> bool {
+    now.duration_since(last_update).is_ok_and(|age| age > max_age)
+}
+
+#[derive(Default)]
+struct GasPricesState {
+    /// Latest prices, as fetched.
+    raw: GasPrices,
+    /// Moving average of the prices times the gas price multipliers, used for block production.
+    smoothed: GasPrices,
+    /// Last samples of each price, indexed by [`GasPriceKind`].
+    samples: [VecDeque<u128>; 4],
+}
 
 #[derive(Clone)]
 pub struct GasPriceProvider {
-    gas_prices: Arc<Mutex<GasPrices>>,
+    gas_prices: Arc<Mutex<GasPricesState>>,
+    /// The L1 gas and L1 data gas prices come from different sources, they are smoothed and tracked separately.
+    smoothing: GasPriceSmoothing,
+    data_gas_smoothing: GasPriceSmoothing,
+    bounds: GasPriceBounds,
+    /// Safety margins applied to the smoothed L1 gas and L1 data gas prices.
+    multiplier: f64,
+    data_gas_multiplier: f64,
+    /// Percentile of the priority fees of the recent L1 blocks added to the L1 base fee, the base fee alone is used
+    /// when unset.
+    fee_history_percentile: Option<f64>,
+    gas_price_source: L1GasPriceSource,
     last_update: Arc<Mutex<SystemTime>>,
+    data_gas_last_update: Arc<Mutex<SystemTime>>,
+    /// Shared with the gas price worker, which reads it before every poll so that it can be changed at runtime.
+    poll_interval: Arc<RwLock<Duration>>,
+    /// Fraction of the poll interval by which every wait of the gas price worker is randomly shortened or lengthened.
+    poll_jitter: f64,
     gas_price_sync_enabled: Arc<AtomicBool>,
     data_gas_price_sync_enabled: Arc<AtomicBool>,
     strk_gas_price_sync_enabled: Arc<AtomicBool>,
@@ -18,9 +161,20 @@ pub struct GasPriceProvider {
 
 impl GasPriceProvider {
     pub fn new() -> Self {
+        let now = SystemTime::now();
         GasPriceProvider {
-            gas_prices: Arc::new(Mutex::new(GasPrices::default())),
-            last_update: Arc::new(Mutex::new(SystemTime::now())),
+            gas_prices: Arc::new(Mutex::new(GasPricesState::default())),
+            smoothing: GasPriceSmoothing::DISABLED,
+            data_gas_smoothing: GasPriceSmoothing::DISABLED,
+            bounds: GasPriceBounds::UNBOUNDED,
+            multiplier: 1.0,
+            data_gas_multiplier: 1.0,
+            fee_history_percentile: None,
+            gas_price_source: L1GasPriceSource::BaseFee,
+            last_update: Arc::new(Mutex::new(now)),
+            data_gas_last_update: Arc::new(Mutex::new(now)),
+            poll_interval: Arc::new(RwLock::new(DEFAULT_GAS_PRICE_POLL_INTERVAL)),
+            poll_jitter: 0.0,
             gas_price_sync_enabled: Arc::new(AtomicBool::new(true)),
             data_gas_price_sync_enabled: Arc::new(AtomicBool::new(true)),
             strk_gas_price_sync_enabled: Arc::new(AtomicBool::new(true)),
@@ -40,6 +194,114 @@ impl GasPriceProvider {
         self
     }
 
+    /// Sets the moving average applied to the L1 gas prices. Must be called before any price is set.
+    pub fn set_smoothing(&mut self, smoothing: GasPriceSmoothing) -> &mut Self {
+        smoothing.assert_valid();
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Sets the moving average applied to the L1 data gas prices. Must be called before any price is set.
+    pub fn set_data_gas_smoothing(&mut self, smoothing: GasPriceSmoothing) -> &mut Self {
+        smoothing.assert_valid();
+        self.data_gas_smoothing = smoothing;
+        self
+    }
+
+    /// Sets the bounds the L1 gas prices fetched by the gas price worker are clamped to.
+    pub fn set_gas_price_bounds(&mut self, bounds: GasPriceBounds) -> &mut Self {
+        assert!(bounds.min <= bounds.max, "Minimum gas price must not be greater than the maximum gas price");
+        self.bounds = bounds;
+        self
+    }
+
+    pub fn gas_price_bounds(&self) -> GasPriceBounds {
+        self.bounds
+    }
+
+    /// Multiplies the L1 gas prices exposed to block production by `multiplier`, as a safety margin against the L1 gas
+    /// price rising. The raw prices are kept as fetched. Only the prices set afterwards are multiplied, so that the
+    /// fixed prices set before are used as is.
+    pub fn set_gas_price_multiplier(&mut self, multiplier: f64) -> &mut Self {
+        assert!(multiplier.is_finite() && multiplier > 0.0, "Gas price multiplier must be positive");
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Same as [`GasPriceProvider::set_gas_price_multiplier`], for the L1 data gas prices.
+    pub fn set_data_gas_price_multiplier(&mut self, multiplier: f64) -> &mut Self {
+        assert!(multiplier.is_finite() && multiplier > 0.0, "Data gas price multiplier must be positive");
+        self.data_gas_multiplier = multiplier;
+        self
+    }
+
+    /// Computes the L1 gas price from the L1 base fee plus this percentile, in [0, 100], of the priority fees of the
+    /// recent L1 blocks, instead of the base fee alone. This follows the price the L1 transactions actually pay.
+    pub fn set_fee_history_percentile(&mut self, percentile: Option<f64>) -> &mut Self {
+        if let Some(percentile) = percentile {
+            assert!((0.0..=100.0).contains(&percentile), "Fee history percentile must be in [0, 100]");
+        }
+        self.fee_history_percentile = percentile;
+        self
+    }
+
+    pub fn fee_history_percentile(&self) -> Option<f64> {
+        self.fee_history_percentile
+    }
+
+    /// Sets where the gas price worker fetches the L1 gas price from.
+    pub fn set_gas_price_source(&mut self, source: L1GasPriceSource) -> &mut Self {
+        self.gas_price_source = source;
+        self
+    }
+
+    pub fn gas_price_source(&self) -> L1GasPriceSource {
+        self.gas_price_source
+    }
+
+    /// The L1 gas price for the next L1 `base_fee` and the `priority_fees` of the recent L1 blocks, as returned by
+    /// `eth_feeHistory` for the [`GasPriceProvider::fee_history_percentile`]: the base fee plus the percentile of the
+    /// priority fees. This is the base fee alone when no percentile is configured.
+    pub fn gas_price_from_fee_history(&self, base_fee: u128, priority_fees: &[u128]) -> u128 {
+        let Some(percentile) = self.fee_history_percentile else { return base_fee };
+        base_fee.saturating_add(nearest_rank_percentile(priority_fees, percentile))
+    }
+
+    /// Interval at which the gas price worker fetches the L1 gas prices.
+    pub fn poll_interval(&self) -> Duration {
+        *self.poll_interval.read().expect("Poisoned lock")
+    }
+
+    /// Changes the gas price poll interval, taking effect from the next poll. Returns the previous interval.
+    pub fn set_poll_interval(&self, interval: Duration) -> Result<Duration, InvalidGasPricePollInterval> {
+        if !(MIN_GAS_PRICE_POLL_INTERVAL..=MAX_GAS_PRICE_POLL_INTERVAL).contains(&interval) {
+            return Err(InvalidGasPricePollInterval { interval });
+        }
+        Ok(std::mem::replace(&mut *self.poll_interval.write().expect("Poisoned lock"), interval))
+    }
+
+    /// Sets the random jitter applied to the gas price poll interval, as a fraction of the interval in [0, 1). This
+    /// keeps nodes that poll the same L1 endpoint from doing so at the same time.
+    pub fn set_poll_jitter(&mut self, jitter: f64) -> &mut Self {
+        assert!((0.0..1.0).contains(&jitter), "Gas price poll jitter must be in [0, 1)");
+        self.poll_jitter = jitter;
+        self
+    }
+
+    pub fn poll_jitter(&self) -> f64 {
+        self.poll_jitter
+    }
+
+    /// Latest gas prices, before smoothing.
+    pub fn get_raw_gas_prices(&self) -> GasPrices {
+        self.gas_prices.lock().unwrap().raw.clone()
+    }
+
+    /// Gas prices smoothed by the moving average, times the gas price multipliers. This is what block production uses.
+    pub fn get_smoothed_gas_prices(&self) -> GasPrices {
+        self.gas_prices.lock().unwrap().smoothed.clone()
+    }
+
     pub fn set_gas_prices(&self, new_prices: GasPrices) {
         self.update_eth_l1_gas_price(new_prices.eth_l1_gas_price);
         self.update_strk_l1_gas_price(new_prices.strk_l1_gas_price);
@@ -47,6 +309,31 @@ impl GasPriceProvider {
         self.update_strk_l1_data_gas_price(new_prices.strk_l1_data_gas_price);
     }
 
+    /// Fixes the L1 gas price: it is not synced from the L1 anymore. Zero is a valid fixed price, such as in devnet.
+    pub fn set_fixed_gas_price(&self, price: u128) {
+        self.push_sample(GasPriceKind::EthL1Gas, price);
+        self.set_gas_price_sync_enabled(false);
+    }
+
+    /// Fixes the L1 data gas price: it is not synced from the L1 anymore. Zero is a valid fixed price, such as in
+    /// devnet.
+    pub fn set_fixed_data_gas_price(&self, price: u128) {
+        self.push_sample(GasPriceKind::EthL1DataGas, price);
+        self.set_data_gas_price_sync_enabled(false);
+    }
+
+    /// The fixed L1 gas price, or `None` when it is synced from the L1. A gas price fixed to zero is `Some(0)`.
+    pub fn fixed_gas_price(&self) -> Option<u128> {
+        (!self.gas_price_sync_enabled.load(Ordering::Relaxed)).then(|| self.get_raw_gas_prices().eth_l1_gas_price)
+    }
+
+    /// The fixed L1 data gas price, or `None` when it is synced from the L1. A data gas price fixed to zero is
+    /// `Some(0)`.
+    pub fn fixed_data_gas_price(&self) -> Option<u128> {
+        (!self.data_gas_price_sync_enabled.load(Ordering::Relaxed))
+            .then(|| self.get_raw_gas_prices().eth_l1_data_gas_price)
+    }
+
     pub fn set_gas_price_sync_enabled(&self, enabled: bool) {
         self.gas_price_sync_enabled.store(enabled, Ordering::Relaxed);
     }
@@ -63,42 +350,83 @@ impl GasPriceProvider {
         self.strk_data_gas_price_sync_enabled.store(enabled, Ordering::Relaxed);
     }
 
+    /// Marks both the L1 gas and L1 data gas prices as up to date.
     pub fn update_last_update_timestamp(&self) {
-        if self.gas_price_sync_enabled.load(Ordering::Relaxed)
-            || self.data_gas_price_sync_enabled.load(Ordering::Relaxed)
-        {
-            let now = SystemTime::now();
-            let mut timestamp = self.last_update.lock().unwrap();
-            *timestamp = now;
+        self.update_gas_price_last_update_timestamp();
+        self.update_data_gas_price_last_update_timestamp();
+    }
+
+    pub fn update_gas_price_last_update_timestamp(&self) {
+        if self.gas_price_sync_enabled.load(Ordering::Relaxed) {
+            *self.last_update.lock().unwrap() = SystemTime::now();
+        }
+    }
+
+    pub fn update_data_gas_price_last_update_timestamp(&self) {
+        if self.data_gas_price_sync_enabled.load(Ordering::Relaxed) {
+            *self.data_gas_last_update.lock().unwrap() = SystemTime::now();
         }
     }
 
+    /// Whether any of the synced gas prices has not been updated for more than `max_age`. Fixed gas prices are never
+    /// stale.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.is_stale_at(SystemTime::now(), max_age)
+    }
+
+    fn is_stale_at(&self, now: SystemTime, max_age: Duration) -> bool {
+        self.is_gas_price_stale_at(now, max_age) || self.is_data_gas_price_stale_at(now, max_age)
+    }
+
+    fn is_gas_price_stale_at(&self, now: SystemTime, max_age: Duration) -> bool {
+        self.gas_price_sync_enabled.load(Ordering::Relaxed)
+            && is_older_than(now, self.get_gas_prices_last_update(), max_age)
+    }
+
+    fn is_data_gas_price_stale_at(&self, now: SystemTime, max_age: Duration) -> bool {
+        self.data_gas_price_sync_enabled.load(Ordering::Relaxed)
+            && is_older_than(now, self.get_data_gas_prices_last_update(), max_age)
+    }
+
     pub fn update_eth_l1_gas_price(&self, new_price: u128) {
         if self.gas_price_sync_enabled.load(Ordering::Relaxed) {
-            let mut prices = self.gas_prices.lock().unwrap();
-            prices.eth_l1_gas_price = new_price;
+            self.push_sample(GasPriceKind::EthL1Gas, new_price);
         }
     }
 
     pub fn update_eth_l1_data_gas_price(&self, new_price: u128) {
         if self.data_gas_price_sync_enabled.load(Ordering::Relaxed) {
-            let mut prices = self.gas_prices.lock().unwrap();
-            prices.eth_l1_data_gas_price = new_price;
+            self.push_sample(GasPriceKind::EthL1DataGas, new_price);
         }
     }
 
     pub fn update_strk_l1_gas_price(&self, new_price: u128) {
         if self.strk_gas_price_sync_enabled.load(Ordering::Relaxed) {
-            let mut prices = self.gas_prices.lock().unwrap();
-            prices.strk_l1_gas_price = new_price;
+            self.push_sample(GasPriceKind::StrkL1Gas, new_price);
         }
     }
 
     pub fn update_strk_l1_data_gas_price(&self, new_price: u128) {
         if self.strk_data_gas_price_sync_enabled.load(Ordering::Relaxed) {
-            let mut prices = self.gas_prices.lock().unwrap();
-            prices.strk_l1_data_gas_price = new_price;
+            self.push_sample(GasPriceKind::StrkL1DataGas, new_price);
+        }
+    }
+
+    fn push_sample(&self, kind: GasPriceKind, new_price: u128) {
+        let mut state = self.gas_prices.lock().unwrap();
+        let samples = &mut state.samples[kind as usize];
+        let (smoothing, multiplier) = if kind.is_data_gas() {
+            (&self.data_gas_smoothing, self.data_gas_multiplier)
+        } else {
+            (&self.smoothing, self.multiplier)
+        };
+        samples.push_back(new_price);
+        while samples.len() > smoothing.window {
+            samples.pop_front();
         }
+        let smoothed = (smoothing.ema(samples) as f64 * multiplier).round() as u128;
+        *kind.price_mut(&mut state.raw) = new_price;
+        *kind.price_mut(&mut state.smoothed) = smoothed;
     }
 }
 
@@ -111,7 +439,12 @@ impl Default for GasPriceProvider {
 #[cfg_attr(any(test, feature = "testing"), mockall::automock)]
 pub trait L1DataProvider: Send + Sync {
     fn get_gas_prices(&self) -> GasPrices;
+    /// Last update of the L1 gas prices.
     fn get_gas_prices_last_update(&self) -> SystemTime;
+    /// Last update of the L1 data gas prices.
+    fn get_data_gas_prices_last_update(&self) -> SystemTime;
+    fn is_gas_price_stale(&self, max_age: Duration) -> bool;
+    fn is_data_gas_price_stale(&self, max_age: Duration) -> bool;
     fn get_da_mode(&self) -> L1DataAvailabilityMode;
 }
 
@@ -119,14 +452,296 @@ pub trait L1DataProvider: Send + Sync {
 /// Gas prices and DA mode
 impl L1DataProvider for GasPriceProvider {
     fn get_gas_prices(&self) -> GasPrices {
-        self.gas_prices.lock().unwrap().clone()
+        self.get_smoothed_gas_prices()
     }
 
     fn get_gas_prices_last_update(&self) -> SystemTime {
         *self.last_update.lock().expect("Failed to acquire lock")
     }
 
+    fn get_data_gas_prices_last_update(&self) -> SystemTime {
+        *self.data_gas_last_update.lock().expect("Failed to acquire lock")
+    }
+
+    fn is_gas_price_stale(&self, max_age: Duration) -> bool {
+        self.is_gas_price_stale_at(SystemTime::now(), max_age)
+    }
+
+    fn is_data_gas_price_stale(&self, max_age: Duration) -> bool {
+        self.is_data_gas_price_stale_at(SystemTime::now(), max_age)
+    }
+
     fn get_da_mode(&self) -> L1DataAvailabilityMode {
         L1DataAvailabilityMode::Blob
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(alpha: f64, window: usize) -> GasPriceProvider {
+        let mut provider = GasPriceProvider::new();
+        provider.set_smoothing(GasPriceSmoothing { alpha, window });
+        provider.set_data_gas_smoothing(GasPriceSmoothing { alpha, window });
+        provider
+    }
+
+    fn assert_close(actual: u128, expected: f64) {
+        assert!((actual as f64 - expected).abs() <= 1.0, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn gas_price_from_fee_history_percentile() {
+        // Priority fees of the recent L1 blocks, at the configured percentile of each block.
+        let priority_fees = [30, 10, 50, 20, 40];
+
+        let mut provider = GasPriceProvider::new();
+        assert_eq!(provider.gas_price_from_fee_history(100, &priority_fees), 100);
+
+        provider.set_fee_history_percentile(Some(50.0));
+        assert_eq!(provider.gas_price_from_fee_history(100, &priority_fees), 130);
+        provider.set_fee_history_percentile(Some(90.0));
+        assert_eq!(provider.gas_price_from_fee_history(100, &priority_fees), 150);
+        provider.set_fee_history_percentile(Some(0.0));
+        assert_eq!(provider.gas_price_from_fee_history(100, &priority_fees), 110);
+        // No priority fee history, such as on an empty range of blocks.
+        assert_eq!(provider.gas_price_from_fee_history(100, &[]), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee history percentile must be in [0, 100]")]
+    fn gas_price_fee_history_percentile_out_of_range() {
+        GasPriceProvider::new().set_fee_history_percentile(Some(101.0));
+    }
+
+    #[test]
+    fn gas_price_staleness() {
+        let provider = GasPriceProvider::new();
+        let max_age = Duration::from_secs(60);
+        provider.update_eth_l1_gas_price(100);
+        provider.update_last_update_timestamp();
+        let last_update = provider.get_gas_prices_last_update();
+
+        assert!(!provider.is_stale(max_age));
+        assert!(!provider.is_stale_at(last_update + max_age, max_age));
+        assert!(provider.is_stale_at(last_update + max_age + Duration::from_secs(1), max_age));
+
+        // A new update makes the prices fresh again.
+        provider.update_last_update_timestamp();
+        let last_update = provider.get_gas_prices_last_update();
+        assert!(!provider.is_stale_at(last_update + max_age, max_age));
+    }
+
+    #[test]
+    fn gas_price_staleness_fixed_prices() {
+        let provider = GasPriceProvider::new();
+        provider.update_eth_l1_gas_price(100);
+        provider.update_eth_l1_data_gas_price(10);
+        provider.set_gas_price_sync_enabled(false);
+        provider.set_data_gas_price_sync_enabled(false);
+
+        let far_future = provider.get_gas_prices_last_update() + Duration::from_secs(60 * 60 * 24);
+        assert!(!provider.is_stale_at(far_future, Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn fixed_gas_price_zero_is_not_unset() {
+        let provider = GasPriceProvider::new();
+        assert_eq!(provider.fixed_gas_price(), None);
+        assert_eq!(provider.fixed_data_gas_price(), None);
+
+        provider.set_fixed_gas_price(0);
+        assert_eq!(provider.fixed_gas_price(), Some(0));
+        assert_eq!(provider.fixed_data_gas_price(), None);
+
+        provider.set_fixed_data_gas_price(7);
+        assert_eq!(provider.fixed_data_gas_price(), Some(7));
+        // Fixed prices are not overwritten by the synced ones.
+        provider.update_eth_l1_gas_price(100);
+        assert_eq!(provider.fixed_gas_price(), Some(0));
+        assert_eq!(provider.get_gas_prices().eth_l1_gas_price, 0);
+    }
+
+    #[test]
+    fn gas_price_staleness_is_tracked_separately() {
+        let provider = GasPriceProvider::new();
+        let max_age = Duration::from_secs(60);
+        let later = provider.get_gas_prices_last_update() + max_age + Duration::from_secs(1);
+
+        // Only the data gas price got updated.
+        std::thread::sleep(Duration::from_millis(10));
+        provider.update_data_gas_price_last_update_timestamp();
+        let data_gas_last_update = provider.get_data_gas_prices_last_update();
+        assert!(data_gas_last_update > provider.get_gas_prices_last_update());
+        assert!(provider.is_gas_price_stale_at(later, max_age));
+        assert!(!provider.is_data_gas_price_stale_at(data_gas_last_update + max_age, max_age));
+        assert!(provider.is_stale_at(later, max_age));
+
+        // A fixed L1 gas price is never stale, the synced data gas price still is.
+        provider.set_gas_price_sync_enabled(false);
+        let later = data_gas_last_update + max_age + Duration::from_secs(1);
+        assert!(!provider.is_gas_price_stale_at(later, max_age));
+        assert!(provider.is_data_gas_price_stale_at(later, max_age));
+        assert!(provider.is_stale_at(later, max_age));
+    }
+
+    #[test]
+    fn gas_price_bounds_clamp() {
+        let bounds = GasPriceBounds { min: 10, max: 1_000 };
+        assert_eq!(bounds.clamp(0), 10);
+        assert_eq!(bounds.clamp(9), 10);
+        assert_eq!(bounds.clamp(10), 10);
+        assert_eq!(bounds.clamp(500), 500);
+        assert_eq!(bounds.clamp(1_000), 1_000);
+        assert_eq!(bounds.clamp(1_001), 1_000);
+        assert_eq!(bounds.clamp(u128::MAX), 1_000);
+        assert_eq!(GasPriceBounds::UNBOUNDED.clamp(u128::MAX), u128::MAX);
+    }
+
+    #[test]
+    fn gas_price_smoothing_disabled_follows_raw_price() {
+        let provider = GasPriceProvider::new();
+        for price in [100, 300, 50] {
+            provider.update_eth_l1_gas_price(price);
+            assert_eq!(provider.get_gas_prices().eth_l1_gas_price, price);
+            assert_eq!(provider.get_raw_gas_prices().eth_l1_gas_price, price);
+        }
+    }
+
+    #[test]
+    fn gas_price_smoothing_ema() {
+        let provider = provider(0.5, 10);
+        let series = [1_000u128, 2_000, 2_000, 500, 3_000];
+
+        let mut expected = series[0] as f64;
+        provider.update_eth_l1_gas_price(series[0]);
+        assert_close(provider.get_gas_prices().eth_l1_gas_price, expected);
+        for price in &series[1..] {
+            provider.update_eth_l1_gas_price(*price);
+            expected = 0.5 * *price as f64 + 0.5 * expected;
+            assert_close(provider.get_gas_prices().eth_l1_gas_price, expected);
+            assert_eq!(provider.get_raw_gas_prices().eth_l1_gas_price, *price);
+        }
+        // 1000 -> 1500 -> 1750 -> 1125 -> 2062.5
+        assert_close(provider.get_smoothed_gas_prices().eth_l1_gas_price, 2_062.5);
+    }
+
+    #[test]
+    fn gas_price_smoothing_window() {
+        let provider = provider(0.2, 3);
+        // A spike should be forgotten once it leaves the window.
+        for price in [1_000_000u128, 100, 100, 100] {
+            provider.update_eth_l1_data_gas_price(price);
+        }
+        assert_close(provider.get_gas_prices().eth_l1_data_gas_price, 100.0);
+
+        provider.update_eth_l1_data_gas_price(200);
+        // Window is [100, 100, 200]: 100 -> 100 -> 0.2 * 200 + 0.8 * 100
+        assert_close(provider.get_gas_prices().eth_l1_data_gas_price, 120.0);
+    }
+
+    #[test]
+    fn gas_price_smoothing_data_gas_is_separate() {
+        let mut provider = GasPriceProvider::new();
+        provider.set_smoothing(GasPriceSmoothing { alpha: 0.5, window: 10 });
+        provider.set_data_gas_smoothing(GasPriceSmoothing::DISABLED);
+        for price in [100, 200] {
+            provider.update_eth_l1_gas_price(price);
+            provider.update_eth_l1_data_gas_price(price);
+            provider.update_strk_l1_data_gas_price(price);
+        }
+
+        let prices = provider.get_gas_prices();
+        assert_close(prices.eth_l1_gas_price, 150.0);
+        assert_eq!(prices.eth_l1_data_gas_price, 200);
+        assert_eq!(prices.strk_l1_data_gas_price, 200);
+    }
+
+    #[test]
+    fn gas_price_smoothing_prices_are_independent() {
+        let provider = provider(0.5, 10);
+        provider.set_gas_prices(GasPrices {
+            eth_l1_gas_price: 100,
+            strk_l1_gas_price: 200,
+            eth_l1_data_gas_price: 300,
+            strk_l1_data_gas_price: 400,
+        });
+        provider.update_strk_l1_gas_price(400);
+
+        let prices = provider.get_gas_prices();
+        assert_eq!(prices.eth_l1_gas_price, 100);
+        assert_close(prices.strk_l1_gas_price, 300.0);
+        assert_eq!(prices.eth_l1_data_gas_price, 300);
+        assert_eq!(prices.strk_l1_data_gas_price, 400);
+        assert_eq!(provider.get_raw_gas_prices().strk_l1_gas_price, 400);
+    }
+
+    #[test]
+    fn gas_price_multiplier() {
+        let mut provider = GasPriceProvider::new();
+        provider.set_gas_price_multiplier(1.1).set_data_gas_price_multiplier(1.5);
+        provider.set_gas_prices(GasPrices {
+            eth_l1_gas_price: 100,
+            strk_l1_gas_price: 200,
+            eth_l1_data_gas_price: 300,
+            strk_l1_data_gas_price: 400,
+        });
+
+        assert_eq!(
+            provider.get_gas_prices(),
+            GasPrices {
+                eth_l1_gas_price: 110,
+                strk_l1_gas_price: 220,
+                eth_l1_data_gas_price: 450,
+                strk_l1_data_gas_price: 600,
+            }
+        );
+        assert_eq!(
+            provider.get_raw_gas_prices(),
+            GasPrices {
+                eth_l1_gas_price: 100,
+                strk_l1_gas_price: 200,
+                eth_l1_data_gas_price: 300,
+                strk_l1_data_gas_price: 400,
+            }
+        );
+    }
+
+    #[test]
+    fn gas_price_multiplier_applies_to_the_smoothed_price() {
+        let mut provider = provider(0.5, 10);
+        provider.set_gas_price_multiplier(2.0);
+        for price in [100, 200] {
+            provider.update_eth_l1_gas_price(price);
+        }
+        assert_close(provider.get_gas_prices().eth_l1_gas_price, 300.0);
+        assert_eq!(provider.get_raw_gas_prices().eth_l1_gas_price, 200);
+    }
+
+    #[test]
+    fn gas_price_poll_interval() {
+        let provider = GasPriceProvider::new();
+        let worker_view = provider.clone();
+        assert_eq!(provider.poll_interval(), DEFAULT_GAS_PRICE_POLL_INTERVAL);
+
+        let previous = provider.set_poll_interval(Duration::from_secs(2)).unwrap();
+        assert_eq!(previous, DEFAULT_GAS_PRICE_POLL_INTERVAL);
+        // Clones share the interval.
+        assert_eq!(worker_view.poll_interval(), Duration::from_secs(2));
+
+        assert!(provider.set_poll_interval(MIN_GAS_PRICE_POLL_INTERVAL).is_ok());
+        assert!(provider.set_poll_interval(MAX_GAS_PRICE_POLL_INTERVAL).is_ok());
+    }
+
+    #[test]
+    fn gas_price_poll_interval_out_of_bounds() {
+        let provider = GasPriceProvider::new();
+        for interval in
+            [Duration::ZERO, Duration::from_millis(99), MAX_GAS_PRICE_POLL_INTERVAL + Duration::from_secs(1)]
+        {
+            assert_eq!(provider.set_poll_interval(interval), Err(InvalidGasPricePollInterval { interval }));
+        }
+        assert_eq!(provider.poll_interval(), DEFAULT_GAS_PRICE_POLL_INTERVAL);
+    }
+}
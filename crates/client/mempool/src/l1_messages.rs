@@ -0,0 +1,50 @@
+//! Accounting of the L1->L2 messages injected into the mempool as L1 handler transactions, for auditing. L1 handler
+//! transactions bypass the mempool limits, a message processed twice would be executed twice.
+
+use mc_db::l1_db::L1MessageOrigin;
+use starknet_api::core::Nonce;
+use starknet_types_core::felt::Felt;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Number of duplicate L1->L2 messages kept for auditing, the oldest ones are forgotten first.
+const MAX_DUPLICATE_L1_MESSAGES: usize = 1024;
+
+/// An L1->L2 message whose L1 handler transaction is waiting in the mempool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InFlightL1Message {
+    pub nonce: Nonce,
+    pub tx_hash: Felt,
+    /// The L1 block the message was consumed from, unknown for the messages consumed before origins were tracked.
+    pub origin: Option<L1MessageOrigin>,
+}
+
+/// An L1->L2 message received again after its nonce was consumed. It was not inserted into the mempool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateL1Message {
+    pub nonce: Nonce,
+    pub tx_hash: Felt,
+    /// The L1 block the duplicate comes from.
+    pub origin: L1MessageOrigin,
+    /// The L1 block the nonce was first consumed from, if known.
+    pub consumed_from: Option<L1MessageOrigin>,
+}
+
+/// The most recent [`DuplicateL1Message`]s.
+#[derive(Default)]
+pub(crate) struct DuplicateL1Messages(Mutex<VecDeque<DuplicateL1Message>>);
+
+impl DuplicateL1Messages {
+    pub fn record(&self, message: DuplicateL1Message) {
+        let mut messages = self.0.lock().expect("Poisoned lock");
+        if messages.len() >= MAX_DUPLICATE_L1_MESSAGES {
+            messages.pop_front();
+        }
+        messages.push_back(message);
+    }
+
+    /// The duplicates, oldest first.
+    pub fn snapshot(&self) -> Vec<DuplicateL1Message> {
+        self.0.lock().expect("Poisoned lock").iter().cloned().collect()
+    }
+}
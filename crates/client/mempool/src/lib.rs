@@ -2,15 +2,21 @@ use anyhow::Context;
 use blockifier::blockifier::stateful_validator::StatefulValidatorError;
 use blockifier::transaction::account_transaction::AccountTransaction;
 use blockifier::transaction::transaction_execution::Transaction;
+use blockifier::transaction::transaction_types::TransactionType;
 use blockifier::transaction::transactions::{
     DeclareTransaction, DeployAccountTransaction, InvokeTransaction, L1HandlerTransaction as BL1HandlerTransaction,
 };
 use header::make_pending_header;
+use l1_messages::DuplicateL1Messages;
 use mc_db::db_block_id::DbBlockId;
+use mc_db::l1_db::L1MessageOrigin;
 use mc_db::{MadaraBackend, MadaraStorageError};
 use mc_exec::ExecutionContext;
-use metrics::MempoolMetrics;
+#[cfg(feature = "metrics")]
+use metrics::{arrival_latency, MempoolMetrics};
+use mp_block::header::GasPrices;
 use mp_block::{BlockId, BlockTag, MadaraPendingBlockInfo};
+use mp_chain_config::StarknetVersion;
 use mp_class::ConvertedClass;
 use mp_convert::ToFelt;
 use mp_transactions::BroadcastedDeclareTransactionV0;
@@ -25,22 +31,33 @@ use starknet_types_rpc::{
     AddInvokeTransactionResult, BroadcastedDeclareTxn, BroadcastedDeployAccountTxn, BroadcastedInvokeTxn,
     BroadcastedTxn, ClassAndTxnHash, ContractAndTxnHash,
 };
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 use std::time::SystemTime;
+use tokio::sync::broadcast;
 use tx::blockifier_to_saved_tx;
 use tx::saved_to_blockifier_tx;
 
 #[cfg(any(test, feature = "testing"))]
 pub use l1::MockL1DataProvider;
-pub use l1::{GasPriceProvider, L1DataProvider};
+pub use l1::{
+    GasPriceBounds, GasPriceProvider, GasPriceSmoothing, InvalidGasPricePollInterval, L1DataProvider, L1GasPriceSource,
+    DEFAULT_GAS_PRICE_POLL_INTERVAL, MAX_GAS_PRICE_POLL_INTERVAL, MIN_GAS_PRICE_POLL_INTERVAL,
+};
 
 pub mod header;
 mod inner;
 mod l1;
+mod l1_messages;
+#[cfg(feature = "metrics")]
 pub mod metrics;
+mod snapshot;
+pub mod sweeper;
 mod tx;
 
 pub use inner::*;
+pub use l1_messages::{DuplicateL1Message, InFlightL1Message};
+pub use snapshot::{MempoolSnapshot, MempoolSnapshotTransaction};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -61,23 +78,48 @@ impl Error {
     }
 }
 
+/// A transaction accepted by the mempool, along with what its insertion did to the mempool.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Accepted<T> {
+    pub result: T,
+    pub outcome: InsertOutcome,
+    /// The mempool is above its [`MempoolLimits::near_capacity_watermark`] once the transaction is inserted. This is a
+    /// hint for clients to slow down.
+    pub near_capacity: bool,
+}
+
 #[cfg_attr(test, mockall::automock)]
 pub trait MempoolProvider: Send + Sync {
-    fn accept_invoke_tx(&self, tx: BroadcastedInvokeTxn<Felt>) -> Result<AddInvokeTransactionResult<Felt>, Error>;
-    fn accept_declare_v0_tx(&self, tx: BroadcastedDeclareTransactionV0) -> Result<ClassAndTxnHash<Felt>, Error>;
-    fn accept_declare_tx(&self, tx: BroadcastedDeclareTxn<Felt>) -> Result<ClassAndTxnHash<Felt>, Error>;
+    fn accept_invoke_tx(
+        &self,
+        tx: BroadcastedInvokeTxn<Felt>,
+    ) -> Result<Accepted<AddInvokeTransactionResult<Felt>>, Error>;
+    fn accept_declare_v0_tx(
+        &self,
+        tx: BroadcastedDeclareTransactionV0,
+    ) -> Result<Accepted<ClassAndTxnHash<Felt>>, Error>;
+    fn accept_declare_tx(&self, tx: BroadcastedDeclareTxn<Felt>) -> Result<Accepted<ClassAndTxnHash<Felt>>, Error>;
     fn accept_deploy_account_tx(
         &self,
         tx: BroadcastedDeployAccountTxn<Felt>,
-    ) -> Result<ContractAndTxnHash<Felt>, Error>;
+    ) -> Result<Accepted<ContractAndTxnHash<Felt>>, Error>;
     fn accept_l1_handler_tx(
         &self,
         tx: L1HandlerTransaction,
         paid_fees_on_l1: u128,
-    ) -> Result<L1HandlerTransactionResult, Error>;
+    ) -> Result<Accepted<L1HandlerTransactionResult>, Error>;
     fn take_txs_chunk<I: Extend<MempoolTransaction> + 'static>(&self, dest: &mut I, n: usize)
     where
         Self: Sized;
+    fn take_priced_txs_chunk<I: Extend<MempoolTransaction> + 'static>(
+        &self,
+        dest: &mut I,
+        n: usize,
+        gas_prices: &GasPrices,
+    ) -> Result<usize, Error>
+    where
+        Self: Sized;
+    fn record_block_fullness(&self, full: bool) -> usize;
     fn take_tx(&self) -> Option<MempoolTransaction>;
     fn re_add_txs<
         I: IntoIterator<Item = MempoolTransaction> + 'static,
@@ -91,46 +133,617 @@ pub trait MempoolProvider: Send + Sync {
     fn chain_id(&self) -> Felt;
 }
 
+/// A mempool transaction, as listed by [`Mempool::transactions_snapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MempoolTransactionInfo {
+    pub tx_hash: Felt,
+    pub contract_address: Felt,
+    pub nonce: Felt,
+    pub tip: u64,
+    pub arrived_at: ArrivedAtTimestamp,
+}
+
+impl From<&MempoolTransaction> for MempoolTransactionInfo {
+    fn from(tx: &MempoolTransaction) -> Self {
+        Self {
+            tx_hash: tx.tx_hash().to_felt(),
+            contract_address: tx.contract_address().to_felt(),
+            nonce: tx.nonce().to_felt(),
+            tip: tx.tip(),
+            arrived_at: tx.arrived_at,
+        }
+    }
+}
+
+/// Capacity of the mempool events channel. Slow subscribers will miss events past this.
+const EVENTS_CHANNEL_CAPACITY: usize = 1024;
+
 pub struct Mempool {
     backend: Arc<MadaraBackend>,
     l1_data_provider: Arc<dyn L1DataProvider>,
     inner: RwLock<MempoolInner>,
+    #[cfg(feature = "metrics")]
     metrics: MempoolMetrics,
+    clock: Arc<dyn Clock>,
+    events: broadcast::Sender<MempoolEvent>,
+    duplicate_l1_messages: DuplicateL1Messages,
 }
 
 impl Mempool {
     pub fn new(backend: Arc<MadaraBackend>, l1_data_provider: Arc<dyn L1DataProvider>, limits: MempoolLimits) -> Self {
+        let events = broadcast::channel(EVENTS_CHANNEL_CAPACITY).0;
+        let inner = MempoolInner::new(limits)
+            .with_ordering(backend.chain_config().mempool_ordering)
+            .with_events(events.clone());
+        #[cfg(feature = "metrics")]
+        let metrics = MempoolMetrics::register();
+        #[cfg(feature = "metrics")]
+        let inner = inner.with_metrics(metrics.clone());
         Mempool {
             backend,
             l1_data_provider,
-            inner: RwLock::new(MempoolInner::new(limits)),
-            metrics: MempoolMetrics::register(),
+            inner: RwLock::new(inner),
+            #[cfg(feature = "metrics")]
+            metrics,
+            clock: Arc::new(SystemClock),
+            events,
+            duplicate_l1_messages: Default::default(),
         }
     }
 
+    /// Read the current time from this clock instead of [`SystemClock`], for the arrival timestamps of the
+    /// transactions and their age checks.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.inner = RwLock::new(self.inner.into_inner().expect("Poisoned lock").with_clock(clock.clone()));
+        self.clock = clock;
+        self
+    }
+
+    /// Consult this policy instead of [`AdmitAll`] before inserting a submitted transaction, once the built-in limits
+    /// have accepted it.
+    pub fn with_admission_policy(mut self, admission_policy: Arc<dyn AdmissionPolicy>) -> Self {
+        let inner = self.inner.into_inner().expect("Poisoned lock");
+        self.inner = RwLock::new(inner.with_admission_policy(admission_policy));
+        self
+    }
+
+    /// Subscribe to the transactions added to and removed from the mempool. A subscriber which falls behind by more
+    /// than the channel capacity gets a [`broadcast::error::RecvError::Lagged`] error, and misses the oldest events.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<MempoolEvent> {
+        self.events.subscribe()
+    }
+
+    /// Lists up to `limit` mempool transactions ordered by hash, starting after the `after` hash. The lock is only held
+    /// while copying the transaction infos; sorting and pagination happen once it is released.
+    pub fn transactions_snapshot(&self, after: Option<Felt>, limit: usize) -> Vec<MempoolTransactionInfo> {
+        let mut txs: Vec<MempoolTransactionInfo> = {
+            let inner = self.inner.read().expect("Poisoned lock");
+            inner
+                .transactions()
+                .filter(|tx| after.map_or(true, |after| tx.tx_hash().to_felt() > after))
+                .map(MempoolTransactionInfo::from)
+                .collect()
+        };
+        txs.sort_unstable_by_key(|tx| tx.tx_hash);
+        txs.truncate(limit);
+        txs
+    }
+
+    /// The nonces and hashes of the ready and pending transactions of `sender`, ordered by nonce. Wallets use it to
+    /// rebuild the chain of nonces an account has waiting in the mempool.
+    pub fn get_by_sender(&self, sender: ContractAddress) -> Vec<(Nonce, TransactionHash)> {
+        let inner = self.inner.read().expect("Poisoned lock");
+        inner.transactions_of(sender).map(|tx| (tx.nonce(), tx.tx_hash())).collect()
+    }
+
+    /// Number of transactions of `tx_type` currently in the mempool, and inserted and removed since it was created.
+    pub fn type_counters(&self, tx_type: TransactionType) -> TransactionTypeCounters {
+        self.inner.read().expect("Poisoned lock").type_counters(tx_type)
+    }
+
+    /// Number of transactions dropped for this `reason` since the mempool was created.
+    pub fn dropped_transactions(&self, reason: DropReason) -> u64 {
+        self.inner.read().expect("Poisoned lock").dropped_transactions(reason)
+    }
+
+    /// Why the transaction with this hash was dropped from the mempool, if it was dropped recently. See
+    /// [`MempoolLimiter::drop_reason`].
+    pub fn drop_reason(&self, tx_hash: Felt) -> Option<DropReason> {
+        self.inner.read().expect("Poisoned lock").drop_reason(&tx_hash)
+    }
+
+    /// Transactions per second accepted in and popped from the mempool, over a sliding window.
+    pub fn throughput(&self) -> MempoolThroughput {
+        self.inner.read().expect("Poisoned lock").throughput()
+    }
+
+    /// Checks that the transaction counter of the mempool limits matches the transactions in the mempool, see
+    /// [`MempoolLimiter::check_counter_consistency`].
+    pub fn check_counter_consistency(&self) -> Option<CounterDivergence> {
+        self.inner.write().expect("Poisoned lock").check_counter_consistency()
+    }
+
+    /// Changes the mempool limits at runtime, and returns the previous ones. Lowering a limit below the current occupancy
+    /// does not evict anything: new transactions are rejected until the mempool drains.
+    pub fn update_limits(&self, update: MempoolLimitsUpdate) -> Result<MempoolLimitsUpdate, InvalidMempoolLimits> {
+        let previous = self.inner.write().expect("Poisoned lock").update_limits(update)?;
+        tracing::info!("Mempool limits updated from {previous:?} with {update:?}");
+        Ok(previous)
+    }
+
+    /// Removes the transactions past the max age or their deadline from the mempool and from the db. Returns the number
+    /// of removed txs.
+    #[tracing::instrument(skip(self), fields(module = "Mempool"))]
+    pub fn remove_age_exceeded_txs(&self) -> Result<usize, Error> {
+        let removed = self.inner.write().expect("Poisoned lock").remove_age_exceeded_txs();
+
+        for tx in &removed {
+            let tx_hash = tx.tx_hash().to_felt();
+            tracing::debug!("Removing expired tx_hash={:#x}", tx_hash);
+            self.backend.remove_mempool_transaction(&tx_hash)?;
+        }
+
+        Ok(removed.len())
+    }
+
+    /// Removes the L1 handler transactions of the L1 messages with these nonces from the mempool and from the db,
+    /// after an L1 reorg. Returns the hashes of the removed txs.
+    #[tracing::instrument(skip(self), fields(module = "Mempool"))]
+    pub fn remove_l1_handler_txs(&self, l1_message_nonces: &HashSet<Nonce>) -> Result<Vec<Felt>, Error> {
+        let removed = self.inner.write().expect("Poisoned lock").remove_l1_handler_txs(l1_message_nonces);
+
+        let mut removed_hashes = Vec::with_capacity(removed.len());
+        for tx in &removed {
+            let tx_hash = tx.tx_hash().to_felt();
+            tracing::debug!("Removing reorged L1 handler tx_hash={:#x}", tx_hash);
+            self.backend.remove_mempool_transaction(&tx_hash)?;
+            removed_hashes.push(tx_hash);
+        }
+
+        Ok(removed_hashes)
+    }
+
+    /// Removes the transaction with this hash from the mempool and from the db, for when it is causing issues. See
+    /// [`MempoolInner::remove_tx_by_hash`]. Returns whether the transaction was in the mempool.
+    #[tracing::instrument(skip(self), fields(module = "Mempool"))]
+    pub fn remove_by_hash(&self, tx_hash: Felt) -> Result<bool, Error> {
+        let Some(removed) = self.inner.write().expect("Poisoned lock").remove_tx_by_hash(tx_hash) else {
+            return Ok(false);
+        };
+        tracing::info!("Removed tx_hash={:#x} from the mempool, nonce={:#x}", tx_hash, removed.nonce().0);
+        self.backend.remove_mempool_transaction(&tx_hash)?;
+        Ok(true)
+    }
+
+    /// Clones of the first `n` ready transactions, in the order block production would take them with
+    /// [`MempoolProvider::take_txs_chunk`], without taking them. Nothing is changed in the mempool, so that block
+    /// production can inspect them before committing to popping them. See [`MempoolInner::peek_ready`].
+    pub fn peek_ready(&self, n: usize) -> Vec<MempoolTransaction> {
+        self.inner.read().expect("Poisoned lock").peek_ready(n).into_iter().cloned().collect()
+    }
+
+    /// The transactions [`MempoolProvider::take_priced_txs_chunk`] would take for the next block, in order, without
+    /// taking them. Up to `n` transactions are returned; the underpriced ones are left out. The gas prices are read from
+    /// the L1 data provider, which is where block production gets the gas prices it passes to
+    /// [`MempoolProvider::take_priced_txs_chunk`].
+    pub fn preview_next_block(&self, n: usize) -> Vec<MempoolTransactionInfo> {
+        let l1_gas_price = self.l1_data_provider.get_gas_prices().strk_l1_gas_price;
+        let inner = self.inner.read().expect("Poisoned lock");
+        inner.peek_next_chunk_priced(n, l1_gas_price).into_iter().map(MempoolTransactionInfo::from).collect()
+    }
+
+    /// Removes the expired transactions skipped while popping from the db. Popping does not fail on a db error, it is
+    /// logged instead: the transactions are dropped again when the mempool is loaded from the db.
+    fn remove_popped_expired_from_db(&self, expired: &[MempoolTransaction]) {
+        for tx in expired {
+            let tx_hash = tx.tx_hash().to_felt();
+            tracing::debug!("Removing expired tx_hash={:#x}", tx_hash);
+            if let Err(err) = self.backend.remove_mempool_transaction(&tx_hash) {
+                tracing::warn!("Could not remove expired mempool transaction tx_hash={:#x} from db: {err:#}", tx_hash);
+            }
+        }
+    }
+
+    /// Records how long the transactions popped for block production waited in the mempool. A transaction which is
+    /// re-added after a block production batch is recorded again when it is popped again.
+    #[cfg(feature = "metrics")]
+    fn record_arrival_latency<'a>(&self, txs: impl IntoIterator<Item = &'a MempoolTransaction>) {
+        let now = self.clock.now();
+        for tx in txs {
+            self.metrics.record_arrival_latency(arrival_latency(tx, now));
+        }
+    }
+
+    /// Inserts the L1 handler transaction of an L1->L2 message from the L1 block `origin`, and marks its nonce as
+    /// consumed. A message whose nonce was already consumed is not inserted again: it is flagged as a duplicate, see
+    /// [`Mempool::duplicate_l1_messages`], and `None` is returned.
+    #[tracing::instrument(skip(self, tx), fields(module = "Mempool"))]
+    pub fn accept_l1_message(
+        &self,
+        tx: L1HandlerTransaction,
+        paid_fees_on_l1: u128,
+        origin: L1MessageOrigin,
+    ) -> Result<Option<Accepted<L1HandlerTransactionResult>>, Error> {
+        let nonce = Nonce(Felt::from(tx.nonce));
+        let (btx, class) =
+            tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version, paid_fees_on_l1)?;
+        let tx_hash = transaction_hash(&btx);
+
+        if self.backend.has_l1_messaging_nonce(nonce)? {
+            tracing::warn!("L1 message with nonce {:#x} was already consumed, tx_hash={:#x}", nonce.0, tx_hash);
+            let consumed_from = self.backend.get_l1_messaging_origin(nonce)?;
+            self.duplicate_l1_messages.record(DuplicateL1Message { nonce, tx_hash, origin, consumed_from });
+            return Ok(None);
+        }
+        self.backend.set_l1_messaging_nonce(nonce, origin)?;
+
+        let res = L1HandlerTransactionResult { transaction_hash: tx_hash };
+        let outcome = self.accept_tx(btx, class, self.clock.now())?;
+        Ok(Some(self.accepted(res, outcome)))
+    }
+
+    /// The L1->L2 messages whose L1 handler transactions are in the mempool, ordered by nonce.
+    pub fn in_flight_l1_messages(&self) -> Result<Vec<InFlightL1Message>, Error> {
+        let mut txs: Vec<(Nonce, Felt)> = {
+            let inner = self.inner.read().expect("Poisoned lock");
+            inner
+                .transactions()
+                .filter(|tx| matches!(tx.tx, Transaction::L1HandlerTransaction(_)))
+                .map(|tx| (tx.nonce(), tx.tx_hash().to_felt()))
+                .collect()
+        };
+        txs.sort_unstable();
+        txs.into_iter()
+            .map(|(nonce, tx_hash)| {
+                Ok(InFlightL1Message { nonce, tx_hash, origin: self.backend.get_l1_messaging_origin(nonce)? })
+            })
+            .collect()
+    }
+
+    /// The most recent L1->L2 messages received after their nonce was consumed, oldest first.
+    pub fn duplicate_l1_messages(&self) -> Vec<DuplicateL1Message> {
+        self.duplicate_l1_messages.snapshot()
+    }
+
+    /// Exports the ready and pending transactions, along with the mempool counters. Nothing is removed from the mempool.
+    pub fn export_snapshot(&self) -> MempoolSnapshot {
+        let (mut transactions, counters) = {
+            let inner = self.inner.read().expect("Poisoned lock");
+            let transactions: Vec<_> = inner.transactions().map(MempoolSnapshotTransaction::from).collect();
+            (transactions, inner.counters())
+        };
+        transactions.sort_unstable_by_key(|tx| tx.tx_hash);
+        MempoolSnapshot { transactions, counters }
+    }
+
+    /// Imports the transactions of a snapshot taken with [`Mempool::export_snapshot`]. They are not validated again,
+    /// but they go through the mempool limits: the transactions that are now too old, or that do not fit, are dropped.
+    /// The imported transactions are saved to the db when mempool persistence is enabled.
+    ///
+    /// Returns the number of imported transactions.
+    pub fn import_snapshot(&self, snapshot: MempoolSnapshot) -> Result<usize, anyhow::Error> {
+        let (mut imported, mut dropped) = (0usize, 0usize);
+
+        for MempoolSnapshotTransaction { tx_hash, tx: saved_tx, converted_class } in snapshot.transactions {
+            let (tx, arrived_at, deadline) = saved_to_blockifier_tx(saved_tx, tx_hash, &converted_class)
+                .context("Converting snapshot tx to blockifier")?;
+            if let Err(err) = self.check_chain_id(&tx) {
+                tracing::warn!("Could not import snapshot transaction tx_hash={:#x}: {err:#}", tx_hash);
+                dropped += 1;
+                continue;
+            }
+            let account_nonce = self.account_nonce(&tx)?;
+            if self.persistence_enabled() {
+                let saved_tx = blockifier_to_saved_tx(&tx, arrived_at, deadline);
+                self.backend.save_mempool_transaction(&saved_tx, tx_hash, &converted_class)?;
+            }
+
+            let force = false;
+            let res = self.inner.write().expect("Poisoned lock").insert_tx(
+                MempoolTransaction { deadline, ..MempoolTransaction::new(tx, arrived_at, converted_class) },
+                force,
+                account_nonce,
+            );
+            match res {
+                Ok(InsertOutcome::Replaced(removed_hash) | InsertOutcome::EvictedToFit(removed_hash)) => {
+                    self.backend.remove_mempool_transaction(&removed_hash)?;
+                    imported += 1;
+                }
+                Ok(InsertOutcome::Added) => imported += 1,
+                Ok(InsertOutcome::AlreadyKnown | InsertOutcome::Refreshed) => {}
+                Err(err) => {
+                    match err {
+                        TxInsersionError::Limit(
+                            MempoolLimitReached::Age { .. } | MempoolLimitReached::DeadlinePassed { .. },
+                        ) => {
+                            tracing::debug!("Dropping expired snapshot transaction tx_hash={:#x}", tx_hash)
+                        }
+                        err => tracing::warn!("Could not import snapshot transaction tx_hash={:#x}: {err:#}", tx_hash),
+                    }
+                    if self.persistence_enabled() {
+                        self.backend.remove_mempool_transaction(&tx_hash)?;
+                    }
+                    dropped += 1;
+                }
+            }
+        }
+
+        tracing::info!("Imported {imported} mempool transactions from a snapshot, dropped {dropped}");
+        Ok(imported)
+    }
+
+    fn persistence_enabled(&self) -> bool {
+        self.backend.chain_config().mempool_persistence_enabled
+    }
+
+    /// Restores the transactions saved in the db. They go through validation and the mempool limits again: the
+    /// transactions that are now too old or otherwise rejected are dropped from the db.
+    ///
+    /// When mempool persistence is disabled, the transactions saved by a previous run are discarded.
     pub fn load_txs_from_db(&mut self) -> Result<(), anyhow::Error> {
-        for res in self.backend.get_mempool_transactions() {
-            let (tx_hash, saved_tx, converted_class) = res.context("Getting mempool transactions")?;
-            let (tx, arrived_at) = saved_to_blockifier_tx(saved_tx, tx_hash, &converted_class)
+        let saved_txs = self.backend.get_mempool_transactions().collect::<Result<Vec<_>, _>>();
+        let saved_txs = saved_txs.context("Getting mempool transactions")?;
+        let (mut restored, mut dropped) = (0usize, 0usize);
+
+        for (tx_hash, saved_tx, converted_class) in saved_txs {
+            if !self.persistence_enabled() {
+                self.backend.remove_mempool_transaction(&tx_hash).context("Removing mempool transaction")?;
+                dropped += 1;
+                continue;
+            }
+
+            let (tx, arrived_at, deadline) = saved_to_blockifier_tx(saved_tx, tx_hash, &converted_class)
                 .context("Converting saved tx to blockifier")?;
 
-            if let Err(err) = self.accept_tx(tx, converted_class, arrived_at) {
-                match err {
-                    Error::InnerMempool(TxInsersionError::Limit(MempoolLimitReached::Age { .. })) => {} // do nothing
-                    err => tracing::warn!("Could not re-add mempool transaction from db: {err:#}"),
+            match self.accept_tx_with(tx, converted_class, arrived_at, deadline, 0) {
+                Ok(_) => restored += 1,
+                Err(err) => {
+                    match err {
+                        Error::InnerMempool(TxInsersionError::Limit(
+                            MempoolLimitReached::Age { .. } | MempoolLimitReached::DeadlinePassed { .. },
+                        )) => {
+                            tracing::debug!("Dropping expired mempool transaction tx_hash={:#x}", tx_hash)
+                        }
+                        err => tracing::warn!("Could not re-add mempool transaction from db: {err:#}"),
+                    }
+                    self.backend.remove_mempool_transaction(&tx_hash).context("Removing mempool transaction")?;
+                    dropped += 1;
                 }
             }
         }
+
+        if restored + dropped > 0 {
+            tracing::info!("🗃️ Restored {restored} mempool transactions from the database, dropped {dropped}");
+        }
         Ok(())
     }
 
+    /// Dry run of the insertion of a transaction: it goes through the same validation, nonce, fee and limit checks as
+    /// an insertion, but neither the mempool, the db nor the metrics are modified. Returns the transaction hash and what
+    /// inserting it would do.
     #[tracing::instrument(skip(self), fields(module = "Mempool"))]
+    pub fn dry_run_tx(&self, tx: BroadcastedTxn<Felt>) -> Result<Accepted<Felt>, Error> {
+        let (tx, converted_class) =
+            tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?;
+        let tx_hash = transaction_hash(&tx);
+
+        self.perform_validations(&tx)?;
+        let account_nonce = self.account_nonce(&tx)?;
+
+        let mempool_tx = MempoolTransaction::new(tx, self.clock.now(), converted_class);
+        self.update_min_tip();
+        let outcome = self.inner.read().expect("Poisoned lock").check_insert_tx(mempool_tx, account_nonce)?;
+        Ok(self.accepted(tx_hash, outcome))
+    }
+
+    /// Inserts the transactions one after the other, as if each was submitted on its own: the limits apply to the
+    /// transactions accepted earlier in the batch, and a rejected transaction does not stop the following ones. Returns
+    /// the transaction hash and insertion outcome, or the error, of each transaction in order.
+    #[tracing::instrument(skip(self, txs), fields(module = "Mempool"))]
+    pub fn accept_tx_batch(&self, txs: Vec<BroadcastedTxn<Felt>>) -> Vec<Result<Accepted<Felt>, Error>> {
+        txs.into_iter().map(|tx| self.accept_broadcasted_tx(tx)).collect()
+    }
+
+    /// Inserts a transaction of any type. Returns the transaction hash and insertion outcome.
+    #[tracing::instrument(skip(self, tx), fields(module = "Mempool"))]
+    pub fn accept_broadcasted_tx(&self, tx: BroadcastedTxn<Felt>) -> Result<Accepted<Felt>, Error> {
+        let (btx, class) = self.convert_broadcasted_tx(tx)?;
+        self.accept_converted_tx(btx, class)
+    }
+
+    /// Converts a transaction the way it is converted on insertion, so that its hash is known before it is inserted
+    /// with [`Mempool::accept_converted_tx`].
+    pub fn convert_broadcasted_tx(
+        &self,
+        tx: BroadcastedTxn<Felt>,
+    ) -> Result<(Transaction, Option<ConvertedClass>), Error> {
+        Ok(tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?)
+    }
+
+    /// Inserts a transaction converted with [`Mempool::convert_broadcasted_tx`]. Returns the transaction hash and
+    /// insertion outcome.
+    #[tracing::instrument(skip(self, tx, converted_class), fields(module = "Mempool"))]
+    pub fn accept_converted_tx(
+        &self,
+        tx: Transaction,
+        converted_class: Option<ConvertedClass>,
+    ) -> Result<Accepted<Felt>, Error> {
+        let tx_hash = transaction_hash(&tx);
+        let outcome = self.accept_tx(tx, converted_class, self.clock.now())?;
+        Ok(self.accepted(tx_hash, outcome))
+    }
+
+    /// Inserts a transaction which is dropped if it has not been included by `deadline`, independently of the mempool
+    /// max age. A deadline which has already passed is rejected.
+    ///
+    /// The deadline is saved with the transaction: a transaction restored from the db after a restart keeps it.
+    #[tracing::instrument(skip(self, tx), fields(module = "Mempool"))]
+    pub fn accept_tx_until(&self, tx: BroadcastedTxn<Felt>, deadline: SystemTime) -> Result<Accepted<Felt>, Error> {
+        let (btx, class) = tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?;
+        let tx_hash = transaction_hash(&btx);
+        let outcome = self.accept_tx_with(btx, class, self.clock.now(), Some(deadline), 0)?;
+        Ok(self.accepted(tx_hash, outcome))
+    }
+
+    /// Inserts a transaction with a [`MempoolTransaction::priority_hint`], which orders it among the transactions with
+    /// the same tip. A hint above [`MAX_PRIORITY_HINT`] is rejected.
+    ///
+    /// Unlike the deadline, the hint is not saved with the transaction.
+    #[tracing::instrument(skip(self, tx), fields(module = "Mempool"))]
+    pub fn accept_tx_with_priority_hint(
+        &self,
+        tx: BroadcastedTxn<Felt>,
+        priority_hint: u8,
+    ) -> Result<Accepted<Felt>, Error> {
+        if priority_hint > MAX_PRIORITY_HINT {
+            return Err(TxInsersionError::InvalidPriorityHint { hint: priority_hint, max: MAX_PRIORITY_HINT }.into());
+        }
+        let (btx, class) = tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?;
+        let tx_hash = transaction_hash(&btx);
+        let outcome = self.accept_tx_with(btx, class, self.clock.now(), None, priority_hint)?;
+        Ok(self.accepted(tx_hash, outcome))
+    }
+
+    fn accepted<T>(&self, result: T, outcome: InsertOutcome) -> Accepted<T> {
+        Accepted { result, outcome, near_capacity: self.inner.read().expect("Poisoned lock").is_near_capacity() }
+    }
+
+    /// Query-only transactions are validated but never inserted, they are reported as [`InsertOutcome::Added`].
     fn accept_tx(
         &self,
         tx: Transaction,
         converted_class: Option<ConvertedClass>,
         arrived_at: SystemTime,
-    ) -> Result<(), Error> {
+    ) -> Result<InsertOutcome, Error> {
+        self.accept_tx_with(tx, converted_class, arrived_at, None, 0)
+    }
+
+    #[tracing::instrument(skip(self), fields(module = "Mempool"))]
+    fn accept_tx_with(
+        &self,
+        tx: Transaction,
+        converted_class: Option<ConvertedClass>,
+        arrived_at: SystemTime,
+        deadline: Option<SystemTime>,
+        priority_hint: u8,
+    ) -> Result<InsertOutcome, Error> {
+        self.check_class_declared(&tx)?;
+        self.perform_validations(&tx)?;
+
+        if is_only_query(&tx) {
+            return Ok(InsertOutcome::Added);
+        }
+
+        self.check_chain_id(&tx)?;
+        let tx_hash = tx_hash(&tx).to_felt();
+        tracing::debug!("Adding to inner mempool tx_hash={:#x}", tx_hash);
+        // Saved to the db once it is inserted.
+        let saved_tx = self
+            .persistence_enabled()
+            .then(|| (blockifier_to_saved_tx(&tx, arrived_at, deadline), converted_class.clone()));
+
+        let account_nonce = self.account_nonce(&tx)?;
+
+        // Add it to the inner mempool
+        self.update_min_tip();
+        let force = false;
+        let res = self.inner.write().expect("Poisoned lock").insert_tx(
+            MempoolTransaction { deadline, priority_hint, ..MempoolTransaction::new(tx, arrived_at, converted_class) },
+            force,
+            account_nonce,
+        );
+        let outcome = res?;
+
+        // The transaction was already saved, and it is not counted twice. The saved one is kept as is: it keeps its
+        // original arrival time and deadline.
+        if let InsertOutcome::AlreadyKnown | InsertOutcome::Refreshed = outcome {
+            return Ok(outcome);
+        }
+        // Add to db
+        if let Some((saved_tx, converted_class)) = saved_tx {
+            if let Err(err) = self.backend.save_mempool_transaction(&saved_tx, tx_hash, &converted_class) {
+                // It would not be restored after a restart.
+                self.inner.write().expect("Poisoned lock").remove_tx_by_hash(tx_hash);
+                return Err(err.into());
+            }
+        }
+        if let InsertOutcome::Replaced(removed_hash) | InsertOutcome::EvictedToFit(removed_hash) = outcome {
+            tracing::debug!("Removing tx_hash={:#x} replaced or evicted by tx_hash={:#x}", removed_hash, tx_hash);
+            self.backend.remove_mempool_transaction(&removed_hash)?;
+        }
+
+        #[cfg(feature = "metrics")]
+        self.metrics.accepted_transaction_counter.add(1, &[]);
+        Ok(outcome)
+    }
+
+    /// Rejects the transactions of another chain, which could otherwise be replayed on this one. A transaction does not
+    /// carry its chain id, but its hash commits to it: the hash is checked against the one computed with the chain id
+    /// of this chain. This holds for every transaction version, the legacy V0 invoke and declare transactions
+    /// included, so they are checked like the others. The transactions received from the rpc are hashed with the
+    /// chain id of this chain, this mostly matters for the transactions of the db and of snapshots.
+    ///
+    /// The hash of some legacy transactions also depends on the protocol version they were converted with, which is
+    /// not saved with them: a transaction saved before a protocol version change is accepted when its hash matches the
+    /// chain id with the hash formula of any protocol version.
+    fn check_chain_id(&self, tx: &Transaction) -> Result<(), TxInsersionError> {
+        let tx_hash = tx_hash(tx).to_felt();
+        let latest = self.backend.chain_config().latest_protocol_version;
+        // Each of these versions stands for one of the hash formulas, from the oldest one.
+        let hash_versions = [StarknetVersion::V_0_0_0, StarknetVersion::V0_7_0, StarknetVersion::POST_LEGACY];
+        if tx_hash != self.expected_tx_hash(tx, latest)
+            && hash_versions.into_iter().all(|version| tx_hash != self.expected_tx_hash(tx, version))
+        {
+            return Err(TxInsersionError::ChainIdMismatch { tx_hash });
+        }
+        Ok(())
+    }
+
+    /// When [`mempool_reject_unknown_classes`](mp_chain_config::ChainConfig::mempool_reject_unknown_classes) is set,
+    /// rejects the invoke and deploy account transactions whose account class is not declared. A class declared by a
+    /// transaction of the mempool, such as an earlier transaction of the same batch, counts as declared.
+    fn check_class_declared(&self, tx: &Transaction) -> Result<(), Error> {
+        if !self.backend.chain_config().mempool_reject_unknown_classes {
+            return Ok(());
+        }
+        let class_hash = match tx {
+            Transaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) => *tx.class_hash(),
+            Transaction::AccountTransaction(AccountTransaction::Invoke(tx)) => {
+                let sender = tx.tx.sender_address().to_felt();
+                match self.backend.get_contract_class_hash_at(&DbBlockId::Pending, &sender)? {
+                    Some(class_hash) => class_hash,
+                    // The account is not deployed yet: it is deployed by a deploy account transaction of the mempool,
+                    // which was checked, or the transaction fails validation.
+                    None => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        if self.backend.get_class_info(&DbBlockId::Pending, &class_hash)?.is_some()
+            || self.inner.read().expect("Poisoned lock").declares_class(&class_hash)
+        {
+            return Ok(());
+        }
+        Err(TxInsersionError::UndeclaredClass { class_hash }.into())
+    }
+
+    /// The hash of `tx` on this chain, when converted with the protocol `version`.
+    fn expected_tx_hash(&self, tx: &Transaction, version: StarknetVersion) -> Felt {
+        let saved_tx = blockifier_to_saved_tx(tx, SystemTime::UNIX_EPOCH, None);
+        saved_tx.tx.compute_hash(self.chain_id(), version, saved_tx.only_query)
+    }
+
+    /// Derives the minimum tip of the mempool from the current L1 gas price, when it is enabled.
+    fn update_min_tip(&self) {
+        let mut inner = self.inner.write().expect("Poisoned lock");
+        if inner.has_min_tip() {
+            inner.update_min_tip(self.l1_data_provider.get_gas_prices().strk_l1_gas_price);
+        }
+    }
+
+    /// Validates the transaction against the pending state: signature, nonce, fee and balance.
+    fn perform_validations(&self, tx: &Transaction) -> Result<(), Error> {
         // Get pending block.
         let pending_block_info = if let Some(block) = self.backend.get_block_info(&DbBlockId::Pending)? {
             block
@@ -150,7 +763,7 @@ impl Mempool {
         // If the contract has been deployed for the same block is is invoked, we need to skip validations.
         // NB: the lock is NOT taken the entire time the tx is being validated. As such, the deploy tx
         //  may appear during that time - but it is not a problem.
-        let deploy_account_tx_hash = if let Transaction::AccountTransaction(AccountTransaction::Invoke(tx)) = &tx {
+        let deploy_account_tx_hash = if let Transaction::AccountTransaction(AccountTransaction::Invoke(tx)) = tx {
             let mempool = self.inner.read().expect("Poisoned lock");
             if mempool.has_deployed_contract(&tx.tx.sender_address()) {
                 Some(tx.tx_hash) // we return the wrong tx hash here but it's ok because the actual hash is unused by blockifier
@@ -161,36 +774,32 @@ impl Mempool {
             None
         };
 
-        let tx_hash = tx_hash(&tx).to_felt();
+        let tx_hash = tx_hash(tx).to_felt();
         tracing::debug!("Mempool verify tx_hash={:#x}", tx_hash);
 
         // Perform validations
         let exec_context = ExecutionContext::new_in_block(Arc::clone(&self.backend), &pending_block_info)?;
         let mut validator = exec_context.tx_validator();
 
-        if let Transaction::AccountTransaction(account_tx) = clone_transaction(&tx) {
+        if let Transaction::AccountTransaction(account_tx) = clone_transaction(tx) {
             validator.perform_validations(account_tx, deploy_account_tx_hash.is_some())?
         }
-
-        if !is_only_query(&tx) {
-            tracing::debug!("Adding to inner mempool tx_hash={:#x}", tx_hash);
-            // Add to db
-            let saved_tx = blockifier_to_saved_tx(&tx, arrived_at);
-            self.backend.save_mempool_transaction(&saved_tx, tx_hash, &converted_class)?;
-
-            // Add it to the inner mempool
-            let force = false;
-            self.inner
-                .write()
-                .expect("Poisoned lock")
-                .insert_tx(MempoolTransaction { tx, arrived_at, converted_class }, force)?;
-
-            self.metrics.accepted_transaction_counter.add(1, &[]);
-        }
-
         Ok(())
     }
 
+    /// Transactions with a nonce gap after the account nonce are buffered until the gap is filled. L1 handler
+    /// transactions use their L1 messaging nonce.
+    fn account_nonce(&self, tx: &Transaction) -> Result<Nonce, Error> {
+        Ok(match tx {
+            Transaction::AccountTransaction(_) => Nonce(
+                self.backend
+                    .get_contract_nonce_at(&DbBlockId::Pending, &contract_addr(tx).to_felt())?
+                    .unwrap_or(Felt::ZERO),
+            ),
+            Transaction::L1HandlerTransaction(tx) => tx.tx.nonce,
+        })
+    }
+
     #[cfg(any(test, feature = "testing"))]
     pub fn is_empty(&self) -> bool {
         self.inner.read().expect("Poisoned lock").is_empty()
@@ -224,25 +833,31 @@ fn deployed_contract_address(tx: &Transaction) -> Option<Felt> {
 
 impl MempoolProvider for Mempool {
     #[tracing::instrument(skip(self), fields(module = "Mempool"))]
-    fn accept_invoke_tx(&self, tx: BroadcastedInvokeTxn<Felt>) -> Result<AddInvokeTransactionResult<Felt>, Error> {
+    fn accept_invoke_tx(
+        &self,
+        tx: BroadcastedInvokeTxn<Felt>,
+    ) -> Result<Accepted<AddInvokeTransactionResult<Felt>>, Error> {
         let tx = BroadcastedTxn::Invoke(tx);
         let (btx, class) = tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?;
 
         let res = AddInvokeTransactionResult { transaction_hash: transaction_hash(&btx) };
-        self.accept_tx(btx, class, ArrivedAtTimestamp::now())?;
-        Ok(res)
+        let outcome = self.accept_tx(btx, class, self.clock.now())?;
+        Ok(self.accepted(res, outcome))
     }
 
     #[tracing::instrument(skip(self), fields(module = "Mempool"))]
-    fn accept_declare_v0_tx(&self, tx: BroadcastedDeclareTransactionV0) -> Result<ClassAndTxnHash<Felt>, Error> {
+    fn accept_declare_v0_tx(
+        &self,
+        tx: BroadcastedDeclareTransactionV0,
+    ) -> Result<Accepted<ClassAndTxnHash<Felt>>, Error> {
         let (btx, class) = tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?;
 
         let res = ClassAndTxnHash {
             transaction_hash: transaction_hash(&btx),
             class_hash: declare_class_hash(&btx).expect("Created transaction should be declare"),
         };
-        self.accept_tx(btx, class, ArrivedAtTimestamp::now())?;
-        Ok(res)
+        let outcome = self.accept_tx(btx, class, self.clock.now())?;
+        Ok(self.accepted(res, outcome))
     }
 
     #[tracing::instrument(skip(self), fields(module = "Mempool"))]
@@ -250,17 +865,17 @@ impl MempoolProvider for Mempool {
         &self,
         tx: L1HandlerTransaction,
         paid_fees_on_l1: u128,
-    ) -> Result<L1HandlerTransactionResult, Error> {
+    ) -> Result<Accepted<L1HandlerTransactionResult>, Error> {
         let (btx, class) =
             tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version, paid_fees_on_l1)?;
 
         let res = L1HandlerTransactionResult { transaction_hash: transaction_hash(&btx) };
-        self.accept_tx(btx, class, ArrivedAtTimestamp::now())?;
-        Ok(res)
+        let outcome = self.accept_tx(btx, class, self.clock.now())?;
+        Ok(self.accepted(res, outcome))
     }
 
     #[tracing::instrument(skip(self), fields(module = "Mempool"))]
-    fn accept_declare_tx(&self, tx: BroadcastedDeclareTxn<Felt>) -> Result<ClassAndTxnHash<Felt>, Error> {
+    fn accept_declare_tx(&self, tx: BroadcastedDeclareTxn<Felt>) -> Result<Accepted<ClassAndTxnHash<Felt>>, Error> {
         let tx = BroadcastedTxn::Declare(tx);
         let (btx, class) = tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?;
 
@@ -268,15 +883,15 @@ impl MempoolProvider for Mempool {
             transaction_hash: transaction_hash(&btx),
             class_hash: declare_class_hash(&btx).expect("Created transaction should be declare"),
         };
-        self.accept_tx(btx, class, ArrivedAtTimestamp::now())?;
-        Ok(res)
+        let outcome = self.accept_tx(btx, class, self.clock.now())?;
+        Ok(self.accepted(res, outcome))
     }
 
     #[tracing::instrument(skip(self), fields(module = "Mempool"))]
     fn accept_deploy_account_tx(
         &self,
         tx: BroadcastedDeployAccountTxn<Felt>,
-    ) -> Result<ContractAndTxnHash<Felt>, Error> {
+    ) -> Result<Accepted<ContractAndTxnHash<Felt>>, Error> {
         let tx = BroadcastedTxn::DeployAccount(tx);
         let (btx, class) = tx.into_blockifier(self.chain_id(), self.backend.chain_config().latest_protocol_version)?;
 
@@ -284,21 +899,69 @@ impl MempoolProvider for Mempool {
             transaction_hash: transaction_hash(&btx),
             contract_address: deployed_contract_address(&btx).expect("Created transaction should be deploy account"),
         };
-        self.accept_tx(btx, class, ArrivedAtTimestamp::now())?;
-        Ok(res)
+        let outcome = self.accept_tx(btx, class, self.clock.now())?;
+        Ok(self.accepted(res, outcome))
     }
 
-    /// Warning: A lock is held while a user-supplied function (extend) is run - Callers should be careful
     #[tracing::instrument(skip(self, dest, n), fields(module = "Mempool"))]
     fn take_txs_chunk<I: Extend<MempoolTransaction> + 'static>(&self, dest: &mut I, n: usize) {
-        let mut inner = self.inner.write().expect("Poisoned lock");
-        inner.pop_next_chunk(dest, n)
+        let mut taken = Vec::with_capacity(n);
+        let expired = self.inner.write().expect("Poisoned lock").pop_next_chunk(&mut taken, n);
+        #[cfg(feature = "metrics")]
+        self.record_arrival_latency(&taken);
+        dest.extend(taken);
+        self.remove_popped_expired_from_db(&expired);
+    }
+
+    /// Takes up to `n` transactions for block production, skipping the V3 transactions whose L1 gas max price cannot
+    /// cover the STRK L1 gas price of `gas_prices`. The skipped transactions are put back in the mempool or removed from
+    /// it and from the db, depending on the `mempool_underpriced_policy` of the chain config. See
+    /// [`MempoolInner::pop_next_chunk_priced`].
+    ///
+    /// Returns the number of removed transactions, the expired ones found on the way included.
+    #[tracing::instrument(skip(self, dest, gas_prices), fields(module = "Mempool"))]
+    fn take_priced_txs_chunk<I: Extend<MempoolTransaction> + 'static>(
+        &self,
+        dest: &mut I,
+        n: usize,
+        gas_prices: &GasPrices,
+    ) -> Result<usize, Error> {
+        let policy = self.backend.chain_config().mempool_underpriced_policy;
+        let mut taken = Vec::with_capacity(n);
+        let removed = self.inner.write().expect("Poisoned lock").pop_next_chunk_priced(
+            &mut taken,
+            n,
+            gas_prices.strk_l1_gas_price,
+            policy,
+        );
+        #[cfg(feature = "metrics")]
+        self.record_arrival_latency(&taken);
+        dest.extend(taken);
+
+        for tx in &removed {
+            let tx_hash = tx.tx_hash().to_felt();
+            tracing::debug!("Removing expired or underpriced tx_hash={:#x}", tx_hash);
+            self.backend.remove_mempool_transaction(&tx_hash)?;
+        }
+
+        Ok(removed.len())
+    }
+
+    /// Reports whether the block just produced was full, to auto-tune the transaction limit. Returns the current
+    /// transaction limit. See [`MempoolLimiter::record_block_fullness`].
+    fn record_block_fullness(&self, full: bool) -> usize {
+        self.inner.write().expect("Poisoned lock").record_block_fullness(full)
     }
 
     #[tracing::instrument(skip(self), fields(module = "Mempool"))]
     fn take_tx(&self) -> Option<MempoolTransaction> {
-        let mut inner = self.inner.write().expect("Poisoned lock");
-        inner.pop_next()
+        let mut taken = Vec::with_capacity(1);
+        let expired = self.inner.write().expect("Poisoned lock").pop_next_chunk(&mut taken, 1);
+        self.remove_popped_expired_from_db(&expired);
+        let tx = taken.pop()?;
+        #[cfg(feature = "metrics")]
+        self.record_arrival_latency([&tx]);
+        Some(tx)
     }
 
     /// Warning: A lock is taken while a user-supplied function (iterator stuff) is run - Callers should be careful
@@ -363,6 +1026,94 @@ pub(crate) fn tx_hash(tx: &Transaction) -> TransactionHash {
     }
 }
 
+/// Transactions before v3 do not have a tip.
+pub(crate) fn tip(tx: &Transaction) -> u64 {
+    v3_tip(tx).unwrap_or(0)
+}
+
+/// The tip of a V3 transaction. Older transactions and L1 handler transactions have no tip.
+pub(crate) fn v3_tip(tx: &Transaction) -> Option<u64> {
+    match tx {
+        Transaction::AccountTransaction(account_tx) => match account_tx {
+            AccountTransaction::Declare(tx) => match &tx.tx {
+                starknet_api::transaction::DeclareTransaction::V3(tx) => Some(tx.tip.0),
+                _ => None,
+            },
+            AccountTransaction::DeployAccount(tx) => match &tx.tx {
+                starknet_api::transaction::DeployAccountTransaction::V3(tx) => Some(tx.tip.0),
+                _ => None,
+            },
+            AccountTransaction::Invoke(tx) => match &tx.tx {
+                starknet_api::transaction::InvokeTransaction::V3(tx) => Some(tx.tip.0),
+                _ => None,
+            },
+        },
+        Transaction::L1HandlerTransaction(_) => None,
+    }
+}
+
+/// The max price the transaction pays per unit of L1 gas, in fri. Only V3 transactions have one: the fee of older
+/// transactions is only bounded by their `max_fee`, which is checked at execution.
+pub(crate) fn max_l1_gas_price(tx: &Transaction) -> Option<u128> {
+    v3_resource_bounds(tx)?.0.get(&starknet_api::transaction::Resource::L1Gas).map(|bounds| bounds.max_price_per_unit)
+}
+
+/// The max amount of L2 gas the transaction can use. Only V3 transactions have one.
+pub(crate) fn max_l2_gas_amount(tx: &Transaction) -> Option<u64> {
+    v3_resource_bounds(tx)?.0.get(&starknet_api::transaction::Resource::L2Gas).map(|bounds| bounds.max_amount)
+}
+
+/// Whether the account transaction pays no fee: its `max_fee` is zero, or for V3 transactions none of its resource
+/// bounds allows a non-zero fee. L1 handler transactions are paid on L1, they are never zero-fee.
+pub(crate) fn is_zero_fee(tx: &Transaction) -> bool {
+    use starknet_api::transaction::{DeclareTransaction, DeployAccountTransaction, InvokeTransaction};
+
+    if let Some(resource_bounds) = v3_resource_bounds(tx) {
+        return resource_bounds.0.values().all(|bounds| bounds.max_amount == 0 || bounds.max_price_per_unit == 0);
+    }
+    let max_fee = match tx {
+        Transaction::AccountTransaction(account_tx) => match account_tx {
+            AccountTransaction::Declare(tx) => match &tx.tx {
+                DeclareTransaction::V0(tx) | DeclareTransaction::V1(tx) => tx.max_fee,
+                DeclareTransaction::V2(tx) => tx.max_fee,
+                DeclareTransaction::V3(_) => return false,
+            },
+            AccountTransaction::DeployAccount(tx) => match &tx.tx {
+                DeployAccountTransaction::V1(tx) => tx.max_fee,
+                DeployAccountTransaction::V3(_) => return false,
+            },
+            AccountTransaction::Invoke(tx) => match &tx.tx {
+                InvokeTransaction::V0(tx) => tx.max_fee,
+                InvokeTransaction::V1(tx) => tx.max_fee,
+                InvokeTransaction::V3(_) => return false,
+            },
+        },
+        Transaction::L1HandlerTransaction(_) => return false,
+    };
+    max_fee.0 == 0
+}
+
+fn v3_resource_bounds(tx: &Transaction) -> Option<&starknet_api::transaction::ResourceBoundsMapping> {
+    let resource_bounds = match tx {
+        Transaction::AccountTransaction(account_tx) => match account_tx {
+            AccountTransaction::Declare(tx) => match &tx.tx {
+                starknet_api::transaction::DeclareTransaction::V3(tx) => &tx.resource_bounds,
+                _ => return None,
+            },
+            AccountTransaction::DeployAccount(tx) => match &tx.tx {
+                starknet_api::transaction::DeployAccountTransaction::V3(tx) => &tx.resource_bounds,
+                _ => return None,
+            },
+            AccountTransaction::Invoke(tx) => match &tx.tx {
+                starknet_api::transaction::InvokeTransaction::V3(tx) => &tx.resource_bounds,
+                _ => return None,
+            },
+        },
+        Transaction::L1HandlerTransaction(_) => return None,
+    };
+    Some(resource_bounds)
+}
+
 // AccountTransaction does not implement Clone :(
 pub(crate) fn clone_transaction(tx: &Transaction) -> Transaction {
     match tx {
@@ -412,6 +1163,7 @@ mod test {
             strk_l1_data_gas_price: 0,
         });
         mock.expect_get_gas_prices_last_update().return_const(std::time::SystemTime::now());
+        mock.expect_get_data_gas_prices_last_update().return_const(std::time::SystemTime::now());
         mock.expect_get_da_mode().return_const(mp_block::header::L1DataAvailabilityMode::Calldata);
         Arc::new(mock)
     }
@@ -454,7 +1206,7 @@ mod test {
     ) {
         let mempool = Mempool::new(backend, l1_data_provider, MempoolLimits::for_testing());
         let result = mempool.accept_tx(tx_account_v0_valid, None, ArrivedAtTimestamp::now());
-        assert_matches::assert_matches!(result, Ok(()));
+        assert_matches::assert_matches!(result, Ok(InsertOutcome::Added));
     }
 
     #[rstest::rstest]
@@ -467,4 +1219,230 @@ mod test {
         let result = mempool.accept_tx(tx_account_v1_invalid, None, ArrivedAtTimestamp::now());
         assert_matches::assert_matches!(result, Err(crate::Error::Validation(_)));
     }
+
+    #[rstest::rstest]
+    fn mempool_flags_duplicate_l1_message(
+        backend: Arc<mc_db::MadaraBackend>,
+        l1_data_provider: Arc<MockL1DataProvider>,
+    ) {
+        let mempool = Mempool::new(backend, l1_data_provider, MempoolLimits::for_testing());
+        let l1_handler_tx = L1HandlerTransaction {
+            version: Felt::ZERO,
+            nonce: 7,
+            contract_address: Felt::from(0x1234),
+            entry_point_selector: Felt::ONE,
+            calldata: vec![Felt::TWO],
+        };
+        let origin = L1MessageOrigin::new(100, [1; 32]);
+        let replayed_from = L1MessageOrigin::new(101, [2; 32]);
+
+        let accepted = mempool.accept_l1_message(l1_handler_tx.clone(), 0, origin).unwrap().unwrap();
+        let tx_hash = accepted.result.transaction_hash;
+        let in_flight = InFlightL1Message { nonce: Nonce(Felt::from(7)), tx_hash, origin: Some(origin) };
+        assert_eq!(mempool.in_flight_l1_messages().unwrap(), [in_flight.clone()]);
+        assert!(mempool.duplicate_l1_messages().is_empty());
+
+        // The same message, seen again from another L1 block.
+        assert_eq!(mempool.accept_l1_message(l1_handler_tx, 0, replayed_from).unwrap(), None);
+        assert_eq!(
+            mempool.duplicate_l1_messages(),
+            [DuplicateL1Message {
+                nonce: Nonce(Felt::from(7)),
+                tx_hash,
+                origin: replayed_from,
+                consumed_from: Some(origin)
+            }]
+        );
+        assert_eq!(mempool.in_flight_l1_messages().unwrap(), [in_flight]);
+        assert_eq!(mempool.inner.read().unwrap().transactions().count(), 1);
+    }
+
+    fn invoke_tx(tx_hash: u64) -> MempoolTransaction {
+        let tx = blockifier::transaction::transaction_execution::Transaction::AccountTransaction(
+            blockifier::transaction::account_transaction::AccountTransaction::Invoke(
+                blockifier::transaction::transactions::InvokeTransaction {
+                    tx: starknet_api::transaction::InvokeTransaction::V1(
+                        starknet_api::transaction::InvokeTransactionV1::default(),
+                    ),
+                    tx_hash: starknet_api::transaction::TransactionHash(Felt::from(tx_hash)),
+                    only_query: false,
+                },
+            ),
+        );
+        MempoolTransaction::new(tx, ArrivedAtTimestamp::now(), None)
+    }
+
+    /// Like [`invoke_tx`], with the hash of the transaction on the chain of `mempool`.
+    fn chain_invoke_tx(mempool: &Mempool) -> MempoolTransaction {
+        let mut tx = invoke_tx(0);
+        let tx_hash = mempool.expected_tx_hash(&tx.tx, mempool.backend.chain_config().latest_protocol_version);
+        if let Transaction::AccountTransaction(AccountTransaction::Invoke(invoke)) = &mut tx.tx {
+            invoke.tx_hash = starknet_api::transaction::TransactionHash(tx_hash);
+        }
+        tx
+    }
+
+    #[rstest::rstest]
+    fn mempool_check_chain_id(backend: Arc<mc_db::MadaraBackend>, l1_data_provider: Arc<MockL1DataProvider>) {
+        let mempool = Mempool::new(backend, l1_data_provider, MempoolLimits::for_testing());
+        assert_eq!(mempool.check_chain_id(&chain_invoke_tx(&mempool).tx), Ok(()));
+
+        // The same transaction, hashed for another chain.
+        let mut other_chain_tx = invoke_tx(0);
+        let saved_tx = blockifier_to_saved_tx(&other_chain_tx.tx, SystemTime::UNIX_EPOCH, None);
+        let other_chain_hash = saved_tx.tx.compute_hash(
+            Felt::from_bytes_be_slice(b"OTHER_CHAIN"),
+            mempool.backend.chain_config().latest_protocol_version,
+            false,
+        );
+        if let Transaction::AccountTransaction(AccountTransaction::Invoke(invoke)) = &mut other_chain_tx.tx {
+            invoke.tx_hash = starknet_api::transaction::TransactionHash(other_chain_hash);
+        }
+        assert_eq!(
+            mempool.check_chain_id(&other_chain_tx.tx),
+            Err(TxInsersionError::ChainIdMismatch { tx_hash: other_chain_hash })
+        );
+    }
+
+    #[rstest::rstest]
+    fn mempool_check_chain_id_of_legacy_tx(
+        backend: Arc<mc_db::MadaraBackend>,
+        l1_data_provider: Arc<MockL1DataProvider>,
+    ) {
+        let mempool = Mempool::new(backend, l1_data_provider, MempoolLimits::for_testing());
+        let legacy_tx = |chain_id: Felt, version: StarknetVersion| {
+            let mut tx = Transaction::AccountTransaction(AccountTransaction::Invoke(
+                blockifier::transaction::transactions::InvokeTransaction {
+                    tx: starknet_api::transaction::InvokeTransaction::V0(
+                        starknet_api::transaction::InvokeTransactionV0::default(),
+                    ),
+                    tx_hash: Default::default(),
+                    only_query: false,
+                },
+            ));
+            let saved_tx = blockifier_to_saved_tx(&tx, SystemTime::UNIX_EPOCH, None);
+            let tx_hash = saved_tx.tx.compute_hash(chain_id, version, false);
+            if let Transaction::AccountTransaction(AccountTransaction::Invoke(invoke)) = &mut tx {
+                invoke.tx_hash = starknet_api::transaction::TransactionHash(tx_hash);
+            }
+            (tx, tx_hash)
+        };
+
+        // A V0 invoke transaction converted before the legacy hash formula was dropped, as if it was saved in the db
+        // before a protocol version change.
+        let (tx, tx_hash) = legacy_tx(mempool.chain_id(), StarknetVersion::V0_7_0);
+        assert_ne!(tx_hash, legacy_tx(mempool.chain_id(), mempool.backend.chain_config().latest_protocol_version).1);
+        assert_eq!(mempool.check_chain_id(&tx), Ok(()));
+
+        let (tx, tx_hash) = legacy_tx(Felt::from_bytes_be_slice(b"OTHER_CHAIN"), StarknetVersion::V0_7_0);
+        assert_eq!(mempool.check_chain_id(&tx), Err(TxInsersionError::ChainIdMismatch { tx_hash }));
+    }
+
+    #[rstest::rstest]
+    fn mempool_snapshot_import_drops_other_chain_txs(
+        backend: Arc<mc_db::MadaraBackend>,
+        #[from(backend)] imported_backend: Arc<mc_db::MadaraBackend>,
+        l1_data_provider: Arc<MockL1DataProvider>,
+    ) {
+        let mempool = Mempool::new(backend, l1_data_provider.clone(), MempoolLimits::for_testing());
+        // The hash of this transaction was not computed with the chain id.
+        mempool.inner.write().unwrap().insert_tx(invoke_tx(1), false, Nonce(Felt::ZERO)).unwrap();
+        let snapshot = mempool.export_snapshot();
+
+        let imported = Mempool::new(imported_backend, l1_data_provider, MempoolLimits::for_testing());
+        assert_eq!(imported.import_snapshot(snapshot).unwrap(), 0);
+        assert!(imported.is_empty());
+        assert_eq!(imported.inner.read().unwrap().counters(), MempoolCounters::default());
+    }
+
+    #[rstest::rstest]
+    fn mempool_snapshot_round_trip(
+        backend: Arc<mc_db::MadaraBackend>,
+        #[from(backend)] imported_backend: Arc<mc_db::MadaraBackend>,
+        l1_data_provider: Arc<MockL1DataProvider>,
+    ) {
+        let mempool = Mempool::new(backend, l1_data_provider.clone(), MempoolLimits::for_testing());
+        let tx = chain_invoke_tx(&mempool);
+        mempool.inner.write().unwrap().insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+        for nonce in 0..3 {
+            let l1_handler_tx = L1HandlerTransaction {
+                version: Felt::ZERO,
+                nonce,
+                contract_address: Felt::from(0x1234),
+                entry_point_selector: Felt::ONE,
+                calldata: vec![Felt::TWO],
+            };
+            mempool.accept_l1_handler_tx(l1_handler_tx, 0).unwrap();
+        }
+
+        let snapshot = mempool.export_snapshot();
+        assert_eq!(snapshot.transactions.len(), 4);
+        assert_eq!(snapshot.counters.transactions, 4);
+        // The snapshot survives serialization.
+        let snapshot: MempoolSnapshot = bincode::deserialize(&bincode::serialize(&snapshot).unwrap()).unwrap();
+        let counters = snapshot.counters;
+
+        let imported = Mempool::new(imported_backend, l1_data_provider, MempoolLimits::for_testing());
+        assert_eq!(imported.import_snapshot(snapshot).unwrap(), 4);
+        assert_eq!(imported.inner.read().unwrap().counters(), counters);
+        assert_eq!(imported.inner.read().unwrap().counters(), mempool.inner.read().unwrap().counters());
+        let tx_hashes = |mempool: &Mempool| {
+            mempool.transactions_snapshot(None, usize::MAX).into_iter().map(|tx| tx.tx_hash).collect::<Vec<_>>()
+        };
+        assert_eq!(tx_hashes(&imported), tx_hashes(&mempool));
+        imported.inner.read().unwrap().check_invariants();
+    }
+
+    #[rstest::rstest]
+    fn mempool_snapshot_import_drops_expired(
+        backend: Arc<mc_db::MadaraBackend>,
+        #[from(backend)] imported_backend: Arc<mc_db::MadaraBackend>,
+        l1_data_provider: Arc<MockL1DataProvider>,
+    ) {
+        let mempool = Mempool::new(backend, l1_data_provider.clone(), MempoolLimits::for_testing());
+        let mut expired = invoke_tx(1);
+        expired.arrived_at = ArrivedAtTimestamp::now() - std::time::Duration::from_secs(60 * 60);
+        // Forced insertions skip the age check.
+        mempool.inner.write().unwrap().insert_tx(expired, true, Nonce(Felt::ZERO)).unwrap();
+        let snapshot = mempool.export_snapshot();
+
+        let limits =
+            MempoolLimits { max_age: Some(std::time::Duration::from_secs(60)), ..MempoolLimits::for_testing() };
+        let imported = Mempool::new(imported_backend, l1_data_provider, limits);
+        assert_eq!(imported.import_snapshot(snapshot).unwrap(), 0);
+        assert!(imported.is_empty());
+        assert_eq!(imported.inner.read().unwrap().counters(), MempoolCounters::default());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn mempool_arrival_latency() {
+        let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        let mut tx = invoke_tx(1);
+        tx.arrived_at = now - std::time::Duration::from_secs(30);
+        assert_eq!(arrival_latency(&tx, now), std::time::Duration::from_secs(30));
+
+        // A transaction which arrived after now did not wait.
+        tx.arrived_at = now + std::time::Duration::from_secs(30);
+        assert_eq!(arrival_latency(&tx, now), std::time::Duration::ZERO);
+    }
+
+    #[rstest::rstest]
+    fn mempool_fake_clock_sweeps_expired(
+        backend: Arc<mc_db::MadaraBackend>,
+        l1_data_provider: Arc<MockL1DataProvider>,
+    ) {
+        let clock = FakeClock::new(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000));
+        let limits =
+            MempoolLimits { max_age: Some(std::time::Duration::from_secs(60)), ..MempoolLimits::for_testing() };
+        let mempool = Mempool::new(backend, l1_data_provider, limits).with_clock(Arc::new(clock.clone()));
+        let mut tx = invoke_tx(1);
+        tx.arrived_at = mempool.clock.now();
+        mempool.inner.write().unwrap().insert_tx(tx, false, Nonce(Felt::ZERO)).unwrap();
+
+        assert_eq!(mempool.remove_age_exceeded_txs().unwrap(), 0);
+        clock.advance(std::time::Duration::from_secs(61));
+        assert_eq!(mempool.remove_age_exceeded_txs().unwrap(), 1);
+        assert!(mempool.is_empty());
+    }
 }
@@ -1,9 +1,46 @@
-use mc_analytics::register_counter_metric_instrument;
-use opentelemetry::metrics::Counter;
+use crate::MempoolTransaction;
+use mc_analytics::{
+    register_counter_metric_instrument, register_gauge_metric_instrument, register_histogram_metric_instrument,
+};
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter};
 use opentelemetry::{global, KeyValue};
+use std::time::{Duration, SystemTime};
 
+#[derive(Clone, Debug)]
 pub struct MempoolMetrics {
     pub accepted_transaction_counter: Counter<u64>,
+    /// Rejected transactions, with the reached limit as the `reason` attribute.
+    pub rejected_transaction_counter: Counter<u64>,
+    /// Transactions inserted in the mempool, with the transaction type as the `type` attribute.
+    pub inserted_transaction_counter: Counter<u64>,
+    /// Transactions removed from the mempool, with the transaction type as the `type` attribute.
+    pub removed_transaction_counter: Counter<u64>,
+    /// Transactions dropped without being included in a block, with the [`crate::DropReason`] as the `reason`
+    /// attribute.
+    pub dropped_transaction_counter: Counter<u64>,
+    /// Transactions marked as removed while the occupancy counters did not account for them.
+    pub counter_underflow_counter: Counter<u64>,
+    /// Consistency checks which found the transaction counter outside of the expected window.
+    pub counter_divergence_counter: Counter<u64>,
+    /// Transactions by which the transaction counter was off the expected window at the last consistency check.
+    pub counter_divergence: Gauge<u64>,
+    /// Seconds between the arrival of a transaction and its pop for block production.
+    pub arrival_latency: Histogram<f64>,
+    /// Seconds since the arrival of the oldest transaction in the mempool, zero when it is empty.
+    pub oldest_transaction_age: Gauge<f64>,
+    /// Transactions per second accepted in the mempool over the last [`crate::THROUGHPUT_WINDOW`].
+    pub accepted_transactions_per_second: Gauge<f64>,
+    /// Transactions per second popped for block production over the last [`crate::THROUGHPUT_WINDOW`].
+    pub popped_transactions_per_second: Gauge<f64>,
+    // Mempool occupancy
+    pub current_transactions: Gauge<u64>,
+    pub current_declare_transactions: Gauge<u64>,
+    /// Cumulative encoded size of the transactions in the mempool.
+    pub current_bytes: Gauge<u64>,
+    /// Transactions in the mempool, with the transaction type as the `type` attribute.
+    pub current_transactions_by_type: Gauge<u64>,
+    pub transactions_utilization: Gauge<f64>,
+    pub declare_transactions_utilization: Gauge<f64>,
 }
 
 impl MempoolMetrics {
@@ -16,14 +53,168 @@ impl MempoolMetrics {
             Some("https://opentelemetry.io/schemas/1.2.0"),
             Some(common_scope_attributes.clone()),
         );
+        Self::register_with_meter(&mempool_meter)
+    }
 
+    /// Registers the mempool metrics on `mempool_meter`, [`MempoolMetrics::register`] uses the global meter.
+    pub fn register_with_meter(mempool_meter: &Meter) -> Self {
         let accepted_transaction_counter = register_counter_metric_instrument(
-            &mempool_meter,
+            mempool_meter,
             "accepted_transaction_count".to_string(),
             "A counter to show accepted transactions in the mempool".to_string(),
             "transaction".to_string(),
         );
 
-        Self { accepted_transaction_counter }
+        let rejected_transaction_counter = register_counter_metric_instrument(
+            mempool_meter,
+            "rejected_transaction_count".to_string(),
+            "A counter to show transactions rejected by the mempool limits".to_string(),
+            "transaction".to_string(),
+        );
+
+        let inserted_transaction_counter = register_counter_metric_instrument(
+            mempool_meter,
+            "mempool_inserted_transaction_count".to_string(),
+            "A counter to show transactions inserted in the mempool, by transaction type".to_string(),
+            "transaction".to_string(),
+        );
+
+        let removed_transaction_counter = register_counter_metric_instrument(
+            mempool_meter,
+            "mempool_removed_transaction_count".to_string(),
+            "A counter to show transactions removed from the mempool, by transaction type".to_string(),
+            "transaction".to_string(),
+        );
+
+        let dropped_transaction_counter = register_counter_metric_instrument(
+            mempool_meter,
+            "mempool_dropped_transaction_count".to_string(),
+            "A counter to show transactions dropped by the mempool, by drop reason".to_string(),
+            "transaction".to_string(),
+        );
+
+        let counter_underflow_counter = register_counter_metric_instrument(
+            mempool_meter,
+            "mempool_counter_underflow_count".to_string(),
+            "A counter to show transactions removed from the mempool which were not counted in its occupancy"
+                .to_string(),
+            "transaction".to_string(),
+        );
+
+        let counter_divergence_counter = register_counter_metric_instrument(
+            mempool_meter,
+            "mempool_counter_divergence_count".to_string(),
+            "A counter to show consistency checks which found the mempool transaction counter diverged".to_string(),
+            "check".to_string(),
+        );
+
+        let counter_divergence = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_counter_divergence".to_string(),
+            "Gauge for the divergence of the mempool transaction counter at the last consistency check".to_string(),
+            "transaction".to_string(),
+        );
+
+        let arrival_latency = register_histogram_metric_instrument(
+            mempool_meter,
+            "mempool_transaction_arrival_latency".to_string(),
+            "Histogram of the time transactions wait in the mempool before being popped for block production"
+                .to_string(),
+            "s".to_string(),
+        );
+
+        let oldest_transaction_age = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_oldest_transaction_age".to_string(),
+            "Gauge for the time since the arrival of the oldest transaction in the mempool".to_string(),
+            "s".to_string(),
+        );
+
+        let accepted_transactions_per_second = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_accepted_transactions_per_second".to_string(),
+            "Gauge for the rate of transactions accepted in the mempool over a sliding window".to_string(),
+            "transaction/s".to_string(),
+        );
+
+        let popped_transactions_per_second = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_popped_transactions_per_second".to_string(),
+            "Gauge for the rate of transactions popped for block production over a sliding window".to_string(),
+            "transaction/s".to_string(),
+        );
+
+        let current_transactions = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_transactions".to_string(),
+            "Gauge for the number of transactions in the mempool".to_string(),
+            "transaction".to_string(),
+        );
+
+        let current_declare_transactions = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_declare_transactions".to_string(),
+            "Gauge for the number of declare transactions in the mempool".to_string(),
+            "transaction".to_string(),
+        );
+
+        let current_bytes = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_bytes".to_string(),
+            "Gauge for the cumulative encoded size of the transactions in the mempool, in bytes".to_string(),
+            "".to_string(),
+        );
+
+        let current_transactions_by_type = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_transactions_by_type".to_string(),
+            "Gauge for the number of transactions in the mempool, by transaction type".to_string(),
+            "transaction".to_string(),
+        );
+
+        let transactions_utilization = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_transactions_utilization".to_string(),
+            "Gauge for the ratio of transactions in the mempool against the transaction limit".to_string(),
+            "".to_string(),
+        );
+
+        let declare_transactions_utilization = register_gauge_metric_instrument(
+            mempool_meter,
+            "mempool_declare_transactions_utilization".to_string(),
+            "Gauge for the ratio of declare transactions in the mempool against the declare transaction limit"
+                .to_string(),
+            "".to_string(),
+        );
+
+        Self {
+            accepted_transaction_counter,
+            rejected_transaction_counter,
+            inserted_transaction_counter,
+            removed_transaction_counter,
+            dropped_transaction_counter,
+            counter_underflow_counter,
+            counter_divergence_counter,
+            counter_divergence,
+            arrival_latency,
+            oldest_transaction_age,
+            accepted_transactions_per_second,
+            popped_transactions_per_second,
+            current_transactions,
+            current_declare_transactions,
+            current_bytes,
+            current_transactions_by_type,
+            transactions_utilization,
+            declare_transactions_utilization,
+        }
+    }
+
+    pub fn record_arrival_latency(&self, latency: Duration) {
+        self.arrival_latency.record(latency.as_secs_f64(), &[]);
     }
 }
+
+/// How long the transaction waited in the mempool before being popped at `now`, zero if it arrived after `now`.
+pub(crate) fn arrival_latency(tx: &MempoolTransaction, now: SystemTime) -> Duration {
+    now.duration_since(tx.arrived_at).unwrap_or_default()
+}
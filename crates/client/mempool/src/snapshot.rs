@@ -0,0 +1,38 @@
+//! Explicit export and import of the mempool content, for chain migrations and integration tests. This is independent
+//! from the persistence of the mempool in the db.
+
+use crate::tx::blockifier_to_saved_tx;
+use crate::{MempoolCounters, MempoolTransaction};
+use mc_db::mempool_db::SavedTransaction;
+use mp_class::ConvertedClass;
+use mp_convert::ToFelt;
+use serde::{Deserialize, Serialize};
+use starknet_types_core::felt::Felt;
+
+/// A transaction of a [`MempoolSnapshot`], in the format it is saved in the db.
+#[derive(Serialize, Deserialize)]
+pub struct MempoolSnapshotTransaction {
+    pub tx_hash: Felt,
+    pub tx: SavedTransaction,
+    pub converted_class: Option<ConvertedClass>,
+}
+
+impl From<&MempoolTransaction> for MempoolSnapshotTransaction {
+    fn from(tx: &MempoolTransaction) -> Self {
+        Self {
+            tx_hash: tx.tx_hash().to_felt(),
+            tx: blockifier_to_saved_tx(&tx.tx, tx.arrived_at, tx.deadline),
+            converted_class: tx.converted_class.clone(),
+        }
+    }
+}
+
+/// The ready and pending transactions of a mempool, see [`Mempool::export_snapshot`](crate::Mempool::export_snapshot).
+#[derive(Serialize, Deserialize)]
+pub struct MempoolSnapshot {
+    /// Ordered by transaction hash.
+    pub transactions: Vec<MempoolSnapshotTransaction>,
+    /// The counters of the exported mempool. They are not imported: the importing mempool counts the transactions it
+    /// accepts.
+    pub counters: MempoolCounters,
+}
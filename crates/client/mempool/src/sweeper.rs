@@ -0,0 +1,29 @@
+//! Background task removing age-exceeded transactions from the mempool. Without it, stale transactions would only be
+//! removed when a new transaction is inserted. The ages are measured with the clock of the mempool, see
+//! [`Mempool::with_clock`]. Every sweep also checks that the mempool counters match its transactions, see
+//! [`Mempool::check_counter_consistency`].
+
+use crate::Mempool;
+use anyhow::Context;
+use mp_utils::service::ServiceContext;
+use mp_utils::wait_or_graceful_shutdown;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub async fn age_sweeper_worker(
+    mempool: Arc<Mempool>,
+    sweep_interval: Duration,
+    ctx: ServiceContext,
+) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(sweep_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    while wait_or_graceful_shutdown(interval.tick(), &ctx).await.is_some() {
+        let removed = mempool.remove_age_exceeded_txs().context("Sweeping age-exceeded mempool transactions")?;
+        if removed > 0 {
+            tracing::debug!("Swept {removed} age-exceeded transactions from the mempool");
+        }
+        // A divergence is reported by the check itself.
+        mempool.check_counter_consistency();
+    }
+    Ok(())
+}
@@ -18,8 +18,14 @@ use starknet_api::{
 };
 use starknet_types_core::felt::Felt;
 
-pub fn blockifier_to_saved_tx(tx: &BTransaction, arrived_at: SystemTime) -> SavedTransaction {
-    let arrived_at = arrived_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+pub fn blockifier_to_saved_tx(
+    tx: &BTransaction,
+    arrived_at: SystemTime,
+    deadline: Option<SystemTime>,
+) -> SavedTransaction {
+    let to_millis = |time: SystemTime| time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    let arrived_at = to_millis(arrived_at);
+    let deadline = deadline.map(to_millis);
     match tx {
         BTransaction::AccountTransaction(AccountTransaction::Declare(tx)) => SavedTransaction {
             only_query: tx.only_query(),
@@ -27,6 +33,7 @@ pub fn blockifier_to_saved_tx(tx: &BTransaction, arrived_at: SystemTime) -> Save
             paid_fee_on_l1: None,
             contract_address: None,
             arrived_at,
+            deadline,
         },
         BTransaction::AccountTransaction(AccountTransaction::DeployAccount(tx)) => SavedTransaction {
             only_query: tx.only_query,
@@ -34,6 +41,7 @@ pub fn blockifier_to_saved_tx(tx: &BTransaction, arrived_at: SystemTime) -> Save
             paid_fee_on_l1: None,
             contract_address: Some(tx.contract_address.to_felt()),
             arrived_at,
+            deadline,
         },
         BTransaction::AccountTransaction(AccountTransaction::Invoke(tx)) => SavedTransaction {
             only_query: tx.only_query,
@@ -41,6 +49,7 @@ pub fn blockifier_to_saved_tx(tx: &BTransaction, arrived_at: SystemTime) -> Save
             paid_fee_on_l1: None,
             contract_address: None,
             arrived_at,
+            deadline,
         },
         BTransaction::L1HandlerTransaction(tx) => SavedTransaction {
             only_query: false,
@@ -48,6 +57,7 @@ pub fn blockifier_to_saved_tx(tx: &BTransaction, arrived_at: SystemTime) -> Save
             paid_fee_on_l1: Some(*tx.paid_fee_on_l1),
             contract_address: None,
             arrived_at,
+            deadline,
         },
     }
 }
@@ -72,9 +82,10 @@ pub fn saved_to_blockifier_tx(
     saved_tx: SavedTransaction,
     tx_hash: Felt,
     converted_class: &Option<ConvertedClass>,
-) -> Result<(BTransaction, SystemTime), SavedToBlockifierTxError> {
+) -> Result<(BTransaction, SystemTime, Option<SystemTime>), SavedToBlockifierTxError> {
     let tx_hash = TransactionHash(tx_hash);
     let arrived_at = SystemTime::UNIX_EPOCH + Duration::from_millis(saved_tx.arrived_at as u64);
+    let deadline = saved_tx.deadline.map(|deadline| SystemTime::UNIX_EPOCH + Duration::from_millis(deadline as u64));
     let tx = match saved_tx.tx {
         mp_transactions::Transaction::L1Handler(tx) => BTransaction::L1HandlerTransaction(L1HandlerTransaction {
             tx: tx.try_into().map_err(|_| SavedToBlockifierTxError::InvalidContractAddress)?,
@@ -125,5 +136,5 @@ pub fn saved_to_blockifier_tx(
         mp_transactions::Transaction::Deploy(_) => return Err(SavedToBlockifierTxError::DeployNotSupported),
     };
 
-    Ok((tx, arrived_at))
+    Ok((tx, arrived_at, deadline))
 }
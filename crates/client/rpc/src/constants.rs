@@ -2,3 +2,7 @@
 pub const MAX_EVENTS_KEYS: usize = 100;
 /// Maximum number of events that can be fetched in a single chunk for the `get_events` RPC.
 pub const MAX_EVENTS_CHUNK_SIZE: usize = 1000;
+/// Maximum number of transactions that can be listed in a single page for the `getMempoolTransactions` admin RPC.
+pub const MAX_MEMPOOL_TRANSACTIONS_PAGE_SIZE: usize = 1000;
+/// Default maximum number of concurrent `subscribeMempool` admin RPC subscribers.
+pub const DEFAULT_MAX_MEMPOOL_SUBSCRIBERS: usize = 16;
@@ -1,5 +1,6 @@
 use crate::utils::display_internal_server_error;
 use mc_db::MadaraStorageError;
+use mc_mempool::MempoolLimitReached;
 use mp_gateway::error::{StarknetError, StarknetErrorCode};
 use serde::Serialize;
 use serde_json::json;
@@ -105,6 +106,10 @@ pub enum StarknetRpcApiError {
     ProofLimitExceeded { kind: StorageProofLimit, limit: usize, got: usize },
     #[error("Cannot create a storage proof for a block that old")]
     CannotMakeProofOnOldBlock,
+    #[error("Too many subscribers to the mempool")]
+    TooManyMempoolSubscribers,
+    #[error("The transaction was rejected by a mempool limit")]
+    MempoolLimitReached { limit: MempoolLimitReached },
 }
 
 impl From<&StarknetRpcApiError> for i32 {
@@ -143,6 +148,10 @@ impl From<&StarknetRpcApiError> for i32 {
             StarknetRpcApiError::UnimplementedMethod => 501,
             StarknetRpcApiError::ProofLimitExceeded { .. } => 10000,
             StarknetRpcApiError::CannotMakeProofOnOldBlock => 10001,
+            StarknetRpcApiError::TooManyMempoolSubscribers => 10002,
+            StarknetRpcApiError::MempoolLimitReached { limit } => {
+                limit.code().try_into().expect("Mempool limit error codes fit in an i32")
+            }
         }
     }
 }
@@ -160,6 +169,9 @@ impl StarknetRpcApiError {
             StarknetRpcApiError::ProofLimitExceeded { kind, limit, got } => {
                 Some(json!({ "kind": kind, "limit": limit, "got": got }))
             }
+            StarknetRpcApiError::MempoolLimitReached { limit } => {
+                Some(json!({ "limit": limit.reason(), "error": limit.to_string() }))
+            }
             _ => None,
         }
     }
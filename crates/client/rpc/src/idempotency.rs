@@ -0,0 +1,183 @@
+//! Idempotency keys of the transaction submissions. A client which retries a submission after a network timeout reuses
+//! its key, and gets the result of the first submission instead of submitting the transaction again. The keys are
+//! recorded by the [`AddTransactionProvider`](crate::providers::AddTransactionProvider) which inserts the transactions
+//! in the mempool.
+
+use starknet_types_core::felt::Felt;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Past this many keys, the least recently used ones are forgotten.
+pub const MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+
+#[derive(Debug, Default)]
+struct IdempotencyKeysInner {
+    /// Transaction hash returned for each key, and when the key was last used.
+    results: HashMap<String, (Felt, u64)>,
+    /// Keys by last use, the least recently used first.
+    by_last_use: BTreeMap<u64, String>,
+    next_use: u64,
+}
+
+/// A key was reused to submit another transaction than the one it was first used for.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Idempotency key {key:?} was already used for transaction {tx_hash:#x}")]
+pub struct IdempotencyKeyReused {
+    pub key: String,
+    /// The transaction submitted with the key first.
+    pub tx_hash: Felt,
+}
+
+/// Bounded LRU map of the idempotency keys to the transaction hash returned for them.
+#[derive(Debug)]
+pub struct IdempotencyKeys {
+    capacity: usize,
+    inner: Mutex<IdempotencyKeysInner>,
+}
+
+impl IdempotencyKeys {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, inner: Default::default() }
+    }
+
+    /// Calls `submit` to submit the transaction `tx_hash` and records it for this key when the key is new. Returns
+    /// `None` without submitting when the key was already used for this transaction, and an error when it was used for
+    /// another one. Rejected submissions are not recorded, so that they can be retried with the same key. Without a
+    /// key, this only calls `submit`.
+    pub fn submit<T, E: From<IdempotencyKeyReused>>(
+        &self,
+        key: Option<String>,
+        tx_hash: Felt,
+        submit: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Option<T>, E> {
+        let Some(key) = key else { return submit().map(Some) };
+        match self.get(&key) {
+            Some(submitted) if submitted == tx_hash => return Ok(None),
+            Some(submitted) => return Err(IdempotencyKeyReused { key, tx_hash: submitted }.into()),
+            None => {}
+        }
+        // The lock is not held while submitting: concurrent submissions with a new key are both made, and the second
+        // one is rejected as a duplicate transaction.
+        let result = submit()?;
+        self.insert(key, tx_hash);
+        Ok(Some(result))
+    }
+
+    /// The transaction hash returned for this key, which becomes the most recently used one.
+    fn get(&self, key: &str) -> Option<Felt> {
+        let mut inner = self.inner.lock().expect("Poisoned lock");
+        let IdempotencyKeysInner { results, by_last_use, next_use } = &mut *inner;
+        let (tx_hash, last_use) = results.get_mut(key)?;
+        let key = by_last_use.remove(&*last_use).expect("Idempotency key missing from the LRU order");
+        *last_use = *next_use;
+        by_last_use.insert(*next_use, key);
+        *next_use += 1;
+        Some(*tx_hash)
+    }
+
+    fn insert(&self, key: String, tx_hash: Felt) {
+        let mut inner = self.inner.lock().expect("Poisoned lock");
+        let IdempotencyKeysInner { results, by_last_use, next_use } = &mut *inner;
+        if let Some((_, last_use)) = results.remove(&key) {
+            by_last_use.remove(&last_use);
+        }
+        while results.len() >= self.capacity {
+            let Some((_, oldest)) = by_last_use.pop_first() else { break };
+            results.remove(&oldest);
+        }
+        results.insert(key.clone(), (tx_hash, *next_use));
+        by_last_use.insert(*next_use, key);
+        *next_use += 1;
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.inner.lock().expect("Poisoned lock").results.len()
+    }
+}
+
+impl Default for IdempotencyKeys {
+    fn default() -> Self {
+        Self::new(MAX_IDEMPOTENCY_KEYS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[derive(Debug, PartialEq)]
+    enum SubmitError {
+        MempoolFull,
+        Reused(IdempotencyKeyReused),
+    }
+
+    impl From<IdempotencyKeyReused> for SubmitError {
+        fn from(value: IdempotencyKeyReused) -> Self {
+            Self::Reused(value)
+        }
+    }
+
+    #[test]
+    fn same_key_submits_once() {
+        let keys = IdempotencyKeys::default();
+        let submissions = Cell::new(0);
+        let submit = || {
+            submissions.set(submissions.get() + 1);
+            Ok::<_, SubmitError>(submissions.get())
+        };
+
+        assert_eq!(keys.submit(Some("retry".into()), Felt::ONE, submit), Ok(Some(1)));
+        assert_eq!(keys.submit(Some("retry".into()), Felt::ONE, submit), Ok(None));
+        assert_eq!(submissions.get(), 1);
+
+        // Other keys, and submissions without a key, are submitted.
+        assert_eq!(keys.submit(Some("other".into()), Felt::TWO, submit), Ok(Some(2)));
+        assert_eq!(keys.submit(None, Felt::THREE, submit), Ok(Some(3)));
+        assert_eq!(keys.submit(None, Felt::THREE, submit), Ok(Some(4)));
+    }
+
+    #[test]
+    fn key_reused_for_another_transaction_is_rejected() {
+        let keys = IdempotencyKeys::default();
+        let submit = || Ok::<_, SubmitError>(());
+
+        keys.submit(Some("retry".into()), Felt::ONE, submit).unwrap();
+        assert_eq!(
+            keys.submit(Some("retry".into()), Felt::TWO, submit),
+            Err(SubmitError::Reused(IdempotencyKeyReused { key: "retry".into(), tx_hash: Felt::ONE }))
+        );
+        // The key still refers to the first transaction.
+        assert_eq!(keys.submit(Some("retry".into()), Felt::ONE, submit), Ok(None));
+    }
+
+    #[test]
+    fn rejected_submissions_are_not_recorded() {
+        let keys = IdempotencyKeys::default();
+
+        assert_eq!(
+            keys.submit(Some("retry".into()), Felt::ONE, || Err::<(), _>(SubmitError::MempoolFull)),
+            Err(SubmitError::MempoolFull)
+        );
+        assert_eq!(keys.submit(Some("retry".into()), Felt::ONE, || Ok::<_, SubmitError>(())), Ok(Some(())));
+        assert_eq!(keys.submit(Some("retry".into()), Felt::ONE, || Err::<(), _>(SubmitError::MempoolFull)), Ok(None));
+    }
+
+    #[test]
+    fn least_recently_used_key_is_forgotten() {
+        let keys = IdempotencyKeys::new(2);
+        let submit = || Ok::<_, SubmitError>(());
+
+        keys.submit(Some("a".into()), Felt::from(1), submit).unwrap();
+        keys.submit(Some("b".into()), Felt::from(2), submit).unwrap();
+        // Using "a" again makes "b" the least recently used key.
+        assert_eq!(keys.submit(Some("a".into()), Felt::from(1), submit), Ok(None));
+        keys.submit(Some("c".into()), Felt::from(3), submit).unwrap();
+        assert_eq!(keys.len(), 2);
+
+        assert_eq!(keys.submit(Some("a".into()), Felt::from(1), submit), Ok(None));
+        // "b" was forgotten, so it can be used for another transaction.
+        assert_eq!(keys.submit(Some("b".into()), Felt::from(20), submit), Ok(Some(())));
+    }
+}
@@ -4,7 +4,9 @@
 
 mod constants;
 mod errors;
+pub mod idempotency;
 pub mod providers;
+pub mod rate_limit;
 #[cfg(test)]
 pub mod test_utils;
 mod types;
@@ -14,15 +16,19 @@ pub mod versions;
 use jsonrpsee::RpcModule;
 use mc_db::db_block_id::DbBlockIdResolvable;
 use mc_db::MadaraBackend;
+use mc_mempool::{GasPriceProvider, Mempool};
 use mp_block::{BlockId, BlockTag, MadaraMaybePendingBlock, MadaraMaybePendingBlockInfo};
-use mp_chain_config::ChainConfig;
+use mp_chain_config::{ChainConfig, L1CoreAddress, L1Endpoints};
 use mp_convert::ToFelt;
-use mp_utils::service::ServiceContext;
+use mp_utils::service::{PauseHandle, ServiceContext};
 use providers::AddTransactionProvider;
 use starknet_types_core::felt::Felt;
+use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
 use utils::ResultExt;
 
+pub use constants::DEFAULT_MAX_MEMPOOL_SUBSCRIBERS;
 pub use errors::{StarknetRpcApiError, StarknetRpcResult};
 
 /// Limits to the storage proof endpoint.
@@ -42,12 +48,53 @@ impl Default for StorageProofConfig {
     }
 }
 
+/// Groups of RPC methods which can be exposed or hidden independently. The methods of a disabled namespace are not
+/// registered, calling them returns a method not found error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RpcNamespace {
+    /// The user RPC methods reading the chain, including traces and websocket subscriptions.
+    Read,
+    /// The user RPC methods submitting transactions to the mempool.
+    Write,
+    /// Every admin RPC method.
+    Admin,
+}
+
+impl RpcNamespace {
+    pub const ALL: [RpcNamespace; 3] = [RpcNamespace::Read, RpcNamespace::Write, RpcNamespace::Admin];
+}
+
+impl std::str::FromStr for RpcNamespace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(RpcNamespace::Read),
+            "write" => Ok(RpcNamespace::Write),
+            "admin" => Ok(RpcNamespace::Admin),
+            other => Err(format!("Unknown RPC namespace `{other}`, expected one of `read`, `write` or `admin`")),
+        }
+    }
+}
+
 /// A Starknet RPC server for Madara
 #[derive(Clone)]
 pub struct Starknet {
     backend: Arc<MadaraBackend>,
     pub(crate) add_transaction_provider: Arc<dyn AddTransactionProvider>,
     storage_proof_config: StorageProofConfig,
+    /// Only set when the mempool can be inspected through the admin RPC.
+    pub(crate) mempool: Option<Arc<Mempool>>,
+    /// Slots of the mempool subscribers, each subscriber holds a permit until it disconnects.
+    pub(crate) mempool_subscribers: Arc<Semaphore>,
+    /// Only set when the L1 gas price worker can be configured through the admin RPC.
+    pub(crate) l1_gas_provider: Option<GasPriceProvider>,
+    /// Only set when the L1 core contract address can be changed through the admin RPC.
+    pub(crate) l1_core_address: Option<L1CoreAddress>,
+    /// Only set when the L1 sync can be paused through the admin RPC.
+    pub(crate) l1_sync_pause: Option<PauseHandle>,
+    /// Only set when the L1 endpoints can be rotated through the admin RPC.
+    pub(crate) l1_endpoints: Option<L1Endpoints>,
     pub ctx: ServiceContext,
 }
 
@@ -58,7 +105,55 @@ impl Starknet {
         storage_proof_config: StorageProofConfig,
         ctx: ServiceContext,
     ) -> Self {
-        Self { backend, add_transaction_provider, storage_proof_config, ctx }
+        Self {
+            backend,
+            add_transaction_provider,
+            storage_proof_config,
+            mempool: None,
+            mempool_subscribers: Arc::new(Semaphore::new(DEFAULT_MAX_MEMPOOL_SUBSCRIBERS)),
+            l1_gas_provider: None,
+            l1_core_address: None,
+            l1_sync_pause: None,
+            l1_endpoints: None,
+            ctx,
+        }
+    }
+
+    /// Exposes the contents of the mempool through the admin RPC.
+    pub fn with_mempool(mut self, mempool: Arc<Mempool>) -> Self {
+        self.mempool = Some(mempool);
+        self
+    }
+
+    /// Accepts up to `max_subscribers` concurrent subscribers to the transactions accepted in the mempool, instead of
+    /// [`DEFAULT_MAX_MEMPOOL_SUBSCRIBERS`].
+    pub fn with_max_mempool_subscribers(mut self, max_subscribers: usize) -> Self {
+        self.mempool_subscribers = Arc::new(Semaphore::new(max_subscribers));
+        self
+    }
+
+    /// Allows configuring the L1 gas price worker through the admin RPC.
+    pub fn with_gas_price_provider(mut self, l1_gas_provider: GasPriceProvider) -> Self {
+        self.l1_gas_provider = Some(l1_gas_provider);
+        self
+    }
+
+    /// Allows changing the address of the L1 core contract watched by the L1 sync through the admin RPC.
+    pub fn with_l1_core_address(mut self, l1_core_address: L1CoreAddress) -> Self {
+        self.l1_core_address = Some(l1_core_address);
+        self
+    }
+
+    /// Allows pausing and resuming the L1 sync through the admin RPC.
+    pub fn with_l1_sync_pause(mut self, l1_sync_pause: PauseHandle) -> Self {
+        self.l1_sync_pause = Some(l1_sync_pause);
+        self
+    }
+
+    /// Allows rotating the L1 endpoints of the L1 sync through the admin RPC.
+    pub fn with_l1_endpoints(mut self, l1_endpoints: L1Endpoints) -> Self {
+        self.l1_endpoints = Some(l1_endpoints);
+        self
     }
 
     pub fn clone_backend(&self) -> Arc<MadaraBackend> {
@@ -110,25 +205,66 @@ impl Starknet {
     }
 }
 
-/// Returns the RpcModule merged with all the supported RPC versions.
-pub fn rpc_api_user(starknet: &Starknet) -> anyhow::Result<RpcModule<()>> {
+/// Returns the RpcModule merged with all the supported RPC versions, restricted to the `namespaces` enabled.
+pub fn rpc_api_user(starknet: &Starknet, namespaces: &HashSet<RpcNamespace>) -> anyhow::Result<RpcModule<()>> {
     let mut rpc_api = RpcModule::new(());
 
-    rpc_api.merge(versions::user::v0_7_1::StarknetReadRpcApiV0_7_1Server::into_rpc(starknet.clone()))?;
-    rpc_api.merge(versions::user::v0_8_0::StarknetReadRpcApiV0_8_0Server::into_rpc(starknet.clone()))?;
-    rpc_api.merge(versions::user::v0_7_1::StarknetWriteRpcApiV0_7_1Server::into_rpc(starknet.clone()))?;
-    rpc_api.merge(versions::user::v0_7_1::StarknetTraceRpcApiV0_7_1Server::into_rpc(starknet.clone()))?;
-    rpc_api.merge(versions::user::v0_8_0::StarknetWsRpcApiV0_8_0Server::into_rpc(starknet.clone()))?;
+    if namespaces.contains(&RpcNamespace::Read) {
+        rpc_api.merge(versions::user::v0_7_1::StarknetReadRpcApiV0_7_1Server::into_rpc(starknet.clone()))?;
+        rpc_api.merge(versions::user::v0_8_0::StarknetReadRpcApiV0_8_0Server::into_rpc(starknet.clone()))?;
+        rpc_api.merge(versions::user::v0_7_1::StarknetTraceRpcApiV0_7_1Server::into_rpc(starknet.clone()))?;
+        rpc_api.merge(versions::user::v0_8_0::StarknetWsRpcApiV0_8_0Server::into_rpc(starknet.clone()))?;
+    }
+    if namespaces.contains(&RpcNamespace::Write) {
+        rpc_api.merge(versions::user::v0_7_1::StarknetWriteRpcApiV0_7_1Server::into_rpc(starknet.clone()))?;
+    }
 
     Ok(rpc_api)
 }
 
-pub fn rpc_api_admin(starknet: &Starknet) -> anyhow::Result<RpcModule<()>> {
+pub fn rpc_api_admin(starknet: &Starknet, namespaces: &HashSet<RpcNamespace>) -> anyhow::Result<RpcModule<()>> {
     let mut rpc_api = RpcModule::new(());
+    if !namespaces.contains(&RpcNamespace::Admin) {
+        return Ok(rpc_api);
+    }
 
     rpc_api.merge(versions::admin::v0_1_0::MadaraWriteRpcApiV0_1_0Server::into_rpc(starknet.clone()))?;
     rpc_api.merge(versions::admin::v0_1_0::MadaraStatusRpcApiV0_1_0Server::into_rpc(starknet.clone()))?;
     rpc_api.merge(versions::admin::v0_1_0::MadaraServicesRpcApiV0_1_0Server::into_rpc(starknet.clone()))?;
+    if starknet.mempool.is_some() {
+        rpc_api.merge(versions::admin::v0_1_0::MadaraMempoolRpcApiV0_1_0Server::into_rpc(starknet.clone()))?;
+    }
+    if starknet.l1_gas_provider.is_some() {
+        rpc_api.merge(versions::admin::v0_1_0::MadaraGasPriceRpcApiV0_1_0Server::into_rpc(starknet.clone()))?;
+    }
+    if starknet.l1_core_address.is_some() || starknet.l1_sync_pause.is_some() || starknet.l1_endpoints.is_some() {
+        rpc_api.merge(versions::admin::v0_1_0::MadaraL1RpcApiV0_1_0Server::into_rpc(starknet.clone()))?;
+    }
 
     Ok(rpc_api)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+    use rstest::rstest;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_disabled_namespace_is_not_found(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_backend, rpc) = rpc_test_setup;
+        let rpc_api = rpc_api_user(&rpc, &HashSet::from([RpcNamespace::Read])).unwrap();
+
+        assert!(rpc_api.method("starknet_V0_7_1_blockNumber").is_some());
+        assert!(rpc_api.method("starknet_V0_7_1_addInvokeTransaction").is_none());
+
+        let request = r#"{"jsonrpc":"2.0","id":0,"method":"starknet_V0_7_1_addInvokeTransaction","params":[{}]}"#;
+        let (response, _) = rpc_api.raw_json_request(request, 1).await.unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["error"]["code"], jsonrpsee::types::error::METHOD_NOT_FOUND_CODE);
+
+        let rpc_api = rpc_api_admin(&rpc, &HashSet::from([RpcNamespace::Read])).unwrap();
+        assert_eq!(rpc_api.method_names().count(), 0);
+    }
+}
@@ -9,7 +9,7 @@ use starknet_types_rpc::{
     ClassAndTxnHash, ContractAndTxnHash,
 };
 
-use super::AddTransactionProvider;
+use super::{AddTransactionProvider, SubmittedTransaction};
 
 pub struct ForwardToProvider {
     provider: GatewayProvider,
@@ -26,13 +26,13 @@ impl AddTransactionProvider for ForwardToProvider {
     async fn add_declare_v0_transaction(
         &self,
         _declare_v0_transaction: BroadcastedDeclareTransactionV0,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
         Err(StarknetRpcApiError::UnimplementedMethod.into())
     }
     async fn add_declare_transaction(
         &self,
         declare_transaction: BroadcastedDeclareTxn<Felt>,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
         let sequencer_response = match self
             .provider
             .add_declare_transaction(
@@ -47,12 +47,12 @@ impl AddTransactionProvider for ForwardToProvider {
             Err(e) => bail_internal_server_error!("Failed to add declare transaction to sequencer: {e}"),
         };
 
-        Ok(sequencer_response)
+        Ok(SubmittedTransaction::new(sequencer_response))
     }
     async fn add_deploy_account_transaction(
         &self,
         deploy_account_transaction: BroadcastedDeployAccountTxn<Felt>,
-    ) -> RpcResult<ContractAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ContractAndTxnHash<Felt>>> {
         let sequencer_response =
             match self.provider.add_deploy_account_transaction(deploy_account_transaction.into()).await {
                 Ok(response) => response,
@@ -62,13 +62,13 @@ impl AddTransactionProvider for ForwardToProvider {
                 Err(e) => bail_internal_server_error!("Failed to add deploy account transaction to sequencer: {e}"),
             };
 
-        Ok(sequencer_response)
+        Ok(SubmittedTransaction::new(sequencer_response))
     }
 
     async fn add_invoke_transaction(
         &self,
         invoke_transaction: BroadcastedInvokeTxn<Felt>,
-    ) -> RpcResult<AddInvokeTransactionResult<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<AddInvokeTransactionResult<Felt>>> {
         let sequencer_response = match self.provider.add_invoke_transaction(invoke_transaction.into()).await {
             Ok(response) => response,
             Err(SequencerError::StarknetError(e)) => {
@@ -77,6 +77,6 @@ impl AddTransactionProvider for ForwardToProvider {
             Err(e) => bail_internal_server_error!("Failed to add invoke transaction to sequencer: {e}"),
         };
 
-        Ok(sequencer_response)
+        Ok(SubmittedTransaction::new(sequencer_response))
     }
 }
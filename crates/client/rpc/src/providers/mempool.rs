@@ -1,24 +1,94 @@
-use super::AddTransactionProvider;
+use super::{AddTransactionProvider, SubmittedTransaction};
+use crate::idempotency::{IdempotencyKeyReused, IdempotencyKeys};
 use crate::{errors::StarknetRpcApiError, utils::display_internal_server_error};
 use jsonrpsee::core::{async_trait, RpcResult};
-use mc_mempool::Mempool;
+use jsonrpsee::types::ErrorObject;
 use mc_mempool::MempoolProvider;
+use mc_mempool::{Accepted, InsertOutcome, Mempool};
 use mp_transactions::BroadcastedDeclareTransactionV0;
 use starknet_types_core::felt::Felt;
 use starknet_types_rpc::AddInvokeTransactionResult;
 use starknet_types_rpc::{
-    BroadcastedDeclareTxn, BroadcastedDeployAccountTxn, BroadcastedInvokeTxn, ClassAndTxnHash, ContractAndTxnHash,
+    BroadcastedDeclareTxn, BroadcastedDeployAccountTxn, BroadcastedInvokeTxn, BroadcastedTxn, ClassAndTxnHash,
+    ContractAndTxnHash,
 };
 use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// JSON-RPC error code returned when the validation pool is full. This is the "limit exceeded" code from EIP-1474.
+pub const VALIDATION_BUSY_CODE: i32 = -32005;
+pub const VALIDATION_BUSY_MSG: &str = "Too many transactions are being validated, try again later";
+
+/// Bounds how many submitted transactions are validated at once, so that a burst of submissions does not starve the
+/// other tasks of the node.
+#[derive(Clone, Copy, Debug)]
+pub struct ValidationPoolConfig {
+    /// Transactions validated at the same time.
+    pub concurrency: usize,
+    /// Transactions waiting for a validation slot. Past this, the submissions are rejected with
+    /// [`VALIDATION_BUSY_CODE`].
+    pub queue_depth: usize,
+}
+
+struct ValidationPool {
+    /// A permit for each transaction being validated.
+    workers: Semaphore,
+    /// A permit for each transaction being validated or waiting for a worker.
+    admitted: Semaphore,
+}
 
 /// This [`AddTransactionProvider`] adds the received transactions to a mempool.
 pub struct MempoolAddTxProvider {
     mempool: Arc<Mempool>,
+    /// Only set when the validations are bounded, they run on the blocking thread pool.
+    validation_pool: Option<ValidationPool>,
+    /// Transactions submitted with an idempotency key.
+    idempotency_keys: Arc<IdempotencyKeys>,
 }
 
 impl MempoolAddTxProvider {
     pub fn new(mempool: Arc<Mempool>) -> Self {
-        Self { mempool }
+        Self { mempool, validation_pool: None, idempotency_keys: Default::default() }
+    }
+
+    /// Validates at most [`ValidationPoolConfig::concurrency`] transactions at once.
+    pub fn with_validation_pool(mut self, config: ValidationPoolConfig) -> Self {
+        self.validation_pool = Some(ValidationPool {
+            workers: Semaphore::new(config.concurrency),
+            admitted: Semaphore::new(config.concurrency.saturating_add(config.queue_depth)),
+        });
+        self
+    }
+
+    /// Validates and inserts a transaction with `accept`, on the validation pool when there is one.
+    async fn accept<T: Send + 'static, E: Send + 'static>(
+        &self,
+        accept: impl FnOnce(&Mempool) -> Result<T, E> + Send + 'static,
+    ) -> RpcResult<T>
+    where
+        StarknetRpcApiError: From<E>,
+    {
+        let Some(pool) = &self.validation_pool else {
+            return accept(&self.mempool).map_err(|err| StarknetRpcApiError::from(err).into());
+        };
+
+        // The permits are released once the validation completes.
+        let Ok(_admitted) = pool.admitted.try_acquire() else {
+            return Err(ErrorObject::owned(VALIDATION_BUSY_CODE, VALIDATION_BUSY_MSG, None::<()>));
+        };
+        let _worker = pool.workers.acquire().await.expect("The validation pool is never closed");
+        let mempool = Arc::clone(&self.mempool);
+        let accepted = tokio::task::spawn_blocking(move || accept(&mempool)).await.map_err(|err| {
+            display_internal_server_error(format!("Transaction validation task failed: {err:#}"));
+            StarknetRpcApiError::InternalServerError
+        })?;
+        accepted.map_err(|err| StarknetRpcApiError::from(err).into())
+    }
+}
+
+impl From<IdempotencyKeyReused> for StarknetRpcApiError {
+    fn from(value: IdempotencyKeyReused) -> Self {
+        StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", value).into()) }
     }
 }
 
@@ -29,13 +99,34 @@ impl From<mc_mempool::Error> for StarknetRpcApiError {
                 StarknetRpcApiError::DuplicateTxn
             }
             mc_mempool::Error::InnerMempool(mc_mempool::TxInsersionError::Limit(limit)) => {
-                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", limit).into()) }
+                StarknetRpcApiError::MempoolLimitReached { limit }
             }
             mc_mempool::Error::InnerMempool(mc_mempool::TxInsersionError::NonceConflict) => {
                 StarknetRpcApiError::FailedToReceiveTxn {
                     err: Some("A transaction with this nonce and sender address already exists".into()),
                 }
             }
+            mc_mempool::Error::InnerMempool(err @ mc_mempool::TxInsersionError::ReplacementUnderpriced { .. }) => {
+                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", err).into()) }
+            }
+            mc_mempool::Error::InnerMempool(err @ mc_mempool::TxInsersionError::NonceTooFar { .. }) => {
+                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", err).into()) }
+            }
+            mc_mempool::Error::InnerMempool(err @ mc_mempool::TxInsersionError::ChainIdMismatch { .. }) => {
+                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", err).into()) }
+            }
+            mc_mempool::Error::InnerMempool(err @ mc_mempool::TxInsersionError::InvalidPriorityHint { .. }) => {
+                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", err).into()) }
+            }
+            mc_mempool::Error::InnerMempool(err @ mc_mempool::TxInsersionError::ReplacementCooldown { .. }) => {
+                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", err).into()) }
+            }
+            mc_mempool::Error::InnerMempool(err @ mc_mempool::TxInsersionError::Rejected(_)) => {
+                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", err).into()) }
+            }
+            mc_mempool::Error::InnerMempool(err @ mc_mempool::TxInsersionError::UndeclaredClass { .. }) => {
+                StarknetRpcApiError::FailedToReceiveTxn { err: Some(format!("{}", err).into()) }
+            }
             mc_mempool::Error::Validation(err) => {
                 StarknetRpcApiError::ValidationFailure { error: format!("{err:#}").into() }
             }
@@ -50,30 +141,209 @@ impl From<mc_mempool::Error> for StarknetRpcApiError {
     }
 }
 
+/// Logs when a submitted transaction pushed another one out of the mempool, and returns the rpc result along with the
+/// insertion outcome. A transaction already in the mempool is reported as a duplicate, unless its arrival time was
+/// refreshed.
+fn log_insert_outcome<T>(tx_hash: Felt, accepted: Accepted<T>) -> RpcResult<SubmittedTransaction<T>> {
+    match accepted.outcome {
+        InsertOutcome::Added => {}
+        InsertOutcome::AlreadyKnown => return Err(StarknetRpcApiError::DuplicateTxn.into()),
+        InsertOutcome::Refreshed => {
+            tracing::debug!("Transaction {tx_hash:#x} resubmitted, its arrival time was refreshed")
+        }
+        InsertOutcome::Replaced(previous) => {
+            tracing::debug!("Transaction {tx_hash:#x} replaced mempool transaction {previous:#x}")
+        }
+        InsertOutcome::EvictedToFit(evicted) => {
+            tracing::debug!("Transaction {tx_hash:#x} evicted mempool transaction {evicted:#x} to fit")
+        }
+    }
+    if accepted.near_capacity {
+        tracing::debug!("Transaction {tx_hash:#x} accepted with the mempool near capacity")
+    }
+    Ok(SubmittedTransaction {
+        result: accepted.result,
+        near_capacity: accepted.near_capacity,
+        outcome: Some(accepted.outcome),
+    })
+}
+
 #[async_trait]
 impl AddTransactionProvider for MempoolAddTxProvider {
     async fn add_declare_v0_transaction(
         &self,
         declare_v0_transaction: BroadcastedDeclareTransactionV0,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
-        Ok(self.mempool.accept_declare_v0_tx(declare_v0_transaction).map_err(StarknetRpcApiError::from)?)
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
+        let accepted = self.accept(move |mempool| mempool.accept_declare_v0_tx(declare_v0_transaction)).await?;
+        log_insert_outcome(accepted.result.transaction_hash, accepted)
     }
     async fn add_declare_transaction(
         &self,
         declare_transaction: BroadcastedDeclareTxn<Felt>,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
-        Ok(self.mempool.accept_declare_tx(declare_transaction).map_err(StarknetRpcApiError::from)?)
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
+        let accepted = self.accept(move |mempool| mempool.accept_declare_tx(declare_transaction)).await?;
+        log_insert_outcome(accepted.result.transaction_hash, accepted)
     }
     async fn add_deploy_account_transaction(
         &self,
         deploy_account_transaction: BroadcastedDeployAccountTxn<Felt>,
-    ) -> RpcResult<ContractAndTxnHash<Felt>> {
-        Ok(self.mempool.accept_deploy_account_tx(deploy_account_transaction).map_err(StarknetRpcApiError::from)?)
+    ) -> RpcResult<SubmittedTransaction<ContractAndTxnHash<Felt>>> {
+        let accepted = self.accept(move |mempool| mempool.accept_deploy_account_tx(deploy_account_transaction)).await?;
+        log_insert_outcome(accepted.result.transaction_hash, accepted)
     }
     async fn add_invoke_transaction(
         &self,
         invoke_transaction: BroadcastedInvokeTxn<Felt>,
-    ) -> RpcResult<AddInvokeTransactionResult<Felt>> {
-        Ok(self.mempool.accept_invoke_tx(invoke_transaction).map_err(StarknetRpcApiError::from)?)
+    ) -> RpcResult<SubmittedTransaction<AddInvokeTransactionResult<Felt>>> {
+        let accepted = self.accept(move |mempool| mempool.accept_invoke_tx(invoke_transaction)).await?;
+        log_insert_outcome(accepted.result.transaction_hash, accepted)
+    }
+    async fn add_transaction(
+        &self,
+        transaction: BroadcastedTxn<Felt>,
+        idempotency_key: Option<String>,
+    ) -> RpcResult<SubmittedTransaction<Felt>> {
+        let idempotency_keys = Arc::clone(&self.idempotency_keys);
+        let (tx_hash, accepted) = self
+            .accept(move |mempool| {
+                let (tx, converted_class) = mempool.convert_broadcasted_tx(transaction)?;
+                let tx_hash = mc_mempool::transaction_hash(&tx);
+                let accepted = idempotency_keys.submit(idempotency_key, tx_hash, || {
+                    let accepted = mempool.accept_converted_tx(tx, converted_class)?;
+                    // A duplicate is rejected, so that it is not recorded for the key.
+                    if accepted.outcome == InsertOutcome::AlreadyKnown {
+                        return Err(StarknetRpcApiError::DuplicateTxn);
+                    }
+                    Ok(accepted)
+                })?;
+                Ok::<_, StarknetRpcApiError>((tx_hash, accepted))
+            })
+            .await?;
+        match accepted {
+            Some(accepted) => log_insert_outcome(tx_hash, accepted),
+            // The transaction was already submitted with this key.
+            None => Ok(SubmittedTransaction::new(tx_hash)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+    use jsonrpsee::types::ErrorObjectOwned;
+    use mc_db::MadaraBackend;
+    use mc_mempool::{MempoolLimitReached, MempoolLimits, MockL1DataProvider, TxInsersionError};
+    use rstest::rstest;
+    use starknet_types_rpc::{DaMode, InvokeTxnV3, ResourceBounds, ResourceBoundsMapping};
+    use std::time::{Duration, SystemTime};
+
+    fn invoke_tx(nonce: u64) -> BroadcastedInvokeTxn<Felt> {
+        BroadcastedInvokeTxn::V3(InvokeTxnV3 {
+            sender_address: Felt::ONE,
+            calldata: vec![],
+            signature: vec![],
+            nonce: Felt::from(nonce),
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+                l2_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+            },
+            tip: 0,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DaMode::L1,
+            fee_data_availability_mode: DaMode::L1,
+        })
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_validation_pool_rejects_when_busy(rpc_test_setup: (Arc<MadaraBackend>, crate::Starknet)) {
+        let (backend, _rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let provider = MempoolAddTxProvider::new(mempool)
+            .with_validation_pool(ValidationPoolConfig { concurrency: 2, queue_depth: 1 });
+
+        let submit = |nonce| provider.add_invoke_transaction(invoke_tx(nonce));
+        let (r0, r1, r2, r3, r4, r5) = tokio::join!(submit(0), submit(1), submit(2), submit(3), submit(4), submit(5));
+        let busy = [r0, r1, r2, r3, r4, r5]
+            .iter()
+            .filter(|res| matches!(res, Err(err) if err.code() == VALIDATION_BUSY_CODE))
+            .count();
+        // Two transactions are validated, one waits for a worker and the others are turned away.
+        assert_eq!(busy, 3);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_idempotency_key(rpc_test_setup: (Arc<MadaraBackend>, crate::Starknet)) {
+        let (backend, _rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let provider = MempoolAddTxProvider::new(Arc::clone(&mempool))
+            .with_validation_pool(ValidationPoolConfig { concurrency: 1, queue_depth: 0 });
+        let (tx, _) = mempool.convert_broadcasted_tx(BroadcastedTxn::Invoke(invoke_tx(0))).unwrap();
+        let tx_hash = mc_mempool::transaction_hash(&tx);
+        provider.idempotency_keys.submit(Some("retry".into()), tx_hash, || Ok::<_, StarknetRpcApiError>(())).unwrap();
+
+        // A retry returns the transaction hash without submitting the transaction again.
+        let retried =
+            provider.add_transaction(BroadcastedTxn::Invoke(invoke_tx(0)), Some("retry".into())).await.unwrap();
+        assert_eq!(retried, SubmittedTransaction::new(tx_hash));
+        assert!(mempool.is_empty());
+
+        // The key cannot be used for another transaction.
+        let err =
+            provider.add_transaction(BroadcastedTxn::Invoke(invoke_tx(1)), Some("retry".into())).await.unwrap_err();
+        assert_eq!(err.code(), ErrorObjectOwned::from(StarknetRpcApiError::FailedToReceiveTxn { err: None }).code());
+        assert_eq!(
+            err.data().map(|data| serde_json::from_str::<String>(data.get()).unwrap()),
+            Some(IdempotencyKeyReused { key: "retry".into(), tx_hash }.to_string())
+        );
+    }
+
+    #[rstest]
+    #[case(InsertOutcome::Added)]
+    #[case(InsertOutcome::Refreshed)]
+    #[case(InsertOutcome::Replaced(Felt::TWO))]
+    #[case(InsertOutcome::EvictedToFit(Felt::TWO))]
+    fn test_insert_outcome_is_returned(#[case] outcome: InsertOutcome) {
+        let accepted = Accepted { result: Felt::ONE, outcome, near_capacity: true };
+        assert_eq!(
+            log_insert_outcome(Felt::ONE, accepted).unwrap(),
+            SubmittedTransaction { result: Felt::ONE, near_capacity: true, outcome: Some(outcome) }
+        );
+    }
+
+    #[test]
+    fn test_already_known_is_a_duplicate() {
+        let accepted = Accepted { result: Felt::ONE, outcome: InsertOutcome::AlreadyKnown, near_capacity: false };
+        let err = log_insert_outcome(Felt::ONE, accepted).unwrap_err();
+        assert_eq!(err.code(), ErrorObjectOwned::from(StarknetRpcApiError::DuplicateTxn).code());
+    }
+
+    #[rstest]
+    #[case(MempoolLimitReached::MaxTransactions { max: 10 }, 10100)]
+    #[case(MempoolLimitReached::MaxDeclareTransactions { max: 10 }, 10101)]
+    #[case(MempoolLimitReached::MaxUnreservedTransactions { max: 10 }, 10102)]
+    #[case(MempoolLimitReached::MaxBytes { max: 10 }, 10103)]
+    #[case(MempoolLimitReached::MaxL2Gas { max: 10 }, 10104)]
+    #[case(MempoolLimitReached::DeclareBytecodeTooLarge { size: 20, max: 10 }, 10105)]
+    #[case(MempoolLimitReached::ZeroFee, 10106)]
+    #[case(MempoolLimitReached::TipTooLow { tip: 1, min_tip: 10 }, 10107)]
+    #[case(MempoolLimitReached::MaxPerSender { sender: Felt::ONE, max: 10 }, 10108)]
+    #[case(MempoolLimitReached::Age { max: Duration::from_secs(10) }, 10109)]
+    #[case(MempoolLimitReached::DeadlinePassed { deadline: SystemTime::UNIX_EPOCH }, 10110)]
+    fn test_mempool_limit_error_codes(#[case] limit: MempoolLimitReached, #[case] code: i32) {
+        let reason = limit.reason();
+        let message = limit.to_string();
+        let err: ErrorObjectOwned =
+            StarknetRpcApiError::from(mc_mempool::Error::InnerMempool(TxInsersionError::Limit(limit))).into();
+        assert_eq!(err.code(), code);
+        assert_eq!(
+            err.data().map(|data| serde_json::from_str::<serde_json::Value>(data.get()).unwrap()),
+            Some(serde_json::json!({ "limit": reason, "error": message }))
+        );
     }
 }
@@ -4,32 +4,108 @@ pub mod mempool;
 pub use forward_to_provider::*;
 pub use mempool::*;
 
+use crate::errors::StarknetRpcApiError;
 use jsonrpsee::core::{async_trait, RpcResult};
+use mc_mempool::InsertOutcome;
 use mp_transactions::BroadcastedDeclareTransactionV0;
+use serde::{Deserialize, Serialize};
 use starknet_types_core::felt::Felt;
 use starknet_types_rpc::{
     AddInvokeTransactionResult, BroadcastedDeclareTxn, BroadcastedDeployAccountTxn, BroadcastedInvokeTxn,
-    ClassAndTxnHash, ContractAndTxnHash,
+    BroadcastedTxn, ClassAndTxnHash, ContractAndTxnHash,
 };
 
+/// Result of a transaction submission, with what it did to the mempool and an advisory backpressure signal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmittedTransaction<T> {
+    #[serde(flatten)]
+    pub result: T,
+    /// The mempool is close to full and will soon start rejecting transactions: clients should slow down. This is
+    /// always `false` when the transaction is forwarded to another sequencer.
+    #[serde(default)]
+    pub near_capacity: bool,
+    /// What the insertion of the transaction did to the mempool, such as the transaction it replaced or evicted. This
+    /// is `None` when the transaction is forwarded to another sequencer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<InsertOutcome>,
+}
+
+impl<T> SubmittedTransaction<T> {
+    /// A submission without any backpressure signal nor insertion outcome.
+    pub fn new(result: T) -> Self {
+        Self { result, near_capacity: false, outcome: None }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> SubmittedTransaction<U> {
+        SubmittedTransaction { result: f(self.result), near_capacity: self.near_capacity, outcome: self.outcome }
+    }
+}
+
 #[async_trait]
 pub trait AddTransactionProvider: Send + Sync {
     async fn add_declare_v0_transaction(
         &self,
         declare_v0_transaction: BroadcastedDeclareTransactionV0,
-    ) -> RpcResult<ClassAndTxnHash<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>>;
     async fn add_declare_transaction(
         &self,
         declare_transaction: BroadcastedDeclareTxn<Felt>,
-    ) -> RpcResult<ClassAndTxnHash<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>>;
 
     async fn add_deploy_account_transaction(
         &self,
         deploy_account_transaction: BroadcastedDeployAccountTxn<Felt>,
-    ) -> RpcResult<ContractAndTxnHash<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<ContractAndTxnHash<Felt>>>;
 
     async fn add_invoke_transaction(
         &self,
         invoke_transaction: BroadcastedInvokeTxn<Felt>,
-    ) -> RpcResult<AddInvokeTransactionResult<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<AddInvokeTransactionResult<Felt>>>;
+
+    /// Submits a transaction of any type, optionally with an [idempotency key](crate::idempotency). Returns the
+    /// transaction hash. Providers which do not record the idempotency keys reject the submissions with a key.
+    async fn add_transaction(
+        &self,
+        transaction: BroadcastedTxn<Felt>,
+        idempotency_key: Option<String>,
+    ) -> RpcResult<SubmittedTransaction<Felt>> {
+        if idempotency_key.is_some() {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        }
+        Ok(match transaction {
+            BroadcastedTxn::Invoke(tx) => self.add_invoke_transaction(tx).await?.map(|res| res.transaction_hash),
+            BroadcastedTxn::Declare(tx) => self.add_declare_transaction(tx).await?.map(|res| res.transaction_hash),
+            BroadcastedTxn::DeployAccount(tx) => {
+                self.add_deploy_account_transaction(tx).await?.map(|res| res.transaction_hash)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn submitted_transaction_serializes_flattened() {
+        let submitted = SubmittedTransaction {
+            result: AddInvokeTransactionResult { transaction_hash: Felt::ONE },
+            near_capacity: true,
+            outcome: Some(InsertOutcome::Replaced(Felt::TWO)),
+        };
+        assert_eq!(
+            serde_json::to_value(&submitted).unwrap(),
+            serde_json::json!({ "transaction_hash": "0x1", "near_capacity": true, "outcome": { "replaced": "0x2" } })
+        );
+        let added = SubmittedTransaction { outcome: Some(InsertOutcome::Added), ..submitted };
+        assert_eq!(
+            serde_json::to_value(&added).unwrap(),
+            serde_json::json!({ "transaction_hash": "0x1", "near_capacity": true, "outcome": "added" })
+        );
+
+        // Responses from sequencers without the signal are still understood.
+        let forwarded: SubmittedTransaction<AddInvokeTransactionResult<Felt>> =
+            serde_json::from_value(serde_json::json!({ "transaction_hash": "0x1" })).unwrap();
+        assert_eq!(forwarded, SubmittedTransaction::new(AddInvokeTransactionResult { transaction_hash: Felt::ONE }));
+    }
 }
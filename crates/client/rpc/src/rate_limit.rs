@@ -0,0 +1,157 @@
+//! Per client IP rate limiting of the transaction submission methods, so that a single client cannot spam the mempool.
+//! Every client gets a token bucket: each submission takes a token, and tokens are refilled at a constant rate up to
+//! the burst size.
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// JSON-RPC error code returned to throttled clients. This is the "limit exceeded" code from EIP-1474.
+pub const RATE_LIMITED_CODE: i32 = -32005;
+pub const RATE_LIMITED_MSG: &str = "Too many transactions submitted, try again later";
+
+/// Past this many tracked clients, the clients whose bucket is full again are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Methods which submit a transaction, without their namespace and version prefix.
+const SUBMIT_METHODS: [&str; 6] = [
+    "addInvokeTransaction",
+    "addDeclareTransaction",
+    "addDeployAccountTransaction",
+    "addDeclareV0Transaction",
+    "addTransactionBatch",
+    "addTransaction",
+];
+
+/// Whether this method submits a transaction. This accepts both `starknet_addInvokeTransaction` and versioned method
+/// names such as `starknet_V0_7_1_addInvokeTransaction`.
+pub fn is_submit_method(method: &str) -> bool {
+    method.rsplit('_').next().is_some_and(|name| SUBMIT_METHODS.contains(&name))
+}
+
+#[derive(Clone, Debug)]
+pub struct SubmitRateLimitConfig {
+    /// Transactions a client can submit per second.
+    pub per_second: u32,
+    /// Transactions a client can submit at once before being throttled.
+    pub burst: u32,
+    /// Clients which are never throttled. Localhost is always exempt.
+    pub allowlist: HashSet<IpAddr>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+#[derive(Debug)]
+pub struct SubmitRateLimiter {
+    config: SubmitRateLimitConfig,
+    buckets: Mutex<HashMap<IpAddr, TokenBucket>>,
+}
+
+impl SubmitRateLimiter {
+    pub fn new(config: SubmitRateLimitConfig) -> Self {
+        Self { config, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Takes a token for a submission from this client. Returns `false` when the client is throttled.
+    pub fn check(&self, ip: IpAddr) -> bool {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn is_exempt(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        ip.is_loopback() || self.config.allowlist.contains(&ip)
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> bool {
+        if self.is_exempt(ip) {
+            return true;
+        }
+
+        let burst = f64::from(self.config.burst);
+        let per_second = f64::from(self.config.per_second);
+        let mut buckets = self.buckets.lock().expect("Poisoned lock");
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&ip) {
+            // A full bucket is the same as an untracked client.
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.saturating_duration_since(bucket.last_refill).as_secs_f64() * per_second < burst
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(TokenBucket { tokens: burst, last_refill: now });
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::time::Duration;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const OTHER_CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    fn limiter(allowlist: impl IntoIterator<Item = IpAddr>) -> SubmitRateLimiter {
+        SubmitRateLimiter::new(SubmitRateLimitConfig {
+            per_second: 2,
+            burst: 5,
+            allowlist: allowlist.into_iter().collect(),
+        })
+    }
+
+    fn burst(limiter: &SubmitRateLimiter, ip: IpAddr, now: Instant, count: usize) -> usize {
+        (0..count).filter(|_| limiter.check_at(ip, now)).count()
+    }
+
+    #[test]
+    fn throttles_bursts() {
+        let limiter = limiter([]);
+        let now = Instant::now();
+
+        assert_eq!(burst(&limiter, CLIENT, now, 20), 5);
+        // Other clients have their own bucket.
+        assert_eq!(burst(&limiter, OTHER_CLIENT, now, 20), 5);
+
+        // Tokens are refilled at the configured rate, up to the burst size.
+        assert_eq!(burst(&limiter, CLIENT, now + Duration::from_millis(500), 20), 1);
+        assert_eq!(burst(&limiter, CLIENT, now + Duration::from_secs(60), 20), 5);
+    }
+
+    #[test]
+    fn localhost_and_allowlist_are_exempt() {
+        let limiter = limiter([OTHER_CLIENT]);
+        let now = Instant::now();
+
+        assert_eq!(burst(&limiter, Ipv4Addr::LOCALHOST.into(), now, 20), 20);
+        assert_eq!(burst(&limiter, Ipv6Addr::LOCALHOST.into(), now, 20), 20);
+        assert_eq!(burst(&limiter, Ipv4Addr::LOCALHOST.to_ipv6_mapped().into(), now, 20), 20);
+        assert_eq!(burst(&limiter, OTHER_CLIENT, now, 20), 20);
+        assert_eq!(burst(&limiter, CLIENT, now, 20), 5);
+    }
+
+    #[test]
+    fn submit_methods() {
+        assert!(is_submit_method("starknet_addInvokeTransaction"));
+        assert!(is_submit_method("starknet_V0_7_1_addDeclareTransaction"));
+        assert!(is_submit_method("madara_V0_1_0_addDeclareV0Transaction"));
+        assert!(is_submit_method("madara_V0_1_0_addTransactionBatch"));
+        assert!(is_submit_method("madara_V0_1_0_addTransaction"));
+        assert!(!is_submit_method("starknet_V0_7_1_getNonce"));
+        assert!(!is_submit_method("starknet_estimateFee"));
+    }
+}
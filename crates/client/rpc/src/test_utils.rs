@@ -23,7 +23,10 @@ use starknet_types_rpc::{
 };
 use std::sync::Arc;
 
-use crate::{providers::AddTransactionProvider, Starknet};
+use crate::{
+    providers::{AddTransactionProvider, SubmittedTransaction},
+    Starknet,
+};
 
 #[cfg(test)]
 pub struct TestTransactionProvider;
@@ -34,25 +37,25 @@ impl AddTransactionProvider for TestTransactionProvider {
     async fn add_declare_v0_transaction(
         &self,
         _declare_v0_transaction: BroadcastedDeclareTransactionV0,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
         unimplemented!()
     }
     async fn add_declare_transaction(
         &self,
         _declare_transaction: BroadcastedDeclareTxn<Felt>,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
         unimplemented!()
     }
     async fn add_deploy_account_transaction(
         &self,
         _deploy_account_transaction: BroadcastedDeployAccountTxn<Felt>,
-    ) -> RpcResult<ContractAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ContractAndTxnHash<Felt>>> {
         unimplemented!()
     }
     async fn add_invoke_transaction(
         &self,
         _invoke_transaction: BroadcastedInvokeTxn<Felt>,
-    ) -> RpcResult<AddInvokeTransactionResult<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<AddInvokeTransactionResult<Felt>>> {
         unimplemented!()
     }
 }
@@ -1,8 +1,175 @@
 use jsonrpsee::core::RpcResult;
 use m_proc_macros::versioned_rpc;
+use mp_block::H256;
 use mp_transactions::BroadcastedDeclareTransactionV0;
+use serde::{Deserialize, Serialize};
 use starknet_types_core::felt::Felt;
-use starknet_types_rpc::ClassAndTxnHash;
+use starknet_types_rpc::{BroadcastedTxn, ClassAndTxnHash};
+
+use crate::providers::SubmittedTransaction;
+
+/// A transaction waiting in the mempool.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolTransactionEntry {
+    pub transaction_hash: Felt,
+    pub sender_address: Felt,
+    pub nonce: Felt,
+    pub tip: u64,
+    /// Unix time in milliseconds at which the transaction was received.
+    pub arrived_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolTransactionsPage {
+    pub transactions: Vec<MempoolTransactionEntry>,
+    /// Cursor to pass to get the next page, absent on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<Felt>,
+}
+
+/// A transaction of a sender waiting in the mempool, see `getSenderTransactions`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderTransactionEntry {
+    pub nonce: Felt,
+    pub transaction_hash: Felt,
+}
+
+/// What submitting a transaction to the mempool would do, without actually submitting it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionValidation {
+    /// The transaction would be added to the mempool.
+    Accepted {
+        transaction_hash: Felt,
+        /// The transaction with the same sender and nonce it would replace.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replaced_transaction_hash: Option<Felt>,
+        /// The transaction it would evict because the mempool is full.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        evicted_transaction_hash: Option<Felt>,
+    },
+    /// The transaction would be rejected, with the rpc error submitting it would return.
+    Rejected {
+        code: i32,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data: Option<serde_json::Value>,
+    },
+}
+
+/// What happened to a transaction of a batch submitted with `addTransactionBatch`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BatchTransactionResult {
+    /// The transaction was added to the mempool.
+    Accepted {
+        transaction_hash: Felt,
+        /// The mempool is close to full, see [`SubmittedTransaction::near_capacity`].
+        near_capacity: bool,
+    },
+    /// The transaction was rejected, with the rpc error submitting it alone would return.
+    Rejected {
+        code: i32,
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data: Option<serde_json::Value>,
+    },
+}
+
+/// Why a transaction was dropped from the mempool without being included in a block, see `getTransactionDropReason`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionDropReason {
+    /// The transaction exceeded the mempool max age or its deadline.
+    Expired,
+    /// The transaction was evicted to make room for a higher priority one.
+    Evicted,
+    /// The transaction was replaced by another one with the same sender and nonce.
+    Replaced,
+    /// The transaction was rejected because a mempool limit was reached.
+    Rejected,
+    /// The transaction was removed by the node operator.
+    RemovedByOperator,
+}
+
+/// The L1 block an L1->L2 message was consumed from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1MessageOriginEntry {
+    pub block_number: u64,
+    pub block_hash: H256,
+}
+
+/// An L1->L2 message whose L1 handler transaction is waiting in the mempool.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InFlightL1MessageEntry {
+    pub nonce: Felt,
+    pub transaction_hash: Felt,
+    /// Absent for the messages consumed before their origin was tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<L1MessageOriginEntry>,
+}
+
+/// An L1->L2 message received again after its nonce was consumed. It was not added to the mempool.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateL1MessageEntry {
+    pub nonce: Felt,
+    pub transaction_hash: Felt,
+    pub origin: L1MessageOriginEntry,
+    /// The L1 block the nonce was first consumed from, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consumed_from: Option<L1MessageOriginEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1MessagesAudit {
+    pub in_flight: Vec<InFlightL1MessageEntry>,
+    /// The most recent duplicates, oldest first.
+    pub duplicates: Vec<DuplicateL1MessageEntry>,
+}
+
+/// Mempool limits to change at runtime, the absent ones are kept as is.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolLimitsUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_transactions: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_declare_transactions: Option<u64>,
+    /// Max age of the transactions, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ServiceState {
+    Running,
+    Stopped,
+}
+
+/// Health of a node service.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub service: String,
+    pub state: ServiceState,
+    /// Whether the service is connected to the remote it depends on, absent for the services which have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connected: Option<bool>,
+    /// Whether the service stopped calling its remote for a cooldown after repeated failures, absent for the services
+    /// without a circuit breaker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_open: Option<bool>,
+    /// Whether the service was paused by the operator, absent for the services which cannot be paused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused: Option<bool>,
+    /// Unix time in milliseconds at which the service last made progress, such as an L1 gas price update.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_update: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeHealth {
+    pub services: Vec<ServiceHealth>,
+}
 
 /// This is an admin method, so semver is different!
 #[versioned_rpc("V0_1_0", "madara")]
@@ -12,7 +179,270 @@ pub trait MadaraWriteRpcApi {
     async fn add_declare_v0_transaction(
         &self,
         declare_v0_transaction: BroadcastedDeclareTransactionV0,
-    ) -> RpcResult<ClassAndTxnHash<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>>;
+}
+
+#[versioned_rpc("V0_1_0", "madara")]
+pub trait MadaraMempoolRpcApi {
+    /// Lists the transactions in the mempool, ordered by transaction hash. This is meant for debugging.
+    ///
+    /// # Arguments
+    ///
+    /// * `continuation_token` - The token returned with the previous page, if any.
+    /// * `limit` - The maximum number of transactions to return.
+    ///
+    /// # Returns
+    ///
+    /// * A page of mempool transactions, with a continuation token if there are more.
+    #[method(name = "getMempoolTransactions")]
+    async fn get_mempool_transactions(
+        &self,
+        continuation_token: Option<Felt>,
+        limit: Option<u64>,
+    ) -> RpcResult<MempoolTransactionsPage>;
+
+    /// Lists the transactions of an account waiting in the mempool, ready and pending. Wallets use it to rebuild the
+    /// chain of nonces the account has in flight.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender_address` - The account whose transactions are listed.
+    ///
+    /// # Returns
+    ///
+    /// * The nonces and hashes of the transactions, ordered by nonce.
+    #[method(name = "getSenderTransactions")]
+    async fn get_sender_transactions(&self, sender_address: Felt) -> RpcResult<Vec<SenderTransactionEntry>>;
+
+    /// Runs the checks the mempool performs on a submitted transaction - validation, nonce, fee and mempool limits -
+    /// without inserting it. Nothing is modified, so this can be used to know why a transaction would be rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction` - The transaction to check.
+    ///
+    /// # Returns
+    ///
+    /// * Whether the transaction would be accepted, and if so which transaction it would replace or evict.
+    #[method(name = "validateTransaction")]
+    async fn validate_transaction(&self, transaction: BroadcastedTxn<Felt>) -> RpcResult<TransactionValidation>;
+
+    /// Submits several transactions in one call. They are added to the mempool in order, each as if it was submitted
+    /// on its own: the mempool limits count the transactions accepted earlier in the batch, and a rejected transaction
+    /// does not prevent the following ones from being added.
+    ///
+    /// # Arguments
+    ///
+    /// * `transactions` - The transactions to submit.
+    ///
+    /// # Returns
+    ///
+    /// * Whether each transaction was accepted, in the order of the batch.
+    #[method(name = "addTransactionBatch")]
+    async fn add_transaction_batch(
+        &self,
+        transactions: Vec<BroadcastedTxn<Felt>>,
+    ) -> RpcResult<Vec<BatchTransactionResult>>;
+
+    /// Submits a transaction, optionally with an idempotency key. A submission with a key used by an earlier accepted
+    /// submission of the same transaction returns the result of that submission, without submitting the transaction
+    /// again: a client can safely retry a submission which timed out. Reusing a key for another transaction is
+    /// rejected. Rejected submissions are not recorded. The least recently used keys are forgotten past 10000 keys, and
+    /// all of them when the node restarts. The transaction goes through the same validation as with
+    /// `starknet_add*Transaction`; the keys are only supported when the node inserts the transactions in its own
+    /// mempool.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction` - The transaction to submit.
+    /// * `idempotency_key` - A key chosen by the client, unique to this submission.
+    ///
+    /// # Returns
+    ///
+    /// * The hash of the transaction.
+    #[method(name = "addTransaction")]
+    async fn add_transaction(
+        &self,
+        transaction: BroadcastedTxn<Felt>,
+        idempotency_key: Option<String>,
+    ) -> RpcResult<Felt>;
+
+    /// Submits a transaction which is dropped from the mempool if it has not been included by `deadline`, regardless
+    /// of the mempool max age. The deadline is not kept across node restarts.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction` - The transaction to submit.
+    /// * `deadline` - Unix time in seconds after which the transaction is dropped. A deadline in the past is rejected.
+    ///
+    /// # Returns
+    ///
+    /// * The hash of the transaction.
+    #[method(name = "addTransactionWithDeadline")]
+    async fn add_transaction_with_deadline(&self, transaction: BroadcastedTxn<Felt>, deadline: u64) -> RpcResult<Felt>;
+
+    /// Submits a transaction with a priority hint, which orders it among the transactions with the same tip: a higher
+    /// hint is popped first. This lets a relayer order its own transactions. The hint is not kept across node restarts.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction` - The transaction to submit.
+    /// * `priority_hint` - The priority hint, from 0 to 100.
+    ///
+    /// # Returns
+    ///
+    /// * The hash of the transaction.
+    #[method(name = "addTransactionWithPriorityHint")]
+    async fn add_transaction_with_priority_hint(
+        &self,
+        transaction: BroadcastedTxn<Felt>,
+        priority_hint: u8,
+    ) -> RpcResult<Felt>;
+
+    /// Changes the mempool limits without restarting the node. Lowering a limit below the current occupancy does not
+    /// evict anything: new transactions are rejected until the mempool drains below it. The transactions older than a
+    /// lowered max age expire as usual.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - The limits to change.
+    ///
+    /// # Returns
+    ///
+    /// * The previous limits, which can be passed back to restore them.
+    #[method(name = "updateMempoolLimits")]
+    async fn update_mempool_limits(&self, limits: MempoolLimitsUpdate) -> RpcResult<MempoolLimitsUpdate>;
+
+    /// Lists the L1->L2 messages whose L1 handler transactions are in the mempool, with the L1 block they come from,
+    /// and the messages received again after their nonce was consumed. L1 handler transactions bypass the mempool
+    /// limits, this is meant to audit them for duplicates.
+    ///
+    /// # Returns
+    ///
+    /// * The in-flight L1->L2 messages ordered by nonce, and the most recent duplicates.
+    #[method(name = "getL1MessagesAudit")]
+    async fn get_l1_messages_audit(&self) -> RpcResult<L1MessagesAudit>;
+
+    /// Lists the transactions block production would take from the mempool for its next batch, given the current
+    /// L1 gas price, without taking them. The transactions which cannot pay the L1 gas price are left out, as well as
+    /// the later transactions of their senders. This is a preview: the bouncer may still close the block earlier.
+    ///
+    /// # Returns
+    ///
+    /// * Up to `execution_batch_size` transactions, in the order they would be executed.
+    #[method(name = "previewNextBlock")]
+    async fn preview_next_block(&self) -> RpcResult<Vec<MempoolTransactionEntry>>;
+
+    /// Removes a transaction from the mempool, for when it is causing issues during block production. The following
+    /// transactions of its sender are kept, but they are not included in blocks until it is submitted again to fill the
+    /// nonce gap.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_hash` - The hash of the transaction to remove.
+    ///
+    /// # Returns
+    ///
+    /// * Whether the transaction was in the mempool.
+    #[method(name = "removeMempoolTransaction")]
+    async fn remove_mempool_transaction(&self, transaction_hash: Felt) -> RpcResult<bool>;
+
+    /// Tells why a transaction left the mempool without being included in a block, to answer "where did my
+    /// transaction go?". Only the most recently dropped transactions are remembered, see the
+    /// `mempool_dropped_tx_lru_size` chain config, and none across node restarts.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction_hash` - The hash of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// * Why the transaction was dropped, or nothing if it was not dropped recently: it may be in the mempool, included
+    ///   in a block, or forgotten.
+    #[method(name = "getTransactionDropReason")]
+    async fn get_transaction_drop_reason(&self, transaction_hash: Felt) -> RpcResult<Option<TransactionDropReason>>;
+
+    /// Streams the hashes of the transactions accepted in the mempool, for real-time dashboards. The subscription is
+    /// rejected past the max number of subscribers, see `--rpc-max-mempool-subscribers`. A subscriber which falls
+    /// behind misses the oldest transactions.
+    ///
+    /// # Sends
+    ///
+    /// * The hash of each transaction accepted in the mempool, including replacements.
+    #[subscription(name = "subscribeMempool", unsubscribe = "unsubscribeMempool", item = Felt)]
+    async fn subscribe_mempool(&self) -> jsonrpsee::core::SubscriptionResult;
+}
+
+#[versioned_rpc("V0_1_0", "madara")]
+pub trait MadaraGasPriceRpcApi {
+    /// Changes the interval at which the L1 gas prices are fetched, taking effect once the current wait is over.
+    ///
+    /// This only affects the gas prices being synced, fixed gas prices are never fetched.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval_ms` - The new poll interval, in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// * The previous poll interval, in milliseconds.
+    #[method(name = "setGasPricePollInterval")]
+    async fn set_gas_price_poll_interval(&self, interval_ms: u64) -> RpcResult<u64>;
+}
+
+#[versioned_rpc("V0_1_0", "madara")]
+pub trait MadaraL1RpcApi {
+    /// Changes the address of the L1 core contract watched by the L1 sync, without restarting the node. The L1 sync
+    /// workers complete their current iteration, then restart with event filters for the new address.
+    ///
+    /// The L1 sync fails once its reconnection attempts are exhausted if no contract is deployed at the new address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The new address, a `0x` prefixed 20 bytes hex string.
+    ///
+    /// # Returns
+    ///
+    /// * The previous address.
+    #[method(name = "setL1CoreContractAddress")]
+    async fn set_l1_core_contract_address(&self, address: String) -> RpcResult<String>;
+
+    /// Pauses the L1 sync, without restarting the node. The L1 sync workers complete their current iteration, then
+    /// stop advancing until `madara_resumeL1Sync` is called. The connection to the L1 endpoint is kept.
+    ///
+    /// The L1 gas prices are not updated while the L1 sync is paused: block production pauses once they are older than
+    /// the gas price max age.
+    ///
+    /// # Returns
+    ///
+    /// * Whether the L1 sync was already paused.
+    #[method(name = "pauseL1Sync")]
+    async fn pause_l1_sync(&self) -> RpcResult<bool>;
+
+    /// Resumes the L1 sync paused with `madara_pauseL1Sync`. The L1 sync workers start over from the last processed L1
+    /// block.
+    ///
+    /// # Returns
+    ///
+    /// * Whether the L1 sync was paused.
+    #[method(name = "resumeL1Sync")]
+    async fn resume_l1_sync(&self) -> RpcResult<bool>;
+
+    /// Rotates the L1 RPC endpoints of the L1 sync, without restarting the node. The L1 sync workers keep running on
+    /// the current endpoints while the new ones are connected to and the L1 gas prices are fetched from them, then they
+    /// restart on the new endpoints: the gas prices do not go stale during the rotation.
+    ///
+    /// The current endpoints are kept when none of the new ones is usable.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoints` - The new `http` or `https` endpoints, tried in order on failover.
+    ///
+    /// # Returns
+    ///
+    /// * Whether the endpoints changed.
+    #[method(name = "setL1Endpoints")]
+    async fn set_l1_endpoints(&self, endpoints: Vec<String>) -> RpcResult<bool>;
 }
 
 #[versioned_rpc("V0_1_0", "madara")]
@@ -33,6 +463,15 @@ pub trait MadaraStatusRpcApi {
     #[method(name = "shutdown")]
     async fn shutdown(&self) -> RpcResult<u64>;
 
+    /// Summarizes the status of each of the node services, including whether the L1 sync is connected to the L1 and
+    /// when it last updated the L1 gas prices.
+    ///
+    /// # Returns
+    ///
+    /// * The health of every service.
+    #[method(name = "health")]
+    async fn health(&self) -> RpcResult<NodeHealth>;
+
     /// Periodically sends a signal that the node is alive.
     ///
     /// # Sends
@@ -109,4 +548,20 @@ pub trait MadaraServicesRpcApi {
     /// True if l1 or l2 sync was previously enabled.
     #[method(name = "syncRestart")]
     async fn service_sync_restart(&self) -> RpcResult<bool>;
+
+    /// Restarts a service by stopping its tasks and starting a fresh instance of it. This can be used to bring back a
+    /// service which died, such as the l1 sync.
+    ///
+    /// This only works for the services which can be restarted, otherwise this
+    /// does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The name of the service, as listed by `health`.
+    ///
+    /// # Returns
+    ///
+    /// True if a restart of the service was requested.
+    #[method(name = "restartService")]
+    async fn restart_service(&self, service: String) -> RpcResult<bool>;
 }
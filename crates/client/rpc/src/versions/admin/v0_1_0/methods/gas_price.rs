@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use jsonrpsee::core::{async_trait, RpcResult};
+
+use crate::{errors::StarknetRpcApiError, versions::admin::v0_1_0::MadaraGasPriceRpcApiV0_1_0Server, Starknet};
+
+#[async_trait]
+impl MadaraGasPriceRpcApiV0_1_0Server for Starknet {
+    async fn set_gas_price_poll_interval(&self, interval_ms: u64) -> RpcResult<u64> {
+        let Some(l1_gas_provider) = &self.l1_gas_provider else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let previous = l1_gas_provider
+            .set_poll_interval(Duration::from_millis(interval_ms))
+            .map_err(|err| StarknetRpcApiError::ErrUnexpectedError { data: err.to_string() })?;
+        tracing::info!("⛽ Gas price poll interval set to {interval_ms}ms");
+
+        Ok(u64::try_from(previous.as_millis()).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+    use mc_db::MadaraBackend;
+    use mc_mempool::{GasPriceProvider, DEFAULT_GAS_PRICE_POLL_INTERVAL};
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_gas_price_poll_interval(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_, rpc) = rpc_test_setup;
+        let l1_gas_provider = GasPriceProvider::new();
+        let rpc = rpc.with_gas_price_provider(l1_gas_provider.clone());
+
+        let previous = rpc.set_gas_price_poll_interval(2_000).await.unwrap();
+        assert_eq!(previous, DEFAULT_GAS_PRICE_POLL_INTERVAL.as_millis() as u64);
+        assert_eq!(l1_gas_provider.poll_interval(), Duration::from_secs(2));
+        assert_eq!(rpc.set_gas_price_poll_interval(500).await.unwrap(), 2_000);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_gas_price_poll_interval_out_of_bounds(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_, rpc) = rpc_test_setup;
+        let l1_gas_provider = GasPriceProvider::new();
+        let rpc = rpc.with_gas_price_provider(l1_gas_provider.clone());
+
+        assert!(rpc.set_gas_price_poll_interval(0).await.is_err());
+        assert!(rpc.set_gas_price_poll_interval(24 * 60 * 60 * 1000).await.is_err());
+        assert_eq!(l1_gas_provider.poll_interval(), DEFAULT_GAS_PRICE_POLL_INTERVAL);
+    }
+}
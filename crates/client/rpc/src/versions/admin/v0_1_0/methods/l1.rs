@@ -0,0 +1,136 @@
+use jsonrpsee::core::{async_trait, RpcResult};
+use mp_chain_config::{parse_l1_core_address, parse_l1_endpoints};
+
+use crate::{errors::StarknetRpcApiError, versions::admin::v0_1_0::MadaraL1RpcApiV0_1_0Server, Starknet};
+
+#[async_trait]
+impl MadaraL1RpcApiV0_1_0Server for Starknet {
+    async fn set_l1_core_contract_address(&self, address: String) -> RpcResult<String> {
+        let Some(l1_core_address) = &self.l1_core_address else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let address = parse_l1_core_address(&address)
+            .map_err(|err| StarknetRpcApiError::ErrUnexpectedError { data: err.to_string() })?;
+        let previous = l1_core_address.set(address);
+        tracing::info!("🔁 L1 core contract address set to {address:#x}, was {previous:#x}");
+
+        Ok(format!("{previous:#x}"))
+    }
+
+    async fn pause_l1_sync(&self) -> RpcResult<bool> {
+        let Some(l1_sync_pause) = &self.l1_sync_pause else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let was_paused = l1_sync_pause.pause();
+        if !was_paused {
+            tracing::info!("⏸️ Pausing the L1 sync");
+        }
+        Ok(was_paused)
+    }
+
+    async fn resume_l1_sync(&self) -> RpcResult<bool> {
+        let Some(l1_sync_pause) = &self.l1_sync_pause else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let was_paused = l1_sync_pause.resume();
+        if was_paused {
+            tracing::info!("▶️ Resuming the L1 sync");
+        }
+        Ok(was_paused)
+    }
+
+    async fn set_l1_endpoints(&self, endpoints: Vec<String>) -> RpcResult<bool> {
+        let Some(l1_endpoints) = &self.l1_endpoints else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let endpoints = parse_l1_endpoints(&endpoints)
+            .map_err(|err| StarknetRpcApiError::ErrUnexpectedError { data: err.to_string() })?;
+        if l1_endpoints.get() == endpoints {
+            return Ok(false);
+        }
+        // The endpoints are not logged, they may embed an API key.
+        tracing::info!("🔁 Rotating to {} L1 endpoints", endpoints.len());
+        l1_endpoints.set(endpoints);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+    use mc_db::MadaraBackend;
+    use mp_chain_config::{L1CoreAddress, L1Endpoints};
+    use mp_utils::service::PauseHandle;
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_l1_core_contract_address(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let initial = backend.chain_config().eth_core_contract_address;
+        let l1_core_address = L1CoreAddress::new(initial);
+        let rpc = rpc.with_l1_core_address(l1_core_address.clone());
+        let new_address = "0xe2bb56ee936fd6433dc0f6e7e3b8365c906aa057";
+
+        let previous = rpc.set_l1_core_contract_address(new_address.into()).await.unwrap();
+        assert_eq!(previous, format!("{initial:#x}"));
+        assert_eq!(format!("{:#x}", l1_core_address.get()), new_address);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_l1_core_contract_address_invalid(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let initial = backend.chain_config().eth_core_contract_address;
+        let l1_core_address = L1CoreAddress::new(initial);
+        let rpc = rpc.with_l1_core_address(l1_core_address.clone());
+
+        assert!(rpc.set_l1_core_contract_address("e2bb56ee936fd6433dc0f6e7e3b8365c906aa057".into()).await.is_err());
+        assert!(rpc.set_l1_core_contract_address("0x1".into()).await.is_err());
+        assert_eq!(l1_core_address.get(), initial);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_pause_and_resume_l1_sync(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_, rpc) = rpc_test_setup;
+        let l1_sync_pause = PauseHandle::new();
+        let rpc = rpc.with_l1_sync_pause(l1_sync_pause.clone());
+
+        assert!(!rpc.pause_l1_sync().await.unwrap());
+        assert!(l1_sync_pause.is_paused());
+        assert!(rpc.pause_l1_sync().await.unwrap());
+
+        assert!(rpc.resume_l1_sync().await.unwrap());
+        assert!(!l1_sync_pause.is_paused());
+        assert!(!rpc.resume_l1_sync().await.unwrap());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_set_l1_endpoints(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_, rpc) = rpc_test_setup;
+        let l1_endpoints = L1Endpoints::new(parse_l1_endpoints(&["http://127.0.0.1:8545".into()]).unwrap());
+        let rpc = rpc.with_l1_endpoints(l1_endpoints.clone());
+        let mut receiver = l1_endpoints.subscribe();
+
+        assert!(!rpc.set_l1_endpoints(vec!["http://127.0.0.1:8545".into()]).await.unwrap());
+        assert!(!receiver.has_changed().unwrap());
+
+        let new_endpoints = vec!["https://eth.example.com/".into(), "http://127.0.0.1:8546/".into()];
+        assert!(rpc.set_l1_endpoints(new_endpoints.clone()).await.unwrap());
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(l1_endpoints.get().iter().map(|url| url.to_string()).collect::<Vec<_>>(), new_endpoints);
+
+        // Invalid endpoints are rejected, the current ones are kept.
+        assert!(rpc.set_l1_endpoints(vec![]).await.is_err());
+        assert!(rpc.set_l1_endpoints(vec!["wss://eth.example.com".into()]).await.is_err());
+        assert_eq!(l1_endpoints.get().len(), 2);
+    }
+}
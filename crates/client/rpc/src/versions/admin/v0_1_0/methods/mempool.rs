@@ -0,0 +1,673 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use jsonrpsee::core::{async_trait, RpcResult};
+use mc_db::l1_db::L1MessageOrigin;
+use mc_mempool::{DropReason, InsertOutcome, MempoolEvent, MempoolTransactionInfo};
+use mp_block::H256;
+use starknet_api::core::ContractAddress;
+use starknet_types_core::felt::Felt;
+use starknet_types_rpc::BroadcastedTxn;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    constants::MAX_MEMPOOL_TRANSACTIONS_PAGE_SIZE,
+    errors::{ErrorExtWs, StarknetRpcApiError},
+    versions::admin::v0_1_0::{
+        BatchTransactionResult, DuplicateL1MessageEntry, InFlightL1MessageEntry, L1MessageOriginEntry, L1MessagesAudit,
+        MadaraMempoolRpcApiV0_1_0Server, MempoolLimitsUpdate, MempoolTransactionEntry, MempoolTransactionsPage,
+        SenderTransactionEntry, TransactionDropReason, TransactionValidation,
+    },
+    Starknet,
+};
+
+#[async_trait]
+impl MadaraMempoolRpcApiV0_1_0Server for Starknet {
+    async fn get_mempool_transactions(
+        &self,
+        continuation_token: Option<Felt>,
+        limit: Option<u64>,
+    ) -> RpcResult<MempoolTransactionsPage> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+        let limit = match limit {
+            Some(limit) => usize::try_from(limit).unwrap_or(usize::MAX),
+            None => MAX_MEMPOOL_TRANSACTIONS_PAGE_SIZE,
+        };
+        if limit > MAX_MEMPOOL_TRANSACTIONS_PAGE_SIZE {
+            return Err(StarknetRpcApiError::PageSizeTooBig.into());
+        }
+
+        // Get one more transaction to know whether there is a next page.
+        let mut transactions = mempool.transactions_snapshot(continuation_token, limit + 1);
+        let continuation_token = if transactions.len() > limit {
+            transactions.truncate(limit);
+            transactions.last().map(|tx| tx.tx_hash)
+        } else {
+            None
+        };
+
+        Ok(MempoolTransactionsPage {
+            transactions: transactions.into_iter().map(to_entry).collect(),
+            continuation_token,
+        })
+    }
+
+    async fn get_sender_transactions(&self, sender_address: Felt) -> RpcResult<Vec<SenderTransactionEntry>> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+        // No transaction can be sent from an address out of the contract address range.
+        let Ok(sender) = ContractAddress::try_from(sender_address) else {
+            return Ok(vec![]);
+        };
+
+        Ok(mempool
+            .get_by_sender(sender)
+            .into_iter()
+            .map(|(nonce, tx_hash)| SenderTransactionEntry { nonce: nonce.0, transaction_hash: tx_hash.0 })
+            .collect())
+    }
+
+    async fn validate_transaction(&self, transaction: BroadcastedTxn<Felt>) -> RpcResult<TransactionValidation> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        match mempool.dry_run_tx(transaction) {
+            Ok(accepted) => {
+                let (replaced_transaction_hash, evicted_transaction_hash) = match accepted.outcome {
+                    InsertOutcome::Added | InsertOutcome::Refreshed => (None, None),
+                    InsertOutcome::Replaced(previous) => (Some(previous), None),
+                    InsertOutcome::EvictedToFit(evicted) => (None, Some(evicted)),
+                    InsertOutcome::AlreadyKnown => {
+                        let err = StarknetRpcApiError::DuplicateTxn;
+                        return Ok(TransactionValidation::Rejected {
+                            code: (&err).into(),
+                            message: err.to_string(),
+                            data: err.data(),
+                        });
+                    }
+                };
+                Ok(TransactionValidation::Accepted {
+                    transaction_hash: accepted.result,
+                    replaced_transaction_hash,
+                    evicted_transaction_hash,
+                })
+            }
+            // Only the rejections are reported, a node failure is still an error.
+            Err(err) if err.is_internal() => Err(StarknetRpcApiError::from(err).into()),
+            Err(err) => {
+                let err = StarknetRpcApiError::from(err);
+                Ok(TransactionValidation::Rejected { code: (&err).into(), message: err.to_string(), data: err.data() })
+            }
+        }
+    }
+
+    async fn add_transaction_batch(
+        &self,
+        transactions: Vec<BroadcastedTxn<Felt>>,
+    ) -> RpcResult<Vec<BatchTransactionResult>> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let results = mempool
+            .accept_tx_batch(transactions)
+            .into_iter()
+            .map(|result| {
+                let err = match result {
+                    Ok(accepted) if accepted.outcome != InsertOutcome::AlreadyKnown => {
+                        return BatchTransactionResult::Accepted {
+                            transaction_hash: accepted.result,
+                            near_capacity: accepted.near_capacity,
+                        };
+                    }
+                    Ok(_) => StarknetRpcApiError::DuplicateTxn,
+                    Err(err) => StarknetRpcApiError::from(err),
+                };
+                BatchTransactionResult::Rejected { code: (&err).into(), message: err.to_string(), data: err.data() }
+            })
+            .collect();
+        Ok(results)
+    }
+
+    async fn add_transaction(
+        &self,
+        transaction: BroadcastedTxn<Felt>,
+        idempotency_key: Option<String>,
+    ) -> RpcResult<Felt> {
+        Ok(self.add_transaction_provider.add_transaction(transaction, idempotency_key).await?.result)
+    }
+
+    async fn add_transaction_with_deadline(&self, transaction: BroadcastedTxn<Felt>, deadline: u64) -> RpcResult<Felt> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let deadline = SystemTime::UNIX_EPOCH
+            .checked_add(Duration::from_secs(deadline))
+            .ok_or_else(|| StarknetRpcApiError::ErrUnexpectedError { data: format!("Invalid deadline: {deadline}") })?;
+        let accepted = mempool.accept_tx_until(transaction, deadline).map_err(StarknetRpcApiError::from)?;
+        if accepted.outcome == InsertOutcome::AlreadyKnown {
+            return Err(StarknetRpcApiError::DuplicateTxn.into());
+        }
+        Ok(accepted.result)
+    }
+
+    async fn add_transaction_with_priority_hint(
+        &self,
+        transaction: BroadcastedTxn<Felt>,
+        priority_hint: u8,
+    ) -> RpcResult<Felt> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let accepted =
+            mempool.accept_tx_with_priority_hint(transaction, priority_hint).map_err(StarknetRpcApiError::from)?;
+        if accepted.outcome == InsertOutcome::AlreadyKnown {
+            return Err(StarknetRpcApiError::DuplicateTxn.into());
+        }
+        Ok(accepted.result)
+    }
+
+    async fn update_mempool_limits(&self, limits: MempoolLimitsUpdate) -> RpcResult<MempoolLimitsUpdate> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let to_usize = |limit: u64| usize::try_from(limit).unwrap_or(usize::MAX);
+        let previous = mempool
+            .update_limits(mc_mempool::MempoolLimitsUpdate {
+                max_transactions: limits.max_transactions.map(to_usize),
+                max_declare_transactions: limits.max_declare_transactions.map(to_usize),
+                max_age: limits.max_age_secs.map(Duration::from_secs),
+            })
+            .map_err(|err| StarknetRpcApiError::ErrUnexpectedError { data: err.to_string() })?;
+
+        let to_u64 = |limit: usize| u64::try_from(limit).unwrap_or(u64::MAX);
+        Ok(MempoolLimitsUpdate {
+            max_transactions: previous.max_transactions.map(to_u64),
+            max_declare_transactions: previous.max_declare_transactions.map(to_u64),
+            max_age_secs: previous.max_age.map(|max_age| max_age.as_secs()),
+        })
+    }
+
+    async fn get_l1_messages_audit(&self) -> RpcResult<L1MessagesAudit> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let in_flight = mempool
+            .in_flight_l1_messages()
+            .map_err(StarknetRpcApiError::from)?
+            .into_iter()
+            .map(|message| InFlightL1MessageEntry {
+                nonce: message.nonce.0,
+                transaction_hash: message.tx_hash,
+                origin: message.origin.map(to_origin_entry),
+            })
+            .collect();
+        let duplicates = mempool
+            .duplicate_l1_messages()
+            .into_iter()
+            .map(|message| DuplicateL1MessageEntry {
+                nonce: message.nonce.0,
+                transaction_hash: message.tx_hash,
+                origin: to_origin_entry(message.origin),
+                consumed_from: message.consumed_from.map(to_origin_entry),
+            })
+            .collect();
+
+        Ok(L1MessagesAudit { in_flight, duplicates })
+    }
+
+    async fn preview_next_block(&self) -> RpcResult<Vec<MempoolTransactionEntry>> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        let n = self.clone_chain_config().execution_batch_size;
+        Ok(mempool.preview_next_block(n).into_iter().map(to_entry).collect())
+    }
+
+    async fn remove_mempool_transaction(&self, transaction_hash: Felt) -> RpcResult<bool> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        Ok(mempool.remove_by_hash(transaction_hash).map_err(StarknetRpcApiError::from)?)
+    }
+
+    async fn get_transaction_drop_reason(&self, transaction_hash: Felt) -> RpcResult<Option<TransactionDropReason>> {
+        let Some(mempool) = &self.mempool else {
+            return Err(StarknetRpcApiError::UnimplementedMethod.into());
+        };
+
+        Ok(mempool.drop_reason(transaction_hash).map(to_drop_reason))
+    }
+
+    async fn subscribe_mempool(
+        &self,
+        subscription_sink: jsonrpsee::PendingSubscriptionSink,
+    ) -> jsonrpsee::core::SubscriptionResult {
+        let Some(mempool) = &self.mempool else {
+            subscription_sink.reject(StarknetRpcApiError::UnimplementedMethod).await;
+            return Ok(());
+        };
+        // The permit is released when the subscription ends, including when the subscriber disconnects.
+        let Ok(_permit) = Arc::clone(&self.mempool_subscribers).try_acquire_owned() else {
+            subscription_sink.reject(StarknetRpcApiError::TooManyMempoolSubscribers).await;
+            return Ok(());
+        };
+        // Subscribe before accepting, so that no transaction accepted once the subscription is established is missed.
+        let mut events = mempool.subscribe_events();
+        let sink =
+            subscription_sink.accept().await.or_internal_server_error("Failed to establish websocket connection")?;
+
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let tx_hash = match event {
+                        Ok(MempoolEvent::Added { tx_hash } | MempoolEvent::Replaced { tx_hash, .. }) => tx_hash,
+                        Ok(MempoolEvent::Removed { .. }) => continue,
+                        Err(RecvError::Lagged(missed)) => {
+                            tracing::debug!("Mempool subscriber fell behind, {missed} mempool events were missed");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return Ok(()),
+                    };
+                    let msg = jsonrpsee::SubscriptionMessage::from_json(&tx_hash).or_else_internal_server_error(|| {
+                        format!("Failed to create response message for transaction {tx_hash:#x}")
+                    })?;
+                    if sink.send(msg).await.is_err() {
+                        // The subscriber disconnected.
+                        return Ok(());
+                    }
+                },
+                _ = sink.closed() => {
+                    return Ok(())
+                }
+            }
+        }
+    }
+}
+
+fn to_drop_reason(reason: DropReason) -> TransactionDropReason {
+    match reason {
+        DropReason::Expired => TransactionDropReason::Expired,
+        DropReason::Evicted => TransactionDropReason::Evicted,
+        DropReason::Replaced => TransactionDropReason::Replaced,
+        DropReason::RejectedOverLimit => TransactionDropReason::Rejected,
+        DropReason::RemovedByOperator => TransactionDropReason::RemovedByOperator,
+    }
+}
+
+fn to_origin_entry(origin: L1MessageOrigin) -> L1MessageOriginEntry {
+    L1MessageOriginEntry { block_number: origin.block_number, block_hash: H256(origin.block_hash) }
+}
+
+fn to_entry(tx: MempoolTransactionInfo) -> MempoolTransactionEntry {
+    let arrived_at = tx.arrived_at.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    MempoolTransactionEntry {
+        transaction_hash: tx.tx_hash,
+        sender_address: tx.contract_address,
+        nonce: tx.nonce,
+        tip: tx.tip,
+        arrived_at: u64::try_from(arrived_at).unwrap_or(u64::MAX),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+    use crate::versions::admin::v0_1_0::MadaraMempoolRpcApiV0_1_0Client;
+    use jsonrpsee::core::ClientError;
+    use jsonrpsee::ws_client::WsClientBuilder;
+    use mc_db::MadaraBackend;
+    use mc_mempool::{L1DataProvider, Mempool, MempoolLimits, MempoolProvider, MempoolTransaction, MockL1DataProvider};
+    use mp_transactions::BroadcastedTransactionExt;
+    use rstest::rstest;
+    use starknet_types_rpc::{BroadcastedInvokeTxn, DaMode, InvokeTxnV3, ResourceBounds, ResourceBoundsMapping};
+    use std::sync::Arc;
+
+    fn broadcasted_invoke_tx(sender_address: Felt, tip: u64) -> BroadcastedTxn<Felt> {
+        BroadcastedTxn::Invoke(BroadcastedInvokeTxn::V3(InvokeTxnV3 {
+            sender_address,
+            calldata: vec![],
+            signature: vec![],
+            nonce: Felt::ZERO,
+            resource_bounds: ResourceBoundsMapping {
+                l1_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+                l2_gas: ResourceBounds { max_amount: 60000, max_price_per_unit: 10000 },
+            },
+            tip,
+            paymaster_data: vec![],
+            account_deployment_data: vec![],
+            nonce_data_availability_mode: DaMode::L1,
+            fee_data_availability_mode: DaMode::L1,
+        }))
+    }
+
+    fn invoke_tx(starknet: &Starknet, sender_address: Felt, tip: u64) -> MempoolTransaction {
+        let (tx, converted_class) = broadcasted_invoke_tx(sender_address, tip)
+            .into_blockifier(starknet.chain_id(), starknet.clone_chain_config().latest_protocol_version)
+            .unwrap();
+        MempoolTransaction::new(tx, SystemTime::now(), converted_class)
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_mempool_transactions(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        let txs = [invoke_tx(&rpc, Felt::ONE, 10), invoke_tx(&rpc, Felt::TWO, 20), invoke_tx(&rpc, Felt::THREE, 30)];
+        let mut expected: Vec<_> = txs.iter().map(|tx| to_entry(MempoolTransactionInfo::from(tx))).collect();
+        expected.sort_by_key(|tx| tx.transaction_hash);
+        mempool.re_add_txs(txs, []);
+
+        let page = rpc.get_mempool_transactions(None, Some(2)).await.unwrap();
+        assert_eq!(page.transactions, expected[..2]);
+        assert_eq!(page.continuation_token, Some(expected[1].transaction_hash));
+
+        let page = rpc.get_mempool_transactions(page.continuation_token, Some(2)).await.unwrap();
+        assert_eq!(page.transactions, expected[2..]);
+        assert_eq!(page.continuation_token, None);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_mempool_transactions_page_size_too_big(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool = Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing());
+        let rpc = rpc.with_mempool(Arc::new(mempool));
+
+        let limit = MAX_MEMPOOL_TRANSACTIONS_PAGE_SIZE as u64 + 1;
+        assert_eq!(
+            rpc.get_mempool_transactions(None, Some(limit)).await,
+            Err(StarknetRpcApiError::PageSizeTooBig.into())
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_sender_transactions(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        let sender_tx = invoke_tx(&rpc, Felt::ONE, 10);
+        let expected = SenderTransactionEntry { nonce: Felt::ZERO, transaction_hash: sender_tx.tx_hash().0 };
+        mempool.re_add_txs([sender_tx, invoke_tx(&rpc, Felt::TWO, 20)], []);
+
+        assert_eq!(rpc.get_sender_transactions(Felt::ONE).await.unwrap(), [expected]);
+        assert!(rpc.get_sender_transactions(Felt::THREE).await.unwrap().is_empty());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_remove_mempool_transaction(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        let tx = invoke_tx(&rpc, Felt::ONE, 10);
+        let tx_hash = tx.tx_hash().0;
+        mempool.re_add_txs([tx, invoke_tx(&rpc, Felt::TWO, 20)], []);
+
+        assert!(rpc.remove_mempool_transaction(tx_hash).await.unwrap());
+        assert!(rpc.get_sender_transactions(Felt::ONE).await.unwrap().is_empty());
+        assert_eq!(rpc.get_sender_transactions(Felt::TWO).await.unwrap().len(), 1);
+        assert!(!rpc.remove_mempool_transaction(tx_hash).await.unwrap());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_transaction_drop_reason(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        let tx = invoke_tx(&rpc, Felt::ONE, 10);
+        let tx_hash = tx.tx_hash().0;
+        mempool.re_add_txs([tx], []);
+        assert_eq!(rpc.get_transaction_drop_reason(tx_hash).await.unwrap(), None);
+
+        assert!(rpc.remove_mempool_transaction(tx_hash).await.unwrap());
+        assert_eq!(
+            rpc.get_transaction_drop_reason(tx_hash).await.unwrap(),
+            Some(TransactionDropReason::RemovedByOperator)
+        );
+        assert_eq!(rpc.get_transaction_drop_reason(Felt::TWO).await.unwrap(), None);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_subscribe_mempool(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool)).with_max_mempool_subscribers(1);
+        let server = jsonrpsee::server::Server::builder().build("127.0.0.1:0").await.expect("Starting server");
+        let server_url = format!("ws://{}", server.local_addr().expect("Retrieving server local address"));
+        // Server will be stopped once this is dropped
+        let _server_handle = server.start(MadaraMempoolRpcApiV0_1_0Server::into_rpc(rpc));
+        let client = WsClientBuilder::default().build(&server_url).await.expect("Building client");
+
+        let sub = client.subscribe_mempool().await.expect("madara_subscribeMempool");
+        let Err(ClientError::Call(err)) = client.subscribe_mempool().await else {
+            panic!("Expected the subscription past the max number of subscribers to be rejected");
+        };
+        assert_eq!(err.code(), 10002);
+
+        // Disconnecting frees the slot of the subscriber.
+        sub.unsubscribe().await.expect("madara_unsubscribeMempool");
+        let mut sub = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(sub) = client.subscribe_mempool().await {
+                    break sub;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("The slot of the subscriber was not freed");
+
+        let l1_handler_tx = mp_transactions::L1HandlerTransaction {
+            nonce: 3,
+            contract_address: Felt::from(0x1234),
+            calldata: vec![Felt::ONE],
+            ..Default::default()
+        };
+        let accepted =
+            mempool.accept_l1_message(l1_handler_tx, 0, L1MessageOrigin::new(100, [1; 32])).unwrap().unwrap();
+        let tx_hash = sub.next().await.expect("Waiting for transaction hash").expect("Waiting for transaction hash");
+        assert_eq!(tx_hash, accepted.result.transaction_hash);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_validate_transaction_reports_rejection(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        // There is no account deployed at this address, the validation fails.
+        let validation = rpc.validate_transaction(broadcasted_invoke_tx(Felt::ONE, 10)).await.unwrap();
+        let TransactionValidation::Rejected { code, .. } = validation else {
+            panic!("Expected the transaction to be rejected, got {validation:?}");
+        };
+        assert_eq!(code, 55);
+        assert!(mempool.is_empty());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_validate_transaction_without_mempool(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_backend, rpc) = rpc_test_setup;
+        assert_eq!(
+            rpc.validate_transaction(broadcasted_invoke_tx(Felt::ONE, 10)).await,
+            Err(StarknetRpcApiError::UnimplementedMethod.into())
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_add_transaction_with_invalid_priority_hint(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        assert_eq!(
+            rpc.add_transaction_with_priority_hint(broadcasted_invoke_tx(Felt::ONE, 10), 101).await,
+            Err(StarknetRpcApiError::FailedToReceiveTxn {
+                err: Some("The priority hint 101 is above the maximum of 100".into())
+            }
+            .into())
+        );
+        assert!(mempool.is_empty());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_add_transaction_with_invalid_deadline(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        assert_eq!(
+            rpc.add_transaction_with_deadline(broadcasted_invoke_tx(Felt::ONE, 10), u64::MAX).await,
+            Err(StarknetRpcApiError::ErrUnexpectedError { data: format!("Invalid deadline: {}", u64::MAX) }.into())
+        );
+        assert!(mempool.is_empty());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_add_transaction_batch_reports_each_rejection(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        // There is no account deployed at these addresses, the validation fails for each of them.
+        let batch = vec![broadcasted_invoke_tx(Felt::ONE, 10), broadcasted_invoke_tx(Felt::TWO, 10)];
+        let results = rpc.add_transaction_batch(batch).await.unwrap();
+        assert_eq!(results.len(), 2);
+        for result in results {
+            let BatchTransactionResult::Rejected { code, .. } = result else {
+                panic!("Expected the transaction to be rejected, got {result:?}");
+            };
+            assert_eq!(code, 55);
+        }
+        assert!(mempool.is_empty());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_add_transaction_batch_without_mempool(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_backend, rpc) = rpc_test_setup;
+        assert_eq!(
+            rpc.add_transaction_batch(vec![broadcasted_invoke_tx(Felt::ONE, 10)]).await,
+            Err(StarknetRpcApiError::UnimplementedMethod.into())
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_update_mempool_limits(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let limits = MempoolLimits { max_transactions: 100, ..MempoolLimits::for_testing() };
+        let mempool = Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), limits));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        let update = MempoolLimitsUpdate { max_transactions: Some(10), ..Default::default() };
+        let previous = rpc.update_mempool_limits(update).await.unwrap();
+        assert_eq!(previous.max_transactions, Some(100));
+        assert_eq!(rpc.update_mempool_limits(previous).await.unwrap().max_transactions, Some(10));
+
+        let invalid = MempoolLimitsUpdate { max_age_secs: Some(0), ..Default::default() };
+        assert!(rpc.update_mempool_limits(invalid).await.is_err());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_l1_messages_audit(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mempool =
+            Arc::new(Mempool::new(backend, Arc::new(MockL1DataProvider::new()), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        let l1_handler_tx = mp_transactions::L1HandlerTransaction {
+            nonce: 3,
+            contract_address: Felt::from(0x1234),
+            calldata: vec![Felt::ONE],
+            ..Default::default()
+        };
+        let origin = L1MessageOrigin::new(100, [1; 32]);
+        assert!(mempool.accept_l1_message(l1_handler_tx.clone(), 0, origin).unwrap().is_some());
+        assert!(mempool.accept_l1_message(l1_handler_tx, 0, L1MessageOrigin::new(101, [2; 32])).unwrap().is_none());
+
+        let audit = rpc.get_l1_messages_audit().await.unwrap();
+        let origin_entry = L1MessageOriginEntry { block_number: 100, block_hash: H256([1; 32]) };
+        assert_eq!(audit.in_flight.len(), 1);
+        assert_eq!(audit.in_flight[0].origin, Some(origin_entry.clone()));
+        assert_eq!(audit.duplicates.len(), 1);
+        assert_eq!(audit.duplicates[0].nonce, Felt::from(3));
+        assert_eq!(audit.duplicates[0].consumed_from, Some(origin_entry));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_preview_next_block_matches_taken_txs(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (backend, rpc) = rpc_test_setup;
+        let mut l1_data_provider = MockL1DataProvider::new();
+        l1_data_provider
+            .expect_get_gas_prices()
+            .return_const(mp_block::header::GasPrices { strk_l1_gas_price: 10000, ..Default::default() });
+        let l1_data_provider: Arc<dyn L1DataProvider> = Arc::new(l1_data_provider);
+        let mempool = Arc::new(Mempool::new(backend, Arc::clone(&l1_data_provider), MempoolLimits::for_testing()));
+        let rpc = rpc.with_mempool(Arc::clone(&mempool));
+
+        let mut underpriced = broadcasted_invoke_tx(Felt::from(4), 40);
+        if let BroadcastedTxn::Invoke(BroadcastedInvokeTxn::V3(tx)) = &mut underpriced {
+            tx.resource_bounds.l1_gas.max_price_per_unit = 5000;
+        }
+        let (underpriced, converted_class) =
+            underpriced.into_blockifier(rpc.chain_id(), rpc.clone_chain_config().latest_protocol_version).unwrap();
+        mempool.re_add_txs(
+            [
+                invoke_tx(&rpc, Felt::ONE, 10),
+                invoke_tx(&rpc, Felt::TWO, 20),
+                invoke_tx(&rpc, Felt::THREE, 30),
+                MempoolTransaction::new(underpriced, SystemTime::now(), converted_class),
+            ],
+            [],
+        );
+
+        let preview = rpc.preview_next_block().await.unwrap();
+        assert_eq!(preview.len(), 3);
+        // Previewing does not take anything from the mempool.
+        assert_eq!(rpc.preview_next_block().await.unwrap(), preview);
+
+        // Take the next batch the way block production does, with the gas prices of the L1 data provider.
+        let n = rpc.clone_chain_config().execution_batch_size;
+        let mut taken = vec![];
+        mempool.take_priced_txs_chunk(&mut taken, n, &l1_data_provider.get_gas_prices()).unwrap();
+        let taken: Vec<_> = taken.iter().map(|tx| to_entry(MempoolTransactionInfo::from(tx))).collect();
+        assert_eq!(preview, taken);
+        assert!(rpc.preview_next_block().await.unwrap().is_empty());
+    }
+}
@@ -1,3 +1,6 @@
+pub mod gas_price;
+pub mod l1;
+pub mod mempool;
 pub mod services;
 pub mod status;
 pub mod write;
@@ -3,7 +3,7 @@ use std::time::Duration;
 use jsonrpsee::core::{async_trait, RpcResult};
 use mp_utils::service::MadaraService;
 
-use crate::{versions::admin::v0_1_0::MadaraServicesRpcApiV0_1_0Server, Starknet};
+use crate::{errors::StarknetRpcApiError, versions::admin::v0_1_0::MadaraServicesRpcApiV0_1_0Server, Starknet};
 
 const RESTART_INTERVAL: Duration = Duration::from_secs(5);
 
@@ -67,4 +67,14 @@ impl MadaraServicesRpcApiV0_1_0Server for Starknet {
 
         Ok(res)
     }
+
+    #[tracing::instrument(skip(self), fields(module = "Admin"))]
+    async fn restart_service(&self, service: String) -> RpcResult<bool> {
+        let Some(svc) = MadaraService::ALL.into_iter().find(|svc| svc.to_string() == service) else {
+            return Err(StarknetRpcApiError::ErrUnexpectedError { data: format!("Unknown service: {service}") }.into());
+        };
+
+        tracing::info!("🔌 Restarting {svc} service...");
+        Ok(self.ctx.service_restart(svc))
+    }
 }
@@ -1,8 +1,13 @@
 use std::time::{Duration, SystemTime};
 
 use jsonrpsee::core::async_trait;
+use mp_utils::service::MadaraService;
 
-use crate::{errors::ErrorExtWs, versions::admin::v0_1_0::MadaraStatusRpcApiV0_1_0Server, Starknet};
+use crate::{
+    errors::ErrorExtWs,
+    versions::admin::v0_1_0::{MadaraStatusRpcApiV0_1_0Server, NodeHealth, ServiceHealth, ServiceState},
+    Starknet,
+};
 
 #[async_trait]
 impl MadaraStatusRpcApiV0_1_0Server for Starknet {
@@ -17,6 +22,32 @@ impl MadaraStatusRpcApiV0_1_0Server for Starknet {
         Ok(unix_now())
     }
 
+    async fn health(&self) -> jsonrpsee::core::RpcResult<NodeHealth> {
+        let services = MadaraService::ALL
+            .into_iter()
+            .map(|svc| {
+                let status = self.ctx.service_status(svc);
+                let last_update = status.last_update.map(|last_update| {
+                    let millis = last_update.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+                    u64::try_from(millis).unwrap_or(u64::MAX)
+                });
+                ServiceHealth {
+                    service: svc.to_string(),
+                    state: if self.ctx.service_check(svc as u16) {
+                        ServiceState::Running
+                    } else {
+                        ServiceState::Stopped
+                    },
+                    connected: status.connected,
+                    circuit_open: status.circuit_open,
+                    paused: status.paused,
+                    last_update,
+                }
+            })
+            .collect();
+        Ok(NodeHealth { services })
+    }
+
     async fn pulse(
         &self,
         subscription_sink: jsonrpsee::PendingSubscriptionSink,
@@ -40,3 +71,60 @@ impl MadaraStatusRpcApiV0_1_0Server for Starknet {
 fn unix_now() -> u64 {
     SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rpc_test_setup;
+    use mc_db::MadaraBackend;
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    fn l1_sync_health(health: &NodeHealth) -> &ServiceHealth {
+        let service = MadaraService::L1Sync.to_string();
+        health.services.iter().find(|health| health.service == service).expect("Missing L1 sync health")
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_health_l1_sync_running_and_stopped(rpc_test_setup: (Arc<MadaraBackend>, Starknet)) {
+        let (_, rpc) = rpc_test_setup;
+        let last_update = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        rpc.ctx.clone().with_id(MadaraService::L1Sync).report_status(|status| {
+            status.connected = Some(true);
+            status.last_update = Some(last_update);
+        });
+
+        let health = rpc.health().await.unwrap();
+        assert_eq!(health.services.len(), MadaraService::ALL.len());
+        assert_eq!(
+            l1_sync_health(&health),
+            &ServiceHealth {
+                service: MadaraService::L1Sync.to_string(),
+                state: ServiceState::Running,
+                connected: Some(true),
+                circuit_open: None,
+                paused: None,
+                last_update: Some(1_000_000),
+            }
+        );
+
+        rpc.ctx.service_remove(MadaraService::L1Sync);
+        rpc.ctx.clone().with_id(MadaraService::L1Sync).report_status(|status| {
+            status.connected = Some(false);
+            status.circuit_open = Some(true);
+        });
+
+        let health = rpc.health().await.unwrap();
+        let l1_sync = l1_sync_health(&health);
+        assert_eq!(l1_sync.state, ServiceState::Stopped);
+        assert_eq!(l1_sync.connected, Some(false));
+        assert_eq!(l1_sync.circuit_open, Some(true));
+        // Other services are not affected.
+        let rpc_service = MadaraService::Rpc.to_string();
+        assert!(health
+            .services
+            .iter()
+            .any(|health| health.service == rpc_service && health.state == ServiceState::Running));
+    }
+}
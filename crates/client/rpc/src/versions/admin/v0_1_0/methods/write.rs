@@ -3,7 +3,7 @@ use mp_transactions::BroadcastedDeclareTransactionV0;
 use starknet_types_core::felt::Felt;
 use starknet_types_rpc::ClassAndTxnHash;
 
-use crate::{versions::admin::v0_1_0::MadaraWriteRpcApiV0_1_0Server, Starknet};
+use crate::{providers::SubmittedTransaction, versions::admin::v0_1_0::MadaraWriteRpcApiV0_1_0Server, Starknet};
 
 #[async_trait]
 impl MadaraWriteRpcApiV0_1_0Server for Starknet {
@@ -19,7 +19,7 @@ impl MadaraWriteRpcApiV0_1_0Server for Starknet {
     async fn add_declare_v0_transaction(
         &self,
         declare_transaction: BroadcastedDeclareTransactionV0,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
         Ok(self.add_transaction_provider.add_declare_v0_transaction(declare_transaction).await?)
     }
 }
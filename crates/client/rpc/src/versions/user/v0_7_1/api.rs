@@ -1,6 +1,7 @@
 use jsonrpsee::core::RpcResult;
 use m_proc_macros::versioned_rpc;
 use mp_block::BlockId;
+use serde::{Deserialize, Serialize};
 use starknet_types_core::felt::Felt;
 use starknet_types_rpc::{
     AddInvokeTransactionResult, BlockHashAndNumber, BroadcastedDeclareTxn, BroadcastedDeployAccountTxn,
@@ -11,6 +12,31 @@ use starknet_types_rpc::{
     TxnFinalityAndExecutionStatus, TxnReceiptWithBlockInfo, TxnWithHash,
 };
 
+use crate::providers::SubmittedTransaction;
+
+/// A fee estimate, extended with the L1 gas prices the node currently uses. This is an extension of the spec: the
+/// extra field is absent when the node does not track the L1 gas prices.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimateWithL1GasPrices {
+    #[serde(flatten)]
+    pub fee_estimate: FeeEstimate<Felt>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_gas_prices: Option<L1GasPrices>,
+}
+
+/// The current L1 gas prices of the node, before they are fixed in a block header.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1GasPrices {
+    pub eth_l1_gas_price: Felt,
+    pub strk_l1_gas_price: Felt,
+    pub eth_l1_data_gas_price: Felt,
+    pub strk_l1_data_gas_price: Felt,
+    /// Unix time in milliseconds at which the L1 gas prices were last updated.
+    pub gas_price_updated_at: u64,
+    /// Unix time in milliseconds at which the L1 data gas prices were last updated.
+    pub data_gas_price_updated_at: u64,
+}
+
 // Starknet RPC API trait and types
 //
 // Starkware maintains [a description of the Starknet API](https://github.com/starkware-libs/starknet-specs/blob/master/api/starknet_api_openrpc.json)
@@ -24,21 +50,21 @@ pub trait StarknetWriteRpcApi {
     async fn add_invoke_transaction(
         &self,
         invoke_transaction: BroadcastedInvokeTxn<Felt>,
-    ) -> RpcResult<AddInvokeTransactionResult<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<AddInvokeTransactionResult<Felt>>>;
 
     /// Submit a new deploy account transaction
     #[method(name = "addDeployAccountTransaction", and_versions = ["V0_8_0"])]
     async fn add_deploy_account_transaction(
         &self,
         deploy_account_transaction: BroadcastedDeployAccountTxn<Felt>,
-    ) -> RpcResult<ContractAndTxnHash<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<ContractAndTxnHash<Felt>>>;
 
     /// Submit a new class declaration transaction
     #[method(name = "addDeclareTransaction", and_versions = ["V0_8_0"])]
     async fn add_declare_transaction(
         &self,
         declare_transaction: BroadcastedDeclareTxn<Felt>,
-    ) -> RpcResult<ClassAndTxnHash<Felt>>;
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>>;
 }
 
 #[versioned_rpc("V0_7_1", "starknet")]
@@ -67,14 +93,14 @@ pub trait StarknetReadRpcApi {
     #[method(name = "getBlockTransactionCount", and_versions = ["V0_8_0"])]
     fn get_block_transaction_count(&self, block_id: BlockId) -> RpcResult<u128>;
 
-    /// Estimate the fee associated with transaction
+    /// Estimate the fee associated with transaction, along with the current L1 gas prices of the node
     #[method(name = "estimateFee", and_versions = ["V0_8_0"])]
     async fn estimate_fee(
         &self,
         request: Vec<BroadcastedTxn<Felt>>,
         simulation_flags: Vec<SimulationFlagForEstimateFee>,
         block_id: BlockId,
-    ) -> RpcResult<Vec<FeeEstimate<Felt>>>;
+    ) -> RpcResult<Vec<FeeEstimateWithL1GasPrices>>;
 
     /// Estimate the L2 fee of a message sent on L1
     #[method(name = "estimateMessageFee", and_versions = ["V0_8_0"])]
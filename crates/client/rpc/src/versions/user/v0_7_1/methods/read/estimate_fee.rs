@@ -2,13 +2,16 @@ use crate::errors::StarknetRpcApiError;
 use crate::errors::StarknetRpcResult;
 use crate::utils::ResultExt;
 use crate::versions::user::v0_7_1::methods::trace::trace_transaction::FALLBACK_TO_SEQUENCER_WHEN_VERSION_BELOW;
+use crate::versions::user::v0_7_1::{FeeEstimateWithL1GasPrices, L1GasPrices};
 use crate::Starknet;
 use mc_exec::ExecutionContext;
+use mc_mempool::L1DataProvider;
 use mp_block::BlockId;
 use mp_transactions::BroadcastedTransactionExt;
 use starknet_types_core::felt::Felt;
-use starknet_types_rpc::{BroadcastedTxn, FeeEstimate, SimulationFlagForEstimateFee};
+use starknet_types_rpc::{BroadcastedTxn, SimulationFlagForEstimateFee};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Estimate the fee associated with transaction
 ///
@@ -19,13 +22,13 @@ use std::sync::Arc;
 ///
 /// # Returns
 ///
-/// * `fee_estimate` - fee estimate in gwei
+/// * `fee_estimate` - fee estimate in gwei, with the current L1 gas prices of the node when it tracks them
 pub async fn estimate_fee(
     starknet: &Starknet,
     request: Vec<BroadcastedTxn<Felt>>,
     simulation_flags: Vec<SimulationFlagForEstimateFee>,
     block_id: BlockId,
-) -> StarknetRpcResult<Vec<FeeEstimate<Felt>>> {
+) -> StarknetRpcResult<Vec<FeeEstimateWithL1GasPrices>> {
     let block_info = starknet.get_block_info(&block_id)?;
     let starknet_version = *block_info.protocol_version();
 
@@ -45,6 +48,7 @@ pub async fn estimate_fee(
 
     let execution_results = exec_context.re_execute_transactions([], transactions, true, validate)?;
 
+    let l1_gas_prices = current_l1_gas_prices(starknet);
     let fee_estimates = execution_results.iter().enumerate().try_fold(
         Vec::with_capacity(execution_results.len()),
         |mut acc, (index, result)| {
@@ -54,10 +58,93 @@ pub async fn estimate_fee(
                     error: result.execution_info.revert_error.clone().unwrap_or_default(),
                 });
             }
-            acc.push(exec_context.execution_result_to_fee_estimate(result));
+            acc.push(FeeEstimateWithL1GasPrices {
+                fee_estimate: exec_context.execution_result_to_fee_estimate(result),
+                l1_gas_prices: l1_gas_prices.clone(),
+            });
             Ok(acc)
         },
     )?;
 
     Ok(fee_estimates)
 }
+
+/// The L1 gas prices of the gas price provider, which the next blocks will use, unlike the gas prices of the block the
+/// fee is estimated in.
+fn current_l1_gas_prices(starknet: &Starknet) -> Option<L1GasPrices> {
+    let l1_gas_provider = starknet.l1_gas_provider.as_ref()?;
+    let gas_prices = l1_gas_provider.get_gas_prices();
+    Some(L1GasPrices {
+        eth_l1_gas_price: gas_prices.eth_l1_gas_price.into(),
+        strk_l1_gas_price: gas_prices.strk_l1_gas_price.into(),
+        eth_l1_data_gas_price: gas_prices.eth_l1_data_gas_price.into(),
+        strk_l1_data_gas_price: gas_prices.strk_l1_data_gas_price.into(),
+        gas_price_updated_at: unix_millis(l1_gas_provider.get_gas_prices_last_update()),
+        data_gas_price_updated_at: unix_millis(l1_gas_provider.get_data_gas_prices_last_update()),
+    })
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    let millis = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+    u64::try_from(millis).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{sample_chain_for_block_getters, SampleChainForBlockGetters};
+    use mc_mempool::GasPriceProvider;
+    use mp_block::header::GasPrices;
+    use rstest::rstest;
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_estimate_fee_reports_l1_gas_prices(
+        sample_chain_for_block_getters: (SampleChainForBlockGetters, Starknet),
+    ) {
+        let (_, rpc) = sample_chain_for_block_getters;
+        let l1_gas_provider = GasPriceProvider::new();
+        let rpc = rpc.with_gas_price_provider(l1_gas_provider.clone());
+
+        l1_gas_provider.set_gas_prices(GasPrices {
+            eth_l1_gas_price: 100,
+            strk_l1_gas_price: 200,
+            eth_l1_data_gas_price: 10,
+            strk_l1_data_gas_price: 20,
+        });
+        l1_gas_provider.update_last_update_timestamp();
+
+        let l1_gas_prices = current_l1_gas_prices(&rpc).unwrap();
+        assert_eq!(
+            l1_gas_prices,
+            L1GasPrices {
+                eth_l1_gas_price: Felt::from(100),
+                strk_l1_gas_price: Felt::from(200),
+                eth_l1_data_gas_price: Felt::from(10),
+                strk_l1_data_gas_price: Felt::from(20),
+                gas_price_updated_at: unix_millis(l1_gas_provider.get_gas_prices_last_update()),
+                data_gas_price_updated_at: unix_millis(l1_gas_provider.get_data_gas_prices_last_update()),
+            }
+        );
+
+        // The gas prices are flattened next to the fields of the spec fee estimate.
+        let estimate = FeeEstimateWithL1GasPrices {
+            fee_estimate: starknet_types_rpc::FeeEstimate {
+                gas_consumed: Felt::ONE,
+                gas_price: Felt::ONE,
+                data_gas_consumed: Felt::ONE,
+                data_gas_price: Felt::ONE,
+                overall_fee: Felt::TWO,
+                unit: starknet_types_rpc::PriceUnit::Fri,
+            },
+            l1_gas_prices: Some(l1_gas_prices),
+        };
+        let json = serde_json::to_value(&estimate).unwrap();
+        assert_eq!(json["overall_fee"], "0x2");
+        assert_eq!(json["l1_gas_prices"]["strk_l1_gas_price"], "0xc8");
+        assert_eq!(serde_json::from_value::<FeeEstimateWithL1GasPrices>(json).unwrap(), estimate);
+
+        // The estimation of no transaction is empty, but still checks the block.
+        assert_eq!(estimate_fee(&rpc, vec![], vec![], BlockId::Number(1)).await.unwrap(), vec![]);
+    }
+}
@@ -31,7 +31,7 @@ use super::get_transaction_receipt::*;
 use super::get_transaction_status::*;
 use super::syncing::*;
 
-use crate::versions::user::v0_7_1::StarknetReadRpcApiV0_7_1Server;
+use crate::versions::user::v0_7_1::{FeeEstimateWithL1GasPrices, StarknetReadRpcApiV0_7_1Server};
 use crate::Starknet;
 
 #[async_trait]
@@ -65,7 +65,7 @@ impl StarknetReadRpcApiV0_7_1Server for Starknet {
         request: Vec<BroadcastedTxn<Felt>>,
         simulation_flags: Vec<SimulationFlagForEstimateFee>,
         block_id: BlockId,
-    ) -> RpcResult<Vec<FeeEstimate<Felt>>> {
+    ) -> RpcResult<Vec<FeeEstimateWithL1GasPrices>> {
         Ok(estimate_fee(self, request, simulation_flags, block_id).await?)
     }
 
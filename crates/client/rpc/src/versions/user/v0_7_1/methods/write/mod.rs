@@ -1,4 +1,4 @@
-use crate::{versions::user::v0_7_1::StarknetWriteRpcApiV0_7_1Server, Starknet};
+use crate::{providers::SubmittedTransaction, versions::user::v0_7_1::StarknetWriteRpcApiV0_7_1Server, Starknet};
 use jsonrpsee::core::{async_trait, RpcResult};
 use starknet_types_core::felt::Felt;
 use starknet_types_rpc::{
@@ -20,7 +20,7 @@ impl StarknetWriteRpcApiV0_7_1Server for Starknet {
     async fn add_declare_transaction(
         &self,
         declare_transaction: BroadcastedDeclareTxn<Felt>,
-    ) -> RpcResult<ClassAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ClassAndTxnHash<Felt>>> {
         Ok(self.add_transaction_provider.add_declare_transaction(declare_transaction).await?)
     }
 
@@ -37,7 +37,7 @@ impl StarknetWriteRpcApiV0_7_1Server for Starknet {
     async fn add_deploy_account_transaction(
         &self,
         deploy_account_transaction: BroadcastedDeployAccountTxn<Felt>,
-    ) -> RpcResult<ContractAndTxnHash<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<ContractAndTxnHash<Felt>>> {
         Ok(self.add_transaction_provider.add_deploy_account_transaction(deploy_account_transaction).await?)
     }
 
@@ -53,7 +53,7 @@ impl StarknetWriteRpcApiV0_7_1Server for Starknet {
     async fn add_invoke_transaction(
         &self,
         invoke_transaction: BroadcastedInvokeTxn<Felt>,
-    ) -> RpcResult<AddInvokeTransactionResult<Felt>> {
+    ) -> RpcResult<SubmittedTransaction<AddInvokeTransactionResult<Felt>>> {
         Ok(self.add_transaction_provider.add_invoke_transaction(invoke_transaction).await?)
     }
 }
@@ -11,7 +11,8 @@ use starknet_api::core::{ChainId, ContractAddress};
 use mp_block::H160;
 use mp_chain_config::{
     deserialize_bouncer_config, deserialize_starknet_version, serialize_bouncer_config, serialize_starknet_version,
-    ChainConfig, StarknetVersion,
+    ChainConfig, MempoolDeclareLimitPolicy, MempoolExpiredTxPolicy, MempoolOrdering, MempoolUnderpricedPolicy,
+    StarknetVersion,
 };
 use mp_utils::parsers::parse_key_value_yaml;
 use mp_utils::serde::{deserialize_duration, deserialize_private_key, serialize_duration};
@@ -51,8 +52,41 @@ pub struct ChainConfigOverridesInner {
     pub private_key: ZeroingPrivateKey,
     pub mempool_tx_limit: usize,
     pub mempool_declare_tx_limit: usize,
+    pub mempool_tx_limit_per_sender: usize,
+    pub mempool_l1_handler_tx_reserved: usize,
+    pub mempool_deploy_account_tx_reserved: usize,
     #[serde(deserialize_with = "deserialize_duration", serialize_with = "serialize_duration")]
     pub mempool_tx_max_age: Duration,
+    pub mempool_eviction_enabled: bool,
+    #[serde(deserialize_with = "deserialize_duration", serialize_with = "serialize_duration")]
+    pub mempool_sweep_interval: Duration,
+    pub mempool_replacement_bump_percent: u64,
+    #[serde(deserialize_with = "deserialize_duration", serialize_with = "serialize_duration")]
+    pub mempool_replacement_min_interval: Duration,
+    pub mempool_persistence_enabled: bool,
+    pub mempool_ordering: MempoolOrdering,
+    pub mempool_underpriced_policy: MempoolUnderpricedPolicy,
+    pub mempool_expired_tx_policy: MempoolExpiredTxPolicy,
+    pub mempool_declare_limit_policy: MempoolDeclareLimitPolicy,
+    pub mempool_min_tip_multiplier: f64,
+    pub mempool_near_capacity_watermark: f64,
+    pub mempool_privileged_senders: Vec<ContractAddress>,
+    pub mempool_max_total_bytes: usize,
+    pub max_declare_bytecode_size: usize,
+    pub mempool_max_total_l2_gas: u64,
+    pub mempool_max_nonce_distance: u64,
+    pub allow_zero_fee_transactions: bool,
+    pub mempool_reject_unknown_classes: bool,
+    pub mempool_refresh_on_resubmit: bool,
+    pub mempool_dropped_tx_lru_size: usize,
+    pub mempool_tx_limit_min: usize,
+    pub mempool_tx_limit_max: usize,
+    pub min_gas_price: u64,
+    pub max_gas_price: u64,
+    #[serde(deserialize_with = "deserialize_duration", serialize_with = "serialize_duration")]
+    pub gas_price_max_age: Duration,
+    pub gas_price_multiplier: f64,
+    pub data_gas_price_multiplier: f64,
 }
 
 impl ChainConfigOverrideParams {
@@ -75,7 +109,37 @@ impl ChainConfigOverrideParams {
             private_key: chain_config.private_key,
             mempool_tx_limit: chain_config.mempool_tx_limit,
             mempool_declare_tx_limit: chain_config.mempool_declare_tx_limit,
+            mempool_tx_limit_per_sender: chain_config.mempool_tx_limit_per_sender,
+            mempool_l1_handler_tx_reserved: chain_config.mempool_l1_handler_tx_reserved,
+            mempool_deploy_account_tx_reserved: chain_config.mempool_deploy_account_tx_reserved,
             mempool_tx_max_age: chain_config.mempool_tx_max_age,
+            mempool_eviction_enabled: chain_config.mempool_eviction_enabled,
+            mempool_sweep_interval: chain_config.mempool_sweep_interval,
+            mempool_replacement_bump_percent: chain_config.mempool_replacement_bump_percent,
+            mempool_replacement_min_interval: chain_config.mempool_replacement_min_interval,
+            mempool_persistence_enabled: chain_config.mempool_persistence_enabled,
+            mempool_ordering: chain_config.mempool_ordering,
+            mempool_underpriced_policy: chain_config.mempool_underpriced_policy,
+            mempool_expired_tx_policy: chain_config.mempool_expired_tx_policy,
+            mempool_declare_limit_policy: chain_config.mempool_declare_limit_policy,
+            mempool_min_tip_multiplier: chain_config.mempool_min_tip_multiplier,
+            mempool_near_capacity_watermark: chain_config.mempool_near_capacity_watermark,
+            mempool_privileged_senders: chain_config.mempool_privileged_senders,
+            mempool_max_total_bytes: chain_config.mempool_max_total_bytes,
+            max_declare_bytecode_size: chain_config.max_declare_bytecode_size,
+            mempool_max_total_l2_gas: chain_config.mempool_max_total_l2_gas,
+            mempool_max_nonce_distance: chain_config.mempool_max_nonce_distance,
+            allow_zero_fee_transactions: chain_config.allow_zero_fee_transactions,
+            mempool_reject_unknown_classes: chain_config.mempool_reject_unknown_classes,
+            mempool_refresh_on_resubmit: chain_config.mempool_refresh_on_resubmit,
+            mempool_dropped_tx_lru_size: chain_config.mempool_dropped_tx_lru_size,
+            mempool_tx_limit_min: chain_config.mempool_tx_limit_min,
+            mempool_tx_limit_max: chain_config.mempool_tx_limit_max,
+            min_gas_price: chain_config.min_gas_price,
+            max_gas_price: chain_config.max_gas_price,
+            gas_price_max_age: chain_config.gas_price_max_age,
+            gas_price_multiplier: chain_config.gas_price_multiplier,
+            data_gas_price_multiplier: chain_config.data_gas_price_multiplier,
             feeder_gateway_url: chain_config.feeder_gateway_url,
             gateway_url: chain_config.gateway_url,
         })
@@ -110,7 +174,7 @@ impl ChainConfigOverrideParams {
         let chain_config_overrides: ChainConfigOverridesInner = serde_yaml::from_value(chain_config_overrides)
             .context("Failed to convert Value to ChainConfigOverridesInner")?;
 
-        Ok(ChainConfig {
+        let chain_config = ChainConfig {
             chain_name: chain_config_overrides.chain_name,
             chain_id: chain_config_overrides.chain_id,
             feeder_gateway_url: chain_config_overrides.feeder_gateway_url,
@@ -129,7 +193,39 @@ impl ChainConfigOverrideParams {
             private_key: chain_config_overrides.private_key,
             mempool_tx_limit: chain_config_overrides.mempool_tx_limit,
             mempool_declare_tx_limit: chain_config_overrides.mempool_declare_tx_limit,
+            mempool_tx_limit_per_sender: chain_config_overrides.mempool_tx_limit_per_sender,
+            mempool_l1_handler_tx_reserved: chain_config_overrides.mempool_l1_handler_tx_reserved,
+            mempool_deploy_account_tx_reserved: chain_config_overrides.mempool_deploy_account_tx_reserved,
             mempool_tx_max_age: chain_config_overrides.mempool_tx_max_age,
-        })
+            mempool_eviction_enabled: chain_config_overrides.mempool_eviction_enabled,
+            mempool_sweep_interval: chain_config_overrides.mempool_sweep_interval,
+            mempool_replacement_bump_percent: chain_config_overrides.mempool_replacement_bump_percent,
+            mempool_replacement_min_interval: chain_config_overrides.mempool_replacement_min_interval,
+            mempool_persistence_enabled: chain_config_overrides.mempool_persistence_enabled,
+            mempool_ordering: chain_config_overrides.mempool_ordering,
+            mempool_underpriced_policy: chain_config_overrides.mempool_underpriced_policy,
+            mempool_expired_tx_policy: chain_config_overrides.mempool_expired_tx_policy,
+            mempool_declare_limit_policy: chain_config_overrides.mempool_declare_limit_policy,
+            mempool_min_tip_multiplier: chain_config_overrides.mempool_min_tip_multiplier,
+            mempool_near_capacity_watermark: chain_config_overrides.mempool_near_capacity_watermark,
+            mempool_privileged_senders: chain_config_overrides.mempool_privileged_senders,
+            mempool_max_total_bytes: chain_config_overrides.mempool_max_total_bytes,
+            max_declare_bytecode_size: chain_config_overrides.max_declare_bytecode_size,
+            mempool_max_total_l2_gas: chain_config_overrides.mempool_max_total_l2_gas,
+            mempool_max_nonce_distance: chain_config_overrides.mempool_max_nonce_distance,
+            allow_zero_fee_transactions: chain_config_overrides.allow_zero_fee_transactions,
+            mempool_reject_unknown_classes: chain_config_overrides.mempool_reject_unknown_classes,
+            mempool_refresh_on_resubmit: chain_config_overrides.mempool_refresh_on_resubmit,
+            mempool_dropped_tx_lru_size: chain_config_overrides.mempool_dropped_tx_lru_size,
+            mempool_tx_limit_min: chain_config_overrides.mempool_tx_limit_min,
+            mempool_tx_limit_max: chain_config_overrides.mempool_tx_limit_max,
+            min_gas_price: chain_config_overrides.min_gas_price,
+            max_gas_price: chain_config_overrides.max_gas_price,
+            gas_price_max_age: chain_config_overrides.gas_price_max_age,
+            gas_price_multiplier: chain_config_overrides.gas_price_multiplier,
+            data_gas_price_multiplier: chain_config_overrides.data_gas_price_multiplier,
+        };
+        chain_config.check_mempool_config()?;
+        Ok(chain_config)
     }
 }
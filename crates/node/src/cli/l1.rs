@@ -1,7 +1,9 @@
+use std::fmt;
 use std::time::Duration;
 
 use url::Url;
 
+use mp_block::H160;
 use mp_utils::parsers::{parse_duration, parse_url};
 
 #[derive(Clone, Debug, clap::Args)]
@@ -10,11 +12,28 @@ pub struct L1SyncParams {
     #[clap(env = "MADARA_SYNC_L1_DISABLED", long, alias = "no-l1-sync", conflicts_with = "l1_endpoint")]
     pub sync_l1_disabled: bool,
 
-    /// The L1 rpc endpoint url for state verification.
-    #[clap(env = "MADARA_L1_ENDPOINT", long, value_parser = parse_url, value_name = "ETHEREUM RPC URL")]
-    pub l1_endpoint: Option<Url>,
+    /// The L1 rpc endpoint url for state verification. Several comma-separated urls can be given, the next one is
+    /// used when the current one fails.
+    #[clap(
+        env = "MADARA_L1_ENDPOINT",
+        long,
+        value_parser = parse_url,
+        value_name = "ETHEREUM RPC URL",
+        value_delimiter = ','
+    )]
+    pub l1_endpoint: Vec<Url>,
 
-    /// Fix the gas price. If the gas price is fixed it won't fetch the fee history from the ethereum.
+    /// HTTP header sent with every request to the L1 endpoints, as `NAME:VALUE`, such as the API key required by a
+    /// managed RPC provider. Can be given several times.
+    #[clap(long, value_parser = parse_http_header, value_name = "NAME:VALUE")]
+    pub l1_endpoint_header: Vec<HttpHeader>,
+
+    /// Bearer token sent in the `Authorization` header of every request to the L1 endpoints.
+    #[clap(env = "MADARA_L1_BEARER_TOKEN", long, value_name = "TOKEN")]
+    pub l1_bearer_token: Option<Secret>,
+
+    /// Fix the gas price. If the gas price is fixed it won't fetch the fee history from the ethereum. A gas price of 0
+    /// is a valid fixed price, such as in devnet.
     #[clap(env = "MADARA_GAS_PRICE", long, alias = "gas-price")]
     pub gas_price: Option<u64>,
 
@@ -22,6 +41,12 @@ pub struct L1SyncParams {
     #[clap(env = "MADARA_DATA_GAS_PRICE", long, alias = "blob-gas-price")]
     pub blob_gas_price: Option<u64>,
 
+    /// Static gas and blob gas price, in wei, for a sequencer without an L1 endpoint. Instead of failing to start, the
+    /// node then runs without the L1 watcher: the state is not verified and no L1 message is processed. `--gas-price`
+    /// and `--blob-gas-price` take precedence over it.
+    #[clap(env = "MADARA_L1_GAS_PRICE_FALLBACK", long, value_name = "WEI")]
+    pub l1_gas_price_fallback: Option<u64>,
+
     /// Fix the strk gas price. If the strk gas price is fixed it won't fetch eth <-> strk price from the oracle.
     #[clap(env = "MADARA_STRK_GAS_PRICE", long, alias = "strk-gas-price")]
     pub strk_gas_price: Option<u64>,
@@ -38,7 +63,8 @@ pub struct L1SyncParams {
     #[clap(env = "ORACLE_API_KEY", long, alias = "oracle-api-key")]
     pub oracle_api_key: Option<String>,
 
-    /// Time in which the gas price worker will fetch the gas price.
+    /// Time in which the gas price worker will fetch the gas price. It can be changed at runtime with the
+    /// `madara_setGasPricePollInterval` admin rpc method.
     #[clap(
 		env = "MADARA_GAS_PRICE_POLL",
         long,
@@ -46,4 +72,251 @@ pub struct L1SyncParams {
         value_parser = parse_duration,
     )]
     pub gas_price_poll: Duration,
+
+    /// Random jitter applied to every gas price poll interval, as a fraction of the interval in [0, 1). With a jitter
+    /// of 0.1, the worker waits between 90% and 110% of `--gas-price-poll`, so that nodes polling the same L1 endpoint
+    /// drift apart instead of polling it at the same time.
+    #[clap(env = "MADARA_GAS_PRICE_POLL_JITTER", long, default_value_t = 0.1, value_parser = parse_poll_jitter)]
+    pub gas_price_poll_jitter: f64,
+
+    /// Smoothing factor of the exponential moving average applied to the fetched L1 gas prices, in (0, 1]. Higher
+    /// values follow the latest price more closely. Smoothing is opt-in: the default of 1 disables it.
+    #[clap(env = "MADARA_GAS_PRICE_EMA_ALPHA", long, default_value_t = 1.0, value_parser = parse_ema_alpha)]
+    pub gas_price_ema_alpha: f64,
+
+    /// Number of most recent gas price samples the moving average is computed over. The default of 1 disables
+    /// smoothing.
+    #[clap(
+        env = "MADARA_GAS_PRICE_EMA_WINDOW",
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub gas_price_ema_window: u64,
+
+    /// Smoothing factor of the moving average applied to the fetched L1 blob gas prices, defaults to
+    /// `--gas-price-ema-alpha`.
+    #[clap(env = "MADARA_BLOB_GAS_PRICE_EMA_ALPHA", long, value_parser = parse_ema_alpha)]
+    pub blob_gas_price_ema_alpha: Option<f64>,
+
+    /// Number of most recent blob gas price samples the moving average is computed over, defaults to
+    /// `--gas-price-ema-window`.
+    #[clap(env = "MADARA_BLOB_GAS_PRICE_EMA_WINDOW", long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub blob_gas_price_ema_window: Option<u64>,
+
+    /// Adds this percentile, in [0, 100], of the priority fees of the recent L1 blocks to the L1 base fee to compute
+    /// the L1 gas price, as returned by `eth_feeHistory`. The L1 base fee alone is used by default.
+    #[clap(env = "MADARA_GAS_PRICE_FEE_HISTORY_PERCENTILE", long, value_parser = parse_percentile)]
+    pub gas_price_fee_history_percentile: Option<f64>,
+
+    /// Where the L1 gas price is fetched from: the L1 base fee, or the `gasPrice()` view function of the L1 oracle
+    /// contract at `--gas-price-oracle-address`, which returns the L1 gas price in wei. The L1 blob gas price always
+    /// comes from the blob base fee.
+    #[clap(env = "MADARA_GAS_PRICE_SOURCE", long, value_enum, default_value_t = GasPriceSource::BaseFee)]
+    pub gas_price_source: GasPriceSource,
+
+    /// Address of the L1 oracle contract the L1 gas price is read from, with `--gas-price-source oracle-contract`.
+    #[clap(
+        env = "MADARA_GAS_PRICE_ORACLE_ADDRESS",
+        long,
+        value_name = "ADDRESS",
+        required_if_eq("gas_price_source", "oracle-contract")
+    )]
+    pub gas_price_oracle_address: Option<H160>,
+
+    /// Number of L1 blocks that must be built on top of a state update before it is used to confirm the local
+    /// state. This protects against L1 reorgs, 0 acts on state updates as soon as they are seen.
+    #[clap(env = "MADARA_L1_CONFIRMATIONS", long, default_value_t = 64)]
+    pub l1_confirmations: u64,
+
+    /// The L1 block the state verification is based on: the confirmations are counted from it. `finalized` blocks
+    /// cannot be reorged but lag ~13 minutes behind the L1 head, `latest` is the fastest but relies on the
+    /// confirmations alone to protect against reorgs.
+    #[clap(env = "MADARA_L1_BLOCK_TAG", long, value_enum, default_value_t = L1BlockTag::Latest)]
+    pub l1_block_tag: L1BlockTag,
+
+    /// How the new L1 heads are found out about: the L1 head is either polled, or pushed by an `eth_subscribe`
+    /// subscription on `--l1-ws-endpoint`. The L1 head is still polled when the subscription cannot be established.
+    #[clap(env = "MADARA_L1_HEAD_MODE", long, value_enum, default_value_t = L1HeadMode::Poll)]
+    pub l1_head_mode: L1HeadMode,
+
+    /// The L1 websocket endpoint url the new L1 heads are subscribed to, with `--l1-head-mode subscribe`.
+    #[clap(
+        env = "MADARA_L1_WS_ENDPOINT",
+        long,
+        value_parser = parse_url,
+        value_name = "ETHEREUM WS URL",
+        required_if_eq("l1_head_mode", "subscribe")
+    )]
+    pub l1_ws_endpoint: Option<Url>,
+
+    /// Timeout of every request to the L1 endpoints, so that a slow endpoint cannot hang the L1 sync. A request which
+    /// times out is handled like a failed request: it counts towards the circuit breaker and triggers a reconnection.
+    /// `0s` disables it.
+    #[clap(
+        env = "MADARA_L1_REQUEST_TIMEOUT",
+        long,
+        default_value = "30s",
+        value_parser = parse_duration,
+    )]
+    pub l1_request_timeout: Duration,
+
+    /// Number of consecutive attempts to reconnect to the L1 before the L1 sync gives up.
+    #[clap(env = "MADARA_L1_RECONNECT_MAX_RETRIES", long, default_value_t = 10)]
+    pub l1_reconnect_max_retries: u32,
+
+    /// Backoff before reconnecting to the L1, doubled after every failed attempt.
+    #[clap(
+        env = "MADARA_L1_RECONNECT_BACKOFF",
+        long,
+        default_value = "1s",
+        value_parser = parse_duration,
+    )]
+    pub l1_reconnect_backoff: Duration,
+
+    /// Number of consecutive L1 failures after which the L1 sync stops calling the L1 endpoint for
+    /// `--l1-circuit-breaker-cooldown`, and reports it as unhealthy. 0 disables the circuit breaker.
+    #[clap(env = "MADARA_L1_CIRCUIT_BREAKER_THRESHOLD", long, default_value_t = 5)]
+    pub l1_circuit_breaker_threshold: u32,
+
+    /// How long the L1 sync stops calling the L1 endpoint once the circuit breaker opens.
+    #[clap(
+        env = "MADARA_L1_CIRCUIT_BREAKER_COOLDOWN",
+        long,
+        default_value = "30s",
+        value_parser = parse_duration,
+    )]
+    pub l1_circuit_breaker_cooldown: Duration,
+
+    /// Number of times the initial L1 gas price fetch is retried before the node fails to start.
+    #[clap(env = "MADARA_L1_GAS_PRICE_INIT_RETRIES", long, default_value_t = 5)]
+    pub l1_gas_price_init_retries: u32,
+
+    /// Backoff before retrying the initial L1 gas price fetch, doubled after every failed attempt.
+    #[clap(
+        env = "MADARA_L1_GAS_PRICE_INIT_BACKOFF",
+        long,
+        default_value = "1s",
+        value_parser = parse_duration,
+    )]
+    pub l1_gas_price_init_backoff: Duration,
+
+    /// Number of L1 blocks whose messages are fetched in a single request when catching up with the L1. When the L1
+    /// endpoint rejects a request for too large a block range, the range is halved until the endpoint accepts it.
+    #[clap(
+        env = "MADARA_L1_LOG_FETCH_RANGE",
+        long,
+        default_value_t = 1000,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub l1_log_fetch_range: u64,
+
+    /// Number of concurrent requests for the L1 messages when catching up with the L1.
+    #[clap(
+        env = "MADARA_L1_LOG_FETCH_CONCURRENCY",
+        long,
+        default_value_t = 4,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub l1_log_fetch_concurrency: usize,
+
+    /// L1 block to start syncing the L1 messages from on a fresh database, instead of the genesis. This speeds up the
+    /// initial sync when no L1 message was sent before a known block. It is ignored once some L1 blocks are processed.
+    #[clap(env = "MADARA_L1_START_BLOCK", long, value_name = "BLOCK NUMBER")]
+    pub l1_start_block: Option<u64>,
+}
+
+/// See [`mc_eth::client::L1BlockTag`].
+#[derive(Clone, Copy, Debug, clap::ValueEnum, PartialEq)]
+pub enum L1BlockTag {
+    /// The head of the L1 chain.
+    Latest,
+    /// The most recent block justified by the beacon chain.
+    Safe,
+    /// The most recent finalized block.
+    Finalized,
+}
+
+impl From<L1BlockTag> for mc_eth::client::L1BlockTag {
+    fn from(value: L1BlockTag) -> Self {
+        match value {
+            L1BlockTag::Latest => Self::Latest,
+            L1BlockTag::Safe => Self::Safe,
+            L1BlockTag::Finalized => Self::Finalized,
+        }
+    }
+}
+
+/// See [`mc_mempool::L1GasPriceSource`].
+#[derive(Clone, Copy, Debug, clap::ValueEnum, PartialEq)]
+pub enum GasPriceSource {
+    /// The L1 base fee, plus `--gas-price-fee-history-percentile` of the priority fees when set.
+    BaseFee,
+    /// The `gasPrice()` view function of an L1 oracle contract.
+    OracleContract,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum, PartialEq)]
+pub enum L1HeadMode {
+    /// Poll the L1 head at the L1 block time.
+    Poll,
+    /// Subscribe to the new L1 heads over websockets.
+    Subscribe,
+}
+
+/// A command line value which is masked in the `Debug` output, such as an API key.
+#[derive(Clone)]
+pub struct Secret(pub String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: Secret,
+}
+
+fn parse_http_header(s: &str) -> Result<HttpHeader, String> {
+    let (name, value) = s.split_once(':').ok_or_else(|| "expected a header as NAME:VALUE".to_string())?;
+    if name.trim().is_empty() {
+        return Err("the header name must not be empty".to_string());
+    }
+    Ok(HttpHeader { name: name.trim().to_string(), value: Secret(value.trim().to_string()) })
+}
+
+fn parse_ema_alpha(s: &str) -> Result<f64, String> {
+    let alpha: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    if alpha > 0.0 && alpha <= 1.0 {
+        Ok(alpha)
+    } else {
+        Err(format!("smoothing factor must be in (0, 1], got {alpha}"))
+    }
+}
+
+fn parse_percentile(s: &str) -> Result<f64, String> {
+    let percentile: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    if (0.0..=100.0).contains(&percentile) {
+        Ok(percentile)
+    } else {
+        Err(format!("percentile must be in [0, 100], got {percentile}"))
+    }
+}
+
+fn parse_poll_jitter(s: &str) -> Result<f64, String> {
+    let jitter: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    if (0.0..1.0).contains(&jitter) {
+        Ok(jitter)
+    } else {
+        Err(format!("poll jitter must be in [0, 1), got {jitter}"))
+    }
 }
@@ -1,9 +1,12 @@
+use std::collections::HashSet;
 use std::convert::Infallible;
-use std::net::{Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::str::FromStr;
 
 use jsonrpsee::server::BatchRequestConfig;
-use mc_rpc::StorageProofConfig;
+use mc_rpc::providers::ValidationPoolConfig;
+use mc_rpc::rate_limit::SubmitRateLimitConfig;
+use mc_rpc::{RpcNamespace, StorageProofConfig, DEFAULT_MAX_MEMPOOL_SUBSCRIBERS};
 
 /// The default port.
 pub const RPC_DEFAULT_PORT: u16 = 9944;
@@ -78,6 +81,34 @@ pub struct RpcParams {
     #[arg(env = "MADARA_RPC_ADMIN_EXTERNAL", long, default_value_t = false)]
     pub rpc_admin_external: bool,
 
+    /// Exposes the contents of the mempool on the admin RPC endpoint, with the
+    /// `madara_getMempoolTransactions` method. This is meant for debugging, as
+    /// it reveals the pending transactions of every user.
+    #[arg(env = "MADARA_RPC_ADMIN_MEMPOOL", long, default_value_t = false)]
+    pub rpc_admin_mempool: bool,
+
+    /// Maximum number of concurrent subscribers to the transactions accepted in the mempool, with the
+    /// `madara_subscribeMempool` admin RPC method. Past this, new subscriptions are rejected.
+    #[arg(
+        env = "MADARA_RPC_MAX_MEMPOOL_SUBSCRIBERS",
+        long,
+        value_name = "COUNT",
+        default_value_t = DEFAULT_MAX_MEMPOOL_SUBSCRIBERS
+    )]
+    pub rpc_max_mempool_subscribers: usize,
+
+    /// Comma separated list of the RPC namespaces to expose: `read` for the methods reading the chain, `write` for
+    /// the methods submitting transactions and `admin` for the admin RPC methods. Calling a method from a namespace
+    /// which is not listed returns a method not found error. All namespaces are exposed by default.
+    #[arg(
+        env = "MADARA_RPC_NAMESPACES",
+        long,
+        value_name = "NAMESPACES",
+        value_delimiter = ',',
+        default_values = ["read", "write", "admin"]
+    )]
+    pub rpc_namespaces: Vec<RpcNamespace>,
+
     /// Set the maximum RPC request payload size for both HTTP and WebSockets in megabytes.
     #[arg(env = "MADARA_RPC_MAX_REQUEST_SIZE", long, default_value_t = RPC_DEFAULT_MAX_REQUEST_SIZE_MB)]
     pub rpc_max_request_size: u32,
@@ -137,6 +168,43 @@ pub struct RpcParams {
     #[arg(env = "MADARA_RPC_CORS", long, value_name = "ORIGINS")]
     pub rpc_cors: Option<Cors>,
 
+    /// Limit how many transactions a single client IP can submit per second on the user RPC endpoint. Throttled
+    /// submissions are answered with a JSON-RPC error before reaching the mempool. Localhost is never throttled.
+    /// Clients are identified by the address they connect from, so clients behind a reverse proxy share its limit.
+    /// Disabled by default.
+    #[arg(env = "MADARA_RPC_SUBMIT_RATE_LIMIT", long, value_name = "TXS PER SECOND")]
+    pub rpc_submit_rate_limit: Option<u32>,
+
+    /// How many transactions a single client IP can submit at once before being throttled. Defaults to the
+    /// `--rpc-submit-rate-limit` value.
+    #[arg(env = "MADARA_RPC_SUBMIT_RATE_LIMIT_BURST", long, value_name = "TXS", requires = "rpc_submit_rate_limit")]
+    pub rpc_submit_rate_limit_burst: Option<u32>,
+
+    /// Comma separated list of client IPs which are exempt from `--rpc-submit-rate-limit`.
+    #[arg(
+        env = "MADARA_RPC_SUBMIT_RATE_LIMIT_ALLOWLIST",
+        long,
+        value_name = "IPS",
+        value_delimiter = ',',
+        requires = "rpc_submit_rate_limit"
+    )]
+    pub rpc_submit_rate_limit_allowlist: Vec<IpAddr>,
+
+    /// Limit how many submitted transactions are validated at the same time, so that a burst of submissions does not
+    /// starve the other tasks of the node. The validations then run on a dedicated thread pool. Unbounded by default.
+    #[arg(
+        env = "MADARA_RPC_VALIDATION_CONCURRENCY",
+        long,
+        value_name = "TXS",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub rpc_validation_concurrency: Option<usize>,
+
+    /// How many submitted transactions can wait for a validation slot when `--rpc-validation-concurrency` is reached.
+    /// Past this, the submissions are rejected with a busy error.
+    #[arg(env = "MADARA_RPC_VALIDATION_QUEUE_DEPTH", long, value_name = "TXS", default_value_t = 64)]
+    pub rpc_validation_queue_depth: usize,
+
     /// Limit how far back in the past we serve storage proofs.
     /// When getting a storage proof, the database will revert the global merkle trie in-memory up until the
     /// block_n specified in the request. If that block_n is too far back in the past, this could make
@@ -209,6 +277,26 @@ impl RpcParams {
         }
     }
 
+    pub fn submit_rate_limit_config(&self) -> Option<SubmitRateLimitConfig> {
+        let per_second = self.rpc_submit_rate_limit?;
+        Some(SubmitRateLimitConfig {
+            per_second,
+            burst: self.rpc_submit_rate_limit_burst.unwrap_or(per_second),
+            allowlist: self.rpc_submit_rate_limit_allowlist.iter().copied().collect(),
+        })
+    }
+
+    pub fn rpc_namespaces(&self) -> HashSet<RpcNamespace> {
+        self.rpc_namespaces.iter().copied().collect()
+    }
+
+    pub fn validation_pool_config(&self) -> Option<ValidationPoolConfig> {
+        Some(ValidationPoolConfig {
+            concurrency: self.rpc_validation_concurrency?,
+            queue_depth: self.rpc_validation_queue_depth,
+        })
+    }
+
     pub fn storage_proof_config(&self) -> StorageProofConfig {
         StorageProofConfig {
             max_keys: self.rpc_storage_proof_max_keys,
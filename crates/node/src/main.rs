@@ -7,18 +7,23 @@ mod util;
 
 use anyhow::{bail, Context};
 use clap::Parser;
+use cli::l1::GasPriceSource;
 use cli::{NetworkType, RunCmd};
 use http::{HeaderName, HeaderValue};
 use mc_analytics::Analytics;
 use mc_block_import::BlockImporter;
 use mc_db::{DatabaseService, TrieLogConfig};
 use mc_gateway_client::GatewayProvider;
-use mc_mempool::{GasPriceProvider, L1DataProvider, Mempool, MempoolLimits};
+use mc_mempool::{
+    GasPriceBounds, GasPriceProvider, GasPriceSmoothing, L1DataProvider, L1GasPriceSource, Mempool, MempoolLimits,
+};
 use mc_rpc::providers::{AddTransactionProvider, ForwardToProvider, MempoolAddTxProvider};
 use mc_telemetry::{SysInfo, TelemetryService};
 use mp_oracle::pragma::PragmaOracleBuilder;
 use mp_utils::service::{Service, ServiceGroup};
-use service::{BlockProductionService, GatewayService, L1SyncService, L2SyncService, RpcService};
+use service::{
+    fix_gas_prices, BlockProductionService, GatewayService, L1SyncService, L2SyncService, MempoolService, RpcService,
+};
 use std::sync::Arc;
 
 const GREET_IMPL_NAME: &str = "Madara";
@@ -90,15 +95,17 @@ async fn main() -> anyhow::Result<()> {
     );
 
     let mut l1_gas_setter = GasPriceProvider::new();
-
-    if let Some(fix_gas) = run_cmd.l1_sync_params.gas_price {
-        l1_gas_setter.update_eth_l1_gas_price(fix_gas as u128);
-        l1_gas_setter.set_gas_price_sync_enabled(false);
-    }
-    if let Some(fix_blob_gas) = run_cmd.l1_sync_params.blob_gas_price {
-        l1_gas_setter.update_eth_l1_data_gas_price(fix_blob_gas as u128);
-        l1_gas_setter.set_data_gas_price_sync_enabled(false);
-    }
+    l1_gas_setter.set_smoothing(GasPriceSmoothing {
+        alpha: run_cmd.l1_sync_params.gas_price_ema_alpha,
+        window: run_cmd.l1_sync_params.gas_price_ema_window as usize,
+    });
+    l1_gas_setter.set_data_gas_smoothing(GasPriceSmoothing {
+        alpha: run_cmd.l1_sync_params.blob_gas_price_ema_alpha.unwrap_or(run_cmd.l1_sync_params.gas_price_ema_alpha),
+        window: run_cmd.l1_sync_params.blob_gas_price_ema_window.unwrap_or(run_cmd.l1_sync_params.gas_price_ema_window)
+            as usize,
+    });
+
+    fix_gas_prices(&run_cmd.l1_sync_params, &l1_gas_setter);
     if let Some(strk_fix_gas) = run_cmd.l1_sync_params.strk_gas_price {
         l1_gas_setter.update_strk_l1_gas_price(strk_fix_gas as u128);
         l1_gas_setter.set_strk_gas_price_sync_enabled(false);
@@ -107,6 +114,39 @@ async fn main() -> anyhow::Result<()> {
         l1_gas_setter.update_strk_l1_data_gas_price(strk_fix_blob_gas as u128);
         l1_gas_setter.set_strk_data_gas_price_sync_enabled(false);
     }
+    anyhow::ensure!(
+        chain_config.min_gas_price <= chain_config.max_gas_price,
+        "Chain config min_gas_price must not be greater than max_gas_price"
+    );
+    l1_gas_setter.set_gas_price_bounds(GasPriceBounds {
+        min: chain_config.min_gas_price.into(),
+        max: chain_config.max_gas_price.into(),
+    });
+    anyhow::ensure!(
+        chain_config.gas_price_multiplier.is_finite()
+            && chain_config.gas_price_multiplier > 0.0
+            && chain_config.data_gas_price_multiplier.is_finite()
+            && chain_config.data_gas_price_multiplier > 0.0,
+        "Chain config gas_price_multiplier and data_gas_price_multiplier must be positive"
+    );
+    // The fixed gas prices are set above, they are not multiplied.
+    l1_gas_setter
+        .set_gas_price_multiplier(chain_config.gas_price_multiplier)
+        .set_data_gas_price_multiplier(chain_config.data_gas_price_multiplier);
+    l1_gas_setter
+        .set_poll_interval(run_cmd.l1_sync_params.gas_price_poll)
+        .context("Invalid gas price poll interval")?;
+    l1_gas_setter.set_poll_jitter(run_cmd.l1_sync_params.gas_price_poll_jitter);
+    l1_gas_setter.set_fee_history_percentile(run_cmd.l1_sync_params.gas_price_fee_history_percentile);
+    l1_gas_setter.set_gas_price_source(match run_cmd.l1_sync_params.gas_price_source {
+        GasPriceSource::BaseFee => L1GasPriceSource::BaseFee,
+        GasPriceSource::OracleContract => L1GasPriceSource::OracleContract {
+            address: run_cmd
+                .l1_sync_params
+                .gas_price_oracle_address
+                .context("--gas-price-source oracle-contract requires --gas-price-oracle-address")?,
+        },
+    });
     if let Some(ref oracle_url) = run_cmd.l1_sync_params.oracle_url {
         if let Some(ref oracle_api_key) = run_cmd.l1_sync_params.oracle_api_key {
             let oracle = PragmaOracleBuilder::new()
@@ -117,7 +157,11 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    // Without an L1 endpoint, the fallback gas price is used instead of syncing the gas prices.
+    let l1_gas_price_fallback_used =
+        run_cmd.l1_sync_params.l1_endpoint.is_empty() && run_cmd.l1_sync_params.l1_gas_price_fallback.is_some();
     if !run_cmd.l1_sync_params.sync_l1_disabled
+        && !l1_gas_price_fallback_used
         && l1_gas_setter.is_oracle_needed()
         && l1_gas_setter.oracle_provider.is_none()
     {
@@ -134,11 +178,12 @@ async fn main() -> anyhow::Result<()> {
     );
     mempool.load_txs_from_db().context("Loading mempool transactions")?;
     let mempool = Arc::new(mempool);
+    let mempool_service = MempoolService::new(&chain_config, Arc::clone(&mempool));
 
     let l1_service = L1SyncService::new(
         &run_cmd.l1_sync_params,
         &db_service,
-        l1_gas_setter,
+        l1_gas_setter.clone(),
         chain_config.chain_id.clone(),
         chain_config.eth_core_contract_address,
         run_cmd.is_sequencer(),
@@ -164,7 +209,12 @@ async fn main() -> anyhow::Result<()> {
                     telemetry_service.new_handle(),
                 )?;
 
-                (ServiceGroup::default().with(block_production_service), Arc::new(MempoolAddTxProvider::new(mempool)))
+                let mut add_tx_provider = MempoolAddTxProvider::new(Arc::clone(&mempool));
+                if let Some(config) = run_cmd.rpc_params.validation_pool_config() {
+                    add_tx_provider = add_tx_provider.with_validation_pool(config);
+                }
+
+                (ServiceGroup::default().with(block_production_service), Arc::new(add_tx_provider))
             }
             // Block sync service. (full node)
             false => {
@@ -194,8 +244,16 @@ async fn main() -> anyhow::Result<()> {
             }
         };
 
-    let rpc_service =
-        RpcService::new(run_cmd.rpc_params, Arc::clone(db_service.backend()), Arc::clone(&rpc_add_txs_method_provider));
+    let rpc_service = RpcService::new(
+        run_cmd.rpc_params,
+        Arc::clone(db_service.backend()),
+        Arc::clone(&rpc_add_txs_method_provider),
+        mempool,
+        l1_gas_setter,
+    )
+    .with_l1_core_address(l1_service.core_address())
+    .with_l1_sync_pause(l1_service.pause_handle())
+    .with_l1_endpoints(l1_service.l1_endpoints());
 
     let gateway_service = GatewayService::new(run_cmd.gateway_params, &db_service, rpc_add_txs_method_provider)
         .await
@@ -205,7 +263,10 @@ async fn main() -> anyhow::Result<()> {
 
     let app = ServiceGroup::default()
         .with(db_service)
-        .with(l1_service)
+        // A failed l1 sync does not stop the node, it is reported as stopped until restarted with
+        // `madara_restartService`.
+        .with_restartable(move || l1_service.clone())
+        .with(mempool_service)
         .with(block_provider_service)
         .with(rpc_service)
         .with(gateway_service)
@@ -121,4 +121,10 @@ impl Service for BlockProductionService {
     fn id(&self) -> MadaraService {
         MadaraService::BlockProduction
     }
+
+    /// Block production prices the transactions with the L1 gas prices synced by the L1 sync service, and takes them
+    /// from the mempool.
+    fn dependencies(&self) -> Vec<MadaraService> {
+        vec![MadaraService::L1Sync, MadaraService::Mempool]
+    }
 }
@@ -1,16 +1,77 @@
-use crate::cli::l1::L1SyncParams;
+use crate::cli::l1::{L1HeadMode, L1SyncParams};
 use alloy::primitives::Address;
 use anyhow::Context;
 use mc_db::{DatabaseService, MadaraBackend};
-use mc_eth::client::{EthereumClient, L1BlockMetrics};
+use mc_eth::client::{EthereumClient, L1BlockMetrics, L1EndpointHeaders};
+use mc_eth::l1_messaging::L1LogFetchConfig;
+use mc_eth::sync::{L1CircuitBreakerConfig, L1ReconnectConfig, MAX_RECONNECT_BACKOFF};
 use mc_mempool::{GasPriceProvider, Mempool};
 use mp_block::H160;
-use mp_utils::service::{MadaraService, Service, ServiceContext};
+use mp_chain_config::{L1CoreAddress, L1Endpoints};
+use mp_utils::service::{MadaraService, PauseHandle, Service, ServiceContext};
 use starknet_api::core::ChainId;
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::task::JoinSet;
 
+/// Fixes the L1 gas and blob gas prices given with `--gas-price` and `--blob-gas-price`, so that they are not synced.
+/// A price of zero is a valid fixed price.
+pub fn fix_gas_prices(config: &L1SyncParams, l1_gas_provider: &GasPriceProvider) {
+    if let Some(gas_price) = config.gas_price {
+        l1_gas_provider.set_fixed_gas_price(gas_price.into());
+    }
+    if let Some(blob_gas_price) = config.blob_gas_price {
+        l1_gas_provider.set_fixed_data_gas_price(blob_gas_price.into());
+    }
+}
+
+/// Runs `attempt` until it succeeds, retrying it at most `max_retries` times. The backoff between two attempts starts
+/// at `backoff` and is doubled after every failure, up to [`MAX_RECONNECT_BACKOFF`]. The error of the last attempt is
+/// returned once the retries are exhausted.
+async fn retry_with_backoff<F, Fut>(max_retries: u32, mut backoff: Duration, mut attempt: F) -> anyhow::Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let mut retries = 0;
+    loop {
+        match attempt().await {
+            Ok(()) => return Ok(()),
+            Err(err) if retries >= max_retries => {
+                return Err(err.context(format!("Giving up after {} attempts", retries + 1)));
+            }
+            Err(err) => {
+                retries += 1;
+                tracing::warn!("Attempt failed, retrying in {backoff:?} (retry {retries}/{max_retries}): {err:#}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+            }
+        }
+    }
+}
+
+/// The chain id of the L1 network a Starknet chain settles on, `None` when it is not known (custom chains).
+fn expected_l1_chain_id(chain_id: &ChainId) -> Option<u64> {
+    match chain_id {
+        ChainId::Mainnet => Some(1),
+        ChainId::Sepolia | ChainId::IntegrationSepolia => Some(11155111),
+        ChainId::Other(_) => None,
+    }
+}
+
+/// Makes sure the L1 endpoint serves the L1 network the configured chain settles on.
+async fn check_l1_chain_id(eth_client: &EthereumClient, chain_id: &ChainId) -> anyhow::Result<()> {
+    let Some(expected) = expected_l1_chain_id(chain_id) else { return Ok(()) };
+    let l1_chain_id = eth_client.get_l1_chain_id().await.context("Getting the L1 chain id")?;
+    if l1_chain_id != expected {
+        anyhow::bail!(
+            "L1 chain id mismatch: the chain {chain_id} settles on the L1 chain id {expected}, but the L1 endpoint serves the chain id {l1_chain_id}. Check that --l1-endpoint points to the right network."
+        );
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct L1SyncService {
     db_backend: Arc<MadaraBackend>,
@@ -18,8 +79,11 @@ pub struct L1SyncService {
     l1_gas_provider: GasPriceProvider,
     chain_id: ChainId,
     gas_price_sync_disabled: bool,
-    gas_price_poll: Duration,
+    l1_confirmations: u64,
     mempool: Arc<Mempool>,
+    reconnect_config: L1ReconnectConfig,
+    log_fetch: L1LogFetchConfig,
+    pause: PauseHandle,
 }
 
 impl L1SyncService {
@@ -34,15 +98,39 @@ impl L1SyncService {
         devnet: bool,
         mempool: Arc<Mempool>,
     ) -> anyhow::Result<Self> {
-        let eth_client = if !config.sync_l1_disabled && (config.l1_endpoint.is_some() || !devnet) {
-            if let Some(l1_rpc_url) = &config.l1_endpoint {
+        let l1_gas_price_fallback = config.l1_gas_price_fallback.filter(|_| authority);
+        let eth_client = if !config.sync_l1_disabled && (!config.l1_endpoint.is_empty() || !devnet) {
+            if !config.l1_endpoint.is_empty() {
                 let core_address = Address::from_slice(l1_core_address.as_bytes());
                 let l1_block_metrics = L1BlockMetrics::register().expect("Registering metrics");
-                Some(
-                    EthereumClient::new(l1_rpc_url.clone(), core_address, l1_block_metrics)
-                        .await
-                        .context("Creating ethereum client")?,
+                let headers = L1EndpointHeaders::new(
+                    config.l1_endpoint_header.iter().map(|header| (header.name.clone(), header.value.0.clone())),
+                    config.l1_bearer_token.as_ref().map(|token| token.0.as_str()),
+                )
+                .context("Parsing the L1 endpoint headers")?;
+                let request_timeout = Some(config.l1_request_timeout).filter(|timeout| !timeout.is_zero());
+                let mut eth_client = EthereumClient::new(
+                    config.l1_endpoint.clone(),
+                    headers,
+                    request_timeout,
+                    core_address,
+                    l1_block_metrics,
                 )
+                .await
+                .context("Creating ethereum client")?
+                .with_block_tag(config.l1_block_tag.into());
+                check_l1_chain_id(&eth_client, &chain_id).await?;
+                if config.l1_head_mode == L1HeadMode::Subscribe {
+                    let ws_endpoint =
+                        config.l1_ws_endpoint.clone().context("--l1-head-mode subscribe requires --l1-ws-endpoint")?;
+                    eth_client = eth_client.with_head_subscription(ws_endpoint);
+                }
+                Some(eth_client)
+            } else if l1_gas_price_fallback.is_some() {
+                tracing::warn!(
+                    "⚠️ No Ethereum endpoint provided, running without the L1 watcher: the synced state is not verified and L1 messages are not processed."
+                );
+                None
             } else {
                 anyhow::bail!(
                     "No Ethereum endpoint provided. You need to provide one using --l1-endpoint <RPC URL> in order to verify the synced state or disable the l1 watcher using --no-l1-sync."
@@ -52,21 +140,44 @@ impl L1SyncService {
             None
         };
 
-        // Note: gas price should be synced in case the madara is running in sequencer mode,
-        // we haven't set any fix price for the gas, hence gas price should be none
-        let gas_price_sync_enabled =
-            authority && !devnet && (config.gas_price.is_none() || config.blob_gas_price.is_none());
-        let gas_price_poll = config.gas_price_poll;
+        // Note: gas price should be synced in case the madara is running in sequencer mode and a gas price is not fixed.
+        // A gas price fixed to zero is `Some(0)`, it is not synced.
+        let mut gas_price_sync_enabled = authority
+            && !devnet
+            && (l1_gas_provider.fixed_gas_price().is_none() || l1_gas_provider.fixed_data_gas_price().is_none());
+
+        if let Some(fallback) = l1_gas_price_fallback.filter(|_| gas_price_sync_enabled && eth_client.is_none()) {
+            tracing::warn!("⚠️ L1 gas prices cannot be synced without an Ethereum endpoint, using the fallback gas price of {fallback} wei.");
+            if l1_gas_provider.fixed_gas_price().is_none() {
+                l1_gas_provider.set_fixed_gas_price(fallback.into());
+            }
+            if l1_gas_provider.fixed_data_gas_price().is_none() {
+                l1_gas_provider.set_fixed_data_gas_price(fallback.into());
+            }
+            gas_price_sync_enabled = false;
+        }
+
+        if !gas_price_sync_enabled {
+            // Nothing updates the gas prices, they are fixed.
+            l1_gas_provider.set_gas_price_sync_enabled(false);
+            l1_gas_provider.set_data_gas_price_sync_enabled(false);
+        }
 
         if gas_price_sync_enabled {
             let eth_client = eth_client
                 .clone()
-                .context("L1 gas prices require the ethereum service to be enabled. Either disable gas prices syncing using `--gas-price 0`, or disable L1 sync using the `--no-l1-sync` argument.")?;
+                .context("L1 gas prices require the ethereum service to be enabled. Either fix the gas prices using `--gas-price` and `--blob-gas-price`, or disable L1 sync using the `--no-l1-sync` argument.")?;
             // running at-least once before the block production service
             tracing::info!("⏳ Getting initial L1 gas prices");
-            mc_eth::l1_gas_price::gas_price_worker_once(&eth_client, l1_gas_provider.clone(), gas_price_poll)
-                .await
-                .context("Getting initial ethereum gas prices")?;
+            retry_with_backoff(config.l1_gas_price_init_retries, config.l1_gas_price_init_backoff, || {
+                mc_eth::l1_gas_price::gas_price_worker_once(
+                    &eth_client,
+                    l1_gas_provider.clone(),
+                    l1_gas_provider.poll_interval(),
+                )
+            })
+            .await
+            .context("Getting initial ethereum gas prices")?;
         }
 
         Ok(Self {
@@ -75,35 +186,122 @@ impl L1SyncService {
             l1_gas_provider,
             chain_id,
             gas_price_sync_disabled: !gas_price_sync_enabled,
-            gas_price_poll,
+            l1_confirmations: config.l1_confirmations,
             mempool,
+            reconnect_config: L1ReconnectConfig {
+                max_retries: config.l1_reconnect_max_retries,
+                backoff: config.l1_reconnect_backoff,
+                circuit_breaker: L1CircuitBreakerConfig {
+                    failure_threshold: config.l1_circuit_breaker_threshold,
+                    cooldown: config.l1_circuit_breaker_cooldown,
+                },
+            },
+            log_fetch: L1LogFetchConfig {
+                range: config.l1_log_fetch_range,
+                concurrency: config.l1_log_fetch_concurrency,
+                start_block: config.l1_start_block,
+            },
+            pause: PauseHandle::new(),
         })
     }
+
+    /// Handle on the address of the watched L1 core contract, `None` when running without the L1 watcher.
+    pub fn core_address(&self) -> Option<L1CoreAddress> {
+        self.eth_client.as_ref().map(EthereumClient::core_address)
+    }
+
+    /// Handle to rotate the L1 endpoints, `None` when running without the L1 watcher.
+    pub fn l1_endpoints(&self) -> Option<L1Endpoints> {
+        self.eth_client.as_ref().map(EthereumClient::l1_endpoints)
+    }
+
+    /// Handle to pause and resume the L1 sync workers, `None` when running without the L1 watcher.
+    pub fn pause_handle(&self) -> Option<PauseHandle> {
+        self.eth_client.as_ref().map(|_| self.pause.clone())
+    }
+
+    /// The L1 client of this instance of the service, `None` when running without the L1 watcher. A restarted service
+    /// is a clone of the one created on startup, its client is connected again to the current L1 endpoints and core
+    /// contract address.
+    async fn connect_eth_client(&mut self) -> anyhow::Result<Option<EthereumClient>> {
+        let Some(eth_client) = self.eth_client.take() else { return Ok(None) };
+        Ok(Some(eth_client.refreshed().await.context("Connecting to the current L1 endpoints and core contract")?))
+    }
 }
 
 #[async_trait::async_trait]
 impl Service for L1SyncService {
     async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>, ctx: ServiceContext) -> anyhow::Result<()> {
-        let L1SyncService { l1_gas_provider, chain_id, gas_price_sync_disabled, gas_price_poll, mempool, .. } =
-            self.clone();
+        let L1SyncService {
+            l1_gas_provider,
+            chain_id,
+            gas_price_sync_disabled,
+            l1_confirmations,
+            mempool,
+            reconnect_config,
+            log_fetch,
+            pause,
+            ..
+        } = self.clone();
 
-        if let Some(eth_client) = self.eth_client.take() {
+        if let Some(eth_client) = self.connect_eth_client().await? {
             // enabled
 
             let db_backend = Arc::clone(&self.db_backend);
+            // The gas prices are fetched on the new L1 endpoints before they are swapped in, so that they do not go
+            // stale during a rotation.
+            let warm_up = {
+                let l1_gas_provider = l1_gas_provider.clone();
+                move |eth_client: EthereumClient| {
+                    let l1_gas_provider = l1_gas_provider.clone();
+                    async move {
+                        if gas_price_sync_disabled {
+                            return Ok(());
+                        }
+                        let poll_interval = l1_gas_provider.poll_interval();
+                        mc_eth::l1_gas_price::gas_price_worker_once(&eth_client, l1_gas_provider, poll_interval).await
+                    }
+                }
+            };
             join_set.spawn(async move {
-                mc_eth::sync::l1_sync_worker(
-                    &db_backend,
-                    &eth_client,
-                    chain_id,
-                    l1_gas_provider,
-                    gas_price_sync_disabled,
-                    gas_price_poll,
-                    mempool,
-                    ctx,
+                // Transient L1 errors restart the workers instead of stopping the service.
+                let res = mc_eth::sync::run_with_reconnect_and_warm_up(
+                    eth_client,
+                    reconnect_config,
+                    pause,
+                    ctx.clone(),
+                    warm_up,
+                    |eth_client, ctx| {
+                        let db_backend = Arc::clone(&db_backend);
+                        let chain_id = chain_id.clone();
+                        let l1_gas_provider = l1_gas_provider.clone();
+                        let mempool = Arc::clone(&mempool);
+                        async move {
+                            mc_eth::sync::l1_sync_worker(
+                                &db_backend,
+                                &eth_client,
+                                chain_id,
+                                l1_gas_provider,
+                                gas_price_sync_disabled,
+                                l1_confirmations,
+                                mempool,
+                                log_fetch,
+                                ctx,
+                            )
+                            .await
+                        }
+                    },
                 )
-                .await
+                .await;
+
+                // The workers only observe the cancellation between two iterations, so their writes are complete by
+                // now. They are written without the WAL: flush them before the node exits.
+                db_backend.flush().context("Flushing the L1 sync writes")?;
+                res
             });
+        } else {
+            // Running without the L1 watcher.
+            ctx.report_status(|status| status.connected = Some(false));
         }
 
         Ok(())
@@ -113,3 +311,160 @@ impl Service for L1SyncService {
         MadaraService::L1Sync
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use mc_mempool::MempoolLimits;
+    use mp_chain_config::ChainConfig;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(clap::Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        l1_sync_params: L1SyncParams,
+    }
+
+    /// Creates the L1 sync service of a sequencer with these command line arguments.
+    async fn sequencer_l1_service(args: &[&str]) -> (anyhow::Result<L1SyncService>, GasPriceProvider) {
+        l1_service(args, false).await
+    }
+
+    async fn l1_service(args: &[&str], devnet: bool) -> (anyhow::Result<L1SyncService>, GasPriceProvider) {
+        l1_service_for_chain(args, devnet, ChainConfig::madara_test()).await
+    }
+
+    async fn l1_service_for_chain(
+        args: &[&str],
+        devnet: bool,
+        chain_config: ChainConfig,
+    ) -> (anyhow::Result<L1SyncService>, GasPriceProvider) {
+        let chain_config = Arc::new(chain_config);
+        let db = DatabaseService::open_for_testing(Arc::clone(&chain_config));
+        let l1_gas_provider = GasPriceProvider::new();
+        let mempool = Arc::new(Mempool::new(
+            Arc::clone(db.backend()),
+            Arc::new(l1_gas_provider.clone()),
+            MempoolLimits::for_testing(),
+        ));
+        let config = Cli::parse_from(std::iter::once("madara").chain(args.iter().copied())).l1_sync_params;
+        fix_gas_prices(&config, &l1_gas_provider);
+
+        let service = L1SyncService::new(
+            &config,
+            &db,
+            l1_gas_provider.clone(),
+            chain_config.chain_id.clone(),
+            chain_config.eth_core_contract_address,
+            true,
+            devnet,
+            mempool,
+        )
+        .await;
+        (service, l1_gas_provider)
+    }
+
+    #[tokio::test]
+    async fn l1_gas_price_fallback_without_l1_endpoint() {
+        let (service, l1_gas_provider) = sequencer_l1_service(&["--l1-gas-price-fallback", "1000"]).await;
+        let service = service.expect("The fallback gas price should let the sequencer start without an L1 endpoint");
+
+        assert!(service.eth_client.is_none());
+        assert!(service.gas_price_sync_disabled);
+        let gas_prices = l1_gas_provider.get_smoothed_gas_prices();
+        assert_eq!(gas_prices.eth_l1_gas_price, 1000);
+        assert_eq!(gas_prices.eth_l1_data_gas_price, 1000);
+        // Nothing updates the fallback gas prices, they never go stale.
+        assert!(!l1_gas_provider.is_stale(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn l1_chain_id_mismatch() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_getCode");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x6080"}));
+        });
+        // Ethereum mainnet, while the chain settles on Sepolia.
+        server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_chainId");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x1"}));
+        });
+
+        let chain_config = ChainConfig { chain_id: ChainId::Sepolia, ..ChainConfig::madara_test() };
+        let (service, _) = l1_service_for_chain(&["--l1-endpoint", &server.url("/")], false, chain_config).await;
+        let err = format!("{:#}", service.err().expect("The L1 chain id mismatch should be detected"));
+        assert!(err.contains("L1 chain id mismatch"), "{err}");
+        assert!(err.contains("11155111") && err.contains("serves the chain id 1."), "{err}");
+    }
+
+    #[tokio::test]
+    async fn restart_watches_the_current_core_address() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.method("POST").path("/").body_contains("eth_getCode");
+            then.status(200).json_body_obj(&serde_json::json!({"jsonrpc":"2.0","id":0,"result":"0x6080"}));
+        });
+        let (service, _) =
+            l1_service(&["--l1-endpoint", &server.url("/"), "--gas-price", "0", "--blob-gas-price", "0"], false).await;
+        let service = service.expect("The L1 endpoint should be usable");
+
+        let new_address = H160::from_low_u64_be(0x1234);
+        service.core_address().expect("Running with the L1 watcher").set(new_address);
+
+        // The restarted service is a clone of the one created on startup, see `main`.
+        let mut restarted = service.clone();
+        let eth_client = restarted.connect_eth_client().await.unwrap().expect("Running with the L1 watcher");
+        assert_eq!(eth_client.l1_core_contract.address().as_slice(), new_address.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn no_l1_endpoint_without_fallback() {
+        let (service, _) = sequencer_l1_service(&[]).await;
+        assert!(service.is_err());
+    }
+
+    #[tokio::test]
+    async fn devnet_zero_gas_price() {
+        let (service, l1_gas_provider) = l1_service(&["--gas-price", "0"], true).await;
+        let service = service.expect("A zero gas price should be valid in devnet");
+
+        assert!(service.eth_client.is_none());
+        assert!(service.gas_price_sync_disabled);
+        // The zero gas price is fixed, not absent.
+        assert_eq!(l1_gas_provider.fixed_gas_price(), Some(0));
+        assert_eq!(l1_gas_provider.get_smoothed_gas_prices().eth_l1_gas_price, 0);
+        assert!(!l1_gas_provider.is_stale(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn initial_gas_price_fetch_retries_until_success() {
+        // A flaky L1 which fails the first two fetches.
+        let attempts = AtomicU32::new(0);
+        let res = retry_with_backoff(5, Duration::from_millis(10), || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => anyhow::bail!("L1 rpc hiccup"),
+                _ => Ok(()),
+            }
+        })
+        .await;
+
+        res.expect("Startup should proceed once the third attempt succeeds");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn initial_gas_price_fetch_gives_up_after_max_retries() {
+        let attempts = AtomicU32::new(0);
+        let err = retry_with_backoff(2, Duration::from_millis(10), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("L1 rpc down")
+        })
+        .await
+        .expect_err("The retries should be exhausted");
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(format!("{err:#}"), "Giving up after 3 attempts: L1 rpc down");
+    }
+}
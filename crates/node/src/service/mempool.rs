@@ -0,0 +1,32 @@
+use mc_mempool::Mempool;
+use mp_chain_config::ChainConfig;
+use mp_utils::service::{MadaraService, Service, ServiceContext};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// Periodically sweeps age-exceeded transactions from the mempool.
+#[derive(Clone)]
+pub struct MempoolService {
+    mempool: Arc<Mempool>,
+    sweep_interval: Duration,
+}
+
+impl MempoolService {
+    pub fn new(chain_config: &ChainConfig, mempool: Arc<Mempool>) -> Self {
+        Self { mempool, sweep_interval: chain_config.mempool_sweep_interval }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for MempoolService {
+    async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>, ctx: ServiceContext) -> anyhow::Result<()> {
+        let MempoolService { mempool, sweep_interval } = self.clone();
+        join_set.spawn(async move { mc_mempool::sweeper::age_sweeper_worker(mempool, sweep_interval, ctx).await });
+        Ok(())
+    }
+
+    fn id(&self) -> MadaraService {
+        MadaraService::Mempool
+    }
+}
@@ -1,11 +1,13 @@
 mod block_production;
 mod gateway;
 mod l1;
+mod mempool;
 mod rpc;
 mod sync;
 
 pub use block_production::BlockProductionService;
 pub use gateway::GatewayService;
-pub use l1::L1SyncService;
+pub use l1::{fix_gas_prices, L1SyncService};
+pub use mempool::MempoolService;
 pub use rpc::RpcService;
 pub use sync::L2SyncService;
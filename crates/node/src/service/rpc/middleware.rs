@@ -2,8 +2,11 @@
 
 use futures::future::{BoxFuture, FutureExt};
 use jsonrpsee::server::middleware::rpc::RpcServiceT;
+use mc_rpc::rate_limit::{is_submit_method, SubmitRateLimiter, RATE_LIMITED_CODE, RATE_LIMITED_MSG};
 use mc_rpc::utils::ResultExt;
 use mp_chain_config::RpcVersion;
+use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::Instant;
 
 pub use super::metrics::Metrics;
@@ -148,3 +151,45 @@ where
         .boxed()
     }
 }
+
+/// Throttles the transaction submissions of a client, see [`SubmitRateLimiter`].
+#[derive(Debug, Clone)]
+pub struct RpcMiddlewareServiceRateLimit<S> {
+    inner: S,
+    limiter: Option<Arc<SubmitRateLimiter>>,
+    remote_ip: IpAddr,
+}
+
+impl<S> RpcMiddlewareServiceRateLimit<S> {
+    pub fn new(inner: S, limiter: Option<Arc<SubmitRateLimiter>>, remote_ip: IpAddr) -> Self {
+        Self { inner, limiter, remote_ip }
+    }
+}
+
+impl<'a, S> RpcServiceT<'a> for RpcMiddlewareServiceRateLimit<S>
+where
+    S: Send + Sync + Clone + RpcServiceT<'a> + 'static,
+{
+    type Future = BoxFuture<'a, jsonrpsee::MethodResponse>;
+
+    fn call(&self, req: jsonrpsee::types::Request<'a>) -> Self::Future {
+        let inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+        let remote_ip = self.remote_ip;
+
+        async move {
+            if let Some(limiter) = limiter {
+                if is_submit_method(req.method_name()) && !limiter.check(remote_ip) {
+                    tracing::debug!("Throttled {} from {remote_ip}", req.method_name());
+                    return jsonrpsee::MethodResponse::error(
+                        req.id,
+                        jsonrpsee::types::ErrorObject::owned(RATE_LIMITED_CODE, RATE_LIMITED_MSG, None::<()>),
+                    );
+                }
+            }
+
+            inner.call(req).await
+        }
+        .boxed()
+    }
+}
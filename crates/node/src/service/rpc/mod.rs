@@ -4,8 +4,11 @@ use jsonrpsee::server::ServerHandle;
 use tokio::task::JoinSet;
 
 use mc_db::MadaraBackend;
+use mc_mempool::{GasPriceProvider, Mempool};
+use mc_rpc::rate_limit::SubmitRateLimiter;
 use mc_rpc::{providers::AddTransactionProvider, rpc_api_admin, rpc_api_user, Starknet};
-use mp_utils::service::{MadaraService, Service, ServiceContext};
+use mp_chain_config::{L1CoreAddress, L1Endpoints};
+use mp_utils::service::{MadaraService, PauseHandle, Service, ServiceContext};
 
 use metrics::RpcMetrics;
 use server::{start_server, ServerConfig};
@@ -22,6 +25,11 @@ pub struct RpcService {
     config: RpcParams,
     backend: Arc<MadaraBackend>,
     add_txs_method_provider: Arc<dyn AddTransactionProvider>,
+    mempool: Arc<Mempool>,
+    l1_gas_provider: GasPriceProvider,
+    l1_core_address: Option<L1CoreAddress>,
+    l1_sync_pause: Option<PauseHandle>,
+    l1_endpoints: Option<L1Endpoints>,
     server_handle_user: Option<ServerHandle>,
     server_handle_admin: Option<ServerHandle>,
 }
@@ -31,22 +39,79 @@ impl RpcService {
         config: RpcParams,
         backend: Arc<MadaraBackend>,
         add_txs_method_provider: Arc<dyn AddTransactionProvider>,
+        mempool: Arc<Mempool>,
+        l1_gas_provider: GasPriceProvider,
     ) -> Self {
-        Self { config, backend, add_txs_method_provider, server_handle_user: None, server_handle_admin: None }
+        Self {
+            config,
+            backend,
+            add_txs_method_provider,
+            mempool,
+            l1_gas_provider,
+            l1_core_address: None,
+            l1_sync_pause: None,
+            l1_endpoints: None,
+            server_handle_user: None,
+            server_handle_admin: None,
+        }
+    }
+
+    /// Allows changing the address of the watched L1 core contract through the admin RPC.
+    pub fn with_l1_core_address(mut self, l1_core_address: Option<L1CoreAddress>) -> Self {
+        self.l1_core_address = l1_core_address;
+        self
+    }
+
+    /// Allows pausing and resuming the L1 sync through the admin RPC.
+    pub fn with_l1_sync_pause(mut self, l1_sync_pause: Option<PauseHandle>) -> Self {
+        self.l1_sync_pause = l1_sync_pause;
+        self
+    }
+
+    /// Allows rotating the L1 endpoints through the admin RPC.
+    pub fn with_l1_endpoints(mut self, l1_endpoints: Option<L1Endpoints>) -> Self {
+        self.l1_endpoints = l1_endpoints;
+        self
     }
 }
 
 #[async_trait::async_trait]
 impl Service for RpcService {
     async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>, ctx: ServiceContext) -> anyhow::Result<()> {
-        let RpcService { config, backend, add_txs_method_provider, .. } = self;
-
-        let starknet =
-            Starknet::new(backend.clone(), add_txs_method_provider.clone(), config.storage_proof_config(), ctx.clone());
+        let RpcService {
+            config,
+            backend,
+            add_txs_method_provider,
+            mempool,
+            l1_gas_provider,
+            l1_core_address,
+            l1_sync_pause,
+            l1_endpoints,
+            ..
+        } = self;
+
+        let mut starknet =
+            Starknet::new(backend.clone(), add_txs_method_provider.clone(), config.storage_proof_config(), ctx.clone())
+                .with_gas_price_provider(l1_gas_provider.clone());
+        if config.rpc_admin_mempool {
+            starknet = starknet
+                .with_mempool(Arc::clone(mempool))
+                .with_max_mempool_subscribers(config.rpc_max_mempool_subscribers);
+        }
+        if let Some(l1_core_address) = l1_core_address {
+            starknet = starknet.with_l1_core_address(l1_core_address.clone());
+        }
+        if let Some(l1_sync_pause) = l1_sync_pause {
+            starknet = starknet.with_l1_sync_pause(l1_sync_pause.clone());
+        }
+        if let Some(l1_endpoints) = l1_endpoints {
+            starknet = starknet.with_l1_endpoints(l1_endpoints.clone());
+        }
         let metrics = RpcMetrics::register()?;
+        let namespaces = config.rpc_namespaces();
 
         let server_config_user = if !config.rpc_disable {
-            let api_rpc_user = rpc_api_user(&starknet)?;
+            let api_rpc_user = rpc_api_user(&starknet, &namespaces)?;
             let methods_user = rpc_api_build("rpc", api_rpc_user).into();
 
             Some(ServerConfig {
@@ -62,13 +127,14 @@ impl Service for RpcService {
                 metrics: metrics.clone(),
                 cors: config.cors(),
                 rpc_version_default: mp_chain_config::RpcVersion::RPC_VERSION_LATEST,
+                submit_rate_limiter: config.submit_rate_limit_config().map(|c| Arc::new(SubmitRateLimiter::new(c))),
             })
         } else {
             None
         };
 
         let server_config_admin = if config.rpc_admin {
-            let api_rpc_admin = rpc_api_admin(&starknet)?;
+            let api_rpc_admin = rpc_api_admin(&starknet, &namespaces)?;
             let methods_admin = rpc_api_build("admin", api_rpc_admin).into();
 
             Some(ServerConfig {
@@ -84,6 +150,7 @@ impl Service for RpcService {
                 metrics,
                 cors: config.cors(),
                 rpc_version_default: mp_chain_config::RpcVersion::RPC_VERSION_LATEST_ADMIN,
+                submit_rate_limiter: None,
             })
         } else {
             None
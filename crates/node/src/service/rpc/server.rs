@@ -3,16 +3,18 @@
 
 use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Context;
+use mc_rpc::rate_limit::SubmitRateLimiter;
 use mp_utils::service::ServiceContext;
 use tokio::task::JoinSet;
 use tower::Service;
 
 use mp_utils::wait_or_graceful_shutdown;
 
-use crate::service::rpc::middleware::RpcMiddlewareServiceVersion;
+use crate::service::rpc::middleware::{RpcMiddlewareServiceRateLimit, RpcMiddlewareServiceVersion};
 
 use super::metrics::RpcMetrics;
 use super::middleware::{Metrics, RpcMiddlewareLayerMetrics};
@@ -35,6 +37,8 @@ pub struct ServerConfig {
     pub methods: jsonrpsee::Methods,
     /// Batch request config.
     pub batch_config: jsonrpsee::server::BatchRequestConfig,
+    /// Per client IP rate limit of the transaction submissions, if any.
+    pub submit_rate_limiter: Option<Arc<SubmitRateLimiter>>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +46,7 @@ struct PerConnection<RpcMiddleware, HttpMiddleware> {
     methods: jsonrpsee::Methods,
     stop_handle: jsonrpsee::server::StopHandle,
     metrics: RpcMetrics,
+    submit_rate_limiter: Option<Arc<SubmitRateLimiter>>,
     service_builder: jsonrpsee::server::TowerServiceBuilder<RpcMiddleware, HttpMiddleware>,
 }
 
@@ -64,6 +69,7 @@ pub async fn start_server(
         message_buffer_capacity,
         methods,
         batch_config,
+        submit_rate_limiter,
     } = config;
 
     let listener = tokio::net::TcpListener::bind(addr)
@@ -96,19 +102,21 @@ pub async fn start_server(
         methods,
         stop_handle: stop_handle.clone(),
         metrics,
+        submit_rate_limiter,
         service_builder: builder.to_service_builder(),
     };
     let ctx1 = ctx.clone();
 
-    let make_service = hyper::service::make_service_fn(move |_| {
+    let make_service = hyper::service::make_service_fn(move |conn: &hyper::server::conn::AddrStream| {
         let cfg = cfg.clone();
         let ctx1 = ctx1.clone();
+        let remote_ip = conn.remote_addr().ip();
 
         async move {
             let cfg = cfg.clone();
 
             Ok::<_, Infallible>(hyper::service::service_fn(move |req| {
-                let PerConnection { service_builder, metrics, stop_handle, methods } = cfg.clone();
+                let PerConnection { service_builder, metrics, submit_rate_limiter, stop_handle, methods } = cfg.clone();
 
                 let is_websocket = jsonrpsee::server::ws::is_upgrade_request(&req);
                 let transport_label = if is_websocket { "ws" } else { "http" };
@@ -119,6 +127,9 @@ pub async fn start_server(
                     .layer_fn(move |service| {
                         RpcMiddlewareServiceVersion::new(service, path.clone(), rpc_version_default)
                     })
+                    .layer_fn(move |service| {
+                        RpcMiddlewareServiceRateLimit::new(service, submit_rate_limiter.clone(), remote_ip)
+                    })
                     .layer(metrics_layer.clone());
 
                 let mut svc = service_builder.set_rpc_middleware(rpc_middleware).build(methods, stop_handle);
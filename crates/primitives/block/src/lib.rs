@@ -7,7 +7,7 @@ use header::{L1DataAvailabilityMode, PendingHeader};
 use mp_chain_config::StarknetVersion;
 use mp_receipt::TransactionReceipt;
 use mp_transactions::Transaction;
-pub use primitive_types::{H160, U256};
+pub use primitive_types::{H160, H256, U256};
 use starknet_types_core::felt::Felt;
 
 use crate::header::GasPrices;
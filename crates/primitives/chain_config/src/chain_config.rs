@@ -68,6 +68,98 @@ lazy_static::lazy_static! {
 #[error("Unsupported protocol version: {0}")]
 pub struct UnsupportedProtocolVersion(StarknetVersion);
 
+/// How the mempool orders the ready transactions for block production.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolOrdering {
+    /// Transactions are served in arrival order, regardless of their tip.
+    Fifo,
+    /// Transactions with a higher tip are served first, ties are served in arrival order.
+    #[default]
+    FeePriority,
+    /// Senders are served in turn, with a share of the transactions weighted by their tips, so that a busy sender
+    /// cannot monopolize the blocks.
+    FairShare,
+}
+
+/// What block production does with the transactions it pops from the mempool whose L1 gas max price is below the
+/// current L1 gas price.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolUnderpricedPolicy {
+    /// Put the transactions back in the mempool, in case the L1 gas price goes down before they expire.
+    #[default]
+    Requeue,
+    /// Remove the transactions from the mempool.
+    Drop,
+}
+
+/// What the mempool does with a submitted transaction which is already older than the mempool max age.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolExpiredTxPolicy {
+    /// Reject the transaction.
+    #[default]
+    Reject,
+    /// Accept the transaction, and leave its removal to the age sweeper. This holds the transactions received while
+    /// catching up after a downtime a little longer instead of dropping them right away.
+    Admit,
+}
+
+/// What the mempool does with a submitted declare transaction once `mempool_declare_tx_limit` is reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolDeclareLimitPolicy {
+    /// Reject the transaction.
+    #[default]
+    Reject,
+    /// Accept the transaction if `mempool_tx_limit` is not reached, and serve it after the other transactions. The
+    /// declare transactions over the limit then wait behind the invoke transactions instead of being rejected.
+    Deprioritize,
+}
+
+/// Named mempool limit defaults, set with the `mempool_preset` key of a chain config file. The preset fills in
+/// `mempool_tx_limit`, `mempool_declare_tx_limit` and `mempool_tx_max_age` when the file leaves them out, and each of
+/// them can still be set in the file to override the preset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolPreset {
+    /// 2000 transactions, 50 declare transactions, and an hour of max age: testnets see fewer transactions but more
+    /// class declarations.
+    Testnet,
+    /// 10000 transactions, 20 declare transactions, and 5 hours of max age.
+    Mainnet,
+    /// 50000 transactions, 100 declare transactions, and 12 hours of max age, for app chains with a single sequencer
+    /// which can afford to buffer more transactions.
+    Appchain,
+}
+
+impl MempoolPreset {
+    /// The `mempool_tx_limit`, `mempool_declare_tx_limit` and `mempool_tx_max_age` of this preset.
+    pub fn limits(self) -> (usize, usize, Duration) {
+        match self {
+            Self::Testnet => (2_000, 50, Duration::from_secs(60 * 60)),
+            Self::Mainnet => (10_000, 20, Duration::from_secs(5 * 60 * 60)),
+            Self::Appchain => (50_000, 100, Duration::from_secs(12 * 60 * 60)),
+        }
+    }
+
+    /// Fills in the mempool limits of this preset which the chain config does not set.
+    fn apply(self, config_value: &mut serde_yaml::Value) -> anyhow::Result<()> {
+        let serde_yaml::Value::Mapping(config) = config_value else { bail!("The chain config is not a mapping") };
+        let (tx_limit, declare_tx_limit, tx_max_age) = self.limits();
+        let defaults: [(&str, serde_yaml::Value); 3] = [
+            ("mempool_tx_limit", tx_limit.into()),
+            ("mempool_declare_tx_limit", declare_tx_limit.into()),
+            ("mempool_tx_max_age", format!("{}s", tx_max_age.as_secs()).into()),
+        ];
+        for (key, value) in defaults {
+            config.entry(key.into()).or_insert(value);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ChainConfig {
     /// Human readable chain name, for displaying to the console.
@@ -135,15 +227,106 @@ pub struct ChainConfig {
     pub mempool_tx_limit: usize,
     /// Transaction limit in the mempool, we have an additional limit for declare transactions.
     pub mempool_declare_tx_limit: usize,
-    /// Max age of a transaction in the mempool.
+    /// Transaction limit in the mempool for a single sender address.
+    pub mempool_tx_limit_per_sender: usize,
+    /// Part of the mempool transaction limit reserved for L1 handler transactions.
+    pub mempool_l1_handler_tx_reserved: usize,
+    /// Part of the mempool transaction limit reserved for deploy account transactions.
+    pub mempool_deploy_account_tx_reserved: usize,
+    /// Max age of a transaction in the mempool. `0s` disables the age limit.
     #[serde(deserialize_with = "deserialize_duration")]
     pub mempool_tx_max_age: Duration,
+    /// When the mempool is full, evict the lowest-tip transaction to make room for an incoming transaction with a
+    /// higher tip, instead of rejecting it.
+    pub mempool_eviction_enabled: bool,
+    /// Interval at which transactions older than `mempool_tx_max_age` are swept from the mempool.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub mempool_sweep_interval: Duration,
+    /// Minimum tip increase, in percent, for a transaction to replace a mempool transaction with the same sender and
+    /// nonce.
+    pub mempool_replacement_bump_percent: u64,
+    /// Minimum time between two replacements of the mempool transaction with the same sender and nonce, so that a
+    /// sender cannot spam the mempool with tip bumps. `0s` disables it.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub mempool_replacement_min_interval: Duration,
+    /// Save the mempool transactions to the database, so that they are restored when the node restarts.
+    pub mempool_persistence_enabled: bool,
+    /// How ready transactions are ordered for block production. L1 handler transactions are always served first.
+    #[serde(default)]
+    pub mempool_ordering: MempoolOrdering,
+    /// What block production does with the transactions whose L1 gas max price is below the current L1 gas price.
+    #[serde(default)]
+    pub mempool_underpriced_policy: MempoolUnderpricedPolicy,
+    /// What the mempool does with the submitted transactions which are already older than `mempool_tx_max_age`.
+    #[serde(default)]
+    pub mempool_expired_tx_policy: MempoolExpiredTxPolicy,
+    /// What the mempool does with the submitted declare transactions once `mempool_declare_tx_limit` is reached.
+    #[serde(default)]
+    pub mempool_declare_limit_policy: MempoolDeclareLimitPolicy,
+    /// The mempool rejects the V3 transactions whose tip is below the current STRK L1 gas price times this multiplier,
+    /// so that it does not fill up with transactions that would not be included while the L1 gas price is high. `0`
+    /// disables this minimum.
+    #[serde(default)]
+    pub mempool_min_tip_multiplier: f64,
+    /// Fraction of `mempool_tx_limit` above which transaction submissions are answered with a `near_capacity` warning,
+    /// so that clients can slow down before transactions start being rejected.
+    pub mempool_near_capacity_watermark: f64,
+    /// Senders which bypass the mempool `mempool_tx_limit` and `mempool_declare_tx_limit`, such as trusted relayers.
+    #[serde(default)]
+    pub mempool_privileged_senders: Vec<ContractAddress>,
+    /// Limit of the cumulative serialized size of the mempool transactions, in bytes.
+    pub mempool_max_total_bytes: usize,
+    /// Limit of the code size of the class of a declare transaction, in bytes. Larger classes are rejected at mempool
+    /// insertion.
+    pub max_declare_bytecode_size: usize,
+    /// Limit of the cumulative L2 gas max amount of the V3 transactions in the mempool, so that the mempool does not
+    /// hold more work than block production can execute. `u64::MAX` disables it.
+    pub mempool_max_total_l2_gas: u64,
+    /// Transactions with a nonce more than this above the nonce of their account are rejected, which bounds the
+    /// transactions buffered behind a nonce gap. `u64::MAX` disables it.
+    pub mempool_max_nonce_distance: u64,
+    /// Whether the mempool accepts the account transactions which pay no fee, such as on chains where all the fees are
+    /// sponsored. When disabled, they are rejected at insertion. L1 handler transactions are always accepted.
+    pub allow_zero_fee_transactions: bool,
+    /// Whether the mempool rejects the invoke and deploy account transactions whose account class is not declared,
+    /// as they cannot execute. The classes declared by the transactions of the mempool count as declared.
+    #[serde(default)]
+    pub mempool_reject_unknown_classes: bool,
+    /// Whether resubmitting a transaction already in the mempool refreshes its arrival time, so that it does not
+    /// expire, instead of being rejected as a duplicate.
+    #[serde(default)]
+    pub mempool_refresh_on_resubmit: bool,
+    /// Number of recently dropped transactions whose drop reason the mempool remembers, so that users can query why
+    /// their transaction was dropped. `0` disables it.
+    pub mempool_dropped_tx_lru_size: usize,
+    /// Floor of the auto-tuned mempool transaction limit, which settles back toward it while the blocks produced are
+    /// not full.
+    #[serde(default)]
+    pub mempool_tx_limit_min: usize,
+    /// Ceiling of the auto-tuned mempool transaction limit, which is raised toward it while the blocks produced are
+    /// consistently full. `mempool_tx_limit` is the starting limit. `0` disables the auto-tuning.
+    #[serde(default)]
+    pub mempool_tx_limit_max: usize,
+
+    /// Lower bound, in wei, the L1 gas and blob gas prices fetched from the L1 are clamped to.
+    pub min_gas_price: u64,
+    /// Upper bound, in wei, the L1 gas and blob gas prices fetched from the L1 are clamped to.
+    pub max_gas_price: u64,
+    /// Block production is paused when the L1 gas prices have not been updated for longer than this, to avoid
+    /// underpricing transactions during L1 outages.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub gas_price_max_age: Duration,
+    /// Block production prices the L1 gas this many times above the L1 gas price fetched from the L1, as a safety
+    /// margin against the L1 gas price rising before the block is settled. `1.0` uses the fetched price as is.
+    pub gas_price_multiplier: f64,
+    /// Same as `gas_price_multiplier`, for the L1 data gas price.
+    pub data_gas_price_multiplier: f64,
 }
 
 impl ChainConfig {
     pub fn from_yaml(path: &Path) -> anyhow::Result<Self> {
         let config_str = fs::read_to_string(path)?;
-        let config_value: serde_yaml::Value =
+        let mut config_value: serde_yaml::Value =
             serde_yaml::from_str(&config_str).context("While deserializing chain config")?;
 
         let versioned_constants_file_paths: BTreeMap<String, String> =
@@ -157,12 +340,26 @@ impl ChainConfig {
             versioned_constants
         };
 
+        apply_mempool_preset(&mut config_value)?;
         let chain_config: ChainConfig =
-            serde_yaml::from_str(&config_str).context("While deserializing chain config")?;
+            serde_yaml::from_value(config_value).context("While deserializing chain config")?;
+        chain_config.check_mempool_config()?;
 
         Ok(ChainConfig { versioned_constants, ..chain_config })
     }
 
+    /// Verify that the mempool settings are consistent.
+    pub fn check_mempool_config(&self) -> anyhow::Result<()> {
+        let (tx_limit_min, tx_limit_max) = (self.mempool_tx_limit_min, self.mempool_tx_limit_max);
+        if tx_limit_max > 0 && tx_limit_min > tx_limit_max {
+            bail!("mempool_tx_limit_min cannot be above mempool_tx_limit_max.")
+        }
+        if self.mempool_sweep_interval.is_zero() {
+            bail!("mempool_sweep_interval cannot be 0.")
+        }
+        Ok(())
+    }
+
     /// Verify that the chain config is valid for block production.
     pub fn precheck_block_production(&self) -> anyhow::Result<()> {
         // block_time != 0 implies that n_pending_ticks_per_block != 0.
@@ -247,7 +444,38 @@ impl ChainConfig {
 
             mempool_tx_limit: 10_000,
             mempool_declare_tx_limit: 20,
+            mempool_tx_limit_per_sender: 10_000,
+            mempool_l1_handler_tx_reserved: 0,
+            mempool_deploy_account_tx_reserved: 0,
             mempool_tx_max_age: Duration::from_secs(60 * 60), // an hour?
+            mempool_eviction_enabled: false,
+            mempool_sweep_interval: Duration::from_secs(60),
+            mempool_replacement_bump_percent: 10,
+            mempool_replacement_min_interval: Duration::ZERO,
+            mempool_persistence_enabled: true,
+            mempool_ordering: MempoolOrdering::FeePriority,
+            mempool_underpriced_policy: MempoolUnderpricedPolicy::Requeue,
+            mempool_expired_tx_policy: MempoolExpiredTxPolicy::Reject,
+            mempool_declare_limit_policy: MempoolDeclareLimitPolicy::Reject,
+            mempool_min_tip_multiplier: 0.0,
+            mempool_near_capacity_watermark: 0.9,
+            mempool_privileged_senders: vec![],
+            mempool_max_total_bytes: 1024 * 1024 * 1024,
+            max_declare_bytecode_size: 4 * 1024 * 1024,
+            mempool_max_total_l2_gas: u64::MAX,
+            mempool_max_nonce_distance: u64::MAX,
+            allow_zero_fee_transactions: true,
+            mempool_reject_unknown_classes: false,
+            mempool_refresh_on_resubmit: false,
+            mempool_dropped_tx_lru_size: 10_000,
+            mempool_tx_limit_min: 0,
+            mempool_tx_limit_max: 0,
+
+            min_gas_price: 0,
+            max_gas_price: 10_000_000_000_000, // 10k gwei
+            gas_price_max_age: Duration::from_secs(10 * 60),
+            gas_price_multiplier: 1.0,
+            data_gas_price_multiplier: 1.0,
         }
     }
 
@@ -411,6 +639,13 @@ impl ChainVersionedConstants {
     }
 }
 
+/// Fills in the mempool limits of the `mempool_preset` of the chain config, if any.
+fn apply_mempool_preset(config_value: &mut serde_yaml::Value) -> anyhow::Result<()> {
+    let Some(preset) = config_value.get("mempool_preset").cloned() else { return Ok(()) };
+    let preset: MempoolPreset = serde_yaml::from_value(preset).context("While deserializing the mempool preset")?;
+    preset.apply(config_value)
+}
+
 pub fn deserialize_starknet_version<'de, D>(deserializer: D) -> Result<StarknetVersion, D::Error>
 where
     D: Deserializer<'de>,
@@ -556,6 +791,73 @@ mod tests {
         );
     }
 
+    /// The mainnet preset file, without its mempool limits.
+    fn config_without_mempool_limits() -> serde_yaml::Value {
+        let mut config_value: serde_yaml::Value =
+            serde_yaml::from_str(include_str!("../../../../configs/presets/mainnet.yaml")).unwrap();
+        let config = config_value.as_mapping_mut().unwrap();
+        for key in ["mempool_tx_limit", "mempool_declare_tx_limit", "mempool_tx_max_age"] {
+            config.remove(key).unwrap();
+        }
+        config_value
+    }
+
+    #[rstest]
+    #[case::testnet("testnet", 2_000, 50, Duration::from_secs(60 * 60))]
+    #[case::mainnet("mainnet", 10_000, 20, Duration::from_secs(5 * 60 * 60))]
+    #[case::appchain("appchain", 50_000, 100, Duration::from_secs(12 * 60 * 60))]
+    fn test_mempool_preset(
+        #[case] preset: &str,
+        #[case] tx_limit: usize,
+        #[case] declare_tx_limit: usize,
+        #[case] tx_max_age: Duration,
+    ) {
+        let mut config_value = config_without_mempool_limits();
+        config_value.as_mapping_mut().unwrap().insert("mempool_preset".into(), preset.into());
+
+        apply_mempool_preset(&mut config_value).unwrap();
+        let chain_config: ChainConfig = serde_yaml::from_value(config_value).unwrap();
+        assert_eq!(chain_config.mempool_tx_limit, tx_limit);
+        assert_eq!(chain_config.mempool_declare_tx_limit, declare_tx_limit);
+        assert_eq!(chain_config.mempool_tx_max_age, tx_max_age);
+    }
+
+    #[rstest]
+    fn test_mempool_preset_overrides() {
+        let mut config_value = config_without_mempool_limits();
+        let config = config_value.as_mapping_mut().unwrap();
+        config.insert("mempool_preset".into(), "appchain".into());
+        config.insert("mempool_declare_tx_limit".into(), 5.into());
+        config.insert("mempool_tx_max_age".into(), "10min".into());
+
+        apply_mempool_preset(&mut config_value).unwrap();
+        let chain_config: ChainConfig = serde_yaml::from_value(config_value).unwrap();
+        assert_eq!(chain_config.mempool_tx_limit, 50_000);
+        assert_eq!(chain_config.mempool_declare_tx_limit, 5);
+        assert_eq!(chain_config.mempool_tx_max_age, Duration::from_secs(10 * 60));
+    }
+
+    #[rstest]
+    fn test_mempool_limits_required_without_preset() {
+        let mut config_value = config_without_mempool_limits();
+
+        apply_mempool_preset(&mut config_value).unwrap();
+        assert!(serde_yaml::from_value::<ChainConfig>(config_value).is_err());
+
+        let mut config_value = config_without_mempool_limits();
+        config_value.as_mapping_mut().unwrap().insert("mempool_preset".into(), "unknown".into());
+        assert!(apply_mempool_preset(&mut config_value).is_err());
+    }
+
+    #[rstest]
+    fn test_mempool_sweep_interval_zero() {
+        let mut chain_config = ChainConfig::madara_test();
+        assert!(chain_config.check_mempool_config().is_ok());
+
+        chain_config.mempool_sweep_interval = Duration::ZERO;
+        assert_eq!(chain_config.check_mempool_config().unwrap_err().to_string(), "mempool_sweep_interval cannot be 0.");
+    }
+
     #[rstest]
     fn test_exec_constants() {
         let chain_config = ChainConfig {
@@ -0,0 +1,88 @@
+use primitive_types::H160;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum L1CoreAddressError {
+    #[error("Missing 0x prefix in L1 address")]
+    MissingPrefix,
+    #[error("Invalid L1 address length: expected 40 hex digits, got {0}")]
+    InvalidLength(usize),
+    #[error("Invalid hex digit in L1 address")]
+    InvalidHex,
+    #[error("The L1 core contract address cannot be zero")]
+    Zero,
+}
+
+/// Parses the address of an L1 core contract: a `0x` prefixed, 20 bytes hex string.
+pub fn parse_l1_core_address(s: &str) -> Result<H160, L1CoreAddressError> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).ok_or(L1CoreAddressError::MissingPrefix)?;
+    if digits.len() != 40 {
+        return Err(L1CoreAddressError::InvalidLength(digits.len()));
+    }
+    let address = H160::from_str(digits).map_err(|_| L1CoreAddressError::InvalidHex)?;
+    if address.is_zero() {
+        return Err(L1CoreAddressError::Zero);
+    }
+    Ok(address)
+}
+
+/// Address of the L1 core contract watched by the L1 sync, shared with the admin RPC so that it can be changed without
+/// restarting the node. The L1 sync restarts its workers to watch the new address whenever it changes.
+#[derive(Clone, Debug)]
+pub struct L1CoreAddress(Arc<watch::Sender<H160>>);
+
+impl L1CoreAddress {
+    pub fn new(address: H160) -> Self {
+        Self(Arc::new(watch::Sender::new(address)))
+    }
+
+    pub fn get(&self) -> H160 {
+        *self.0.borrow()
+    }
+
+    /// Returns the previous address.
+    pub fn set(&self, address: H160) -> H160 {
+        self.0.send_replace(address)
+    }
+
+    /// Notified whenever the address changes.
+    pub fn subscribe(&self) -> watch::Receiver<H160> {
+        self.0.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn parse_l1_core_address_valid() {
+        let address = parse_l1_core_address("0xc662c410C0ECf747543f5bA90660f6ABeBD9C8c4").unwrap();
+        assert_eq!(address, H160::from_str("c662c410c0ecf747543f5ba90660f6abebd9c8c4").unwrap());
+    }
+
+    #[rstest]
+    #[case::no_prefix("c662c410C0ECf747543f5bA90660f6ABeBD9C8c4", L1CoreAddressError::MissingPrefix)]
+    #[case::too_short("0xc662c410C0ECf747543f5bA90660f6ABeBD9C8", L1CoreAddressError::InvalidLength(38))]
+    #[case::too_long("0xc662c410C0ECf747543f5bA90660f6ABeBD9C8c400", L1CoreAddressError::InvalidLength(42))]
+    #[case::not_hex("0xg662c410C0ECf747543f5bA90660f6ABeBD9C8c4", L1CoreAddressError::InvalidHex)]
+    #[case::zero("0x0000000000000000000000000000000000000000", L1CoreAddressError::Zero)]
+    fn parse_l1_core_address_invalid(#[case] s: &str, #[case] expected: L1CoreAddressError) {
+        assert_eq!(parse_l1_core_address(s), Err(expected));
+    }
+
+    #[test]
+    fn l1_core_address_notifies_changes() {
+        let core_address = L1CoreAddress::new(H160::from_low_u64_be(1));
+        let mut receiver = core_address.subscribe();
+        assert!(!receiver.has_changed().unwrap());
+
+        assert_eq!(core_address.clone().set(H160::from_low_u64_be(2)), H160::from_low_u64_be(1));
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(*receiver.borrow_and_update(), H160::from_low_u64_be(2));
+        assert_eq!(core_address.get(), H160::from_low_u64_be(2));
+    }
+}
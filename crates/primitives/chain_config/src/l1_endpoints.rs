@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use url::Url;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum L1EndpointsError {
+    #[error("At least one L1 endpoint is required")]
+    Empty,
+    #[error("Invalid L1 endpoint URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("Unsupported L1 endpoint scheme {0:?}: expected http or https")]
+    UnsupportedScheme(String),
+}
+
+/// Parses the L1 RPC endpoints, tried in order on failover: at least one `http` or `https` URL.
+pub fn parse_l1_endpoints(endpoints: &[String]) -> Result<Vec<Url>, L1EndpointsError> {
+    if endpoints.is_empty() {
+        return Err(L1EndpointsError::Empty);
+    }
+    endpoints
+        .iter()
+        .map(|endpoint| {
+            let url = Url::parse(endpoint)?;
+            match url.scheme() {
+                "http" | "https" => Ok(url),
+                scheme => Err(L1EndpointsError::UnsupportedScheme(scheme.into())),
+            }
+        })
+        .collect()
+}
+
+/// L1 RPC endpoints of the L1 sync, shared with the admin RPC so that they can be rotated without restarting the node.
+/// The L1 sync connects to the new endpoints whenever they change, and only then swaps them in.
+#[derive(Clone, Debug)]
+pub struct L1Endpoints(Arc<watch::Sender<Vec<Url>>>);
+
+impl L1Endpoints {
+    pub fn new(endpoints: Vec<Url>) -> Self {
+        Self(Arc::new(watch::Sender::new(endpoints)))
+    }
+
+    pub fn get(&self) -> Vec<Url> {
+        self.0.borrow().clone()
+    }
+
+    /// Returns the previous endpoints.
+    pub fn set(&self, endpoints: Vec<Url>) -> Vec<Url> {
+        self.0.send_replace(endpoints)
+    }
+
+    /// Notified whenever the endpoints change.
+    pub fn subscribe(&self) -> watch::Receiver<Vec<Url>> {
+        self.0.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn parse_l1_endpoints_valid() {
+        let endpoints = parse_l1_endpoints(&["https://eth.example.com/v1".into(), "http://127.0.0.1:8545".into()]);
+        assert_eq!(
+            endpoints.unwrap(),
+            [Url::parse("https://eth.example.com/v1").unwrap(), Url::parse("http://127.0.0.1:8545").unwrap()]
+        );
+    }
+
+    #[rstest]
+    #[case::empty(&[], L1EndpointsError::Empty)]
+    #[case::invalid(&["not a url"], L1EndpointsError::InvalidUrl(url::ParseError::RelativeUrlWithoutBase))]
+    #[case::websocket(&["wss://eth.example.com"], L1EndpointsError::UnsupportedScheme("wss".into()))]
+    fn parse_l1_endpoints_invalid(#[case] endpoints: &[&str], #[case] expected: L1EndpointsError) {
+        let endpoints: Vec<String> = endpoints.iter().map(|endpoint| endpoint.to_string()).collect();
+        assert_eq!(parse_l1_endpoints(&endpoints), Err(expected));
+    }
+
+    #[test]
+    fn l1_endpoints_notify_changes() {
+        let first = vec![Url::parse("http://127.0.0.1:8545").unwrap()];
+        let second = vec![Url::parse("http://127.0.0.1:8546").unwrap()];
+        let endpoints = L1Endpoints::new(first.clone());
+        let mut receiver = endpoints.subscribe();
+        assert!(!receiver.has_changed().unwrap());
+
+        assert_eq!(endpoints.clone().set(second.clone()), first);
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(*receiver.borrow_and_update(), second);
+        assert_eq!(endpoints.get(), second);
+    }
+}
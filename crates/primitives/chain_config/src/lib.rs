@@ -1,7 +1,11 @@
 mod chain_config;
+mod l1_core_address;
+mod l1_endpoints;
 mod rpc_version;
 mod starknet_version;
 
 pub use chain_config::*;
+pub use l1_core_address::*;
+pub use l1_endpoints::*;
 pub use rpc_version::*;
 pub use starknet_version::*;
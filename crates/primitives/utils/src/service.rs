@@ -1,11 +1,18 @@
 //! Service trait and combinators.
 
-use anyhow::Context;
-use std::{fmt::Display, panic, sync::Arc};
+use anyhow::{bail, Context};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    panic,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+use tokio::sync::watch;
 use tokio::task::JoinSet;
 
-#[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[repr(u16)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
 pub enum MadaraService {
     #[default]
     None = 0,
@@ -17,6 +24,22 @@ pub enum MadaraService {
     RpcAdmin = 32,
     Gateway = 64,
     Telemetry = 128,
+    Mempool = 256,
+}
+
+impl MadaraService {
+    /// Every service, except [MadaraService::None].
+    pub const ALL: [Self; 9] = [
+        Self::Database,
+        Self::L1Sync,
+        Self::L2Sync,
+        Self::BlockProduction,
+        Self::Rpc,
+        Self::RpcAdmin,
+        Self::Gateway,
+        Self::Telemetry,
+        Self::Mempool,
+    ];
 }
 
 impl Display for MadaraService {
@@ -34,6 +57,7 @@ impl Display for MadaraService {
                 MadaraService::RpcAdmin => "rpc admin",
                 MadaraService::Gateway => "gateway",
                 MadaraService::Telemetry => "telemetry",
+                MadaraService::Mempool => "mempool",
             }
         )
     }
@@ -41,33 +65,121 @@ impl Display for MadaraService {
 
 #[repr(transparent)]
 #[derive(Default)]
-pub struct MadaraServiceMask(std::sync::atomic::AtomicU8);
+pub struct MadaraServiceMask(std::sync::atomic::AtomicU16);
 
 impl MadaraServiceMask {
     #[cfg(feature = "testing")]
     pub fn new_for_testing() -> Self {
-        Self(std::sync::atomic::AtomicU8::new(u8::MAX))
+        Self(std::sync::atomic::AtomicU16::new(u16::MAX))
     }
 
     #[inline(always)]
-    pub fn is_active(&self, cap: u8) -> bool {
+    pub fn is_active(&self, cap: u16) -> bool {
         self.0.load(std::sync::atomic::Ordering::SeqCst) & cap > 0
     }
 
     #[inline(always)]
     pub fn activate(&self, cap: MadaraService) -> bool {
-        let prev = self.0.fetch_or(cap as u8, std::sync::atomic::Ordering::SeqCst);
-        prev & cap as u8 > 0
+        let prev = self.0.fetch_or(cap as u16, std::sync::atomic::Ordering::SeqCst);
+        prev & cap as u16 > 0
     }
 
     #[inline(always)]
     pub fn deactivate(&self, cap: MadaraService) -> bool {
-        let cap = cap as u8;
+        let cap = cap as u16;
         let prev = self.0.fetch_and(!cap, std::sync::atomic::Ordering::SeqCst);
         prev & cap > 0
     }
 }
 
+/// Lightweight status a service reports about itself with [ServiceContext::report_status], on top of whether it is
+/// active or not.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServiceStatus {
+    /// Whether the service is connected to the remote it depends on, for services which have one such as the L1
+    /// endpoint of the L1 sync.
+    pub connected: Option<bool>,
+    /// Whether the circuit breaker of the service is open: after repeated failures of its remote, the service stopped
+    /// calling it for a cooldown.
+    pub circuit_open: Option<bool>,
+    /// Last time the service made progress, such as an update of the L1 gas prices.
+    pub last_update: Option<SystemTime>,
+    /// Whether the service was paused by the operator, for services which can be paused such as the L1 sync.
+    pub paused: Option<bool>,
+}
+
+/// Statuses reported by the services, shared by all the services in the same global scope.
+#[derive(Default)]
+pub struct ServiceStatusRegistry(RwLock<HashMap<MadaraService, ServiceStatus>>);
+
+impl ServiceStatusRegistry {
+    pub fn get(&self, svc: MadaraService) -> ServiceStatus {
+        self.0.read().expect("Poisoned lock").get(&svc).cloned().unwrap_or_default()
+    }
+
+    pub fn update(&self, svc: MadaraService, update: impl FnOnce(&mut ServiceStatus)) {
+        update(self.0.write().expect("Poisoned lock").entry(svc).or_default())
+    }
+}
+
+/// Restart requests for the services which can be restarted, see [ServiceGroup::push_restartable]. Shared by all
+/// the services in the same global scope.
+#[derive(Default)]
+pub struct ServiceRestartRegistry(RwLock<HashMap<MadaraService, Arc<tokio::sync::Notify>>>);
+
+impl ServiceRestartRegistry {
+    fn register(&self, svc: MadaraService) -> Arc<tokio::sync::Notify> {
+        Arc::clone(self.0.write().expect("Poisoned lock").entry(svc).or_default())
+    }
+
+    /// Returns false if the service cannot be restarted.
+    pub fn request(&self, svc: MadaraService) -> bool {
+        match self.0.read().expect("Poisoned lock").get(&svc) {
+            Some(notify) => {
+                notify.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Pauses a running service without stopping it, shared with the admin RPC. A paused service keeps its resources, such
+/// as its connection to a remote, but stops making progress until it is resumed.
+#[derive(Clone, Debug)]
+pub struct PauseHandle(Arc<watch::Sender<bool>>);
+
+impl PauseHandle {
+    pub fn new() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Returns whether the service was already paused.
+    pub fn pause(&self) -> bool {
+        self.0.send_replace(true)
+    }
+
+    /// Returns whether the service was paused.
+    pub fn resume(&self) -> bool {
+        self.0.send_replace(false)
+    }
+
+    /// Notified whenever the service is paused or resumed.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.0.subscribe()
+    }
+}
+
+impl Default for PauseHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub enum MadaraState {
@@ -120,6 +232,8 @@ pub struct ServiceContext {
     token_local: Option<tokio_util::sync::CancellationToken>,
     services: Arc<MadaraServiceMask>,
     services_notify: Arc<tokio::sync::Notify>,
+    statuses: Arc<ServiceStatusRegistry>,
+    restarts: Arc<ServiceRestartRegistry>,
     state: Arc<std::sync::atomic::AtomicU8>,
     id: MadaraService,
 }
@@ -131,6 +245,8 @@ impl Clone for ServiceContext {
             token_local: self.token_local.clone(),
             services: Arc::clone(&self.services),
             services_notify: Arc::clone(&self.services_notify),
+            statuses: Arc::clone(&self.statuses),
+            restarts: Arc::clone(&self.restarts),
             state: Arc::clone(&self.state),
             id: self.id,
         }
@@ -144,6 +260,8 @@ impl ServiceContext {
             token_local: None,
             services: Arc::new(MadaraServiceMask::default()),
             services_notify: Arc::new(tokio::sync::Notify::new()),
+            statuses: Arc::new(ServiceStatusRegistry::default()),
+            restarts: Arc::new(ServiceRestartRegistry::default()),
             state: Arc::new(std::sync::atomic::AtomicU8::new(MadaraState::default() as u8)),
             id: MadaraService::default(),
         }
@@ -156,6 +274,8 @@ impl ServiceContext {
             token_local: None,
             services: Arc::new(MadaraServiceMask::new_for_testing()),
             services_notify: Arc::new(tokio::sync::Notify::new()),
+            statuses: Arc::new(ServiceStatusRegistry::default()),
+            restarts: Arc::new(ServiceRestartRegistry::default()),
             state: Arc::new(std::sync::atomic::AtomicU8::new(MadaraState::default() as u8)),
             id: MadaraService::default(),
         }
@@ -205,7 +325,7 @@ impl ServiceContext {
     pub fn is_cancelled(&self) -> bool {
         self.token_global.is_cancelled()
             || self.token_local.as_ref().map(|t| t.is_cancelled()).unwrap_or(false)
-            || !self.services.is_active(self.id as u8)
+            || !self.services.is_active(self.id as u16)
             || self.state() == MadaraState::Shutdown
     }
 
@@ -233,6 +353,8 @@ impl ServiceContext {
             token_local: Some(token_local),
             services: Arc::clone(&self.services),
             services_notify: Arc::clone(&self.services_notify),
+            statuses: Arc::clone(&self.statuses),
+            restarts: Arc::clone(&self.restarts),
             state: Arc::clone(&self.state),
             id: self.id,
         }
@@ -243,7 +365,7 @@ impl ServiceContext {
     /// You can combine multiple [MadaraService] into a single bitmask to
     /// check the state of multiple services at once.
     #[inline(always)]
-    pub fn service_check(&self, cap: u8) -> bool {
+    pub fn service_check(&self, cap: u16) -> bool {
         self.services.is_active(cap)
     }
 
@@ -275,7 +397,28 @@ impl ServiceContext {
     /// or [ServiceContext::service_add]
     #[inline(always)]
     pub fn is_active(&self) -> bool {
-        self.services.is_active(self.id as u8)
+        self.services.is_active(self.id as u16)
+    }
+
+    /// Updates the status reported by the service associated to this [ServiceContext].
+    ///
+    /// This will immediately be visible to all services in the same global
+    /// scope. This is true across threads.
+    pub fn report_status(&self, update: impl FnOnce(&mut ServiceStatus)) {
+        self.statuses.update(self.id, update)
+    }
+
+    /// The status last reported by a service, see [ServiceContext::report_status].
+    pub fn service_status(&self, svc: MadaraService) -> ServiceStatus {
+        self.statuses.get(svc)
+    }
+
+    /// Requests a restart of a service: its tasks are stopped and `start` is called again on a fresh instance.
+    ///
+    /// This only works for the services added with [ServiceGroup::push_restartable], otherwise this does nothing and
+    /// returns false.
+    pub fn service_restart(&self, svc: MadaraService) -> bool {
+        self.restarts.request(svc)
     }
 
     /// Atomically checks the state of the node
@@ -316,10 +459,71 @@ pub trait Service: 'static + Send + Sync {
     }
 
     fn id(&self) -> MadaraService;
+
+    /// The services which must be started before this one. A [ServiceGroup] starts its services in an order which
+    /// respects these, the dependencies on services outside of the group are ignored.
+    fn dependencies(&self) -> Vec<MadaraService> {
+        vec![]
+    }
+}
+
+type ServiceFactory = Box<dyn Fn() -> Box<dyn Service> + Send + Sync>;
+
+enum GroupService {
+    Service(Box<dyn Service>),
+    /// The `first` instance is started with the group, the `factory` creates a fresh instance on every restart.
+    Restartable {
+        first: Box<dyn Service>,
+        factory: ServiceFactory,
+    },
+}
+
+impl GroupService {
+    fn id(&self) -> MadaraService {
+        match self {
+            Self::Service(svc) | Self::Restartable { first: svc, .. } => svc.id(),
+        }
+    }
+
+    fn dependencies(&self) -> Vec<MadaraService> {
+        match self {
+            Self::Service(svc) | Self::Restartable { first: svc, .. } => svc.dependencies(),
+        }
+    }
+}
+
+/// Orders the services so that each one comes after the services of the group it depends on, keeping the order they
+/// were added in otherwise. Fails when the dependencies form a cycle.
+fn order_by_dependencies(services: Vec<GroupService>) -> anyhow::Result<Vec<GroupService>> {
+    let ids: Vec<MadaraService> = services.iter().map(GroupService::id).collect();
+    let dependencies: Vec<Vec<usize>> = services
+        .iter()
+        .map(|svc| {
+            let dependencies = svc.dependencies();
+            (0..ids.len()).filter(|&i| ids[i] != MadaraService::None && dependencies.contains(&ids[i])).collect()
+        })
+        .collect();
+
+    let mut ordered = vec![false; services.len()];
+    let mut order = Vec::with_capacity(services.len());
+    while order.len() < services.len() {
+        // The first service added whose dependencies are all ordered.
+        let next = (0..services.len()).find(|&i| !ordered[i] && dependencies[i].iter().all(|&dep| ordered[dep]));
+        let Some(next) = next else {
+            let blocked: Vec<String> =
+                (0..services.len()).filter(|&i| !ordered[i]).map(|i| ids[i].to_string()).collect();
+            bail!("The dependencies of the services form a cycle, cannot start: {}", blocked.join(", "));
+        };
+        ordered[next] = true;
+        order.push(next);
+    }
+
+    let mut services: Vec<Option<GroupService>> = services.into_iter().map(Some).collect();
+    Ok(order.into_iter().map(|i| services[i].take().expect("Service ordered twice")).collect())
 }
 
 pub struct ServiceGroup {
-    services: Vec<Box<dyn Service>>,
+    services: Vec<GroupService>,
     join_set: Option<JoinSet<anyhow::Result<()>>>,
 }
 
@@ -331,7 +535,7 @@ impl Default for ServiceGroup {
 
 impl ServiceGroup {
     pub fn new(services: Vec<Box<dyn Service>>) -> Self {
-        Self { services, join_set: Some(Default::default()) }
+        Self { services: services.into_iter().map(GroupService::Service).collect(), join_set: Some(Default::default()) }
     }
 
     /// Add a new service to the service group.
@@ -339,13 +543,33 @@ impl ServiceGroup {
         if self.join_set.is_none() {
             panic!("Cannot add services to a group that has been started.")
         }
-        self.services.push(Box::new(value));
+        self.services.push(GroupService::Service(Box::new(value)));
     }
 
     pub fn with(mut self, value: impl Service) -> Self {
         self.push(value);
         self
     }
+
+    /// Add a new service to the service group, which can be restarted with [ServiceContext::service_restart].
+    ///
+    /// The `factory` is called once here to create the instance started with the group, then again to create a fresh
+    /// instance of the service on every restart. Unlike the other services of the group, a restartable service which fails, or
+    /// whose restart fails to start, does not stop the group: the error is logged and the service is marked as
+    /// inactive until it is restarted. Only a failure to start it along with the group is fatal.
+    pub fn push_restartable<S: Service>(&mut self, factory: impl Fn() -> S + Send + Sync + 'static) {
+        if self.join_set.is_none() {
+            panic!("Cannot add services to a group that has been started.")
+        }
+        let first = Box::new(factory());
+        let factory: ServiceFactory = Box::new(move || Box::new(factory()));
+        self.services.push(GroupService::Restartable { first, factory });
+    }
+
+    pub fn with_restartable<S: Service>(mut self, factory: impl Fn() -> S + Send + Sync + 'static) -> Self {
+        self.push_restartable(factory);
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -353,9 +577,21 @@ impl Service for ServiceGroup {
     async fn start(&mut self, join_set: &mut JoinSet<anyhow::Result<()>>, ctx: ServiceContext) -> anyhow::Result<()> {
         // drive the join set as a nested task
         let mut own_join_set = self.join_set.take().expect("Service has already been started.");
-        for svc in self.services.iter_mut() {
-            ctx.service_add(svc.id());
-            svc.start(&mut own_join_set, ctx.child().with_id(svc.id())).await.context("Starting service")?;
+        for svc in order_by_dependencies(std::mem::take(&mut self.services))? {
+            match svc {
+                GroupService::Service(mut svc) => {
+                    ctx.service_add(svc.id());
+                    svc.start(&mut own_join_set, ctx.child().with_id(svc.id())).await.context("Starting service")?;
+                }
+                GroupService::Restartable { first, factory } => {
+                    let id = first.id();
+                    let svc_ctx = ctx.child().with_id(id);
+                    let mut svc_join_set = JoinSet::new();
+                    start_restartable(first, &ctx, &svc_ctx, &mut svc_join_set).await?;
+                    let restart = ctx.restarts.register(id);
+                    own_join_set.spawn(supervise_restartable(factory, ctx.clone(), restart, svc_join_set, svc_ctx));
+                }
+            }
         }
 
         join_set.spawn(drive_joinset(own_join_set));
@@ -365,6 +601,77 @@ impl Service for ServiceGroup {
     fn id(&self) -> MadaraService {
         MadaraService::None
     }
+
+    /// The dependencies of the services of the group on services outside of it.
+    fn dependencies(&self) -> Vec<MadaraService> {
+        let ids: Vec<MadaraService> = self.services.iter().map(GroupService::id).collect();
+        self.services.iter().flat_map(GroupService::dependencies).filter(|dep| !ids.contains(dep)).collect()
+    }
+}
+
+/// Starts an instance of a restartable service in its own join set and local scope.
+async fn start_restartable(
+    mut svc: Box<dyn Service>,
+    ctx: &ServiceContext,
+    svc_ctx: &ServiceContext,
+    join_set: &mut JoinSet<anyhow::Result<()>>,
+) -> anyhow::Result<()> {
+    ctx.service_add(svc_ctx.id());
+    svc.start(join_set, svc_ctx.clone()).await.context("Starting service")
+}
+
+/// Cancels the tasks of a restartable service and waits for them to end, so that they can complete their writes.
+async fn stop_restartable(svc_ctx: &ServiceContext, join_set: &mut JoinSet<anyhow::Result<()>>) {
+    svc_ctx.cancel_local();
+    while let Some(result) = join_set.join_next().await {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => tracing::error!("❗ The {} service stopped: {err:#}", svc_ctx.id()),
+            Err(panic_error) if panic_error.is_panic() => panic::resume_unwind(panic_error.into_panic()),
+            Err(_task_cancelled_error) => {}
+        }
+    }
+}
+
+/// Drives the tasks of a restartable service, stopping them and starting a fresh instance of the service on every
+/// restart request.
+async fn supervise_restartable(
+    factory: ServiceFactory,
+    ctx: ServiceContext,
+    restart: Arc<tokio::sync::Notify>,
+    mut join_set: JoinSet<anyhow::Result<()>>,
+    mut svc_ctx: ServiceContext,
+) -> anyhow::Result<()> {
+    let id = svc_ctx.id();
+    loop {
+        tokio::select! {
+            _ = ctx.cancelled() => break,
+            _ = restart.notified() => {
+                tracing::info!("🔁 Restarting {id} service...");
+                stop_restartable(&svc_ctx, &mut join_set).await;
+                svc_ctx = ctx.child().with_id(id);
+                if let Err(err) = start_restartable(factory(), &ctx, &svc_ctx, &mut join_set).await {
+                    tracing::error!("❗ Failed to restart the {id} service: {err:#}");
+                    stop_restartable(&svc_ctx, &mut join_set).await;
+                    ctx.service_remove(id);
+                }
+            }
+            Some(result) = join_set.join_next() => match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    tracing::error!("❗ The {id} service stopped: {err:#}");
+                    stop_restartable(&svc_ctx, &mut join_set).await;
+                    ctx.service_remove(id);
+                }
+                Err(panic_error) if panic_error.is_panic() => {
+                    panic::resume_unwind(panic_error.into_panic());
+                }
+                Err(_task_cancelled_error) => {}
+            }
+        }
+    }
+
+    drive_joinset(join_set).await
 }
 
 async fn drive_joinset(mut join_set: JoinSet<anyhow::Result<()>>) -> anyhow::Result<()> {
@@ -381,3 +688,180 @@ async fn drive_joinset(mut join_set: JoinSet<anyhow::Result<()>>) -> anyhow::Res
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct DummyService {
+        starts: tokio::sync::mpsc::UnboundedSender<()>,
+    }
+
+    #[async_trait::async_trait]
+    impl Service for DummyService {
+        async fn start(
+            &mut self,
+            join_set: &mut JoinSet<anyhow::Result<()>>,
+            ctx: ServiceContext,
+        ) -> anyhow::Result<()> {
+            self.starts.send(()).expect("Test receiver dropped");
+            join_set.spawn(async move {
+                ctx.cancelled().await;
+                Ok(())
+            });
+            Ok(())
+        }
+
+        fn id(&self) -> MadaraService {
+            MadaraService::L1Sync
+        }
+    }
+
+    #[tokio::test]
+    async fn service_restart_starts_a_fresh_instance() {
+        let (starts, mut started) = tokio::sync::mpsc::unbounded_channel();
+        let mut group = ServiceGroup::default().with_restartable(move || DummyService { starts: starts.clone() });
+        let ctx = ServiceContext::new();
+        let mut join_set = JoinSet::new();
+
+        group.start(&mut join_set, ctx.clone()).await.expect("Starting the group");
+        started.recv().await.expect("Service was not started");
+        assert!(ctx.service_check(MadaraService::L1Sync as u16));
+
+        assert!(ctx.service_restart(MadaraService::L1Sync));
+        started.recv().await.expect("Service was not restarted");
+        assert!(ctx.service_check(MadaraService::L1Sync as u16));
+
+        // Only the restartable services can be restarted.
+        assert!(!ctx.service_restart(MadaraService::L2Sync));
+
+        ctx.cancel_global();
+        drive_joinset(join_set).await.expect("Driving the group to the end");
+        assert!(started.try_recv().is_err());
+    }
+
+    /// Only starts the first time.
+    struct FailingRestartService {
+        starts: tokio::sync::mpsc::UnboundedSender<()>,
+        start_count: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Service for FailingRestartService {
+        async fn start(
+            &mut self,
+            join_set: &mut JoinSet<anyhow::Result<()>>,
+            ctx: ServiceContext,
+        ) -> anyhow::Result<()> {
+            self.starts.send(()).expect("Test receiver dropped");
+            if self.start_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst) > 0 {
+                bail!("Failed to restart");
+            }
+            join_set.spawn(async move {
+                ctx.cancelled().await;
+                Ok(())
+            });
+            Ok(())
+        }
+
+        fn id(&self) -> MadaraService {
+            MadaraService::L1Sync
+        }
+    }
+
+    #[tokio::test]
+    async fn service_restart_failure_does_not_stop_the_group() {
+        let (starts, mut started) = tokio::sync::mpsc::unbounded_channel();
+        let start_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut group = ServiceGroup::default().with_restartable(move || FailingRestartService {
+            starts: starts.clone(),
+            start_count: Arc::clone(&start_count),
+        });
+        let ctx = ServiceContext::new();
+        let mut join_set = JoinSet::new();
+
+        group.start(&mut join_set, ctx.clone()).await.expect("Starting the group");
+        started.recv().await.expect("Service was not started");
+        assert!(ctx.service_check(MadaraService::L1Sync as u16));
+
+        assert!(ctx.service_restart(MadaraService::L1Sync));
+        started.recv().await.expect("Service was not restarted");
+        while ctx.service_check(MadaraService::L1Sync as u16) {
+            tokio::task::yield_now().await;
+        }
+
+        // The group keeps running, and ends without an error.
+        ctx.cancel_global();
+        drive_joinset(join_set).await.expect("Driving the group to the end");
+    }
+
+    struct OrderedService {
+        id: MadaraService,
+        dependencies: Vec<MadaraService>,
+        started: Arc<std::sync::Mutex<Vec<MadaraService>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Service for OrderedService {
+        async fn start(
+            &mut self,
+            _join_set: &mut JoinSet<anyhow::Result<()>>,
+            _ctx: ServiceContext,
+        ) -> anyhow::Result<()> {
+            self.started.lock().expect("Poisoned lock").push(self.id);
+            Ok(())
+        }
+
+        fn id(&self) -> MadaraService {
+            self.id
+        }
+
+        fn dependencies(&self) -> Vec<MadaraService> {
+            self.dependencies.clone()
+        }
+    }
+
+    fn ordered_group(
+        services: impl IntoIterator<Item = (MadaraService, Vec<MadaraService>)>,
+    ) -> (ServiceGroup, Arc<std::sync::Mutex<Vec<MadaraService>>>) {
+        let started = Arc::new(std::sync::Mutex::new(vec![]));
+        let group = services.into_iter().fold(ServiceGroup::default(), |group, (id, dependencies)| {
+            group.with(OrderedService { id, dependencies, started: Arc::clone(&started) })
+        });
+        (group, started)
+    }
+
+    #[tokio::test]
+    async fn service_group_starts_dependencies_first() {
+        let (mut group, started) = ordered_group([
+            (MadaraService::Rpc, vec![MadaraService::Mempool]),
+            (MadaraService::Mempool, vec![MadaraService::Database]),
+            (MadaraService::Database, vec![]),
+            // The dependencies on services outside of the group are ignored.
+            (MadaraService::Gateway, vec![MadaraService::Telemetry]),
+        ]);
+        assert_eq!(group.dependencies(), [MadaraService::Telemetry]);
+
+        let mut join_set = JoinSet::new();
+        group.start(&mut join_set, ServiceContext::new()).await.expect("Starting the group");
+        assert_eq!(
+            *started.lock().unwrap(),
+            [MadaraService::Database, MadaraService::Mempool, MadaraService::Rpc, MadaraService::Gateway]
+        );
+    }
+
+    #[tokio::test]
+    async fn service_group_rejects_dependency_cycles() {
+        let (mut group, started) = ordered_group([
+            (MadaraService::Database, vec![]),
+            (MadaraService::L1Sync, vec![MadaraService::L2Sync]),
+            (MadaraService::L2Sync, vec![MadaraService::L1Sync]),
+        ]);
+
+        let mut join_set = JoinSet::new();
+        let err = group.start(&mut join_set, ServiceContext::new()).await.unwrap_err();
+        assert!(format!("{err:#}").contains("cycle"), "{err:#}");
+        // No service is started when the order cannot be resolved.
+        assert!(started.lock().unwrap().is_empty());
+    }
+}